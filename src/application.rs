@@ -1,10 +1,18 @@
-use crate::clear_data::{ClearDataDialog, ClearDataOptions};
+use crate::clear_data::{self, ClearDataDialog, ClearDataOptions};
 use crate::config;
-use crate::content_filter::ContentFilterManager;
+use crate::content_filter::{ContentFilterManager, ScriptletError};
+use crate::download::{self, DownloadItem, DownloadStatus};
+use crate::history::ImportSource;
+use crate::javascript::JavaScriptOverrides;
 use crate::preferences::PreferencesWindow;
-use crate::profile::ProfileManager;
+use crate::profile::{self, Container, ProfileError, ProfileManager};
 use crate::recently_closed::RecentlyClosedWindows;
-use crate::session::MultiWindowSession;
+use crate::session::{self, MultiWindowSession, NamedSession, SavedWindow, SessionsDialog, StartupMode};
+use crate::snippets::{self, SnippetManager};
+use crate::url_bar;
+use crate::user_agent::{UserAgentMode, UserAgentOverrides};
+use crate::userscripts::UserScriptManager;
+use crate::webview;
 use crate::window::QayeqWindow;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
@@ -12,6 +20,9 @@ use gtk4::{gio, glib};
 use libadwaita::prelude::*;
 use libadwaita::subclass::prelude::*;
 use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 
 mod imp {
     use super::*;
@@ -20,8 +31,21 @@ mod imp {
     pub struct QayeqApplication {
         pub profile_manager: OnceCell<ProfileManager>,
         pub content_filter_manager: OnceCell<ContentFilterManager>,
+        /// User scripts of each container, by container ID
+        pub user_scripts: RefCell<HashMap<String, Rc<UserScriptManager>>>,
+        pub snippets: RefCell<HashMap<String, Rc<SnippetManager>>>,
         pub closed_windows: RefCell<RecentlyClosedWindows>,
         pub session_restored: Cell<bool>,
+        /// The last run didn't shut down cleanly, so its session is offered rather than restored
+        pub previous_run_crashed: Cell<bool>,
+        pub user_agent_overrides: RefCell<UserAgentOverrides>,
+        pub javascript_overrides: RefCell<JavaScriptOverrides>,
+        /// Colors of user-defined containers, regenerated when they change
+        pub container_css: gtk4::CssProvider,
+        /// Pending coalesced session save
+        pub session_save_source: RefCell<Option<glib::SourceId>>,
+        /// Watches preferences that apply to the shared WebContext and network sessions
+        pub web_context_settings: OnceCell<gio::Settings>,
     }
 
     #[glib::object_subclass]
@@ -37,37 +61,54 @@ mod imp {
         fn activate(&self) {
             let app = self.obj();
 
-            // On first activation, try to restore multi-window session
+            // On first activation, restore the multi-window session or open the startup pages
+            let mut pages = Vec::new();
             if !self.session_restored.get() {
                 self.session_restored.set(true);
-
-                if let Some(pm) = self.profile_manager.get() {
-                    if let Some(profile) = pm.current_profile() {
-                        match MultiWindowSession::load(profile.path()) {
-                            Ok(session) if session.has_windows() => {
-                                log::info!("Restoring {} windows from session", session.windows.len());
-                                for saved_window in session.windows {
-                                    let window = QayeqWindow::new_with_session(&app, saved_window);
-                                    window.present();
-                                }
-                                return;
-                            }
-                            Ok(_) => {
-                                log::debug!("No windows in session to restore");
-                            }
-                            Err(e) => {
-                                log::warn!("Failed to load session: {}", e);
-                            }
-                        }
-                    }
+                if app.restore_startup_session() {
+                    return;
                 }
+                pages = app.startup_pages();
             }
 
             // Default: create a single empty window
-            let window = QayeqWindow::new_empty(&app);
+            let window = if pages.is_empty() {
+                QayeqWindow::new_empty(&app)
+            } else {
+                QayeqWindow::new_with_session(&app, SavedWindow::with_pages(pages))
+            };
+            app.offer_crash_restore(&window);
             window.present();
         }
 
+        fn open(&self, files: &[gio::File], _hint: &str) {
+            let app = self.obj();
+
+            // Launched with links: the session still comes back, but not the startup pages
+            if !self.session_restored.get() {
+                self.session_restored.set(true);
+                app.restore_startup_session();
+            }
+
+            let urls: Vec<url::Url> = files
+                .iter()
+                .filter_map(|file| {
+                    let uri = file.uri();
+                    match url::Url::parse(&uri) {
+                        Ok(url) => Some(url),
+                        Err(e) => {
+                            log::warn!("Ignoring link {} from another app: {}", uri, e);
+                            None
+                        }
+                    }
+                })
+                .collect();
+            app.open_external_links(&urls);
+            if let Some(window) = app.active_window().and_downcast::<QayeqWindow>() {
+                app.offer_crash_restore(&window);
+            }
+        }
+
         fn startup(&self) {
             self.parent_startup();
 
@@ -81,9 +122,32 @@ mod imp {
                 Ok(pm) => {
                     // Initialize content filter manager with the profile's data directory
                     let data_dir = pm.base_dir().clone();
+                    self.user_agent_overrides.replace(UserAgentOverrides::load(&data_dir));
+                    self.javascript_overrides.replace(JavaScriptOverrides::load(&data_dir));
                     let cfm = ContentFilterManager::new(&data_dir);
                     cfm.initialize_filters();
-                    cfm.initialize_scriptlets();
+                    let containers: Vec<_> = pm
+                        .containers()
+                        .into_iter()
+                        .map(|container| {
+                            let dir = pm.container_dir(&container.id);
+                            (container.id, dir)
+                        })
+                        .collect();
+                    cfm.initialize_scriptlets(&containers);
+                    cfm.initialize_privacy_signals();
+                    cfm.initialize_referrer_policy();
+                    cfm.initialize_fingerprinting_protection();
+                    cfm.initialize_force_dark();
+                    cfm.initialize_mixed_content_blocking();
+
+                    let dirs: Vec<_> = containers.iter().map(|(_, dir)| dir.clone()).collect();
+                    if let Err(e) = snippets::split_shared(&data_dir, &dirs) {
+                        log::warn!("Failed to copy text snippets into containers: {}", e);
+                    }
+                    for (container, dir) in &containers {
+                        app.load_container_scripts(&cfm, container, dir);
+                    }
 
                     if self.content_filter_manager.set(cfm).is_err() {
                         log::warn!("Content filter manager already initialized");
@@ -91,8 +155,10 @@ mod imp {
                         log::info!("Content filter manager initialized");
                     }
 
+                    self.previous_run_crashed.set(session::begin_run(&data_dir));
                     self.profile_manager.set(pm).unwrap();
                     log::info!("Profile manager initialized");
+                    app.update_container_css();
                 }
                 Err(e) => {
                     log::error!("Failed to initialize profile manager: {}", e);
@@ -102,14 +168,27 @@ mod imp {
             // Set up application actions
             app.setup_actions();
             app.setup_accels();
+            app.setup_download_notifications();
+            app.setup_launcher_progress();
+            app.setup_spell_checking();
+            app.setup_cache_limit();
         }
 
         fn shutdown(&self) {
             self.parent_shutdown();
 
-            // Save all windows to multi-window session
+            // Save all windows to multi-window session, including any change still waiting
             let app = self.obj();
-            app.save_all_windows();
+            app.flush_session_save();
+            if let Some(cfm) = app.content_filter_manager() {
+                cfm.shutdown();
+            }
+            if let Some(pm) = app.profile_manager() {
+                pm.shutdown();
+                if let Err(e) = session::mark_clean_exit(pm.base_dir()) {
+                    log::warn!("Failed to mark clean exit: {}", e);
+                }
+            }
         }
     }
 
@@ -140,6 +219,285 @@ impl QayeqApplication {
         self.imp().content_filter_manager.get()
     }
 
+    /// Get a container's user script manager
+    pub fn user_scripts(&self, container_id: &str) -> Option<Rc<UserScriptManager>> {
+        self.imp().user_scripts.borrow().get(container_id).cloned()
+    }
+
+    /// Get a container's text snippet manager
+    pub fn snippets(&self, container_id: &str) -> Option<Rc<SnippetManager>> {
+        self.imp().snippets.borrow().get(container_id).cloned()
+    }
+
+    /// Get the user agent requested for a site, if any
+    pub fn site_user_agent(&self, host: &str) -> Option<UserAgentMode> {
+        self.imp().user_agent_overrides.borrow().get(host)
+    }
+
+    /// Set or clear the user agent requested for a site
+    pub fn set_site_user_agent(&self, host: &str, mode: Option<UserAgentMode>) {
+        self.imp().user_agent_overrides.borrow_mut().set(host, mode);
+    }
+
+    /// Whether JavaScript runs on a site: its override, or the global default
+    pub fn site_javascript_enabled(&self, host: &str) -> bool {
+        let default = gio::Settings::new(config::APP_ID).boolean("javascript-enabled");
+        self.imp().javascript_overrides.borrow().get(host).unwrap_or(default)
+    }
+
+    /// Enable or disable JavaScript on a site.
+    /// Matching the global default clears the override so the site follows it.
+    pub fn set_site_javascript(&self, host: &str, enabled: bool) {
+        let default = gio::Settings::new(config::APP_ID).boolean("javascript-enabled");
+        let enabled = (enabled != default).then_some(enabled);
+        self.imp().javascript_overrides.borrow_mut().set(host, enabled);
+    }
+
+    /// Apply spell checking preferences now and whenever they change
+    fn setup_spell_checking(&self) {
+        let Some(context) = webkit6::WebContext::default() else {
+            return;
+        };
+        let settings = self
+            .imp()
+            .web_context_settings
+            .get_or_init(|| gio::Settings::new(config::APP_ID));
+
+        webview::apply_spell_checking(&context, settings);
+        for key in ["spell-checking", "spell-checking-languages"] {
+            let context = context.clone();
+            settings.connect_changed(Some(key), move |settings, _| {
+                webview::apply_spell_checking(&context, settings);
+            });
+        }
+    }
+
+    /// Trim containers' disk caches to the size limit now and then, and as soon
+    /// as the limit is lowered
+    fn setup_cache_limit(&self) {
+        glib::timeout_add_seconds_local(
+            config::CACHE_LIMIT_CHECK_INTERVAL_SECS,
+            glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    app.enforce_cache_limit();
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+
+        let settings = self
+            .imp()
+            .web_context_settings
+            .get_or_init(|| gio::Settings::new(config::APP_ID));
+        settings.connect_changed(
+            Some("cache-size-limit-mb"),
+            glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move |_, _| app.enforce_cache_limit()
+            ),
+        );
+    }
+
+    /// Evict the least recently visited sites from each loaded container's
+    /// disk cache while it's over the limit
+    fn enforce_cache_limit(&self) {
+        let Some(pm) = self.profile_manager() else {
+            return;
+        };
+        let limit_mb = gio::Settings::new(config::APP_ID).int("cache-size-limit-mb");
+        if limit_mb <= 0 {
+            return;
+        }
+        for name in pm.loaded_profiles() {
+            if let Some(profile) = pm.get_profile(&name) {
+                profile::enforce_cache_limit(&profile, limit_mb as u64 * 1024 * 1024);
+            }
+        }
+    }
+
+    /// Send a desktop notification when a download completes
+    fn setup_download_notifications(&self) {
+        let settings = gio::Settings::new(config::APP_ID);
+        // Last seen status of each download, to notify only on the transition
+        let statuses: RefCell<HashMap<u64, DownloadStatus>> = RefCell::new(HashMap::new());
+        let app_weak = self.downgrade();
+
+        download::subscribe_to_changes(move || {
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let mut statuses = statuses.borrow_mut();
+            for item in download::recent_downloads(100) {
+                let previous = statuses.insert(item.id, item.status.clone());
+                let completed = item.status == DownloadStatus::Completed
+                    && previous.is_some_and(|status| status != DownloadStatus::Completed);
+                if completed && settings.boolean("download-notifications") {
+                    app.notify_download_complete(&item);
+                }
+            }
+        });
+    }
+
+    /// Mirror download progress on the dock icon through the Unity launcher API,
+    /// which GNOME docks such as Dash to Dock also implement
+    fn setup_launcher_progress(&self) {
+        // Last (progress percent, active count) sent, to skip redundant D-Bus signals
+        let last_sent: Cell<Option<(u32, usize)>> = Cell::new(None);
+        let app_weak = self.downgrade();
+
+        download::subscribe_to_changes(move || {
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let progress = download::aggregate_progress();
+            let count = download::active_download_count();
+            let state = progress.map(|fraction| ((fraction * 100.0).round() as u32, count));
+            if last_sent.get() == state {
+                return;
+            }
+            last_sent.set(state);
+            app.update_launcher_entry(progress, count);
+        });
+    }
+
+    /// Show or hide the launcher progress bar and download count
+    fn update_launcher_entry(&self, progress: Option<f64>, count: usize) {
+        let Some(connection) = self.dbus_connection() else {
+            return;
+        };
+        let Some(object_path) = self.dbus_object_path() else {
+            return;
+        };
+
+        let properties = glib::VariantDict::new(None);
+        properties.insert_value("progress", &progress.unwrap_or(0.0).to_variant());
+        properties.insert_value("progress-visible", &progress.is_some().to_variant());
+        properties.insert_value("count", &(count as i64).to_variant());
+        properties.insert_value("count-visible", &(progress.is_some() && count > 0).to_variant());
+
+        let app_uri = format!("application://{}.desktop", config::APP_ID);
+        let parameters = glib::Variant::tuple_from_iter([app_uri.to_variant(), properties.end()]);
+        if let Err(e) = connection.emit_signal(
+            None,
+            &object_path,
+            "com.canonical.Unity.LauncherEntry",
+            "Update",
+            Some(&parameters),
+        ) {
+            log::debug!("Failed to update launcher entry: {}", e);
+        }
+    }
+
+    fn notify_download_complete(&self, item: &DownloadItem) {
+        let notification = gio::Notification::new("Download complete");
+        notification.set_body(Some(&item.filename));
+        let target = item.id.to_variant();
+        notification.set_default_action_and_target_value("app.open-download", Some(&target));
+        notification.add_button_with_target_value("Open", "app.open-download", Some(&target));
+        notification.add_button_with_target_value("Show in Folder", "app.show-download", Some(&target));
+        self.send_notification(Some(&format!("download-{}", item.id)), &notification);
+    }
+
+    /// Add the app.new-tab-<id> action for a container
+    fn add_container_action(&self, container_id: &str) {
+        let action_name = format!("new-tab-{}", container_id);
+        let container_id_owned = container_id.to_string();
+        let action = gio::SimpleAction::new(&action_name, None);
+        action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.new_tab_in_container(None, Some(&container_id_owned));
+                    }
+                }
+            }
+        ));
+        self.add_action(&action);
+        log::debug!("Added action app.{} for container '{}'", action_name, container_id);
+    }
+
+    /// All containers, built-in and user-defined
+    pub fn containers(&self) -> Vec<Container> {
+        self.profile_manager().map(|pm| pm.containers()).unwrap_or_default()
+    }
+
+    /// Display name of a container, falling back to its ID
+    pub fn container_name(&self, container_id: &str) -> String {
+        self.profile_manager()
+            .and_then(|pm| pm.container(container_id))
+            .map(|c| c.name)
+            .unwrap_or_else(|| container_id.to_string())
+    }
+
+    /// Create a user container, ready for new tabs
+    pub fn create_container(&self, name: &str, color: &str) -> Result<Container, ProfileError> {
+        let pm = self.profile_manager().ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+        let container = pm.create_container(name, color)?;
+        if let Some(cfm) = self.content_filter_manager() {
+            self.load_container_scripts(cfm, &container.id, &pm.container_dir(&container.id));
+        }
+        self.add_container_action(&container.id);
+        self.update_container_css();
+        Ok(container)
+    }
+
+    /// Inject a container's user scripts and text snippets into its webviews
+    fn load_container_scripts(&self, cfm: &ContentFilterManager, container_id: &str, dir: &Path) {
+        let user_scripts = UserScriptManager::new(container_id, dir, cfm.content_managers());
+        user_scripts.initialize();
+        self.imp().user_scripts.borrow_mut().insert(container_id.to_string(), user_scripts);
+
+        let snippets = SnippetManager::new(container_id, dir, cfm.content_managers());
+        snippets.initialize();
+        self.imp().snippets.borrow_mut().insert(container_id.to_string(), snippets);
+    }
+
+    /// Rename a user container
+    pub fn rename_container(&self, container_id: &str, name: &str) -> Result<(), ProfileError> {
+        let pm = self.profile_manager().ok_or_else(|| ProfileError::NotFound(container_id.to_string()))?;
+        pm.rename_container(container_id, name)
+    }
+
+    /// Delete a user container, as long as no open tab uses it
+    pub fn delete_container(&self, container_id: &str) -> Result<(), ProfileError> {
+        if self.container_in_use(container_id) {
+            return Err(ProfileError::InUse(container_id.to_string()));
+        }
+        let pm = self.profile_manager().ok_or_else(|| ProfileError::NotFound(container_id.to_string()))?;
+        pm.delete_container(container_id)?;
+        if let Some(user_scripts) = self.imp().user_scripts.borrow_mut().remove(container_id) {
+            user_scripts.unload();
+        }
+        if let Some(snippets) = self.imp().snippets.borrow_mut().remove(container_id) {
+            snippets.unload();
+        }
+        self.remove_action(&format!("new-tab-{}", container_id));
+        self.update_container_css();
+        Ok(())
+    }
+
+    /// Whether any tab in any window belongs to a container
+    fn container_in_use(&self, container_id: &str) -> bool {
+        self.windows().iter().any(|window| {
+            window
+                .downcast_ref::<QayeqWindow>()
+                .is_some_and(|w| w.uses_container(container_id))
+        })
+    }
+
+    /// Regenerate the CSS that colors user containers
+    fn update_container_css(&self) {
+        let css = profile::container_css(&self.containers());
+        self.imp().container_css.load_from_string(&css);
+    }
+
     fn load_css(&self) {
         let provider = gtk4::CssProvider::new();
         provider.load_from_resource("/dev/myyc/qayeq/style.css");
@@ -149,6 +507,11 @@ impl QayeqApplication {
             &provider,
             gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
+        gtk4::style_context_add_provider_for_display(
+            &gtk4::gdk::Display::default().expect("Could not get default display"),
+            &self.imp().container_css,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
 
         log::info!("CSS loaded");
     }
@@ -192,24 +555,9 @@ impl QayeqApplication {
         ));
         self.add_action(&new_tab_action);
 
-        // New tab in container actions (Ctrl+Shift+1-4)
-        for (container_id, container_name, _) in config::DEFAULT_CONTAINERS {
-            let action_name = format!("new-tab-{}", container_id);
-            let container_id_owned = container_id.to_string();
-            let action = gio::SimpleAction::new(&action_name, None);
-            action.connect_activate(glib::clone!(
-                #[weak(rename_to = app)]
-                self,
-                move |_, _| {
-                    if let Some(window) = app.active_window() {
-                        if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
-                            qayeq_window.new_tab_in_container(None, Some(&container_id_owned));
-                        }
-                    }
-                }
-            ));
-            self.add_action(&action);
-            log::debug!("Added action app.{} for container '{}'", action_name, container_name);
+        // New tab in container actions (Ctrl+Shift+1-4 for the built-in ones)
+        for container in self.containers() {
+            self.add_container_action(&container.id);
         }
 
         // Close tab action
@@ -227,6 +575,21 @@ impl QayeqApplication {
         ));
         self.add_action(&close_tab_action);
 
+        // Close tab even if it's pinned
+        let force_close_tab_action = gio::SimpleAction::new("force-close-tab", None);
+        force_close_tab_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.force_close_current_tab();
+                    }
+                }
+            }
+        ));
+        self.add_action(&force_close_tab_action);
+
         // Reopen closed tab action
         let reopen_tab_action = gio::SimpleAction::new("reopen-tab", None);
         reopen_tab_action.connect_activate(glib::clone!(
@@ -253,6 +616,82 @@ impl QayeqApplication {
         ));
         self.add_action(&reopen_window_action);
 
+        // Reopen a specific entry from the "Recently Closed" menu
+        let reopen_tab_at_action = gio::SimpleAction::new("reopen-tab-at", Some(glib::VariantTy::UINT32));
+        reopen_tab_at_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, param| {
+                let Some(index) = param.and_then(|p| p.get::<u32>()) else {
+                    return;
+                };
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.imp().reopen_closed_tab_at(index as usize);
+                    }
+                }
+            }
+        ));
+        self.add_action(&reopen_tab_at_action);
+
+        let reopen_window_at_action = gio::SimpleAction::new("reopen-window-at", Some(glib::VariantTy::UINT32));
+        reopen_window_at_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, param| {
+                if let Some(index) = param.and_then(|p| p.get::<u32>()) {
+                    app.reopen_closed_window_at(index as usize);
+                }
+            }
+        ));
+        self.add_action(&reopen_window_at_action);
+
+        let move_tab_to_window_action = gio::SimpleAction::new("move-tab-to-window", Some(glib::VariantTy::UINT32));
+        move_tab_to_window_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, param| {
+                if let Some(window_id) = param.and_then(|p| p.get::<u32>()) {
+                    app.move_tab_to_window(window_id);
+                }
+            }
+        ));
+        self.add_action(&move_tab_to_window_action);
+
+        // Download notification actions
+        let open_download_action = gio::SimpleAction::new("open-download", Some(glib::VariantTy::UINT64));
+        open_download_action.connect_activate(|_, param| {
+            let item = param.and_then(|p| p.get::<u64>()).and_then(download::get_download);
+            if let Some(item) = item {
+                download::open_file(&item.destination);
+            }
+        });
+        self.add_action(&open_download_action);
+
+        let show_download_action = gio::SimpleAction::new("show-download", Some(glib::VariantTy::UINT64));
+        show_download_action.connect_activate(|_, param| {
+            let item = param.and_then(|p| p.get::<u64>()).and_then(download::get_download);
+            if let Some(item) = item {
+                download::show_in_folder(&item.destination);
+            }
+        });
+        self.add_action(&show_download_action);
+
+        // Search tabs action
+        let search_tabs_action = gio::SimpleAction::new("search-tabs", None);
+        search_tabs_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.show_tab_search();
+                    }
+                }
+            }
+        ));
+        self.add_action(&search_tabs_action);
+
         // Focus URL bar action
         let focus_url_action = gio::SimpleAction::new("focus-url-bar", None);
         focus_url_action.connect_activate(glib::clone!(
@@ -313,90 +752,455 @@ impl QayeqApplication {
         ));
         self.add_action(&forward_action);
 
-        // About action
-        let about_action = gio::SimpleAction::new("about", None);
-        about_action.connect_activate(glib::clone!(
+        // Go home action
+        let home_action = gio::SimpleAction::new("go-home", None);
+        home_action.connect_activate(glib::clone!(
             #[weak(rename_to = app)]
             self,
             move |_, _| {
-                app.show_about();
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.go_home();
+                    }
+                }
             }
         ));
-        self.add_action(&about_action);
+        self.add_action(&home_action);
 
-        // Toggle sidebar action (TAB key)
-        let toggle_sidebar_action = gio::SimpleAction::new("toggle-sidebar", None);
-        toggle_sidebar_action.connect_activate(glib::clone!(
+        // Toggle developer tools action
+        let devtools_action = gio::SimpleAction::new("toggle-devtools", None);
+        devtools_action.connect_activate(glib::clone!(
             #[weak(rename_to = app)]
             self,
             move |_, _| {
                 if let Some(window) = app.active_window() {
                     if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
-                        qayeq_window.toggle_sidebar();
+                        qayeq_window.toggle_inspector();
                     }
                 }
             }
         ));
-        self.add_action(&toggle_sidebar_action);
+        self.add_action(&devtools_action);
 
-        // Clear browsing data action
-        let clear_data_action = gio::SimpleAction::new("clear-data", None);
-        clear_data_action.connect_activate(glib::clone!(
+        // Open the ad blocking statistics page in a new tab
+        let filter_stats_action = gio::SimpleAction::new("filter-stats", None);
+        filter_stats_action.connect_activate(glib::clone!(
             #[weak(rename_to = app)]
             self,
             move |_, _| {
-                app.show_clear_data_dialog();
+                let Ok(url) = url::Url::parse(config::FILTER_STATS_URL) else {
+                    return;
+                };
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.new_tab(Some(&url));
+                    }
+                }
             }
         ));
-        self.add_action(&clear_data_action);
+        self.add_action(&filter_stats_action);
 
-        // Preferences action (placeholder for now)
-        let preferences_action = gio::SimpleAction::new("preferences", None);
-        preferences_action.connect_activate(glib::clone!(
+        // Print action
+        let print_action = gio::SimpleAction::new("print", None);
+        print_action.connect_activate(glib::clone!(
             #[weak(rename_to = app)]
             self,
             move |_, _| {
-                app.show_preferences();
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.print_page();
+                    }
+                }
             }
         ));
-        self.add_action(&preferences_action);
-    }
-
-    fn setup_accels(&self) {
-        self.set_accels_for_action("app.quit", &["<Control>q"]);
-        self.set_accels_for_action("app.new-window", &["<Control><Shift>n"]);
-        self.set_accels_for_action("app.new-tab", &["<Control>t"]);
-        self.set_accels_for_action("app.close-tab", &["<Control>w"]);
-        self.set_accels_for_action("app.reopen-tab", &["<Control><Shift>t"]);
-        self.set_accels_for_action("app.focus-url-bar", &["<Control>l"]);
-        self.set_accels_for_action("app.reload", &["<Control>r", "F5"]);
-        self.set_accels_for_action("app.go-back", &["<Alt>Left"]);
-        self.set_accels_for_action("app.go-forward", &["<Alt>Right"]);
-        self.set_accels_for_action("app.toggle-sidebar", &["F1"]);
-        self.set_accels_for_action("app.preferences", &["<Control>comma"]);
+        self.add_action(&print_action);
 
-        // Container shortcuts: Ctrl+Shift+1-4 for opening tabs in containers
-        // Matches order in config::DEFAULT_CONTAINERS
-        self.set_accels_for_action("app.new-tab-personal", &["<Control><Shift>1"]);
-        self.set_accels_for_action("app.new-tab-work", &["<Control><Shift>2"]);
-        self.set_accels_for_action("app.new-tab-shopping", &["<Control><Shift>3"]);
-        self.set_accels_for_action("app.new-tab-banking", &["<Control><Shift>4"]);
-    }
+        // Share the current page to a phone
+        let share_qr_action = gio::SimpleAction::new("share-qr", None);
+        share_qr_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.show_qr_code();
+                    }
+                }
+            }
+        ));
+        self.add_action(&share_qr_action);
 
-    fn show_about(&self) {
-        let about = libadwaita::AboutDialog::builder()
-            .application_name(config::APP_NAME)
-            .version(config::APP_VERSION)
-            .application_icon(config::APP_ID)
-            .developer_name("myyc")
-            .license_type(gtk4::License::Gpl30)
-            .website("https://github.com/myyc/qayeq")
-            .issue_url("https://github.com/myyc/qayeq/issues")
-            .comments("A GNOME web browser")
-            .build();
+        // Save as PDF action
+        let save_pdf_action = gio::SimpleAction::new("save-pdf", None);
+        save_pdf_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.save_as_pdf();
+                    }
+                }
+            }
+        ));
+        self.add_action(&save_pdf_action);
 
-        if let Some(window) = self.active_window() {
-            about.present(Some(&window));
+        // Show the active page's source in a new tab
+        let view_source_action = gio::SimpleAction::new("view-source", None);
+        view_source_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.view_source();
+                    }
+                }
+            }
+        ));
+        self.add_action(&view_source_action);
+
+        // Fullscreen the active window, or leave a page's fullscreen
+        let fullscreen_action = gio::SimpleAction::new("toggle-fullscreen", None);
+        fullscreen_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.toggle_fullscreen();
+                    }
+                }
+            }
+        ));
+        self.add_action(&fullscreen_action);
+
+        // Focus mode hides the toolbar and sidebar of the active window
+        let focus_mode_action = gio::SimpleAction::new("focus-mode", None);
+        focus_mode_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.toggle_focus_mode();
+                    }
+                }
+            }
+        ));
+        self.add_action(&focus_mode_action);
+
+        // Copied addresses leave out tracking parameters, per preferences
+        let copy_url_action = gio::SimpleAction::new("copy-page-url", None);
+        copy_url_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.copy_page_url();
+                    }
+                }
+            }
+        ));
+        self.add_action(&copy_url_action);
+
+        let copy_title_action = gio::SimpleAction::new("copy-page-title", None);
+        copy_title_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.copy_page_title();
+                    }
+                }
+            }
+        ));
+        self.add_action(&copy_title_action);
+
+        // Zoom is remembered per site
+        for (name, step) in [("zoom-in", 1), ("zoom-out", -1), ("zoom-reset", 0)] {
+            let action = gio::SimpleAction::new(name, None);
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move |_, _| {
+                    if let Some(window) = app.active_window() {
+                        if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                            qayeq_window.zoom(step);
+                        }
+                    }
+                }
+            ));
+            self.add_action(&action);
+        }
+
+        // Caret browsing applies to every tab; the tab manager updates open ones
+        let caret_browsing_action = gio::SimpleAction::new("toggle-caret-browsing", None);
+        caret_browsing_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                let settings = gio::Settings::new(config::APP_ID);
+                let enabled = !settings.boolean("caret-browsing");
+                if let Err(e) = settings.set_boolean("caret-browsing", enabled) {
+                    log::warn!("Failed to save caret browsing: {}", e);
+                    return;
+                }
+                app.show_toast(if enabled {
+                    "Caret browsing on"
+                } else {
+                    "Caret browsing off"
+                });
+            }
+        ));
+        self.add_action(&caret_browsing_action);
+
+        // Save the page with its resources as MHTML
+        let save_page_action = gio::SimpleAction::new("save-page", None);
+        save_page_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.save_page();
+                    }
+                }
+            }
+        ));
+        self.add_action(&save_page_action);
+
+        // Screenshot actions
+        for (action_name, region) in [
+            ("screenshot", webkit6::SnapshotRegion::Visible),
+            ("screenshot-full-page", webkit6::SnapshotRegion::FullDocument),
+        ] {
+            let action = gio::SimpleAction::new(action_name, None);
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move |_, _| {
+                    if let Some(window) = app.active_window() {
+                        if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                            qayeq_window.save_screenshot(region);
+                        }
+                    }
+                }
+            ));
+            self.add_action(&action);
+        }
+
+        // Request desktop/mobile site actions (toggle back to default when repeated)
+        for (action_name, mode) in [
+            ("request-desktop-site", UserAgentMode::Desktop),
+            ("request-mobile-site", UserAgentMode::Mobile),
+        ] {
+            let action = gio::SimpleAction::new(action_name, None);
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move |_, _| {
+                    if let Some(window) = app.active_window() {
+                        if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                            qayeq_window.toggle_site_user_agent(mode);
+                        }
+                    }
+                }
+            ));
+            self.add_action(&action);
+        }
+
+        // Export/import session actions
+        let export_session_action = gio::SimpleAction::new("export-session", None);
+        export_session_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.export_session();
+            }
+        ));
+        self.add_action(&export_session_action);
+
+        let import_session_action = gio::SimpleAction::new("import-session", None);
+        import_session_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.import_session();
+            }
+        ));
+        self.add_action(&import_session_action);
+
+        let save_session_as_action = gio::SimpleAction::new("save-session-as", None);
+        save_session_as_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.save_session_as();
+            }
+        ));
+        self.add_action(&save_session_as_action);
+
+        let open_session_action = gio::SimpleAction::new("open-session", None);
+        open_session_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.show_saved_sessions();
+            }
+        ));
+        self.add_action(&open_session_action);
+
+        // Enabled once activate has set a session aside
+        let restore_previous_action = gio::SimpleAction::new("restore-previous-session", None);
+        restore_previous_action.set_enabled(false);
+        restore_previous_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.restore_previous_session();
+            }
+        ));
+        self.add_action(&restore_previous_action);
+
+        let import_data_action = gio::SimpleAction::new("import-data", None);
+        import_data_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.import_browser_data();
+            }
+        ));
+        self.add_action(&import_data_action);
+
+        // Work offline: a toggle in the menu, applied to every profile
+        let work_offline_action = gio::SimpleAction::new_stateful("work-offline", None, &false.to_variant());
+        work_offline_action.connect_change_state(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |action, state| {
+                let Some(offline) = state.and_then(|s| s.get::<bool>()) else {
+                    return;
+                };
+                action.set_state(&offline.to_variant());
+                app.set_offline(offline);
+            }
+        ));
+        self.add_action(&work_offline_action);
+
+        // Fetch filter lists now, reporting the outcome in a toast
+        let update_filters_action = gio::SimpleAction::new("update-filters", None);
+        update_filters_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                let toast_app = app.clone();
+                app.update_filters(move |message| toast_app.show_toast(message));
+            }
+        ));
+        self.add_action(&update_filters_action);
+
+        // About action
+        let about_action = gio::SimpleAction::new("about", None);
+        about_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.show_about();
+            }
+        ));
+        self.add_action(&about_action);
+
+        // Toggle sidebar action (TAB key)
+        let toggle_sidebar_action = gio::SimpleAction::new("toggle-sidebar", None);
+        toggle_sidebar_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                        qayeq_window.toggle_sidebar();
+                    }
+                }
+            }
+        ));
+        self.add_action(&toggle_sidebar_action);
+
+        // Clear browsing data action
+        let clear_data_action = gio::SimpleAction::new("clear-data", None);
+        clear_data_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.show_clear_data_dialog();
+            }
+        ));
+        self.add_action(&clear_data_action);
+
+        // Preferences action (placeholder for now)
+        let preferences_action = gio::SimpleAction::new("preferences", None);
+        preferences_action.connect_activate(glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, _| {
+                app.show_preferences();
+            }
+        ));
+        self.add_action(&preferences_action);
+    }
+
+    fn setup_accels(&self) {
+        self.set_accels_for_action("app.quit", &["<Control>q"]);
+        self.set_accels_for_action("app.new-window", &["<Control><Shift>n"]);
+        self.set_accels_for_action("app.new-tab", &["<Control>t"]);
+        self.set_accels_for_action("app.close-tab", &["<Control>w"]);
+        self.set_accels_for_action("app.force-close-tab", &["<Control><Shift>w"]);
+        self.set_accels_for_action("app.reopen-tab", &["<Control><Shift>t"]);
+        self.set_accels_for_action("app.search-tabs", &["<Control><Shift>a"]);
+        self.set_accels_for_action("app.focus-url-bar", &["<Control>l"]);
+        self.set_accels_for_action("app.copy-page-url", &["<Control><Shift>l"]);
+        self.set_accels_for_action("app.copy-page-title", &["<Control><Alt>c"]);
+        self.set_accels_for_action("app.reload", &["<Control>r", "F5"]);
+        self.set_accels_for_action("app.go-back", &["<Alt>Left"]);
+        self.set_accels_for_action("app.go-forward", &["<Alt>Right"]);
+        self.set_accels_for_action("app.go-home", &["<Alt>Home"]);
+        self.set_accels_for_action("app.toggle-sidebar", &["F1"]);
+        self.set_accels_for_action("app.toggle-fullscreen", &["F11"]);
+        self.set_accels_for_action("app.focus-mode", &["<Shift>F11"]);
+        self.set_accels_for_action("app.preferences", &["<Control>comma"]);
+        self.set_accels_for_action("app.print", &["<Control>p"]);
+        self.set_accels_for_action("app.save-page", &["<Control>s"]);
+        self.set_accels_for_action("app.view-source", &["<Control>u"]);
+        self.set_accels_for_action("app.toggle-caret-browsing", &["F7"]);
+        self.set_accels_for_action("app.zoom-in", &["<Control>plus", "<Control>equal", "<Control>KP_Add"]);
+        self.set_accels_for_action("app.zoom-out", &["<Control>minus", "<Control>KP_Subtract"]);
+        self.set_accels_for_action("app.zoom-reset", &["<Control>0", "<Control>KP_0"]);
+        self.set_accels_for_action("app.toggle-devtools", &["F12", "<Control><Shift>i"]);
+
+        // Container shortcuts: Ctrl+Shift+1-4 for opening tabs in containers
+        // Matches order in config::DEFAULT_CONTAINERS
+        self.set_accels_for_action("app.new-tab-personal", &["<Control><Shift>1"]);
+        self.set_accels_for_action("app.new-tab-work", &["<Control><Shift>2"]);
+        self.set_accels_for_action("app.new-tab-shopping", &["<Control><Shift>3"]);
+        self.set_accels_for_action("app.new-tab-banking", &["<Control><Shift>4"]);
+    }
+
+    fn show_about(&self) {
+        let about = libadwaita::AboutDialog::builder()
+            .application_name(config::APP_NAME)
+            .version(config::APP_VERSION)
+            .application_icon(config::APP_ID)
+            .developer_name("myyc")
+            .license_type(gtk4::License::Gpl30)
+            .website("https://github.com/myyc/qayeq")
+            .issue_url("https://github.com/myyc/qayeq/issues")
+            .comments("A GNOME web browser")
+            .build();
+
+        if let Some(window) = self.active_window() {
+            about.present(Some(&window));
         }
     }
 
@@ -415,10 +1219,11 @@ impl QayeqApplication {
 
     fn clear_browsing_data(&self, options: ClearDataOptions) {
         log::info!(
-            "Clearing browsing data: history={}, cookies={}, cache={}, range={:?}",
+            "Clearing browsing data: history={}, cookies={}, cache={}, site storage={}, range={:?}",
             options.clear_history,
             options.clear_cookies,
             options.clear_cache,
+            options.clear_site_storage,
             options.time_range
         );
 
@@ -441,8 +1246,8 @@ impl QayeqApplication {
             }
         }
 
-        // Clear cookies and cache via WebKit
-        if options.clear_cookies || options.clear_cache {
+        // Clear cookies, cache and site storage via WebKit
+        if options.clear_cookies || options.clear_cache || options.clear_site_storage {
             if let Some(pm) = self.profile_manager() {
                 if let Some(profile) = pm.current_profile() {
                     let session = profile.network_session();
@@ -455,6 +1260,9 @@ impl QayeqApplication {
                             types |= webkit6::WebsiteDataTypes::DISK_CACHE;
                             types |= webkit6::WebsiteDataTypes::MEMORY_CACHE;
                         }
+                        if options.clear_site_storage {
+                            types |= clear_data::site_storage_types();
+                        }
 
                         // WebKit's clear method needs a timespan (seconds from now going back)
                         // For "all time", use a very large value
@@ -501,34 +1309,90 @@ impl QayeqApplication {
         self.imp().closed_windows.borrow_mut().push(closed_window);
     }
 
+    /// Recently closed windows, most recent first.
+    pub fn closed_windows(&self) -> std::cell::Ref<'_, RecentlyClosedWindows> {
+        self.imp().closed_windows.borrow()
+    }
+
     /// Reopen the most recently closed window.
     pub fn reopen_last_closed_window(&self) {
-        if let Some(closed_window) = self.imp().closed_windows.borrow_mut().pop() {
-            let window = QayeqWindow::new_empty(self);
+        self.reopen_closed_window_at(0);
+    }
 
-            // Restore tabs from the closed window
-            for closed_tab in &closed_window.tabs {
-                if let Ok(url) = url::Url::parse(&closed_tab.url) {
-                    window.new_tab_in_container(Some(&url), Some(&closed_tab.container));
-                }
+    /// Open links passed by other apps or on the command line, as tabs of the
+    /// active window or in a new window depending on the preference
+    fn open_external_links(&self, urls: &[url::Url]) {
+        if urls.is_empty() {
+            // Still bring the browser up, like activating it
+            match self.active_window() {
+                Some(window) => window.present(),
+                None => QayeqWindow::new_empty(self).present(),
             }
+            return;
+        }
 
-            window.present();
+        let in_new_window = gio::Settings::new(config::APP_ID).string("open-links-in") == "window";
+        let target = self
+            .active_window()
+            .and_downcast::<QayeqWindow>()
+            .filter(|_| !in_new_window);
+        match target {
+            Some(window) => {
+                // The last one opened is the active tab
+                for url in urls {
+                    window.new_tab(Some(url));
+                }
+                window.present();
+            }
+            None => {
+                let pages = urls.iter().map(|url| url.to_string());
+                QayeqWindow::new_with_session(self, SavedWindow::with_pages(pages)).present();
+            }
         }
+        log::info!("Opened {} links from another app", urls.len());
     }
 
-    /// Save all open windows to multi-window session.
-    /// Called immediately when session changes (tab added/closed, navigation).
-    pub fn save_all_windows(&self) {
-        let Some(pm) = self.profile_manager() else {
-            log::warn!("Cannot save windows: no profile manager");
+    /// Move the active window's current tab to another open window. A webview
+    /// can't be moved between windows, so the page is reopened in the target
+    /// window and the original tab closed without being recorded as recently closed.
+    fn move_tab_to_window(&self, window_id: u32) {
+        let Some(source) = self.active_window().and_downcast::<QayeqWindow>() else {
             return;
         };
-        let Some(profile) = pm.current_profile() else {
-            log::warn!("Cannot save windows: no current profile");
+        let Some(target) = self.window_by_id(window_id).and_downcast::<QayeqWindow>() else {
+            return;
+        };
+        if source == target {
+            return;
+        }
+        let Some((url, container, pinned)) = source.take_current_tab() else {
             return;
         };
+        target.add_moved_tab(url.as_ref(), &container, pinned);
+        target.present();
+    }
+
+    /// Reopen a specific recently closed window (0 is the most recent).
+    pub fn reopen_closed_window_at(&self, index: usize) {
+        let closed_window = self.imp().closed_windows.borrow_mut().reopen_at(index);
+        if let Some(closed_window) = closed_window {
+            let window = QayeqWindow::new_empty(self);
 
+            // Restore tabs from the closed window
+            for closed_tab in &closed_window.tabs {
+                match url::Url::parse(&closed_tab.url) {
+                    Ok(url) => window.new_tab_in_container(Some(&url), Some(&closed_tab.container)),
+                    Err(_) => window.new_tab_in_container(None, Some(&closed_tab.container)),
+                }
+            }
+            window.switch_to_tab_at(closed_window.active_tab_index);
+
+            window.present();
+        }
+    }
+
+    /// Collect the session data of all open windows
+    fn collect_session(&self) -> MultiWindowSession {
         let mut session = MultiWindowSession::new();
 
         // Iterate through all windows and collect their session data
@@ -541,7 +1405,503 @@ impl QayeqApplication {
                 }
             }
         }
+        session
+    }
+
+    /// Write all open windows to a user-chosen file.
+    /// A ".csv" name exports a flat list of tabs, anything else exports JSON.
+    fn export_session(&self) {
+        let window = self.active_window();
+        let dialog = gtk4::FileDialog::builder()
+            .title("Export Session")
+            .initial_name("qayeq-session.json")
+            .initial_folder(&gio::File::for_path(download::last_save_directory()))
+            .build();
+
+        dialog.save(window.as_ref(), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+
+                let session = app.collect_session();
+                let is_csv = path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+                let contents = if is_csv {
+                    Ok(session.to_csv())
+                } else {
+                    session.to_json()
+                };
+
+                let written = contents
+                    .map_err(std::io::Error::from)
+                    .and_then(|contents| std::fs::write(&path, contents));
+                let message = match written {
+                    Ok(()) => {
+                        log::info!("Exported {} windows to {:?}", session.windows.len(), path);
+                        format!("Exported {} windows", session.windows.len())
+                    }
+                    Err(e) => {
+                        log::error!("Failed to export session to {:?}: {}", path, e);
+                        "Couldn't export session".to_string()
+                    }
+                };
+                app.show_toast(&message);
+            }
+        ));
+    }
+
+    /// Ask for a name and save all open windows under it
+    fn save_session_as(&self) {
+        let Some(window) = self.active_window() else {
+            return;
+        };
+
+        let dialog = libadwaita::AlertDialog::new(
+            Some("Save Session"),
+            Some("Save the open windows and their tabs to reopen later."),
+        );
+        let entry = gtk4::Entry::new();
+        entry.set_placeholder_text(Some("Session name"));
+        entry.set_activates_default(true);
+        dialog.set_extra_child(Some(&entry));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("save", "Save");
+        dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+        dialog.set_response_enabled("save", false);
+        dialog.set_default_response(Some("save"));
+        dialog.set_close_response("cancel");
+
+        let dialog_weak = dialog.downgrade();
+        entry.connect_changed(move |entry| {
+            if let Some(dialog) = dialog_weak.upgrade() {
+                dialog.set_response_enabled("save", !entry.text().trim().is_empty());
+            }
+        });
+
+        dialog.connect_response(None, glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            #[weak]
+            entry,
+            move |_, response| {
+                let name = entry.text().trim().to_string();
+                if response != "save" || name.is_empty() {
+                    return;
+                }
+                let Some(profile) = app.profile_manager().and_then(|pm| pm.current_profile()) else {
+                    return;
+                };
+                if NamedSession::exists(profile.path(), &name) {
+                    app.confirm_replace_named_session(name);
+                } else {
+                    app.save_named_session(name);
+                }
+            }
+        ));
+
+        dialog.present(Some(&window));
+    }
+
+    fn confirm_replace_named_session(&self, name: String) {
+        let Some(window) = self.active_window() else {
+            return;
+        };
+        let dialog = libadwaita::AlertDialog::new(
+            Some(&format!("Replace \"{}\"?", name)),
+            Some("A session with this name is already saved. Saving replaces it with the open windows."),
+        );
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("replace", "Replace");
+        dialog.set_response_appearance("replace", libadwaita::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+        dialog.connect_response(None, glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, response| {
+                if response == "replace" {
+                    app.save_named_session(name.clone());
+                }
+            }
+        ));
+        dialog.present(Some(&window));
+    }
+
+    fn save_named_session(&self, name: String) {
+        let Some(profile) = self.profile_manager().and_then(|pm| pm.current_profile()) else {
+            return;
+        };
+        let named = NamedSession {
+            name,
+            session: self.collect_session(),
+        };
+        match named.save(profile.path()) {
+            Ok(()) => {
+                log::info!("Saved {} windows as session '{}'", named.session.windows.len(), named.name);
+                self.show_toast(&format!("Saved session \"{}\"", glib::markup_escape_text(&named.name)));
+            }
+            Err(e) => {
+                log::error!("Failed to save session '{}': {}", named.name, e);
+                self.show_toast("Couldn't save session");
+            }
+        }
+    }
+
+    /// List the sessions saved by name, opening the picked one in new windows
+    fn show_saved_sessions(&self) {
+        let (Some(window), Some(profile)) = (
+            self.active_window(),
+            self.profile_manager().and_then(|pm| pm.current_profile()),
+        ) else {
+            return;
+        };
+        let dialog = SessionsDialog::new(profile.path(), glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |mut named| {
+                let skipped = named.session.retain_valid_tabs();
+                log::info!(
+                    "Opening session '{}' with {} windows ({} tabs skipped)",
+                    named.name,
+                    named.session.windows.len(),
+                    skipped
+                );
+                for saved_window in named.session.windows {
+                    let window = QayeqWindow::new_with_session(&app, saved_window);
+                    window.present();
+                }
+            }
+        ));
+        dialog.present(&window);
+    }
+
+    /// Open a previously exported session, one new window per saved window
+    fn import_session(&self) {
+        let window = self.active_window();
+        let dialog = gtk4::FileDialog::builder()
+            .title("Import Session")
+            .initial_folder(&gio::File::for_path(download::last_save_directory()))
+            .build();
+
+        dialog.open(window.as_ref(), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+
+                let session = std::fs::read_to_string(&path)
+                    .and_then(|contents| MultiWindowSession::from_export(&contents));
+                let mut session = match session {
+                    Ok(session) => session,
+                    Err(e) => {
+                        log::error!("Failed to import session from {:?}: {}", path, e);
+                        app.show_toast("Couldn't read session file");
+                        return;
+                    }
+                };
+
+                let skipped = session.retain_valid_tabs();
+                let window_count = session.windows.len();
+                for saved_window in session.windows {
+                    let window = QayeqWindow::new_with_session(&app, saved_window);
+                    window.present();
+                }
+
+                log::info!("Imported {} windows ({} tabs skipped)", window_count, skipped);
+                let message = if skipped > 0 {
+                    format!("Imported {} windows, skipped {} invalid tabs", window_count, skipped)
+                } else {
+                    format!("Imported {} windows", window_count)
+                };
+                app.show_toast(&message);
+            }
+        ));
+    }
+
+    /// After a crash, offer the set-aside session on the first window shown
+    fn offer_crash_restore(&self, window: &QayeqWindow) {
+        if self.imp().previous_run_crashed.take() && self.has_previous_session() {
+            window.set_restore_banner_revealed(true);
+        }
+    }
+
+    /// Reopen the last session's windows if restoring is enabled, or set it aside.
+    /// Returns whether any window was opened.
+    fn restore_startup_session(&self) -> bool {
+        let Some(profile) = self.profile_manager().and_then(|pm| pm.current_profile()) else {
+            return false;
+        };
+        // Read on every launch, so a change made before the last exit applies now.
+        // After a crash, restoring is offered instead, in case the session caused it.
+        let settings = gio::Settings::new(config::APP_ID);
+        let restore = StartupMode::current(&settings) == StartupMode::RestoreSession;
+        if !restore || self.imp().previous_run_crashed.get() {
+            // Keep it for "Restore Previous Session" rather than saving over it
+            if let Err(e) = MultiWindowSession::set_aside(profile.path()) {
+                log::warn!("Failed to set previous session aside: {}", e);
+            }
+            self.update_restore_previous_action();
+            return false;
+        }
+
+        match MultiWindowSession::load(profile.path()) {
+            Ok(session) if session.has_windows() => {
+                log::info!("Restoring {} windows from session", session.windows.len());
+                for saved_window in session.windows {
+                    let window = QayeqWindow::new_with_session(self, saved_window);
+                    window.present();
+                }
+                true
+            }
+            Ok(_) => {
+                log::debug!("No windows in session to restore");
+                false
+            }
+            Err(e) => {
+                log::warn!("Failed to load session: {}", e);
+                false
+            }
+        }
+    }
+
+    /// The pages the first window opens with when the session isn't restored:
+    /// the homepage or the startup pages, depending on the startup mode
+    fn startup_pages(&self) -> Vec<String> {
+        let settings = gio::Settings::new(config::APP_ID);
+        let pages: Vec<String> = match StartupMode::current(&settings) {
+            StartupMode::RestoreSession => Vec::new(),
+            StartupMode::Homepage => vec![settings.string("homepage").to_string()],
+            StartupMode::StartupPages => settings.strv("startup-pages").iter().map(|p| p.to_string()).collect(),
+        };
+        pages
+            .iter()
+            .map(|page| page.trim())
+            .filter(|page| !page.is_empty() && *page != "about:blank")
+            .filter_map(|page| match url::Url::parse(page) {
+                Ok(url) => Some(url.to_string()),
+                // Bare domains such as "example.com"
+                Err(_) => match url_bar::parse_input(page) {
+                    url_bar::UrlBarInput::Url(url) => Some(url.to_string()),
+                    url_bar::UrlBarInput::Search(_) => {
+                        log::warn!("Ignoring invalid startup page: {}", page);
+                        None
+                    }
+                },
+            })
+            .collect()
+    }
+
+    /// Reopen the windows of the session that wasn't restored at startup
+    fn restore_previous_session(&self) {
+        let Some(profile) = self.profile_manager().and_then(|pm| pm.current_profile()) else {
+            return;
+        };
+        let session = MultiWindowSession::take_previous(profile.path());
+        self.update_restore_previous_action();
+        match session {
+            Ok(session) if session.has_windows() => {
+                log::info!("Restoring {} windows from the previous session", session.windows.len());
+                for saved_window in session.windows {
+                    let window = QayeqWindow::new_with_session(self, saved_window);
+                    window.present();
+                }
+            }
+            Ok(_) => self.show_toast("The previous session had no tabs"),
+            Err(e) => {
+                log::error!("Failed to restore previous session: {}", e);
+                self.show_toast("Couldn't read the previous session");
+            }
+        }
+    }
+
+    fn has_previous_session(&self) -> bool {
+        self.profile_manager()
+            .and_then(|pm| pm.current_profile())
+            .is_some_and(|profile| MultiWindowSession::has_previous(profile.path()))
+    }
+
+    fn update_restore_previous_action(&self) {
+        let has_previous = self.has_previous_session();
+        if let Some(action) = self.lookup_action("restore-previous-session").and_downcast::<gio::SimpleAction>() {
+            action.set_enabled(has_previous);
+        }
+        if !has_previous {
+            for window in self.windows() {
+                if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                    qayeq_window.set_restore_banner_revealed(false);
+                }
+            }
+        }
+    }
+
+    /// Cut network access in every profile, or restore it, and show the state in each window
+    fn set_offline(&self, offline: bool) {
+        if let Some(pm) = self.profile_manager() {
+            pm.set_offline(offline);
+        }
+        for window in self.windows() {
+            if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                qayeq_window.set_offline_indicator(offline);
+            }
+        }
+    }
+
+    /// Fetch the filter lists now and pass a message describing the outcome to `done`,
+    /// escaped for use as toast or row markup.
+    /// `app.update-filters` is disabled until the update finishes, so runs don't overlap.
+    pub fn update_filters<F: FnOnce(&str) + 'static>(&self, done: F) {
+        let action = self
+            .lookup_action("update-filters")
+            .and_downcast::<gio::SimpleAction>();
+        if action.as_ref().is_some_and(|action| !action.is_enabled()) {
+            done("Filter lists are already being updated");
+            return;
+        }
+        if let Some(action) = &action {
+            action.set_enabled(false);
+        }
+
+        let app = self.clone();
+        glib::spawn_future_local(async move {
+            let result = match app.content_filter_manager() {
+                Some(cfm) => cfm.update_filters().await,
+                None => Err(ScriptletError::CacheError("Ad blocking isn't set up".into())),
+            };
+            if let Some(action) = action {
+                action.set_enabled(true);
+            }
+            let message = match result {
+                Ok(true) => "Filter lists updated".to_string(),
+                Ok(false) => "Filter lists are already up to date".to_string(),
+                Err(e) => {
+                    log::warn!("Failed to update filter lists: {}", e);
+                    // Errors can quote the server
+                    format!("Couldn't update filter lists: {}", glib::markup_escape_text(&e.to_string()))
+                }
+            };
+            done(&message);
+        });
+    }
+
+    /// Ask which installed browser to import history from, then import it
+    /// into the default container in the background
+    fn import_browser_data(&self) {
+        let sources: Vec<_> = ImportSource::ALL
+            .into_iter()
+            .filter_map(|source| Some((source, source.locate()?)))
+            .collect();
+        if sources.is_empty() {
+            self.show_toast("No Firefox or Chromium history found");
+            return;
+        }
+
+        let dialog = libadwaita::AlertDialog::new(
+            Some("Import History"),
+            Some("Pages visited in the chosen browser are added to your history. It can stay open while importing."),
+        );
+        dialog.add_response("cancel", "Cancel");
+        for (source, _) in &sources {
+            dialog.add_response(source.id(), source.label());
+        }
+        dialog.set_response_appearance(sources[0].0.id(), libadwaita::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some(sources[0].0.id()));
+        dialog.set_close_response("cancel");
+
+        dialog.connect_response(None, glib::clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, response| {
+                let Some((source, path)) = sources
+                    .iter()
+                    .find(|(source, _)| source.id() == response)
+                    .cloned()
+                else {
+                    return;
+                };
+
+                glib::spawn_future_local(async move {
+                    let read = gio::spawn_blocking(move || source.read_history(&path)).await;
+                    let visits = match read {
+                        Ok(Ok(visits)) => visits,
+                        Ok(Err(e)) => {
+                            log::error!("Failed to import history from {}: {}", source.label(), e);
+                            app.show_toast(&format!("Couldn't read {} history", source.label()));
+                            return;
+                        }
+                        Err(_) => return,
+                    };
+
+                    let Some(profile) = app.profile_manager().and_then(|pm| pm.current_profile()) else {
+                        return;
+                    };
+                    let count = visits.len();
+                    profile.history().import(visits);
+                    app.show_toast(&format!("Imported {} pages from {}", count, source.label()));
+                });
+            }
+        ));
+        dialog.present(self.active_window().as_ref());
+    }
+
+    /// Show a toast in the active window
+    fn show_toast(&self, message: &str) {
+        if let Some(window) = self.active_window() {
+            if let Some(qayeq_window) = window.downcast_ref::<QayeqWindow>() {
+                qayeq_window.show_toast(message);
+            }
+        }
+    }
+
+    /// Schedule a session save. Requests made while one is pending are coalesced;
+    /// the save collects the windows when it runs, so it always writes the latest state.
+    pub fn request_session_save(&self) {
+        let imp = self.imp();
+        if imp.session_save_source.borrow().is_some() {
+            return;
+        }
+
+        let source = glib::timeout_add_seconds_local_once(
+            config::SESSION_SAVE_DELAY_SECS,
+            glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move || {
+                    // Clear first: the source is gone once this returns
+                    app.imp().session_save_source.take();
+                    app.save_all_windows();
+                }
+            ),
+        );
+        imp.session_save_source.replace(Some(source));
+    }
+
+    /// Cancel any pending session save and write the session now
+    pub fn flush_session_save(&self) {
+        if let Some(source) = self.imp().session_save_source.take() {
+            source.remove();
+        }
+        self.save_all_windows();
+    }
+
+    /// Save all open windows to multi-window session.
+    /// Session changes go through request_session_save; this writes immediately.
+    pub fn save_all_windows(&self) {
+        let Some(pm) = self.profile_manager() else {
+            log::warn!("Cannot save windows: no profile manager");
+            return;
+        };
+        let Some(profile) = pm.current_profile() else {
+            log::warn!("Cannot save windows: no current profile");
+            return;
+        };
 
+        let session = self.collect_session();
         if session.has_windows() {
             if let Err(e) = session.save(profile.path()) {
                 log::error!("Failed to save multi-window session: {}", e);
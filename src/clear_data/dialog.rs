@@ -52,12 +52,14 @@ pub struct ClearDataOptions {
     pub clear_history: bool,
     pub clear_cookies: bool,
     pub clear_cache: bool,
+    /// Local and session storage, databases, service workers and their caches
+    pub clear_site_storage: bool,
 }
 
 impl ClearDataOptions {
     /// Check if any data type is selected for clearing.
     pub fn has_selection(&self) -> bool {
-        self.clear_history || self.clear_cookies || self.clear_cache
+        self.clear_history || self.clear_cookies || self.clear_cache || self.clear_site_storage
     }
 }
 
@@ -68,6 +70,7 @@ pub struct ClearDataDialog {
     history_check: gtk4::CheckButton,
     cookies_check: gtk4::CheckButton,
     cache_check: gtk4::CheckButton,
+    site_storage_check: gtk4::CheckButton,
 }
 
 impl ClearDataDialog {
@@ -116,7 +119,7 @@ impl ClearDataDialog {
         history_check.set_active(true);
         checks_box.append(&history_check);
 
-        let cookies_check = gtk4::CheckButton::with_label("Cookies and site data");
+        let cookies_check = gtk4::CheckButton::with_label("Cookies");
         cookies_check.set_active(false);
         checks_box.append(&cookies_check);
 
@@ -124,6 +127,10 @@ impl ClearDataDialog {
         cache_check.set_active(false);
         checks_box.append(&cache_check);
 
+        let site_storage_check = gtk4::CheckButton::with_label("Site storage and offline data");
+        site_storage_check.set_active(false);
+        checks_box.append(&site_storage_check);
+
         content.append(&checks_box);
 
         dialog.set_extra_child(Some(&content));
@@ -141,6 +148,7 @@ impl ClearDataDialog {
             history_check,
             cookies_check,
             cache_check,
+            site_storage_check,
         }
     }
 
@@ -153,6 +161,7 @@ impl ClearDataDialog {
         let history_check = self.history_check.clone();
         let cookies_check = self.cookies_check.clone();
         let cache_check = self.cache_check.clone();
+        let site_storage_check = self.site_storage_check.clone();
         let callback = Rc::new(RefCell::new(Some(callback)));
 
         self.dialog.connect_response(None, move |_, response| {
@@ -162,6 +171,7 @@ impl ClearDataDialog {
                     clear_history: history_check.is_active(),
                     clear_cookies: cookies_check.is_active(),
                     clear_cache: cache_check.is_active(),
+                    clear_site_storage: site_storage_check.is_active(),
                 };
 
                 if options.has_selection() {
@@ -1,5 +1,7 @@
 //! Clear browsing data dialog and functionality.
 
 mod dialog;
+mod site;
 
 pub use dialog::{ClearDataDialog, ClearDataOptions};
+pub use site::{clear_site_data, site_storage_types};
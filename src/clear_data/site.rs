@@ -0,0 +1,68 @@
+//! Clearing the data stored by a single site.
+
+use gtk4::{gio, glib};
+use webkit6::{NetworkSession, WebsiteData, WebsiteDataTypes};
+
+/// Data that sites store for themselves through web APIs
+pub fn site_storage_types() -> WebsiteDataTypes {
+    WebsiteDataTypes::LOCAL_STORAGE
+        | WebsiteDataTypes::SESSION_STORAGE
+        | WebsiteDataTypes::INDEXEDDB_DATABASES
+        | WebsiteDataTypes::SERVICE_WORKER_REGISTRATIONS
+        | WebsiteDataTypes::DOM_CACHE
+        | WebsiteDataTypes::OFFLINE_APPLICATION_CACHE
+}
+
+/// Remove the cookies and storage of a host and its parent site from a session.
+/// The callback gets whether there was anything to remove.
+pub fn clear_site_data<F>(session: &NetworkSession, host: &str, callback: F)
+where
+    F: FnOnce(Result<bool, glib::Error>) + Send + 'static,
+{
+    let Some(data_manager) = session.website_data_manager() else {
+        callback(Ok(false));
+        return;
+    };
+    let types = WebsiteDataTypes::COOKIES | site_storage_types();
+    let host = host.to_string();
+    let manager = data_manager.clone();
+    data_manager.fetch(types, gio::Cancellable::NONE, move |result| {
+        let records = match result {
+            Ok(records) => records,
+            Err(e) => return callback(Err(e)),
+        };
+        // Records are named after the registrable domain, e.g. "example.com" for "www.example.com"
+        let matching: Vec<&WebsiteData> = records
+            .iter()
+            .filter(|record| record.name().is_some_and(|name| host_belongs_to(&host, &name)))
+            .collect();
+        if matching.is_empty() {
+            return callback(Ok(false));
+        }
+        log::info!("Removing {} website data records for {}", matching.len(), host);
+        manager.remove(types, &matching, gio::Cancellable::NONE, move |result| {
+            callback(result.map(|_| true));
+        });
+    });
+}
+
+/// Whether `host` is `site` or one of its subdomains
+fn host_belongs_to(host: &str, site: &str) -> bool {
+    host == site
+        || host
+            .strip_suffix(site)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_belongs_to_site() {
+        assert!(host_belongs_to("example.com", "example.com"));
+        assert!(host_belongs_to("www.example.com", "example.com"));
+        assert!(!host_belongs_to("badexample.com", "example.com"));
+        assert!(!host_belongs_to("example.com", "www.example.com"));
+    }
+}
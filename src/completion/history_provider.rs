@@ -3,6 +3,8 @@
 use crate::completion::provider::CompletionItem;
 use crate::config;
 use crate::history::HistoryDatabase;
+use gtk4::gdk::Texture;
+use gtk4::glib;
 use url::Url;
 
 /// Provide completions from browsing history.
@@ -42,10 +44,11 @@ pub fn get_history_completions(
             entries
                 .into_iter()
                 .map(|entry| {
+                    let favicon = host_favicon(history, &entry.url);
                     CompletionItem::from_history(
                         entry.title.unwrap_or_else(|| entry.url.clone()),
                         entry.url,
-                        None, // TODO: Add favicon support
+                        favicon,
                         entry.visit_count as u32,
                     )
                 })
@@ -69,3 +72,12 @@ fn url_host_starts_with(url_str: &str, prefix: &str) -> bool {
     }
     false
 }
+
+/// Decode the favicon stored for a URL's host, if there is one
+fn host_favicon(history: &HistoryDatabase, url_str: &str) -> Option<Texture> {
+    let url = Url::parse(url_str).ok()?;
+    let png = history.favicon(url.host_str()?).ok()??;
+    Texture::from_bytes(&glib::Bytes::from_owned(png))
+        .map_err(|e| log::debug!("Ignoring unreadable favicon for {}: {}", url_str, e))
+        .ok()
+}
@@ -1,6 +1,6 @@
 //! Completion item types and providers.
 
-use gtk4::gdk_pixbuf::Pixbuf;
+use gtk4::gdk::Texture;
 
 /// Type of completion item.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,14 +21,14 @@ pub struct CompletionItem {
     /// URL for history items, None for search suggestions.
     pub url: Option<String>,
     /// Favicon for history items.
-    pub favicon: Option<Pixbuf>,
+    pub favicon: Option<Texture>,
     /// Visit count for history items (for sorting).
     pub visit_count: u32,
 }
 
 impl CompletionItem {
     /// Create a new history completion item.
-    pub fn from_history(title: String, url: String, favicon: Option<Pixbuf>, visit_count: u32) -> Self {
+    pub fn from_history(title: String, url: String, favicon: Option<Texture>, visit_count: u32) -> Self {
         Self {
             completion_type: CompletionType::History,
             title,
@@ -79,8 +79,7 @@ impl CompletionRow {
 
         // Set icon - use favicon if available, otherwise type icon
         if let Some(ref favicon) = item.favicon {
-            let texture = gtk4::gdk::Texture::for_pixbuf(favicon);
-            imp.icon.set_paintable(Some(&texture));
+            imp.icon.set_paintable(Some(favicon));
         } else {
             let icon_name = match item.completion_type {
                 CompletionType::History => "globe-symbolic",
@@ -21,7 +21,10 @@ pub const DEFAULT_SEARCH_URL: &str = "https://duckduckgo.com/?q={}";
 pub const DEFAULT_HOMEPAGE: &str = "about:blank";
 
 /// Default new tab page
-pub const NEW_TAB_URL: &str = "about:blank";
+pub const NEW_TAB_URL: &str = "qayeq://newtab";
+
+/// Ad blocking statistics page
+pub const FILTER_STATS_URL: &str = "qayeq://stats";
 
 /// Profile directory name
 pub const PROFILES_DIR: &str = "profiles";
@@ -32,6 +35,9 @@ pub const DEFAULT_PROFILE: &str = "default";
 /// History database filename
 pub const HISTORY_DB: &str = "history.db";
 
+/// Largest favicon kept in the history database (PNG bytes); bigger ones are skipped
+pub const MAX_FAVICON_BYTES: usize = 32 * 1024;
+
 /// Minimum sidebar width in pixels
 pub const MIN_SIDEBAR_WIDTH: i32 = 180;
 
@@ -57,6 +63,81 @@ pub const SEARCH_ENGINES: &[(&str, &str, &str)] = &[
 /// DuckDuckGo autocomplete API URL
 pub const DUCKDUCKGO_SUGGESTIONS_URL: &str = "https://duckduckgo.com/ac/?q={}&type=list";
 
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Tab list layouts: (id, display_name)
+pub const TAB_LAYOUTS: &[(&str, &str)] = &[
+    ("vertical", "Vertical Sidebar"),
+    ("horizontal", "Horizontal Strip"),
+];
+
+/// Protection of pinned tabs from the close button and Ctrl+W: (id, display_name)
+pub const PINNED_TAB_CLOSE_MODES: &[(&str, &str)] = &[
+    ("close", "Close Right Away"),
+    ("confirm", "Ask First"),
+    ("shortcut", "Only With Ctrl+Shift+W"),
+];
+
+/// Handling of HTTP resources on HTTPS pages: (id, display_name).
+/// WebKit always blocks insecure scripts, so there is no "allow" policy.
+pub const MIXED_CONTENT_POLICIES: &[(&str, &str)] = &[
+    ("warn", "Warn"),
+    ("block", "Block"),
+];
+
+/// Startup modes: (id, display_name), in the order of the preferences row
+pub const STARTUP_MODES: &[(&str, &str)] = &[
+    ("session", "Restore Previous Session"),
+    ("homepage", "Open Homepage"),
+    ("pages", "Open Startup Pages"),
+];
+
+/// Where links from other apps open: (id, display_name)
+pub const OPEN_LINKS_TARGETS: &[(&str, &str)] = &[
+    ("tab", "New Tab"),
+    ("window", "New Window"),
+];
+
+/// Referrer policies: (id, display_name). "full" leaves WebKit's default in place.
+pub const REFERRER_POLICIES: &[(&str, &str)] = &[
+    ("full", "Full"),
+    ("origin", "Origin Only"),
+    ("none", "None"),
+];
+
+/// When tracking parameters are stripped from addresses: (id, display_name)
+pub const STRIP_TRACKING_MODES: &[(&str, &str)] = &[
+    ("never", "Never"),
+    ("copy", "When Copying"),
+    ("always", "Always"),
+];
+
+/// Hardware acceleration policies: (id, display_name).
+/// WebKitGTK 6 dropped the on-demand policy, leaving always and never.
+pub const HARDWARE_ACCELERATION_POLICIES: &[(&str, &str)] = &[
+    ("always", "Always"),
+    ("never", "Never"),
+];
+
+/// Autoplay policies: (id, display_name)
+pub const AUTOPLAY_POLICIES: &[(&str, &str)] = &[
+    ("allow", "Allow"),
+    ("block-audio", "Block Audio"),
+    ("block", "Block All"),
+];
+
+/// How often filter lists are updated: (id, display_name)
+pub const FILTER_UPDATE_INTERVALS: &[(&str, &str)] = &[
+    ("never", "Never"),
+    ("daily", "Daily"),
+    ("weekly", "Weekly"),
+];
+
+/// Zoom levels stepped through by zoom in and out, 1.0 being 100%
+pub const ZOOM_LEVELS: &[f64] = &[0.3, 0.5, 0.67, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0];
+
 // ============================================================================
 // URL Completion
 // ============================================================================
@@ -88,3 +169,20 @@ pub const DEFAULT_CONTAINERS: &[(&str, &str, &str)] = &[
 
 /// Default container for new tabs
 pub const DEFAULT_CONTAINER: &str = "personal";
+
+// ============================================================================
+// Session
+// ============================================================================
+
+/// Longest a session change waits before it's written to disk; changes within
+/// this window are coalesced into a single save (seconds)
+pub const SESSION_SAVE_DELAY_SECS: u32 = 2;
+
+/// Interval for capturing tab scroll offsets for session restore (seconds)
+pub const SCROLL_CAPTURE_INTERVAL_SECS: u32 = 5;
+
+/// Interval for checking whether idle background tabs should hibernate (seconds)
+pub const HIBERNATION_CHECK_INTERVAL_SECS: u32 = 60;
+
+/// Interval for checking containers' disk caches against the size limit (seconds)
+pub const CACHE_LIMIT_CHECK_INTERVAL_SECS: u32 = 10 * 60;
@@ -0,0 +1,115 @@
+//! Per-site exceptions for injected content.
+//!
+//! Sites on an allowlist are excluded from a feature by turning the hosts
+//! into WebKit URL patterns for the block list of a user script or stylesheet.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A persisted set of hosts excluded from a feature
+#[derive(Debug, Default)]
+pub struct SiteAllowlist {
+    path: Option<PathBuf>,
+    hosts: BTreeSet<String>,
+}
+
+impl SiteAllowlist {
+    /// Load the allowlist stored at `path` (empty if it doesn't exist yet)
+    pub fn load(path: &Path) -> Self {
+        let hosts = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            path: Some(path.to_path_buf()),
+            hosts,
+        }
+    }
+
+    /// Whether a host, or a domain it belongs to, is on the list
+    pub fn contains(&self, host: &str) -> bool {
+        let host = normalize(host);
+        self.hosts
+            .iter()
+            .any(|entry| host == *entry || host.ends_with(&format!(".{}", entry)))
+    }
+
+    /// Add or remove a host and persist the change.
+    /// Returns true if the list changed.
+    pub fn set(&mut self, host: &str, listed: bool) -> bool {
+        let host = normalize(host);
+        let changed = if listed {
+            self.hosts.insert(host)
+        } else {
+            self.hosts.remove(&host)
+        };
+
+        if changed {
+            if let Err(e) = self.save() {
+                log::warn!("Failed to save site allowlist: {}", e);
+            }
+        }
+        changed
+    }
+
+    /// WebKit URL patterns matching every listed host and its subdomains
+    pub fn url_patterns(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .flat_map(|host| [format!("*://{}/*", host), format!("*://*.{}/*", host)])
+            .collect()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.hosts)?;
+        fs::write(path, contents)
+    }
+}
+
+/// Lowercase and drop a leading "www." so both forms share one entry
+fn normalize(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host.strip_prefix("www.").map(str::to_string).unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_matches_subdomains() {
+        let mut allowlist = SiteAllowlist::default();
+        allowlist.set("www.Example.com", true);
+
+        assert!(allowlist.contains("example.com"));
+        assert!(allowlist.contains("docs.example.com"));
+        assert!(!allowlist.contains("notexample.com"));
+        assert_eq!(
+            allowlist.url_patterns(),
+            ["*://example.com/*", "*://*.example.com/*"]
+        );
+    }
+
+    #[test]
+    fn test_persists_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("allowlist.json");
+
+        let mut allowlist = SiteAllowlist::load(&path);
+        assert!(allowlist.set("example.com", true));
+        assert!(!allowlist.set("example.com", true));
+        allowlist.set("example.org", true);
+        allowlist.set("example.com", false);
+
+        let reloaded = SiteAllowlist::load(&path);
+        assert!(reloaded.contains("example.org"));
+        assert!(!reloaded.contains("example.com"));
+    }
+}
@@ -0,0 +1,337 @@
+//! One WebKit content manager per webview.
+//!
+//! A webview keeps the content manager it was created with, so anything that
+//! applies to one container or one tab only needs a content manager of its
+//! own. Everything else is added to every content manager through
+//! [`ContentManagers`], and replayed to the ones made for webviews opened later.
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use webkit6::javascriptcore;
+use webkit6::{UserContentFilter, UserContentManager, UserScript, UserStyleSheet};
+
+type MessageCallback = Rc<dyn Fn(&javascriptcore::Value)>;
+
+/// A script message handler, registered on every content manager
+struct MessageHandler {
+    name: String,
+    world: Option<String>,
+    callback: MessageCallback,
+}
+
+/// Where content filters are added: a webview's content manager, or a fake in tests
+trait FilterTarget: PartialEq {
+    type Filter: Clone + PartialEq;
+
+    fn add_filter(&self, filter: &Self::Filter);
+    fn remove_filter(&self, filter: &Self::Filter);
+}
+
+impl FilterTarget for UserContentManager {
+    type Filter = UserContentFilter;
+
+    fn add_filter(&self, filter: &UserContentFilter) {
+        UserContentManager::add_filter(self, filter);
+    }
+
+    fn remove_filter(&self, filter: &UserContentFilter) {
+        UserContentManager::remove_filter(self, filter);
+    }
+}
+
+/// The content filters in use, and the content managers kept out of some of them
+struct Filters<M: FilterTarget> {
+    filters: Vec<M::Filter>,
+    exemptions: Vec<(M, M::Filter)>,
+}
+
+impl<M: FilterTarget> Default for Filters<M> {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            exemptions: Vec::new(),
+        }
+    }
+}
+
+impl<M: FilterTarget> Filters<M> {
+    fn is_exempt(&self, manager: &M, filter: &M::Filter) -> bool {
+        self.exemptions.iter().any(|(m, f)| m == manager && f == filter)
+    }
+
+    /// Add the filters to a new content manager
+    fn apply_to(&self, manager: &M) {
+        for filter in &self.filters {
+            if !self.is_exempt(manager, filter) {
+                manager.add_filter(filter);
+            }
+        }
+    }
+
+    fn add(&mut self, managers: &[M], filter: &M::Filter) {
+        for manager in managers {
+            if !self.is_exempt(manager, filter) {
+                manager.add_filter(filter);
+            }
+        }
+        self.filters.push(filter.clone());
+    }
+
+    fn remove(&mut self, managers: &[M], filter: &M::Filter) {
+        self.filters.retain(|added| added != filter);
+        for manager in managers {
+            manager.remove_filter(filter);
+        }
+    }
+
+    fn remove_all(&mut self, managers: &[M]) {
+        for filter in std::mem::take(&mut self.filters) {
+            for manager in managers {
+                manager.remove_filter(&filter);
+            }
+        }
+    }
+
+    /// Keep a filter out of one content manager, or put it back there
+    fn set_exempt(&mut self, manager: M, filter: &M::Filter, exempt: bool) {
+        let in_use = self.filters.contains(filter);
+        if exempt {
+            if self.is_exempt(&manager, filter) {
+                return;
+            }
+            if in_use {
+                manager.remove_filter(filter);
+            }
+            self.exemptions.push((manager, filter.clone()));
+        } else if let Some(index) = self.exemptions.iter().position(|(m, f)| *m == manager && f == filter) {
+            self.exemptions.remove(index);
+            if in_use {
+                manager.add_filter(filter);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Content manager of each open webview, with its container
+    managers: Vec<(String, glib::WeakRef<UserContentManager>)>,
+    /// Scripts and the container they're limited to, if any
+    scripts: Vec<(UserScript, Option<String>)>,
+    style_sheets: Vec<UserStyleSheet>,
+    filters: Filters<UserContentManager>,
+    handlers: Vec<MessageHandler>,
+}
+
+impl Inner {
+    /// Content managers whose webviews are still open, with their containers
+    fn managers(&self) -> Vec<(String, UserContentManager)> {
+        self.managers
+            .iter()
+            .filter_map(|(container, ucm)| Some((container.clone(), ucm.upgrade()?)))
+            .collect()
+    }
+
+    fn all_managers(&self) -> Vec<UserContentManager> {
+        self.managers().into_iter().map(|(_, ucm)| ucm).collect()
+    }
+}
+
+/// The content managers of every webview, kept in step
+#[derive(Clone, Default)]
+pub struct ContentManagers(Rc<RefCell<Inner>>);
+
+impl ContentManagers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A content manager for a new webview in a container, with everything
+    /// added so far
+    pub fn for_webview(&self, container: &str) -> UserContentManager {
+        let mut inner = self.0.borrow_mut();
+        inner.managers.retain(|(_, ucm)| ucm.upgrade().is_some());
+
+        let ucm = UserContentManager::new();
+        for handler in &inner.handlers {
+            register_handler(&ucm, handler);
+        }
+        for (script, only) in &inner.scripts {
+            if only.as_deref().is_none_or(|only| only == container) {
+                ucm.add_script(script);
+            }
+        }
+        for style_sheet in &inner.style_sheets {
+            ucm.add_style_sheet(style_sheet);
+        }
+        inner.filters.apply_to(&ucm);
+        inner.managers.push((container.to_string(), ucm.downgrade()));
+        ucm
+    }
+
+    /// Add a script to every container
+    pub fn add_script(&self, script: &UserScript) {
+        let mut inner = self.0.borrow_mut();
+        for ucm in inner.all_managers() {
+            ucm.add_script(script);
+        }
+        inner.scripts.push((script.clone(), None));
+    }
+
+    /// Add a script to one container only
+    pub fn add_container_script(&self, container: &str, script: &UserScript) {
+        let mut inner = self.0.borrow_mut();
+        for (_, ucm) in inner.managers().iter().filter(|(c, _)| c == container) {
+            ucm.add_script(script);
+        }
+        inner.scripts.push((script.clone(), Some(container.to_string())));
+    }
+
+    /// Remove a script, from every container or the one it was added to
+    pub fn remove_script(&self, script: &UserScript) {
+        let mut inner = self.0.borrow_mut();
+        let Some(index) = inner.scripts.iter().position(|(added, _)| added == script) else {
+            return;
+        };
+        let (_, only) = inner.scripts.remove(index);
+        for (container, ucm) in inner.managers() {
+            if only.as_ref().is_none_or(|only| *only == container) {
+                ucm.remove_script(script);
+            }
+        }
+    }
+
+    pub fn add_style_sheet(&self, style_sheet: &UserStyleSheet) {
+        let mut inner = self.0.borrow_mut();
+        for ucm in inner.all_managers() {
+            ucm.add_style_sheet(style_sheet);
+        }
+        inner.style_sheets.push(style_sheet.clone());
+    }
+
+    pub fn remove_style_sheet(&self, style_sheet: &UserStyleSheet) {
+        let mut inner = self.0.borrow_mut();
+        inner.style_sheets.retain(|added| added != style_sheet);
+        for ucm in inner.all_managers() {
+            ucm.remove_style_sheet(style_sheet);
+        }
+    }
+
+    pub fn add_filter(&self, filter: &UserContentFilter) {
+        let mut inner = self.0.borrow_mut();
+        let managers = inner.all_managers();
+        inner.filters.add(&managers, filter);
+    }
+
+    pub fn remove_filter(&self, filter: &UserContentFilter) {
+        let mut inner = self.0.borrow_mut();
+        let managers = inner.all_managers();
+        inner.filters.remove(&managers, filter);
+    }
+
+    pub fn remove_all_filters(&self) {
+        let mut inner = self.0.borrow_mut();
+        let managers = inner.all_managers();
+        inner.filters.remove_all(&managers);
+    }
+
+    /// Keep a filter out of one webview's content manager, or put it back.
+    /// The filter stays in every other content manager either way.
+    pub fn set_filter_exempt(&self, ucm: &UserContentManager, filter: &UserContentFilter, exempt: bool) {
+        self.0.borrow_mut().filters.set_exempt(ucm.clone(), filter, exempt);
+    }
+
+    /// Register a script message handler in `world` (the page's world for
+    /// `None`), calling `callback` with the messages posted to it from any
+    /// webview
+    pub fn register_script_message_handler(
+        &self,
+        name: &str,
+        world: Option<&str>,
+        callback: impl Fn(&javascriptcore::Value) + 'static,
+    ) {
+        let handler = MessageHandler {
+            name: name.to_string(),
+            world: world.map(str::to_string),
+            callback: Rc::new(callback),
+        };
+        let mut inner = self.0.borrow_mut();
+        for ucm in inner.all_managers() {
+            register_handler(&ucm, &handler);
+        }
+        inner.handlers.push(handler);
+    }
+}
+
+fn register_handler(ucm: &UserContentManager, handler: &MessageHandler) {
+    if !ucm.register_script_message_handler(&handler.name, handler.world.as_deref()) {
+        log::warn!("Failed to register the {} message handler", handler.name);
+        return;
+    }
+    let callback = Rc::clone(&handler.callback);
+    ucm.connect_script_message_received(Some(&handler.name), move |_, value| callback(value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A content manager that records which filters it has
+    #[derive(Default)]
+    struct FakeManager {
+        id: u32,
+        filters: RefCell<Vec<&'static str>>,
+    }
+
+    impl PartialEq for FakeManager {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl FilterTarget for Rc<FakeManager> {
+        type Filter = &'static str;
+
+        fn add_filter(&self, filter: &&'static str) {
+            self.filters.borrow_mut().push(filter);
+        }
+
+        fn remove_filter(&self, filter: &&'static str) {
+            self.filters.borrow_mut().retain(|added| added != filter);
+        }
+    }
+
+    fn manager(id: u32) -> Rc<FakeManager> {
+        Rc::new(FakeManager { id, ..FakeManager::default() })
+    }
+
+    #[test]
+    fn test_exempt_manager_leaves_others_filtered() {
+        let (tab, other_tab) = (manager(1), manager(2));
+        let managers = vec![Rc::clone(&tab), Rc::clone(&other_tab)];
+        let mut filters = Filters::default();
+        filters.add(&managers, &"ads");
+        filters.add(&managers, &"mixed-content");
+
+        filters.set_exempt(Rc::clone(&tab), &"mixed-content", true);
+        assert_eq!(*tab.filters.borrow(), vec!["ads"]);
+        assert_eq!(*other_tab.filters.borrow(), vec!["ads", "mixed-content"]);
+
+        // Turning the policy off and on again doesn't undo the exemption
+        filters.remove(&managers, &"mixed-content");
+        filters.add(&managers, &"mixed-content");
+        assert_eq!(*tab.filters.borrow(), vec!["ads"]);
+        assert_eq!(*other_tab.filters.borrow(), vec!["ads", "mixed-content"]);
+
+        // Webviews opened meanwhile are filtered
+        let new_tab = manager(3);
+        filters.apply_to(&new_tab);
+        assert_eq!(*new_tab.filters.borrow(), vec!["ads", "mixed-content"]);
+
+        filters.set_exempt(Rc::clone(&tab), &"mixed-content", false);
+        assert_eq!(*tab.filters.borrow(), vec!["ads", "mixed-content"]);
+        assert!(filters.exemptions.is_empty());
+    }
+}
@@ -3,29 +3,195 @@
 //! Uses WebKit's native UserContentFilter with Safari Content Blocker format,
 //! plus uBlock Origin-style scriptlet injection for dynamic ad blocking.
 
+mod allowlist;
+mod content_managers;
 pub mod scriptlets;
 
 use gtk4::gio;
+use gtk4::prelude::*;
 use gtk4::glib;
 use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use webkit6::{UserContentFilterStore, UserContentManager};
+use std::time::SystemTime;
+use webkit6::{
+    UserContentFilter, UserContentFilterStore, UserContentInjectedFrames, UserContentManager, UserScript,
+    UserScriptInjectionTime, UserStyleLevel, UserStyleSheet,
+};
 
-pub use scriptlets::ScriptletManager;
+pub use allowlist::SiteAllowlist;
+pub use content_managers::ContentManagers;
+pub use scriptlets::{FilterCheck, FilterListStatus, ScriptletError, ScriptletManager};
+use scriptlets::FilterFetcher;
 
 /// Filter list identifiers
 const FILTER_EASYLIST: &str = "easylist";
 const FILTER_EASYPRIVACY: &str = "easyprivacy";
 const FILTER_YOUTUBE_ADS: &str = "youtube-ads";
+const FILTER_MIXED_CONTENT: &str = "mixed-content";
+
+/// Blocks HTTP subresources and frames of HTTPS pages. A top-level HTTP
+/// navigation has an HTTP top URL, so it isn't affected.
+const MIXED_CONTENT_RULES: &str = r#"[{"trigger":{"url-filter":"^http:","if-top-url":["^https:"]},"action":{"type":"block"}}]"#;
+
+/// Advertises Global Privacy Control and Do Not Track to page scripts.
+/// WebKit has no way to add headers to requests from the UI process, so sites
+/// only see the signal through `navigator`, not as `Sec-GPC`/`DNT` headers.
+const PRIVACY_SIGNALS_SCRIPT: &str = r#"(function() {
+    const define = (name, value) => {
+        try {
+            Object.defineProperty(Navigator.prototype, name, { get: () => value, configurable: true });
+        } catch (e) {}
+    };
+    define('globalPrivacyControl', true);
+    define('doNotTrack', '1');
+})();"#;
+
+/// Sets the page's referrer policy before its own scripts and requests run.
+/// `POLICY` is replaced with the policy for the setting.
+const REFERRER_POLICY_SCRIPT: &str = r#"(function() {
+    const meta = document.createElement('meta');
+    meta.name = 'referrer';
+    meta.content = 'POLICY';
+    (document.head || document.documentElement).prepend(meta);
+})();"#;
+
+/// Sites opted out of forced dark mode
+const FORCE_DARK_EXCEPTIONS_FILE: &str = "force-dark-exceptions.json";
+
+/// Sites opted out of fingerprinting protection
+const FINGERPRINTING_EXCEPTIONS_FILE: &str = "fingerprinting-exceptions.json";
+
+/// Site and directive pairs remembered as logged; past this they're forgotten
+/// and logged again, rather than growing with every site visited
+const MAX_REPORTED_CSP_VIOLATIONS: usize = 256;
+
+/// Makes the common fingerprinting surfaces useless for telling users apart:
+/// canvas reads get a little noise that changes with every page load, WebGL
+/// reports a generic GPU, and `navigator` reports common values instead of the
+/// machine's own. Noise is seeded once per page, so a page reading the same
+/// canvas twice sees the same pixels.
+const FINGERPRINTING_PROTECTION_SCRIPT: &str = r#"(function() {
+    const seed = crypto.getRandomValues(new Uint32Array(1))[0];
+    const noisy = (index) => {
+        let x = (index ^ seed) >>> 0;
+        x = Math.imul(x ^ (x >>> 16), 0x45d9f3b);
+        x = Math.imul(x ^ (x >>> 16), 0x45d9f3b);
+        return ((x ^ (x >>> 16)) & 0x1f) === 0;
+    };
+    const addNoise = (data) => {
+        // Flip the lowest bit of about one channel in 32
+        for (let i = 0; i < data.length; i++) {
+            if ((i & 3) !== 3 && noisy(i)) data[i] ^= 1;
+        }
+    };
+    const replace = (proto, name, wrap) => {
+        if (!proto || typeof proto[name] !== 'function') return;
+        const original = proto[name];
+        try {
+            Object.defineProperty(proto, name, { value: wrap(original), configurable: true, writable: true });
+        } catch (e) {}
+    };
+    const define = (proto, name, value) => {
+        try {
+            Object.defineProperty(proto, name, { get: () => value, configurable: true });
+        } catch (e) {}
+    };
+
+    // Canvas: reads go through a noisy copy, leaving the page's canvas untouched
+    const getImageData = CanvasRenderingContext2D.prototype.getImageData;
+    replace(CanvasRenderingContext2D.prototype, 'getImageData', (original) => function(...args) {
+        const image = original.apply(this, args);
+        addNoise(image.data);
+        return image;
+    });
+    const noisyCopy = (canvas) => {
+        if (!canvas.width || !canvas.height) return null;
+        try {
+            const copy = document.createElement('canvas');
+            copy.width = canvas.width;
+            copy.height = canvas.height;
+            const context = copy.getContext('2d');
+            context.drawImage(canvas, 0, 0);
+            const image = getImageData.call(context, 0, 0, copy.width, copy.height);
+            addNoise(image.data);
+            context.putImageData(image, 0, 0);
+            return copy;
+        } catch (e) {
+            return null;
+        }
+    };
+    replace(HTMLCanvasElement.prototype, 'toDataURL', (original) => function(...args) {
+        const copy = noisyCopy(this);
+        return original.apply(copy || this, args);
+    });
+    replace(HTMLCanvasElement.prototype, 'toBlob', (original) => function(...args) {
+        const copy = noisyCopy(this);
+        return original.apply(copy || this, args);
+    });
+
+    // WebGL: report a generic GPU instead of the real vendor and renderer
+    const UNMASKED_VENDOR = 0x9245;
+    const UNMASKED_RENDERER = 0x9246;
+    const spoofParameter = (original) => function(parameter) {
+        if (parameter === UNMASKED_VENDOR || parameter === this.VENDOR) return 'WebKit';
+        if (parameter === UNMASKED_RENDERER || parameter === this.RENDERER) return 'WebKit WebGL';
+        return original.call(this, parameter);
+    };
+    replace(window.WebGLRenderingContext && WebGLRenderingContext.prototype, 'getParameter', spoofParameter);
+    replace(window.WebGL2RenderingContext && WebGL2RenderingContext.prototype, 'getParameter', spoofParameter);
+
+    // Navigator: common values, and no plugin or MIME type lists to enumerate
+    define(Navigator.prototype, 'hardwareConcurrency', 4);
+    define(Navigator.prototype, 'deviceMemory', 8);
+    define(Navigator.prototype, 'plugins', Object.freeze([]));
+    define(Navigator.prototype, 'mimeTypes', Object.freeze([]));
+})();"#;
+
+/// Darkens light pages by inverting them, then inverts media back so photos
+/// and videos keep their colors. Deliberately simple to avoid breaking layouts.
+const FORCE_DARK_STYLESHEET: &str = r#":root {
+    color-scheme: dark;
+    filter: invert(0.9) hue-rotate(180deg) !important;
+    background-color: #fff !important;
+}
+img, picture, video, canvas, iframe, embed, object, svg image, [style*="background-image"] {
+    filter: invert(1) hue-rotate(180deg) !important;
+}"#;
+
+/// Counts and freshness of the filter lists
+pub struct FilterStats {
+    /// Compiled scriptlet (rules, scripts), or `None` while they're still loading
+    pub scriptlets: Option<(usize, usize)>,
+    /// Bundled network filters and whether each has been applied yet
+    pub network_filters: Vec<(&'static str, bool)>,
+    /// Remote scriptlet lists and their cached copies
+    pub lists: Vec<FilterListStatus>,
+    /// When the remote lists were last fetched
+    pub last_updated: Option<SystemTime>,
+}
 
 /// Manages content blocking filters and scriptlet injection
 pub struct ContentFilterManager {
-    user_content_manager: UserContentManager,
+    content_managers: ContentManagers,
     filter_store: UserContentFilterStore,
     filters_loaded: Cell<bool>,
+    /// Identifiers of the network filters added to the content manager so far
+    active_filters: Rc<RefCell<Vec<String>>>,
     data_dir: PathBuf,
     scriptlet_manager: RefCell<Option<Rc<RefCell<ScriptletManager>>>>,
+    scriptlet_update_timer: Rc<RefCell<Option<glib::SourceId>>>,
+    /// Cancels filter fetches still running when the app shuts down
+    cancellable: gio::Cancellable,
+    settings: gio::Settings,
+    privacy_signals_script: Rc<RefCell<Option<UserScript>>>,
+    referrer_policy_script: Rc<RefCell<Option<UserScript>>>,
+    force_dark_sheet: Rc<RefCell<Option<UserStyleSheet>>>,
+    force_dark_exceptions: Rc<RefCell<SiteAllowlist>>,
+    mixed_content_filter: Rc<RefCell<Option<UserContentFilter>>>,
+    fingerprinting_script: Rc<RefCell<Option<UserScript>>>,
+    fingerprinting_exceptions: Rc<RefCell<SiteAllowlist>>,
 }
 
 impl ContentFilterManager {
@@ -37,20 +203,33 @@ impl ContentFilterManager {
         let filter_store = UserContentFilterStore::new(
             filters_path.to_str().expect("valid UTF-8 path"),
         );
-        let user_content_manager = UserContentManager::new();
-
         Self {
-            user_content_manager,
+            content_managers: ContentManagers::new(),
             filter_store,
             filters_loaded: Cell::new(false),
+            active_filters: Rc::new(RefCell::new(Vec::new())),
             data_dir: data_dir.to_path_buf(),
             scriptlet_manager: RefCell::new(None),
+            scriptlet_update_timer: Rc::new(RefCell::new(None)),
+            cancellable: gio::Cancellable::new(),
+            settings: gio::Settings::new(crate::config::APP_ID),
+            privacy_signals_script: Rc::new(RefCell::new(None)),
+            referrer_policy_script: Rc::new(RefCell::new(None)),
+            force_dark_sheet: Rc::new(RefCell::new(None)),
+            force_dark_exceptions: Rc::new(RefCell::new(SiteAllowlist::load(
+                &data_dir.join(FORCE_DARK_EXCEPTIONS_FILE),
+            ))),
+            mixed_content_filter: Rc::new(RefCell::new(None)),
+            fingerprinting_script: Rc::new(RefCell::new(None)),
+            fingerprinting_exceptions: Rc::new(RefCell::new(SiteAllowlist::load(
+                &data_dir.join(FINGERPRINTING_EXCEPTIONS_FILE),
+            ))),
         }
     }
 
-    /// Get the user content manager for use with WebViews
-    pub fn user_content_manager(&self) -> &UserContentManager {
-        &self.user_content_manager
+    /// The content managers of every webview, for adding to all of them
+    pub fn content_managers(&self) -> &ContentManagers {
+        &self.content_managers
     }
 
     /// Initialize filters - loads from store or compiles from bundled JSON
@@ -80,25 +259,32 @@ impl ContentFilterManager {
         self.filters_loaded.set(true);
     }
 
-    /// Initialize scriptlet injection for YouTube ad blocking
+    /// Initialize scriptlet injection for YouTube ad blocking, with the
+    /// custom filters in each (container, directory)
     ///
     /// This loads bundled scriptlets immediately, then fetches updates in background.
-    pub fn initialize_scriptlets(&self) {
+    pub fn initialize_scriptlets(&self, containers: &[(String, PathBuf)]) {
         let mut manager = ScriptletManager::new(&self.data_dir);
+        for (container, dir) in containers {
+            manager.load_custom_filters(container, dir);
+        }
 
         // Load bundled filters synchronously for immediate protection
         manager.initialize_bundled();
+        self.watch_csp_violations();
 
         // Add scripts to user content manager
-        manager.add_to_content_manager(&self.user_content_manager);
+        manager.add_to_content_managers(&self.content_managers);
 
         let manager = Rc::new(RefCell::new(manager));
         *self.scriptlet_manager.borrow_mut() = Some(Rc::clone(&manager));
 
         // Fetch updates in background
+        let update_interval = scriptlets::update_interval(&self.settings.string("filter-update-interval"));
+        let cancellable = self.cancellable.clone();
         glib::spawn_future_local(async move {
             let mut mgr = manager.borrow_mut();
-            match mgr.initialize().await {
+            match mgr.initialize(update_interval, &cancellable).await {
                 Ok(()) => {
                     log::info!("Scriptlets initialized with remote filters");
                 }
@@ -109,36 +295,371 @@ impl ContentFilterManager {
             }
         });
 
-        // Schedule periodic updates (every 24 hours)
+        // Schedule periodic updates, rescheduling when the interval changes
         self.schedule_scriptlet_updates();
     }
 
-    /// Schedule periodic scriptlet updates
+    /// Stop scheduled filter updates and cancel any fetch still running,
+    /// so they don't outlive the app
+    pub fn shutdown(&self) {
+        self.cancellable.cancel();
+        if let Some(source) = self.scriptlet_update_timer.borrow_mut().take() {
+            source.remove();
+        }
+    }
+
+    /// Fetch the scriptlet filter lists now, whatever their age, and apply
+    /// them to pages loaded from then on. Returns whether anything changed.
+    ///
+    /// The network filters are bundled with the app, so only the scriptlet
+    /// lists have anything to fetch.
+    pub async fn update_filters(&self) -> Result<bool, ScriptletError> {
+        let Some(manager) = self.scriptlet_manager.borrow().clone() else {
+            return Err(ScriptletError::CacheError("Filters are still loading".into()));
+        };
+        // Startup and scheduled updates hold the manager while they fetch
+        let Ok(fetcher) = manager.try_borrow().map(|m| m.fetcher().clone()) else {
+            return Err(ScriptletError::UpdateInProgress);
+        };
+
+        let previous = fetcher.load_cached().ok();
+        let filters = fetcher.refresh(&self.cancellable).await?;
+        if previous.as_ref() == Some(&filters) {
+            return Ok(false);
+        }
+        let Ok(mut manager) = manager.try_borrow_mut() else {
+            return Err(ScriptletError::UpdateInProgress);
+        };
+        manager.apply_filters(&filters);
+        Ok(true)
+    }
+
+    /// A container's own filter rules, or `None` while scriptlets are still loading
+    pub fn custom_filters(&self, container: &str) -> Option<String> {
+        let manager = self.scriptlet_manager.borrow().clone()?;
+        let manager = manager.try_borrow().ok()?;
+        Some(manager.custom_filters(container).to_string())
+    }
+
+    /// Save a container's own filter rules in its directory and apply them to
+    /// its pages loaded from then on. Returns how many of the lines were
+    /// recognized as rules.
+    pub fn set_custom_filters(&self, container: &str, dir: &Path, text: &str) -> Result<FilterCheck, ScriptletError> {
+        let Some(manager) = self.scriptlet_manager.borrow().clone() else {
+            return Err(ScriptletError::CacheError("Filters are still loading".into()));
+        };
+        let Ok(mut manager) = manager.try_borrow_mut() else {
+            return Err(ScriptletError::UpdateInProgress);
+        };
+        let check = manager.set_custom_filters(container, dir, text)?;
+        log::info!(
+            "Custom filters for {} saved: {} rules recognized, {} skipped",
+            container,
+            check.recognized,
+            check.skipped
+        );
+        Ok(check)
+    }
+
+    /// Counts and freshness of the filter lists, for the statistics page
+    pub fn stats(&self) -> FilterStats {
+        // The manager is borrowed while filters are fetched at startup
+        let scriptlets = self
+            .scriptlet_manager
+            .borrow()
+            .as_ref()
+            .and_then(|manager| manager.try_borrow().ok().map(|m| (m.rule_count(), m.script_count())));
+        let active = self.active_filters.borrow();
+        let network_filters = [FILTER_EASYLIST, FILTER_EASYPRIVACY, FILTER_YOUTUBE_ADS]
+            .into_iter()
+            .map(|id| (id, active.iter().any(|a| a == id)))
+            .collect();
+        let fetcher = FilterFetcher::new(&self.data_dir.join(scriptlets::CACHE_DIR));
+
+        FilterStats {
+            scriptlets,
+            network_filters,
+            lists: fetcher.list_status(),
+            last_updated: fetcher.last_updated(),
+        }
+    }
+
+    /// Log when a page's Content-Security-Policy blocks inline script or eval
+    /// where scriptlets run, since scriptlets relying on them don't work there.
+    /// Each site and directive is logged once, until too many were seen.
+    fn watch_csp_violations(&self) {
+        let reported: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+        let handler = move |value: &webkit6::javascriptcore::Value| {
+            let Some(json) = value.to_json(0) else {
+                return;
+            };
+            let Ok(violation) = serde_json::from_str::<serde_json::Value>(&json) else {
+                return;
+            };
+            let field = |name: &str| violation.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let (page, directive, blocked) = (field("page"), field("directive"), field("blocked"));
+            let host = url::Url::parse(&page)
+                .ok()
+                .and_then(|url| url.host_str().map(|h| h.to_string()))
+                .unwrap_or(page);
+
+            let mut reported = reported.borrow_mut();
+            if reported.len() >= MAX_REPORTED_CSP_VIOLATIONS {
+                reported.clear();
+            }
+            if reported.insert(format!("{} {}", host, directive)) {
+                log::warn!(
+                    "Content-Security-Policy {} on {} blocks {} scripts; scriptlets relying on them won't work there",
+                    directive,
+                    host,
+                    blocked
+                );
+            }
+        };
+        self.content_managers.register_script_message_handler(
+            scriptlets::CSP_MESSAGE_HANDLER,
+            Some(scriptlets::CSP_MONITOR_WORLD),
+            handler,
+        );
+    }
+
+    /// Inject the Global Privacy Control / Do Not Track script while the setting is on.
+    /// Toggling the setting adds or removes just that script, so no restart is needed.
+    pub fn initialize_privacy_signals(&self) {
+        let managers = self.content_managers.clone();
+        let script = Rc::clone(&self.privacy_signals_script);
+        let apply = move |enabled: bool| {
+            let mut script = script.borrow_mut();
+            match (enabled, script.as_ref()) {
+                (true, None) => {
+                    let user_script = UserScript::new(
+                        PRIVACY_SIGNALS_SCRIPT,
+                        UserContentInjectedFrames::AllFrames,
+                        UserScriptInjectionTime::Start,
+                        &[],
+                        &[],
+                    );
+                    managers.add_script(&user_script);
+                    *script = Some(user_script);
+                    log::info!("Global Privacy Control enabled");
+                }
+                (false, Some(user_script)) => {
+                    managers.remove_script(user_script);
+                    *script = None;
+                    log::info!("Global Privacy Control disabled");
+                }
+                _ => {}
+            }
+        };
+
+        apply(self.settings.boolean("global-privacy-control"));
+        self.settings
+            .connect_changed(Some("global-privacy-control"), move |settings, key| {
+                apply(settings.boolean(key));
+            });
+    }
+
+    /// Inject the script setting the referrer policy chosen in the settings,
+    /// swapping it when the setting changes.
+    ///
+    /// WebKit can't rewrite `Referer` headers from the UI process, so this sets
+    /// the policy a page would set with `<meta name="referrer">`. It covers the
+    /// page's subresources and the links followed from it, but a `Referrer-Policy`
+    /// header, a later meta tag of the page's own, or `referrerpolicy` and
+    /// `rel="noreferrer"` on links still take precedence, and requests made
+    /// before the document exists, such as redirects, are unaffected.
+    pub fn initialize_referrer_policy(&self) {
+        let managers = self.content_managers.clone();
+        let script = Rc::clone(&self.referrer_policy_script);
+        let apply = move |policy: &str| {
+            let mut script = script.borrow_mut();
+            if let Some(user_script) = script.take() {
+                managers.remove_script(&user_script);
+            }
+            if let Some(source) = referrer_policy_script(policy) {
+                let user_script = UserScript::new(
+                    &source,
+                    UserContentInjectedFrames::AllFrames,
+                    UserScriptInjectionTime::Start,
+                    &[],
+                    &[],
+                );
+                managers.add_script(&user_script);
+                *script = Some(user_script);
+            }
+            log::info!("Referrer policy: {}", policy);
+        };
+
+        apply(&self.settings.string("referrer-policy"));
+        self.settings
+            .connect_changed(Some("referrer-policy"), move |settings, key| {
+                apply(&settings.string(key));
+            });
+    }
+
+    /// Apply the force-dark stylesheet while the setting is on, updating live when it changes.
+    /// Only pages change: the app keeps the color scheme the desktop or user chose.
+    pub fn initialize_force_dark(&self) {
+        self.update_force_dark();
+
+        let managers = self.content_managers.clone();
+        let sheet = Rc::clone(&self.force_dark_sheet);
+        let exceptions = Rc::clone(&self.force_dark_exceptions);
+        self.settings
+            .connect_changed(Some("force-dark-mode"), move |settings, key| {
+                apply_force_dark(&managers, &sheet, &exceptions.borrow(), settings.boolean(key));
+            });
+    }
+
+    /// Whether pages are forced dark at all
+    pub fn force_dark_enabled(&self) -> bool {
+        self.settings.boolean("force-dark-mode")
+    }
+
+    /// Whether forced dark mode applies to a site (it's on and the site isn't opted out)
+    pub fn force_dark_applies_to(&self, host: &str) -> bool {
+        self.force_dark_enabled() && !self.force_dark_exceptions.borrow().contains(host)
+    }
+
+    /// Opt a site in or out of forced dark mode. Open pages update immediately.
+    pub fn set_force_dark_for_site(&self, host: &str, enabled: bool) {
+        let changed = self.force_dark_exceptions.borrow_mut().set(host, !enabled);
+        if changed {
+            log::info!("Force dark mode {} for {}", if enabled { "enabled" } else { "disabled" }, host);
+            self.update_force_dark();
+        }
+    }
+
+    fn update_force_dark(&self) {
+        apply_force_dark(
+            &self.content_managers,
+            &self.force_dark_sheet,
+            &self.force_dark_exceptions.borrow(),
+            self.force_dark_enabled(),
+        );
+    }
+
+    /// Inject the fingerprinting protection script while the setting is on,
+    /// adding or removing it live when the setting changes.
+    ///
+    /// Spoofing can break sites that draw with canvas or WebGL and read the
+    /// result back, such as image editors and maps, so sites can be opted out
+    /// from the site information menu.
+    pub fn initialize_fingerprinting_protection(&self) {
+        self.update_fingerprinting_protection();
+
+        let managers = self.content_managers.clone();
+        let script = Rc::clone(&self.fingerprinting_script);
+        let exceptions = Rc::clone(&self.fingerprinting_exceptions);
+        self.settings
+            .connect_changed(Some("fingerprinting-protection"), move |settings, key| {
+                apply_fingerprinting_protection(&managers, &script, &exceptions.borrow(), settings.boolean(key));
+            });
+    }
+
+    /// Whether fingerprinting protection is on at all
+    pub fn fingerprinting_protection_enabled(&self) -> bool {
+        self.settings.boolean("fingerprinting-protection")
+    }
+
+    /// Whether fingerprinting protection applies to a site (it's on and the site isn't opted out)
+    pub fn fingerprinting_protection_applies_to(&self, host: &str) -> bool {
+        self.fingerprinting_protection_enabled() && !self.fingerprinting_exceptions.borrow().contains(host)
+    }
+
+    /// Opt a site in or out of fingerprinting protection. Takes effect when its pages reload.
+    pub fn set_fingerprinting_protection_for_site(&self, host: &str, enabled: bool) {
+        let changed = self.fingerprinting_exceptions.borrow_mut().set(host, !enabled);
+        if changed {
+            log::info!(
+                "Fingerprinting protection {} for {}",
+                if enabled { "enabled" } else { "disabled" },
+                host
+            );
+            self.update_fingerprinting_protection();
+        }
+    }
+
+    fn update_fingerprinting_protection(&self) {
+        apply_fingerprinting_protection(
+            &self.content_managers,
+            &self.fingerprinting_script,
+            &self.fingerprinting_exceptions.borrow(),
+            self.fingerprinting_protection_enabled(),
+        );
+    }
+
+    /// Compile the mixed content rule and apply it while the policy is "block",
+    /// updating live when the policy changes
+    pub fn initialize_mixed_content_blocking(&self) {
+        let managers = self.content_managers.clone();
+        let filter = Rc::clone(&self.mixed_content_filter);
+        let settings = self.settings.clone();
+        let bytes = glib::Bytes::from_static(MIXED_CONTENT_RULES.as_bytes());
+        self.filter_store.save(
+            FILTER_MIXED_CONTENT,
+            &bytes,
+            gio::Cancellable::NONE,
+            move |result| match result {
+                Ok(compiled) => {
+                    if settings.string("mixed-content") == "block" {
+                        managers.add_filter(&compiled);
+                    }
+                    *filter.borrow_mut() = Some(compiled);
+                }
+                Err(e) => log::error!("Failed to compile mixed content filter: {}", e),
+            },
+        );
+
+        let managers = self.content_managers.clone();
+        let filter = Rc::clone(&self.mixed_content_filter);
+        self.settings
+            .connect_changed(Some("mixed-content"), move |settings, key| {
+                let Some(filter) = filter.borrow().clone() else {
+                    return;
+                };
+                managers.remove_filter(&filter);
+                if settings.string(key) == "block" {
+                    managers.add_filter(&filter);
+                }
+                log::info!("Mixed content policy: {}", settings.string(key));
+            });
+    }
+
+    /// Whether HTTP resources on HTTPS pages are blocked
+    pub fn mixed_content_blocked(&self) -> bool {
+        self.settings.string("mixed-content") == "block"
+    }
+
+    /// Let one webview load insecure content, or block it there again.
+    /// Every other webview stays blocked while the policy is "block".
+    pub fn set_mixed_content_allowed(&self, ucm: &UserContentManager, allowed: bool) {
+        let Some(filter) = self.mixed_content_filter.borrow().clone() else {
+            return;
+        };
+        self.content_managers.set_filter_exempt(ucm, &filter, allowed);
+    }
+
+    /// Schedule periodic scriptlet updates at the configured interval and
+    /// reschedule them whenever the setting changes
     fn schedule_scriptlet_updates(&self) {
         let manager = match self.scriptlet_manager.borrow().clone() {
             Some(m) => m,
             None => return,
         };
 
-        glib::timeout_add_local(
-            std::time::Duration::from_secs(24 * 60 * 60),
-            move || {
-                let manager = Rc::clone(&manager);
-                glib::spawn_future_local(async move {
-                    let mut mgr = manager.borrow_mut();
-                    match mgr.check_for_updates().await {
-                        Ok(true) => log::info!("Scriptlets updated"),
-                        Ok(false) => log::debug!("Scriptlets up to date"),
-                        Err(e) => log::warn!("Failed to update scriptlets: {}", e),
-                    }
-                });
-                glib::ControlFlow::Continue
-            },
-        );
+        let timer = Rc::clone(&self.scriptlet_update_timer);
+        let cancellable = self.cancellable.clone();
+        apply_update_interval(&manager, &timer, &cancellable, &self.settings.string("filter-update-interval"));
+        self.settings
+            .connect_changed(Some("filter-update-interval"), move |settings, key| {
+                apply_update_interval(&manager, &timer, &cancellable, &settings.string(key));
+            });
     }
 
     fn load_or_compile_filter(&self, identifier: &str, resource_path: &str) {
-        let ucm = self.user_content_manager.clone();
+        let managers = self.content_managers.clone();
+        let active = Rc::clone(&self.active_filters);
         let store = self.filter_store.clone();
         let store_for_compile = store.clone();
         let id = identifier.to_string();
@@ -152,11 +673,12 @@ impl ContentFilterManager {
                 match result {
                     Ok(filter) => {
                         log::info!("Loaded compiled filter: {}", id);
-                        ucm.add_filter(&filter);
+                        managers.add_filter(&filter);
+                        active.borrow_mut().push(id);
                     }
                     Err(_) => {
                         // Filter not compiled yet, compile from bundled JSON
-                        Self::compile_filter_from_resource(&store_for_compile, &ucm, &id, &resource);
+                        Self::compile_filter_from_resource(&store_for_compile, &managers, &active, &id, &resource);
                     }
                 }
             },
@@ -165,7 +687,8 @@ impl ContentFilterManager {
 
     fn compile_filter_from_resource(
         store: &UserContentFilterStore,
-        ucm: &UserContentManager,
+        managers: &ContentManagers,
+        active: &Rc<RefCell<Vec<String>>>,
         identifier: &str,
         resource_path: &str,
     ) {
@@ -178,7 +701,8 @@ impl ContentFilterManager {
             }
         };
 
-        let ucm = ucm.clone();
+        let managers = managers.clone();
+        let active = Rc::clone(active);
         let id = identifier.to_string();
 
         store.save(
@@ -189,7 +713,8 @@ impl ContentFilterManager {
                 match result {
                     Ok(filter) => {
                         log::info!("Compiled and saved filter: {}", id);
-                        ucm.add_filter(&filter);
+                        managers.add_filter(&filter);
+                        active.borrow_mut().push(id);
                     }
                     Err(e) => {
                         log::error!("Failed to compile filter {}: {}", id, e);
@@ -202,7 +727,7 @@ impl ContentFilterManager {
     /// Remove all filters (for settings toggle)
     #[allow(dead_code)]
     pub fn disable_filters(&self) {
-        self.user_content_manager.remove_all_filters();
+        self.content_managers.remove_all_filters();
     }
 
     /// Re-enable filters
@@ -212,3 +737,122 @@ impl ContentFilterManager {
         self.initialize_filters();
     }
 }
+
+/// The script enforcing a `referrer-policy` setting, or None to keep WebKit's default
+fn referrer_policy_script(policy: &str) -> Option<String> {
+    let policy = match policy {
+        // Never sends an HTTPS origin to an HTTP page
+        "origin" => "strict-origin",
+        "none" => "no-referrer",
+        // "full": same-site pages get the full address, other sites only the origin
+        _ => return None,
+    };
+    Some(REFERRER_POLICY_SCRIPT.replace("POLICY", policy))
+}
+
+/// Replace the scriptlet update timer with one firing at the interval for a
+/// `filter-update-interval` setting. "never" leaves no timer running.
+fn apply_update_interval(
+    manager: &Rc<RefCell<ScriptletManager>>,
+    timer: &Rc<RefCell<Option<glib::SourceId>>>,
+    cancellable: &gio::Cancellable,
+    setting: &str,
+) {
+    if let Some(source) = timer.borrow_mut().take() {
+        source.remove();
+    }
+
+    let Some(interval) = scriptlets::update_interval(setting) else {
+        log::info!("Filter list updates disabled");
+        return;
+    };
+
+    let manager = Rc::clone(manager);
+    let cancellable = cancellable.clone();
+    let source = glib::timeout_add_local(interval, move || {
+        let manager = Rc::clone(&manager);
+        let cancellable = cancellable.clone();
+        glib::spawn_future_local(async move {
+            let mut mgr = manager.borrow_mut();
+            match mgr.check_for_updates(interval, &cancellable).await {
+                Ok(true) => log::info!("Scriptlets updated"),
+                Ok(false) => log::debug!("Scriptlets up to date"),
+                Err(e) => log::warn!("Failed to update scriptlets: {}", e),
+            }
+        });
+        glib::ControlFlow::Continue
+    });
+    *timer.borrow_mut() = Some(source);
+    log::info!("Filter lists update every {} hours", interval.as_secs() / 3600);
+}
+
+/// Swap in a force-dark stylesheet reflecting the current exceptions, or remove it
+fn apply_force_dark(
+    managers: &ContentManagers,
+    sheet: &RefCell<Option<UserStyleSheet>>,
+    exceptions: &SiteAllowlist,
+    enabled: bool,
+) {
+    if let Some(old) = sheet.borrow_mut().take() {
+        managers.remove_style_sheet(&old);
+    }
+
+    if enabled {
+        // The block list keeps opted-out sites untouched
+        let patterns = exceptions.url_patterns();
+        let pattern_refs: Vec<&str> = patterns.iter().map(|p| p.as_str()).collect();
+        let style_sheet = UserStyleSheet::new(
+            FORCE_DARK_STYLESHEET,
+            UserContentInjectedFrames::TopFrame,
+            UserStyleLevel::User,
+            &[],
+            &pattern_refs,
+        );
+        managers.add_style_sheet(&style_sheet);
+        *sheet.borrow_mut() = Some(style_sheet);
+    }
+}
+
+/// Swap in a fingerprinting protection script skipping the current exceptions, or remove it
+fn apply_fingerprinting_protection(
+    managers: &ContentManagers,
+    script: &RefCell<Option<UserScript>>,
+    exceptions: &SiteAllowlist,
+    enabled: bool,
+) {
+    if let Some(old) = script.borrow_mut().take() {
+        managers.remove_script(&old);
+    }
+    if !enabled {
+        return;
+    }
+
+    // Runs before page scripts, in every frame, so embedded fingerprinters are covered too
+    let patterns = exceptions.url_patterns();
+    let pattern_refs: Vec<&str> = patterns.iter().map(|p| p.as_str()).collect();
+    let user_script = UserScript::new(
+        FINGERPRINTING_PROTECTION_SCRIPT,
+        UserContentInjectedFrames::AllFrames,
+        UserScriptInjectionTime::Start,
+        &[],
+        &pattern_refs,
+    );
+    managers.add_script(&user_script);
+    *script.borrow_mut() = Some(user_script);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referrer_policy_script_for_each_setting() {
+        assert_eq!(referrer_policy_script("full"), None);
+        let origin = referrer_policy_script("origin").unwrap();
+        assert!(origin.contains("meta.content = 'strict-origin';"));
+        let none = referrer_policy_script("none").unwrap();
+        assert!(none.contains("meta.content = 'no-referrer';"));
+        assert!(!none.contains("POLICY"));
+        assert_eq!(referrer_policy_script("unknown"), None);
+    }
+}
@@ -15,6 +15,7 @@ pub fn generate_script(rule: &ScriptletRule) -> Option<String> {
         "abort-on-property-read" => generate_abort_on_property_read(&rule.args),
         "abort-on-property-write" => generate_abort_on_property_write(&rule.args),
         "addEventListener-defuser" => generate_aeld(&rule.args),
+        "addEventListener-logger" => generate_aell(&rule.args),
         "no-setTimeout-if" => generate_no_settimeout_if(&rule.args),
         "no-setInterval-if" => generate_no_setinterval_if(&rule.args),
         "prevent-fetch" | "no-fetch-if" => generate_prevent_fetch(&rule.args),
@@ -63,7 +64,7 @@ fn generate_set_constant(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const chain = '{}';
+        const chain = {};
         const cValue = {};
 
         const setConstant = function(chain, cValue) {{
@@ -101,12 +102,16 @@ fn generate_set_constant(args: &[String]) -> Option<String> {
 
         setConstant(chain, cValue);
 "#,
-        property, js_value
+        js_string(property), js_value
     ))
 }
 
 /// json-prune: Remove properties from JSON.parse results
 /// Args: [properties_to_remove, optional_needle]
+///
+/// Property paths are dot-separated. A `*` segment matches every key and a
+/// `[]` segment every element of an array, so `*.ads` prunes `ads` one level
+/// down and `items.[].ad` prunes `ad` from each item.
 fn generate_json_prune(args: &[String]) -> Option<String> {
     let props = args.get(0)?;
     let needle = args.get(1).map(|s| s.as_str()).unwrap_or("");
@@ -114,23 +119,35 @@ fn generate_json_prune(args: &[String]) -> Option<String> {
     // Optimized: only prune if text contains a needle (fast string check)
     Some(format!(
         r#"
-        const propsToRemove = '{}';
-        const needle = '{}';
+        const propsToRemove = {};
+        const needle = {};
+
+        const pruneAt = function(node, parts, depth) {{
+            if (typeof node !== 'object' || node === null) return;
+            const part = parts[depth];
+            let keys;
+            if (part === '[]') {{
+                if (!Array.isArray(node)) return;
+                keys = Object.keys(node);
+            }} else if (part === '*') {{
+                keys = Object.keys(node);
+            }} else {{
+                keys = Object.prototype.hasOwnProperty.call(node, part) ? [part] : [];
+            }}
+            for (const key of keys) {{
+                if (depth === parts.length - 1) {{
+                    delete node[key];
+                }} else {{
+                    pruneAt(node[key], parts, depth + 1);
+                }}
+            }}
+        }};
 
         const pruner = function(obj, props) {{
             if (typeof obj !== 'object' || obj === null) return obj;
-            const propList = props.split(' ');
-            for (const propPath of propList) {{
+            for (const propPath of props.split(' ')) {{
                 if (!propPath) continue;
-                const parts = propPath.split('.');
-                let current = obj;
-                for (let i = 0; i < parts.length - 1; i++) {{
-                    if (!current || typeof current !== 'object') break;
-                    current = current[parts[i]];
-                }}
-                if (current && typeof current === 'object') {{
-                    delete current[parts[parts.length - 1]];
-                }}
+                pruneAt(obj, propPath.split('.'), 0);
             }}
             return obj;
         }};
@@ -145,7 +162,7 @@ fn generate_json_prune(args: &[String]) -> Option<String> {
             return pruner(result, propsToRemove);
         }};
 "#,
-        props, needle
+        js_string(props), js_string(needle)
     ))
 }
 
@@ -156,7 +173,7 @@ fn generate_abort_on_property_read(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const chain = '{}';
+        const chain = {};
         const props = chain.split('.');
         let owner = window;
 
@@ -176,7 +193,7 @@ fn generate_abort_on_property_read(args: &[String]) -> Option<String> {
             configurable: true
         }});
 "#,
-        property
+        js_string(property)
     ))
 }
 
@@ -187,7 +204,7 @@ fn generate_abort_on_property_write(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const chain = '{}';
+        const chain = {};
         const props = chain.split('.');
         let owner = window;
 
@@ -208,7 +225,7 @@ fn generate_abort_on_property_write(args: &[String]) -> Option<String> {
             configurable: true
         }});
 "#,
-        property
+        js_string(property)
     ))
 }
 
@@ -220,8 +237,8 @@ fn generate_aeld(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const typePattern = '{}';
-        const handlerPattern = '{}';
+        const typePattern = {};
+        const handlerPattern = {};
 
         const origAddEventListener = EventTarget.prototype.addEventListener;
         EventTarget.prototype.addEventListener = function(type, handler, options) {{
@@ -234,7 +251,36 @@ fn generate_aeld(args: &[String]) -> Option<String> {
             // Blocked
         }};
 "#,
-        type_pattern, handler_pattern
+        js_string(type_pattern), js_string(handler_pattern)
+    ))
+}
+
+/// addEventListener-logger: Log listener registrations to the console, still adding them
+/// Args: [optional_type_pattern, optional_handler_pattern]; with none every registration is logged
+fn generate_aell(args: &[String]) -> Option<String> {
+    let matcher = |arg: Option<&String>| match arg.map(|s| s.as_str()).filter(|s| !s.is_empty()) {
+        Some(pattern) => js_matcher(pattern),
+        None => "() => true".to_string(),
+    };
+
+    Some(format!(
+        r#"
+        const typeMatches = {};
+        const handlerMatches = {};
+
+        const origAddEventListener = EventTarget.prototype.addEventListener;
+        EventTarget.prototype.addEventListener = function(type, handler, options) {{
+            try {{
+                const handlerText = handler ? String(handler) : '';
+                if (typeMatches(String(type)) && handlerMatches(handlerText)) {{
+                    console.log('[qayeq aell]', type, this, handlerText.slice(0, 200));
+                }}
+            }} catch (e) {{}}
+            return origAddEventListener.call(this, type, handler, options);
+        }};
+"#,
+        matcher(args.first()),
+        matcher(args.get(1))
     ))
 }
 
@@ -245,7 +291,7 @@ fn generate_no_settimeout_if(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const pattern = '{}';
+        const pattern = {};
         const origSetTimeout = window.setTimeout;
 
         window.setTimeout = function(fn, delay, ...args) {{
@@ -256,7 +302,7 @@ fn generate_no_settimeout_if(args: &[String]) -> Option<String> {
             return origSetTimeout.call(this, fn, delay, ...args);
         }};
 "#,
-        pattern
+        js_string(pattern)
     ))
 }
 
@@ -267,7 +313,7 @@ fn generate_no_setinterval_if(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const pattern = '{}';
+        const pattern = {};
         const origSetInterval = window.setInterval;
 
         window.setInterval = function(fn, delay, ...args) {{
@@ -278,33 +324,84 @@ fn generate_no_setinterval_if(args: &[String]) -> Option<String> {
             return origSetInterval.call(this, fn, delay, ...args);
         }};
 "#,
-        pattern
+        js_string(pattern)
     ))
 }
 
-/// prevent-fetch: Block fetch calls matching a URL pattern
-/// Args: [url_pattern]
+/// prevent-fetch: Block fetch calls matching uBO's propsToMatch
+/// Args: [props_to_match], e.g. `/ads\.js$/` or `url:pagead method:POST`
 fn generate_prevent_fetch(args: &[String]) -> Option<String> {
-    let pattern = args.get(0).map(|s| s.as_str()).unwrap_or("");
+    let conditions = request_conditions(args.get(0).map(|s| s.as_str()).unwrap_or(""));
 
     Some(format!(
         r#"
-        const pattern = '{}';
+        const conditions = {};
         const origFetch = window.fetch;
 
         window.fetch = function(resource, options) {{
-            const url = typeof resource === 'string' ? resource :
-                        resource instanceof Request ? resource.url : String(resource);
-            if (pattern && url.includes(pattern)) {{
+            const request = resource instanceof Request ? resource : null;
+            const details = {{
+                url: typeof resource === 'string' ? resource :
+                     request ? request.url : String(resource),
+                method: String((options && options.method) || (request ? request.method : 'GET')).toUpperCase(),
+                body: options && options.body != null ? String(options.body) : '',
+            }};
+            if (conditions.length && conditions.every(([key, test]) => test(details[key]))) {{
                 return Promise.reject(new TypeError('Fetch blocked'));
             }}
             return origFetch.call(this, resource, options);
         }};
 "#,
-        pattern
+        conditions
     ))
 }
 
+/// The request properties uBO's `prevent-fetch`/`prevent-xhr` can match on
+const REQUEST_PROPERTIES: &[&str] = &["url", "method", "body"];
+
+/// Compile a propsToMatch argument to a JS array of [property, test] pairs.
+///
+/// The argument is space-separated `property:pattern` qualifiers; a value
+/// without a known property qualifies the URL. All of them must match.
+fn request_conditions(props_to_match: &str) -> String {
+    let conditions: Vec<String> = props_to_match
+        .split_whitespace()
+        .map(|token| {
+            let (property, pattern) = match token.split_once(':') {
+                Some((property, pattern)) if REQUEST_PROPERTIES.contains(&property) => (property, pattern),
+                _ => ("url", token),
+            };
+            format!("['{}', {}]", property, js_matcher(pattern))
+        })
+        .collect();
+    format!("[{}]", conditions.join(", "))
+}
+
+/// Compile a uBO pattern to a JS function testing a string: `/source/flags`
+/// becomes a regular expression, anything else a substring match
+fn js_matcher(pattern: &str) -> String {
+    let regex = pattern
+        .strip_prefix('/')
+        .and_then(|rest| rest.rsplit_once('/'))
+        .filter(|(source, flags)| {
+            !source.is_empty() && flags.chars().all(|c| "dgimsuy".contains(c))
+        });
+
+    match regex {
+        Some((source, flags)) => format!(
+            "((re) => (s) => re.test(s))(new RegExp({}, {}))",
+            js_string(source),
+            js_string(flags)
+        ),
+        None => format!("(s) => s.includes({})", js_string(pattern)),
+    }
+}
+
+/// Quote a string as a JS string literal
+fn js_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "''".to_string())
+}
+
 /// prune-property-on-set: Intercept when a window property is SET and prune sub-properties
 /// Args: [property_name, properties_to_remove]
 /// E.g., prune-property-on-set(ytInitialPlayerResponse, adPlacements playerAds adSlots)
@@ -314,8 +411,8 @@ fn generate_prune_property_on_set(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const propName = '{}';
-        const propsToRemove = '{}';
+        const propName = {};
+        const propsToRemove = {};
 
         const deepPrune = function(obj, props) {{
             if (!obj || typeof obj !== 'object') return;
@@ -346,7 +443,7 @@ fn generate_prune_property_on_set(args: &[String]) -> Option<String> {
             enumerable: true
         }});
 "#,
-        prop_name, props_to_remove
+        js_string(prop_name), js_string(props_to_remove)
     ))
 }
 
@@ -358,7 +455,7 @@ fn generate_prune_fetch_response(args: &[String]) -> Option<String> {
 
     Some(format!(
         r#"
-        const propsToRemove = '{}';
+        const propsToRemove = {};
 
         const deepPrune = function(obj, props) {{
             if (!obj || typeof obj !== 'object') return;
@@ -407,35 +504,94 @@ fn generate_prune_fetch_response(args: &[String]) -> Option<String> {
             }}
         }};
 "#,
-        props
+        js_string(props)
     ))
 }
 
-/// prevent-xhr: Block XMLHttpRequest calls matching a URL pattern
-/// Args: [url_pattern]
+/// prevent-xhr: Block XMLHttpRequest calls matching uBO's propsToMatch
+/// Args: [props_to_match], as for prevent-fetch
 fn generate_prevent_xhr(args: &[String]) -> Option<String> {
-    let pattern = args.get(0).map(|s| s.as_str()).unwrap_or("");
+    let conditions = request_conditions(args.get(0).map(|s| s.as_str()).unwrap_or(""));
 
     Some(format!(
         r#"
-        const pattern = '{}';
+        const conditions = {};
         const origOpen = XMLHttpRequest.prototype.open;
 
         XMLHttpRequest.prototype.open = function(method, url, ...args) {{
-            if (pattern && url.includes(pattern)) {{
-                this._blocked = true;
-            }}
+            this._requestDetails = {{ url: String(url), method: String(method).toUpperCase() }};
             return origOpen.call(this, method, url, ...args);
         }};
 
         const origSend = XMLHttpRequest.prototype.send;
         XMLHttpRequest.prototype.send = function(body) {{
-            if (this._blocked) {{
+            const details = Object.assign({{}}, this._requestDetails, {{
+                body: body != null ? String(body) : '',
+            }});
+            if (conditions.length && conditions.every(([key, test]) => test(details[key] || ''))) {{
                 return;
             }}
             return origSend.call(this, body);
         }};
 "#,
-        pattern
+        conditions
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: &str) -> ScriptletRule {
+        ScriptletRule::parse(line).unwrap()
+    }
+
+    #[test]
+    fn test_aell_calls_through() {
+        let js = generate_script(&rule("example.com##+js(aell)")).unwrap();
+        assert!(js.contains("const typeMatches = () => true;"));
+        assert!(js.contains("return origAddEventListener.call(this, type, handler, options);"));
+        assert!(!js.contains("// Blocked"));
+
+        let js = generate_script(&rule("example.com##+js(aell, click, /popunder/)")).unwrap();
+        assert!(js.contains(r#"const typeMatches = (s) => s.includes("click");"#));
+        assert!(js.contains(r#"new RegExp("popunder", "")"#));
+    }
+
+    #[test]
+    fn test_json_prune_wildcards() {
+        let js = generate_script(&rule("example.com##+js(json-prune, *.ads items.[].ad)")).unwrap();
+        assert!(js.contains(r#"const propsToRemove = "*.ads items.[].ad";"#));
+        assert!(js.contains("part === '*'"));
+        assert!(js.contains("part === '[]'"));
+    }
+
+    #[test]
+    fn test_arguments_are_quoted() {
+        let js = generate_json_prune(&["a'b".to_string(), r"c\d".to_string()]).unwrap();
+        assert!(js.contains(r#"const propsToRemove = "a'b";"#));
+        assert!(js.contains(r#"const needle = "c\\d";"#));
+    }
+
+    #[test]
+    fn test_prevent_fetch_regex_pattern() {
+        let js = generate_script(&rule(r"example.com##+js(no-fetch-if, /ads\.js$/i)")).unwrap();
+        assert!(js.contains(r#"new RegExp("ads\\.js$", "i")"#));
+        assert!(!js.contains(".includes("));
+    }
+
+    #[test]
+    fn test_prevent_fetch_plain_pattern() {
+        let js = generate_script(&rule("example.com##+js(prevent-fetch, pagead)")).unwrap();
+        assert!(js.contains(r#"['url', (s) => s.includes("pagead")]"#));
+        assert!(!js.contains("new RegExp"));
+    }
+
+    #[test]
+    fn test_prevent_xhr_qualifiers() {
+        let js = generate_script(&rule("example.com##+js(no-xhr-if, url:/ads/ method:POST body:track)")).unwrap();
+        assert!(js.contains(r#"['url', ((re) => (s) => re.test(s))(new RegExp("ads", ""))]"#));
+        assert!(js.contains(r#"['method', (s) => s.includes("POST")]"#));
+        assert!(js.contains(r#"['body', (s) => s.includes("track")]"#));
+    }
+}
@@ -3,13 +3,17 @@
 //! Fetches uBlock Origin filter lists from GitHub and caches them locally.
 
 use super::ScriptletError;
+use crate::http::{HttpClient, SoupClient};
 use gtk4::gio;
-use soup::prelude::*;
+use gtk4::prelude::*;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
-/// How often to check for updates (in seconds)
-const UPDATE_INTERVAL_SECS: u64 = 24 * 60 * 60; // 24 hours
+/// Seconds in a day, the unit of the update interval setting
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Seconds a filter request may take before it's abandoned
+const FETCH_TIMEOUT_SECS: u32 = 30;
 
 /// Remote filter sources
 const FILTER_SOURCES: &[(&str, &str)] = &[(
@@ -20,7 +24,48 @@ const FILTER_SOURCES: &[(&str, &str)] = &[(
 /// Bundled filter resource path
 const BUNDLED_FILTERS: &str = "/dev/myyc/qayeq/filters/youtube-scriptlets.txt";
 
+/// GET a filter list as text, failing on anything but 200 OK
+async fn fetch_text<C: HttpClient>(
+    client: &C,
+    url: &str,
+    cancellable: &gio::Cancellable,
+) -> Result<String, ScriptletError> {
+    let response = client
+        .get(url, cancellable)
+        .await
+        .map_err(|e| ScriptletError::FetchError(e.to_string()))?;
+
+    if response.status != soup::Status::Ok {
+        return Err(ScriptletError::FetchError(format!(
+            "HTTP error: {:?}",
+            response.status
+        )));
+    }
+
+    String::from_utf8(response.body)
+        .map_err(|e| ScriptletError::FetchError(format!("Invalid UTF-8: {}", e)))
+}
+
+/// How often filters are updated for a `filter-update-interval` setting,
+/// or `None` if they never are
+pub fn update_interval(setting: &str) -> Option<Duration> {
+    match setting {
+        "never" => None,
+        "weekly" => Some(Duration::from_secs(7 * DAY_SECS)),
+        _ => Some(Duration::from_secs(DAY_SECS)),
+    }
+}
+
+/// A remote filter list and its cached copy
+pub struct FilterListStatus {
+    pub name: &'static str,
+    pub url: &'static str,
+    /// Size of the cached copy, or `None` if it was never fetched
+    pub cached_bytes: Option<u64>,
+}
+
 /// Manages fetching and caching of filter lists
+#[derive(Clone)]
 pub struct FilterFetcher {
     cache_dir: PathBuf,
 }
@@ -38,16 +83,21 @@ impl FilterFetcher {
         let mut filters = Vec::new();
 
         // Always include bundled filters
-        filters.push(self.load_bundled());
+        let bundled = self.load_bundled();
 
         // Load cached remote filters
+        let mut cached = Vec::new();
         for (name, _url) in FILTER_SOURCES {
             let cache_path = self.cache_dir.join(format!("{}.txt", name));
             if cache_path.exists() {
                 match std::fs::read_to_string(&cache_path) {
+                    // An older copy of the bundled list itself gives way to it
+                    Ok(content) if superseded_by(&bundled, &content) => {
+                        log::info!("Bundled filters supersede cached filter: {}", name);
+                    }
                     Ok(content) => {
                         log::debug!("Loaded cached filter: {}", name);
-                        filters.push(content);
+                        cached.push(content);
                     }
                     Err(e) => {
                         log::warn!("Failed to read cached filter {}: {}", name, e);
@@ -56,6 +106,8 @@ impl FilterFetcher {
             }
         }
 
+        filters.push(bundled);
+        filters.extend(cached);
         if filters.is_empty() {
             return Err(ScriptletError::CacheError("No cached filters found".into()));
         }
@@ -77,6 +129,13 @@ impl FilterFetcher {
         }
     }
 
+    /// Whether the bundled list has a newer version than the one recorded
+    /// when the cache was last fetched
+    fn bundled_is_newer(&self) -> bool {
+        let metadata = std::fs::read_to_string(self.cache_dir.join("metadata.json")).ok();
+        bundled_newer_than(&self.load_bundled(), metadata.as_deref())
+    }
+
     /// Fallback filters if bundled resource is not available
     fn fallback_filters() -> String {
         r#"! Fallback YouTube ad blocking rules
@@ -87,11 +146,12 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
         .to_string()
     }
 
-    /// Check if cached filters need updating
-    pub fn needs_update(&self) -> bool {
+    /// Check if cached filters are older than the update interval, or were
+    /// fetched alongside an older bundled list
+    pub fn needs_update(&self, interval: Duration) -> bool {
         let metadata_path = self.cache_dir.join("metadata.json");
 
-        if !metadata_path.exists() {
+        if !metadata_path.exists() || self.bundled_is_newer() {
             return true;
         }
 
@@ -101,7 +161,7 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
                     let age = SystemTime::now()
                         .duration_since(modified)
                         .unwrap_or(Duration::MAX);
-                    age > Duration::from_secs(UPDATE_INTERVAL_SECS)
+                    age > interval
                 } else {
                     true
                 }
@@ -110,8 +170,37 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
         }
     }
 
-    /// Fetch filters from remote sources
-    pub async fn fetch_filters(&self) -> Result<Vec<String>, ScriptletError> {
+    /// When filters were last fetched, from the metadata file
+    pub fn last_updated(&self) -> Option<SystemTime> {
+        let metadata = std::fs::read_to_string(self.cache_dir.join("metadata.json")).ok()?;
+        parse_last_updated(&metadata)
+    }
+
+    /// Each remote source with the size of its cached copy
+    pub fn list_status(&self) -> Vec<FilterListStatus> {
+        FILTER_SOURCES
+            .iter()
+            .map(|(name, url)| FilterListStatus {
+                name,
+                url,
+                cached_bytes: std::fs::metadata(self.cache_dir.join(format!("{}.txt", name)))
+                    .ok()
+                    .map(|meta| meta.len()),
+            })
+            .collect()
+    }
+
+    /// Fetch filters from remote sources, stopping when `cancellable` is cancelled
+    pub async fn fetch_filters(&self, cancellable: &gio::Cancellable) -> Result<Vec<String>, ScriptletError> {
+        self.fetch_filters_with(&SoupClient::with_timeout(FETCH_TIMEOUT_SECS), cancellable)
+            .await
+    }
+
+    async fn fetch_filters_with<C: HttpClient>(
+        &self,
+        client: &C,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<String>, ScriptletError> {
         let mut filters = Vec::new();
 
         // Always include bundled filters
@@ -121,7 +210,7 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
         for (name, url) in FILTER_SOURCES {
             log::info!("Fetching filter: {} from {}", name, url);
 
-            match self.fetch_url(url).await {
+            match fetch_text(client, url, cancellable).await {
                 Ok(content) => {
                     // Cache the fetched content
                     let cache_path = self.cache_dir.join(format!("{}.txt", name));
@@ -130,6 +219,7 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
                     }
                     filters.push(content);
                 }
+                Err(e) if cancellable.is_cancelled() => return Err(e),
                 Err(e) => {
                     log::warn!("Failed to fetch {}: {}", name, e);
                     // Try to use cached version
@@ -148,29 +238,35 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
         Ok(filters)
     }
 
-    /// Fetch content from a URL using soup
-    async fn fetch_url(&self, url: &str) -> Result<String, ScriptletError> {
-        let session = soup::Session::new();
-        let message = soup::Message::new("GET", url)
-            .map_err(|e| ScriptletError::FetchError(format!("Invalid URL: {}", e)))?;
+    /// Fetch every remote source, failing if any of them can't be fetched
+    ///
+    /// Unlike `fetch_filters`, nothing falls back to the cache, so the caller
+    /// learns why an update didn't happen. The cache is only written once all
+    /// sources have been fetched.
+    pub async fn refresh(&self, cancellable: &gio::Cancellable) -> Result<Vec<String>, ScriptletError> {
+        let mut fetched = Vec::new();
+        for (name, url) in FILTER_SOURCES {
+            log::info!("Fetching filter: {} from {}", name, url);
+            fetched.push((name, self.fetch_url(url, cancellable).await?));
+        }
 
-        // Send request asynchronously
-        let bytes = session
-            .send_and_read_future(&message, soup::glib::Priority::DEFAULT)
-            .await
-            .map_err(|e| ScriptletError::FetchError(format!("Request failed: {}", e)))?;
-
-        // Check status code
-        let status = message.status();
-        if status != soup::Status::Ok {
-            return Err(ScriptletError::FetchError(format!(
-                "HTTP error: {:?}",
-                status
-            )));
+        let mut filters = vec![self.load_bundled()];
+        for (name, content) in fetched {
+            let cache_path = self.cache_dir.join(format!("{}.txt", name));
+            if let Err(e) = std::fs::write(&cache_path, &content) {
+                log::warn!("Failed to cache filter {}: {}", name, e);
+            }
+            filters.push(content);
         }
+        self.update_metadata();
 
-        String::from_utf8(bytes.to_vec())
-            .map_err(|e| ScriptletError::FetchError(format!("Invalid UTF-8: {}", e)))
+        Ok(filters)
+    }
+
+    /// Fetch content from a URL using soup, giving up after a timeout or
+    /// when `cancellable` is cancelled
+    async fn fetch_url(&self, url: &str, cancellable: &gio::Cancellable) -> Result<String, ScriptletError> {
+        fetch_text(&SoupClient::with_timeout(FETCH_TIMEOUT_SECS), url, cancellable).await
     }
 
     /// Update the metadata file timestamp
@@ -181,7 +277,8 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
-            "version": 1
+            "version": 1,
+            "bundled_version": extract_version(&self.load_bundled()),
         });
 
         if let Err(e) = std::fs::write(&metadata_path, metadata.to_string()) {
@@ -189,3 +286,180 @@ youtube.com,m.youtube.com,music.youtube.com##+js(json-prune, playerResponse.adPl
         }
     }
 }
+
+/// A `! Key:` header of a filter list, as written
+fn extract_header<'a>(filters: &'a str, key: &str) -> Option<&'a str> {
+    filters
+        .lines()
+        .map_while(|line| line.trim().strip_prefix('!'))
+        .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+/// The `! Version:` header of a filter list, as written
+fn extract_version(filters: &str) -> Option<&str> {
+    extract_header(filters, "Version")
+}
+
+/// Whether a cached list is an older copy of the bundled one, going by
+/// the `! Title:` and `! Version:` headers
+fn superseded_by(bundled: &str, cached: &str) -> bool {
+    let title = extract_header(bundled, "Title");
+    title.is_some()
+        && title == extract_header(cached, "Title")
+        && parse_version(cached) < parse_version(bundled)
+}
+
+/// Whether a bundled list is newer than the bundled version in the cache's
+/// metadata. If either version is unknown, as for caches written before
+/// versions were recorded, it isn't.
+fn bundled_newer_than(bundled: &str, metadata: Option<&str>) -> bool {
+    let (Some(bundled), Some(cached)) = (
+        parse_version(bundled),
+        metadata.and_then(parse_metadata_version),
+    ) else {
+        return false;
+    };
+    bundled > cached
+}
+
+/// Split a dotted version such as `2024.12.27` into comparable parts
+fn version_parts(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// The version in a filter list's header
+fn parse_version(filters: &str) -> Option<Vec<u64>> {
+    extract_version(filters).and_then(version_parts)
+}
+
+/// The bundled list version recorded by `update_metadata`
+fn parse_metadata_version(metadata: &str) -> Option<Vec<u64>> {
+    let value: serde_json::Value = serde_json::from_str(metadata).ok()?;
+    version_parts(value.get("bundled_version")?.as_str()?)
+}
+
+/// The `last_updated` time written by `update_metadata`
+fn parse_last_updated(metadata: &str) -> Option<SystemTime> {
+    let value: serde_json::Value = serde_json::from_str(metadata).ok()?;
+    let secs = value.get("last_updated")?.as_u64()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::{FakeClient, StalledClient};
+    use gtk4::glib;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_update_interval() {
+        assert_eq!(update_interval("never"), None);
+        assert_eq!(update_interval("daily"), Some(Duration::from_secs(DAY_SECS)));
+        assert_eq!(update_interval("weekly"), Some(Duration::from_secs(7 * DAY_SECS)));
+    }
+
+    #[test]
+    fn test_parse_last_updated() {
+        assert_eq!(
+            parse_last_updated(r#"{"last_updated":86400,"version":1}"#),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(86400))
+        );
+        assert_eq!(parse_last_updated("{}"), None);
+        assert_eq!(parse_last_updated("not json"), None);
+    }
+
+    #[test]
+    fn test_cancelled_mid_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(format!("{}.txt", FILTER_SOURCES[0].0));
+        std::fs::write(&cache_path, "example.com##+js(set, a, b)\n").unwrap();
+        let fetcher = Rc::new(FilterFetcher::new(dir.path()));
+        let cancellable = gio::Cancellable::new();
+        let result = Rc::new(RefCell::new(None));
+
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                context.spawn_local(glib::clone!(
+                    #[strong]
+                    fetcher,
+                    #[strong]
+                    cancellable,
+                    #[strong]
+                    result,
+                    async move {
+                        let filters = fetcher.fetch_filters_with(&StalledClient, &cancellable).await;
+                        result.replace(Some(filters));
+                    }
+                ));
+                while context.iteration(false) {}
+                assert!(result.borrow().is_none(), "the request should still be waiting");
+
+                cancellable.cancel();
+                while result.borrow().is_none() {
+                    context.iteration(true);
+                }
+            })
+            .unwrap();
+
+        // Cancelled, the cached copy isn't used instead
+        assert!(matches!(result.take(), Some(Err(ScriptletError::FetchError(_)))));
+    }
+
+    #[test]
+    fn test_fetch_text_status() {
+        let cancellable = gio::Cancellable::new();
+        let context = glib::MainContext::new();
+
+        let ok = FakeClient::new(soup::Status::Ok, &[b"! Title\n", b"example.com##+js(set, a, b)\n"]);
+        let text = context.block_on(fetch_text(&ok, "https://example.com/a.txt", &cancellable));
+        assert_eq!(text.unwrap(), "! Title\nexample.com##+js(set, a, b)\n");
+
+        let missing = FakeClient::new(soup::Status::NotFound, &[b"Not Found"]);
+        let result = context.block_on(fetch_text(&missing, "https://example.com/b.txt", &cancellable));
+        assert!(matches!(result, Err(ScriptletError::FetchError(e)) if e == "HTTP error: NotFound"));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let filters = "! Title\n! Version: 2024.12.27\n\nexample.com##+js(set, a, b)\n";
+        assert_eq!(parse_version(filters), Some(vec![2024, 12, 27]));
+        // Only the leading comment block is the header
+        assert_eq!(parse_version("example.com##+js(set, a, b)\n! Version: 1\n"), None);
+        assert!(parse_version("! Version: 2025.1.2") > parse_version("! Version: 2024.12.27"));
+    }
+
+    #[test]
+    fn test_parse_metadata_version() {
+        assert_eq!(
+            parse_metadata_version(r#"{"last_updated":1,"version":1,"bundled_version":"2024.12.27"}"#),
+            Some(vec![2024, 12, 27])
+        );
+        assert_eq!(parse_metadata_version(r#"{"last_updated":1,"version":1}"#), None);
+    }
+
+    #[test]
+    fn test_bundled_newer_than() {
+        let bundled = "! Title: Bundled\n! Version: 2025.1.2\n";
+        let older = r#"{"last_updated":1,"version":1,"bundled_version":"2024.12.27"}"#;
+        let same = r#"{"last_updated":1,"version":1,"bundled_version":"2025.1.2"}"#;
+        assert!(bundled_newer_than(bundled, Some(older)));
+        assert!(!bundled_newer_than(bundled, Some(same)));
+        // Caches from before versions were recorded are of unknown age
+        assert!(!bundled_newer_than(bundled, Some(r#"{"last_updated":1,"version":1}"#)));
+        assert!(!bundled_newer_than(bundled, None));
+    }
+
+    #[test]
+    fn test_superseded_by() {
+        let bundled = "! Title: Bundled\n! Version: 2025.1.2\n";
+        assert!(superseded_by(bundled, "! Title: Bundled\n! Version: 2024.12.27\n"));
+        assert!(!superseded_by(bundled, "! Title: Bundled\n! Version: 2025.2.1\n"));
+        // Other lists are always kept
+        assert!(!superseded_by(bundled, "! Title: uBlock filters - Quick fixes\n! Version: 2020.1.1\n"));
+        assert!(!superseded_by("! Version: 2025.1.2\n", "! Version: 2024.1.1\n"));
+    }
+}
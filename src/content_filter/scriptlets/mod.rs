@@ -7,23 +7,112 @@ mod builtin;
 mod fetcher;
 mod parser;
 
-pub use fetcher::FilterFetcher;
+pub use fetcher::{update_interval, FilterFetcher, FilterListStatus};
 pub use parser::ScriptletRule;
 
+use super::ContentManagers;
+use std::collections::HashMap;
 use std::path::Path;
-use webkit6::{UserContentInjectedFrames, UserContentManager, UserScript, UserScriptInjectionTime};
+use std::time::Duration;
+use webkit6::{UserContentInjectedFrames, UserScript, UserScriptInjectionTime};
+
+/// Directory under the data directory where fetched filters are cached
+pub const CACHE_DIR: &str = "scriptlets";
+
+/// Script message handler receiving CSP violations from pages with scriptlets
+pub const CSP_MESSAGE_HANDLER: &str = "qayeqCspViolation";
+
+/// Isolated world the CSP monitor runs in. Its message handler is only
+/// reachable from there, so pages can't post made-up violations.
+pub const CSP_MONITOR_WORLD: &str = "qayeq-csp-monitor";
+
+/// Reports violations of a page's Content-Security-Policy that block inline
+/// scripts or eval. WebKit runs user scripts outside the page's CSP, so the
+/// scriptlets themselves always run, but what they add to the page as inline
+/// script, or code they eval, is subject to it. Only violations raised from
+/// user scripts count, which WebKit names `user-script:N`; the page's own
+/// blocked inline scripts are none of our business.
+const CSP_MONITOR_SCRIPT: &str = r#"(function() {
+    document.addEventListener('securitypolicyviolation', function(e) {
+        if (!e.violatedDirective.startsWith('script-src')) return;
+        if (e.blockedURI !== 'inline' && e.blockedURI !== 'eval') return;
+        if (!String(e.sourceFile).startsWith('user-script:')) return;
+        try {
+            window.webkit.messageHandlers.qayeqCspViolation.postMessage({
+                page: location.href,
+                directive: e.violatedDirective,
+                blocked: e.blockedURI
+            });
+        } catch (err) {}
+    }, true);
+})();"#;
+
+/// File in a container's directory holding the user's own filter rules
+const CUSTOM_FILTERS_FILE: &str = "my-filters.txt";
+
+/// How many lines of a filter list compiled into rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterCheck {
+    pub recognized: usize,
+    /// Rule lines that aren't supported, such as cosmetic or network rules
+    pub skipped: usize,
+}
+
+/// Count the lines of a filter list that compile into scriptlets and those that don't.
+/// Comments and blank lines count as neither.
+pub fn check_filters(text: &str) -> FilterCheck {
+    let lines = text.lines().filter(|line| !is_comment(line)).count();
+    let recognized = compile_rules(text).len();
+    FilterCheck {
+        recognized,
+        skipped: lines - recognized,
+    }
+}
+
+/// Blank lines and `!` comments. `#` comments too, but not `##` rules.
+fn is_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('!') || (line.starts_with('#') && !line.starts_with("##"))
+}
+
+/// Parse the lines of a filter list and generate the script for each rule
+fn compile_rules(text: &str) -> Vec<(ScriptletRule, String)> {
+    text.lines()
+        .filter(|line| !is_comment(line))
+        .filter_map(|line| ScriptletRule::parse(line.trim()))
+        .filter_map(|rule| {
+            let js = builtin::generate_script(&rule)?;
+            Some((rule, js))
+        })
+        .collect()
+}
+
+/// A container's own filter rules and the scripts compiled from them
+struct CustomFilters {
+    text: String,
+    scripts: Vec<UserScript>,
+}
 
 /// Manages scriptlet injection
 pub struct ScriptletManager {
     fetcher: FilterFetcher,
+    /// Rules of the filter lists and of every container's custom filters
     rules: Vec<ScriptletRule>,
     scripts: Vec<UserScript>,
+    /// Content managers the scripts were added to, so recompiled scripts replace them
+    content_managers: Option<ContentManagers>,
+    /// Reports CSP violations on the pages the scripts run on
+    csp_monitor: Option<UserScript>,
+    /// Bundled and remote filters last compiled, kept to recompile with custom rules
+    filters: Vec<String>,
+    /// Custom filters by container
+    custom_filters: HashMap<String, CustomFilters>,
 }
 
 impl ScriptletManager {
     /// Create a new ScriptletManager with the given data directory for caching
     pub fn new(data_dir: &Path) -> Self {
-        let cache_dir = data_dir.join("scriptlets");
+        let cache_dir = data_dir.join(CACHE_DIR);
         if let Err(e) = std::fs::create_dir_all(&cache_dir) {
             log::warn!("Failed to create scriptlets cache directory: {}", e);
         }
@@ -32,13 +121,36 @@ impl ScriptletManager {
             fetcher: FilterFetcher::new(&cache_dir),
             rules: Vec::new(),
             scripts: Vec::new(),
+            content_managers: None,
+            csp_monitor: None,
+            filters: Vec::new(),
+            custom_filters: HashMap::new(),
         }
     }
 
+    /// Read a container's custom filters from its directory, to be compiled
+    /// along with the filter lists
+    pub fn load_custom_filters(&mut self, container: &str, dir: &Path) {
+        let text = std::fs::read_to_string(dir.join(CUSTOM_FILTERS_FILE)).unwrap_or_default();
+        self.custom_filters.insert(
+            container.to_string(),
+            CustomFilters {
+                text,
+                scripts: Vec::new(),
+            },
+        );
+    }
+
     /// Initialize scriptlets - load from cache or fetch from remote
     ///
-    /// This should be called during application startup.
-    pub async fn initialize(&mut self) -> Result<(), ScriptletError> {
+    /// This should be called during application startup. The cache is refreshed
+    /// if it's older than `update_interval`; `None` means filters are never fetched.
+    /// Cancelling `cancellable` abandons the fetch.
+    pub async fn initialize(
+        &mut self,
+        update_interval: Option<Duration>,
+        cancellable: &gio::Cancellable,
+    ) -> Result<(), ScriptletError> {
         log::info!("Initializing scriptlet manager...");
 
         // Try to load from cache first
@@ -46,9 +158,9 @@ impl ScriptletManager {
             Ok(cached) => {
                 log::info!("Loaded scriptlets from cache");
                 // Check for updates in background if cache is stale
-                if self.fetcher.needs_update() {
+                if update_interval.is_some_and(|interval| self.fetcher.needs_update(interval)) {
                     log::info!("Cache is stale, fetching updates...");
-                    match self.fetcher.fetch_filters().await {
+                    match self.fetcher.fetch_filters(cancellable).await {
                         Ok(fresh) => fresh,
                         Err(e) => {
                             log::warn!("Failed to fetch updates, using cache: {}", e);
@@ -59,10 +171,14 @@ impl ScriptletManager {
                     cached
                 }
             }
+            Err(_) if update_interval.is_none() => {
+                log::info!("No cache found and updates are off, keeping bundled scriptlets");
+                vec![self.fetcher.load_bundled()]
+            }
             Err(_) => {
                 // No cache, must fetch
                 log::info!("No cache found, fetching scriptlets...");
-                self.fetcher.fetch_filters().await?
+                self.fetcher.fetch_filters(cancellable).await?
             }
         };
 
@@ -89,39 +205,35 @@ impl ScriptletManager {
         );
     }
 
-    /// Parse filter text and compile to UserScripts
+    /// Parse filter text and compile to UserScripts along with each
+    /// container's custom filters, replacing the scripts already in the
+    /// content managers
     fn parse_and_compile(&mut self, filters: &[String]) {
+        if let Some(managers) = &self.content_managers {
+            for script in self.all_scripts() {
+                managers.remove_script(script);
+            }
+        }
         self.rules.clear();
         self.scripts.clear();
+        self.filters = filters.to_vec();
 
-        for filter_text in filters {
-            for line in filter_text.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with('!') {
-                    continue;
-                }
-
-                if let Some(rule) = ScriptletRule::parse(line) {
-                    // Generate JavaScript for this rule
-                    if let Some(js) = builtin::generate_script(&rule) {
-                        let allow_list = rule.webkit_allow_list();
-                        let allow_list_refs: Vec<&str> =
-                            allow_list.iter().map(|s| s.as_str()).collect();
-
-                        let user_script = UserScript::new(
-                            &js,
-                            UserContentInjectedFrames::AllFrames,
-                            UserScriptInjectionTime::Start, // Inject at document start
-                            &allow_list_refs,
-                            &[], // No block list
-                        );
-
-                        self.scripts.push(user_script);
-                        self.rules.push(rule);
-                    }
-                }
+        for (rule, js) in filters.iter().flat_map(|filter_text| compile_rules(filter_text)) {
+            self.scripts.push(rule_script(&rule, &js));
+            self.rules.push(rule);
+        }
+        for custom in self.custom_filters.values_mut() {
+            custom.scripts.clear();
+            for (rule, js) in compile_rules(&custom.text) {
+                custom.scripts.push(rule_script(&rule, &js));
+                self.rules.push(rule);
             }
         }
+        self.csp_monitor = self.compile_csp_monitor();
+
+        if let Some(managers) = &self.content_managers {
+            self.add_scripts(managers);
+        }
 
         log::debug!(
             "Compiled {} scriptlet rules into {} scripts",
@@ -130,30 +242,117 @@ impl ScriptletManager {
         );
     }
 
-    /// Add all compiled scripts to a UserContentManager
-    pub fn add_to_content_manager(&self, ucm: &UserContentManager) {
-        for script in &self.scripts {
-            ucm.add_script(script);
+    /// Every compiled script, the custom filters' ones included
+    fn all_scripts(&self) -> impl Iterator<Item = &UserScript> {
+        self.scripts
+            .iter()
+            .chain(&self.csp_monitor)
+            .chain(self.custom_filters.values().flat_map(|custom| &custom.scripts))
+    }
+
+    /// Add the scripts to the content managers, each container's custom
+    /// filters to that container only
+    fn add_scripts(&self, managers: &ContentManagers) {
+        for script in self.scripts.iter().chain(&self.csp_monitor) {
+            managers.add_script(script);
         }
-        log::info!("Added {} user scripts to content manager", self.scripts.len());
+        for (container, custom) in &self.custom_filters {
+            for script in &custom.scripts {
+                managers.add_container_script(container, script);
+            }
+        }
+    }
+
+    /// The CSP monitor, limited to the sites the rules apply to
+    fn compile_csp_monitor(&self) -> Option<UserScript> {
+        let mut allow_list: Vec<String> = self.rules.iter().flat_map(|rule| rule.webkit_allow_list()).collect();
+        allow_list.sort();
+        allow_list.dedup();
+        if allow_list.is_empty() {
+            return None;
+        }
+        let allow_list_refs: Vec<&str> = allow_list.iter().map(|s| s.as_str()).collect();
+        Some(UserScript::for_world(
+            CSP_MONITOR_SCRIPT,
+            UserContentInjectedFrames::AllFrames,
+            UserScriptInjectionTime::Start,
+            CSP_MONITOR_WORLD,
+            &allow_list_refs,
+            &[],
+        ))
+    }
+
+    /// A container's own filter rules
+    pub fn custom_filters(&self, container: &str) -> &str {
+        self.custom_filters
+            .get(container)
+            .map(|custom| custom.text.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Save a container's own filter rules in its directory and recompile
+    /// every filter with them
+    pub fn set_custom_filters(
+        &mut self,
+        container: &str,
+        dir: &Path,
+        text: &str,
+    ) -> Result<FilterCheck, ScriptletError> {
+        std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(dir.join(CUSTOM_FILTERS_FILE), text))
+            .map_err(|e| ScriptletError::CacheError(format!("Couldn't save custom filters: {}", e)))?;
+        self.custom_filters.insert(
+            container.to_string(),
+            CustomFilters {
+                text: text.to_string(),
+                scripts: Vec::new(),
+            },
+        );
+
+        let filters = std::mem::take(&mut self.filters);
+        self.parse_and_compile(&filters);
+        Ok(check_filters(text))
     }
 
-    /// Check for and apply filter updates
+    /// Add all compiled scripts to the content managers. Scripts compiled
+    /// later from updated filters replace them there.
+    pub fn add_to_content_managers(&mut self, managers: &ContentManagers) {
+        self.add_scripts(managers);
+        self.content_managers = Some(managers.clone());
+        log::info!("Added {} user scripts to content managers", self.script_count());
+    }
+
+    /// Check for and apply filter updates if the cache is older than `interval`
     ///
     /// Returns true if updates were applied.
-    pub async fn check_for_updates(&mut self) -> Result<bool, ScriptletError> {
-        if !self.fetcher.needs_update() {
+    pub async fn check_for_updates(
+        &mut self,
+        interval: Duration,
+        cancellable: &gio::Cancellable,
+    ) -> Result<bool, ScriptletError> {
+        if !self.fetcher.needs_update(interval) {
             return Ok(false);
         }
 
         log::info!("Checking for scriptlet updates...");
-        let filters = self.fetcher.fetch_filters().await?;
+        let filters = self.fetcher.fetch_filters(cancellable).await?;
         self.parse_and_compile(&filters);
         log::info!("Scriptlets updated: {} rules", self.rules.len());
 
         Ok(true)
     }
 
+    /// The fetcher, for fetching without holding the manager across the request
+    pub fn fetcher(&self) -> &FilterFetcher {
+        &self.fetcher
+    }
+
+    /// Compile freshly fetched filters, replacing the current scripts
+    pub fn apply_filters(&mut self, filters: &[String]) {
+        self.parse_and_compile(filters);
+        log::info!("Scriptlets updated: {} rules", self.rules.len());
+    }
+
     /// Get the number of loaded rules
     pub fn rule_count(&self) -> usize {
         self.rules.len()
@@ -161,10 +360,23 @@ impl ScriptletManager {
 
     /// Get the number of compiled scripts
     pub fn script_count(&self) -> usize {
-        self.scripts.len()
+        self.scripts.len() + self.custom_filters.values().map(|custom| custom.scripts.len()).sum::<usize>()
     }
 }
 
+/// The script running a rule's scriptlet at document start on the sites it applies to
+fn rule_script(rule: &ScriptletRule, js: &str) -> UserScript {
+    let allow_list = rule.webkit_allow_list();
+    let allow_list_refs: Vec<&str> = allow_list.iter().map(|s| s.as_str()).collect();
+    UserScript::new(
+        js,
+        UserContentInjectedFrames::AllFrames,
+        UserScriptInjectionTime::Start, // Inject at document start
+        &allow_list_refs,
+        &[], // No block list
+    )
+}
+
 /// Errors that can occur during scriptlet operations
 #[derive(Debug)]
 pub enum ScriptletError {
@@ -174,6 +386,8 @@ pub enum ScriptletError {
     CacheError(String),
     /// Failed to parse filters
     ParseError(String),
+    /// Another update is still running
+    UpdateInProgress,
 }
 
 impl std::fmt::Display for ScriptletError {
@@ -182,8 +396,53 @@ impl std::fmt::Display for ScriptletError {
             ScriptletError::FetchError(msg) => write!(f, "Fetch error: {}", msg),
             ScriptletError::CacheError(msg) => write!(f, "Cache error: {}", msg),
             ScriptletError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            ScriptletError::UpdateInProgress => write!(f, "An update is already running"),
         }
     }
 }
 
 impl std::error::Error for ScriptletError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_filters() {
+        let text = "! My filters\n\
+                    example.com##+js(set-constant, ads, undefined)\n\
+                    \n\
+                    example.com##.banner\n\
+                    ||ads.example.com^\n\
+                    example.com##+js(no-such-scriptlet)\n";
+        assert_eq!(
+            check_filters(text),
+            FilterCheck {
+                recognized: 1,
+                skipped: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_custom_filters_per_container() {
+        let data = tempdir().unwrap();
+        let work = data.path().join("work");
+        let text = "example.com##+js(set-constant, ads, undefined)\n";
+
+        let mut manager = ScriptletManager::new(data.path());
+        manager.set_custom_filters("work", &work, text).unwrap();
+        assert_eq!(manager.custom_filters("work"), text);
+        assert_eq!(manager.custom_filters("personal"), "");
+        assert_eq!(manager.script_count(), 1);
+        assert_eq!(std::fs::read_to_string(work.join(CUSTOM_FILTERS_FILE)).unwrap(), text);
+
+        // Read back from each container's own directory
+        let mut manager = ScriptletManager::new(data.path());
+        manager.load_custom_filters("work", &work);
+        manager.load_custom_filters("personal", &data.path().join("personal"));
+        assert_eq!(manager.custom_filters("work"), text);
+        assert_eq!(manager.custom_filters("personal"), "");
+    }
+}
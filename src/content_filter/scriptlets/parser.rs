@@ -102,6 +102,10 @@ impl ScriptletRule {
 
         for c in content.chars() {
             if escape_next {
+                // `\,` escapes a comma; other escapes, as in regexes, are kept
+                if c != ',' {
+                    current.push('\\');
+                }
                 current.push(c);
                 escape_next = false;
                 continue;
@@ -205,6 +209,18 @@ mod tests {
         assert_eq!(rule.scriptlet_name, "abort-on-property-read");
     }
 
+    #[test]
+    fn test_parse_keeps_regex_escapes() {
+        let rule = ScriptletRule::parse(r"example.com##+js(no-fetch-if, /ads\.js$/, a\,b)").unwrap();
+        assert_eq!(rule.args, vec![r"/ads\.js$/", "a,b"]);
+    }
+
+    #[test]
+    fn test_parse_wildcard_paths() {
+        let rule = ScriptletRule::parse("example.com##+js(json-prune, *.ads [].playerAds)").unwrap();
+        assert_eq!(rule.args, vec!["*.ads [].playerAds"]);
+    }
+
     #[test]
     fn test_skip_comment() {
         assert!(ScriptletRule::parse("! This is a comment").is_none());
@@ -0,0 +1,151 @@
+//! Dialog listing a site's cookies with per-cookie delete.
+
+use crate::profile::with_session_profile;
+use gtk4::prelude::*;
+use gtk4::glib;
+use libadwaita::prelude::*;
+use url::Url;
+use webkit6::soup::Cookie;
+use webkit6::NetworkSession;
+
+/// Longest cookie value shown before it's cut off
+const MAX_VALUE_CHARS: usize = 60;
+
+/// Dialog for inspecting and deleting the cookies of a site.
+pub struct CookiesDialog {
+    dialog: libadwaita::Dialog,
+    list: gtk4::ListBox,
+    placeholder: gtk4::Label,
+    url: Url,
+    session: NetworkSession,
+}
+
+impl CookiesDialog {
+    /// Create a dialog for the cookies sent to `url` in the container of `session`.
+    pub fn new(url: &Url, session: &NetworkSession) -> Self {
+        let host = url.host_str().unwrap_or_default();
+
+        let dialog = libadwaita::Dialog::builder()
+            .title(format!("Cookies for {}", host))
+            .content_width(480)
+            .content_height(420)
+            .build();
+
+        let placeholder = gtk4::Label::new(Some("Loading cookies..."));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(24);
+        placeholder.set_margin_bottom(24);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+        list.set_placeholder(Some(&placeholder));
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&list);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .vexpand(true)
+            .child(&content)
+            .build();
+
+        let toolbar_view = libadwaita::ToolbarView::new();
+        toolbar_view.add_top_bar(&libadwaita::HeaderBar::new());
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+
+        Self {
+            dialog,
+            list,
+            placeholder,
+            url: url.clone(),
+            session: session.clone(),
+        }
+    }
+
+    /// Load the cookies and present the dialog.
+    pub fn present(&self, parent: &impl IsA<gtk4::Widget>) {
+        let list = self.list.clone();
+        let placeholder = self.placeholder.clone();
+        let session = self.session.clone();
+
+        let loaded = with_session_profile(Some(&self.session), |profile| {
+            profile.cookies_for(&self.url, move |result| match result {
+                Ok(cookies) => {
+                    placeholder.set_label("No cookies for this site");
+                    for cookie in cookies {
+                        list.append(&cookie_row(&list, cookie, &session));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to load cookies: {}", e);
+                    placeholder.set_label("Couldn't load cookies");
+                }
+            });
+        });
+        if loaded.is_none() {
+            self.placeholder.set_label("Couldn't load cookies");
+        }
+
+        self.dialog.present(Some(parent));
+    }
+}
+
+/// Build a row for one cookie, deleting it from the live session on request
+fn cookie_row(list: &gtk4::ListBox, mut cookie: Cookie, session: &NetworkSession) -> libadwaita::ActionRow {
+    let name = cookie.name().map(|n| n.to_string()).unwrap_or_default();
+    let value = cookie.value().map(|v| v.to_string()).unwrap_or_default();
+    let value = if value.chars().count() > MAX_VALUE_CHARS {
+        format!("{}…", value.chars().take(MAX_VALUE_CHARS - 1).collect::<String>())
+    } else {
+        value
+    };
+    let expiry = match cookie.expires().and_then(|e| e.format("%Y-%m-%d %H:%M").ok()) {
+        Some(date) => format!("Expires {}", date),
+        None => "Session cookie".to_string(),
+    };
+
+    let row = libadwaita::ActionRow::builder()
+        .title(glib::markup_escape_text(&name))
+        .subtitle(glib::markup_escape_text(&format!("{}\n{}", value, expiry)))
+        .subtitle_lines(2)
+        .build();
+
+    let delete_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+    delete_button.set_valign(gtk4::Align::Center);
+    delete_button.set_tooltip_text(Some("Delete Cookie"));
+    delete_button.add_css_class("flat");
+    row.add_suffix(&delete_button);
+
+    let list = list.clone();
+    let session = session.clone();
+    let row_weak = row.downgrade();
+    delete_button.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        let list = list.clone();
+        let row_weak = row_weak.clone();
+        let button = button.clone();
+        let name = name.clone();
+        with_session_profile(Some(&session), |profile| {
+            profile.delete_cookie(&cookie, move |result| match result {
+                Ok(()) => {
+                    log::info!("Deleted cookie {}", name);
+                    if let Some(row) = row_weak.upgrade() {
+                        list.remove(&row);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to delete cookie {}: {}", name, e);
+                    button.set_sensitive(true);
+                }
+            });
+        });
+    });
+
+    row
+}
@@ -0,0 +1,5 @@
+//! Per-site cookie viewer.
+
+mod dialog;
+
+pub use dialog::CookiesDialog;
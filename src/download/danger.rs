@@ -0,0 +1,61 @@
+//! Recognizing downloads that can run code (executables, installers, scripts).
+
+/// MIME types of executables, installers and scripts
+const DANGEROUS_MIME_TYPES: &[&str] = &[
+    "application/x-executable",
+    "application/x-msdos-program",
+    "application/x-msdownload",
+    "application/x-ms-installer",
+    "application/x-msi",
+    "application/x-sh",
+    "application/x-shellscript",
+    "application/x-deb",
+    "application/vnd.debian.binary-package",
+    "application/x-rpm",
+    "application/x-redhat-package-manager",
+    "application/vnd.appimage",
+    "application/java-archive",
+    "application/x-apple-diskimage",
+    "application/vnd.android.package-archive",
+    "application/x-desktop",
+];
+
+/// Whether a download should be confirmed before it's saved, judged by its
+/// extension (from the user's list) or its MIME type
+pub fn is_dangerous(filename: &str, mime_type: Option<&str>, extensions: &[String]) -> bool {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    if let Some(extension) = extension {
+        if extensions
+            .iter()
+            .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+        {
+            return true;
+        }
+    }
+
+    mime_type.is_some_and(|mime| {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        DANGEROUS_MIME_TYPES.iter().any(|t| mime.eq_ignore_ascii_case(t))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dangerous_by_extension_or_mime() {
+        let extensions: Vec<String> = ["exe", ".sh", "AppImage"].iter().map(|s| s.to_string()).collect();
+
+        assert!(is_dangerous("setup.EXE", None, &extensions));
+        assert!(is_dangerous("install.sh", Some("text/plain"), &extensions));
+        assert!(is_dangerous("Tool-x86_64.appimage", None, &extensions));
+        assert!(is_dangerous("package", Some("application/vnd.debian.binary-package"), &extensions));
+        assert!(!is_dangerous("photo.jpg", Some("image/jpeg"), &extensions));
+        assert!(!is_dangerous("archive.zip", Some("application/zip"), &extensions));
+        // An empty list leaves only the MIME check
+        assert!(!is_dangerous("setup.exe", Some("application/octet-stream"), &[]));
+    }
+}
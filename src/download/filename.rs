@@ -0,0 +1,109 @@
+//! Sanitizing server-suggested download filenames.
+
+use url::Url;
+
+/// Longest filename kept, in bytes. Leaves room under the usual 255-byte
+/// limit for the counter `unique_filename` may add.
+const MAX_FILENAME_BYTES: usize = 200;
+
+/// Longest extension preserved when a name is shortened
+const MAX_EXTENSION_BYTES: usize = 16;
+
+/// Name used when neither the suggestion nor the URL gives a usable one
+const FALLBACK_FILENAME: &str = "download";
+
+/// Turn a suggested filename into a safe name for a file in the download folder.
+/// Directory components are stripped, illegal characters replaced and the
+/// length limited. If nothing usable is left, a name is derived from the URL.
+pub fn sanitize_filename(suggested: &str, uri: Option<&str>) -> String {
+    if let Some(name) = clean(suggested) {
+        return name;
+    }
+
+    uri.and_then(|uri| Url::parse(uri).ok())
+        .and_then(|url| {
+            let segment = url
+                .path_segments()
+                .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                .map(|s| urlencoding::decode(s).map(|s| s.into_owned()).unwrap_or_else(|_| s.to_string()));
+            segment
+                .and_then(|s| clean(&s))
+                .or_else(|| url.host_str().and_then(clean))
+        })
+        .unwrap_or_else(|| FALLBACK_FILENAME.to_string())
+}
+
+/// The cleaned-up name, or None if nothing meaningful is left
+fn clean(name: &str) -> Option<String> {
+    // Keep only the last path component, whichever separator was used
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() => '_',
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+
+    // Leading dots would hide the file (or make it "." / ".."), trailing ones get lost on some filesystems
+    let trimmed = replaced.trim().trim_matches('.').trim();
+    if !trimmed.chars().any(|c| c.is_alphanumeric()) {
+        return None;
+    }
+    Some(truncate(trimmed))
+}
+
+/// Shorten a name to MAX_FILENAME_BYTES, keeping a short extension
+fn truncate(name: &str) -> String {
+    if name.len() <= MAX_FILENAME_BYTES {
+        return name.to_string();
+    }
+
+    let extension = name
+        .rfind('.')
+        .map(|dot| &name[dot..])
+        .filter(|ext| ext.len() <= MAX_EXTENSION_BYTES)
+        .unwrap_or("");
+    let stem = &name[..name.len() - extension.len()];
+
+    let mut end = MAX_FILENAME_BYTES - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", stem[..end].trim_end(), extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_directories_and_illegal_characters() {
+        assert_eq!(sanitize_filename("../../etc/passwd", None), "passwd");
+        assert_eq!(sanitize_filename("..\\..\\Windows\\evil.exe", None), "evil.exe");
+        assert_eq!(sanitize_filename("report\u{0}<2024>?.pdf", None), "report__2024__.pdf");
+        assert_eq!(sanitize_filename(".bashrc", None), "bashrc");
+    }
+
+    #[test]
+    fn test_falls_back_to_url() {
+        let uri = Some("https://example.com/files/My%20Report.pdf?dl=1");
+        assert_eq!(sanitize_filename("", uri), "My Report.pdf");
+        assert_eq!(sanitize_filename("../..", uri), "My Report.pdf");
+        assert_eq!(sanitize_filename("", Some("https://example.com/")), "example.com");
+        assert_eq!(sanitize_filename("  ", None), "download");
+    }
+
+    #[test]
+    fn test_truncates_long_names() {
+        let long = format!("{}.tar.gz", "a".repeat(1000));
+        let name = sanitize_filename(&long, None);
+        assert_eq!(name.len(), MAX_FILENAME_BYTES);
+        assert!(name.ends_with(".gz"));
+
+        let multibyte = "é".repeat(300);
+        assert!(sanitize_filename(&multibyte, None).len() <= MAX_FILENAME_BYTES);
+    }
+}
@@ -1,15 +1,22 @@
 //! Download manager for tracking and displaying downloads.
 
+mod danger;
+mod filename;
 mod row;
+mod samples;
 
+pub use danger::is_dangerous;
+pub use filename::sanitize_filename;
 pub use row::DownloadRow;
+pub use samples::ProgressSamples;
 
-use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
 use std::cell::RefCell;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
 // Thread-local shared state for pending "Save As" URIs and download tracking
 // This allows TabManager (context menu), download handler, and window to communicate
@@ -65,6 +72,7 @@ pub fn add_download(url: &str, filename: &str, destination: PathBuf) -> u64 {
             status: DownloadStatus::InProgress,
             started_at: SystemTime::now(),
             supports_resume: false,
+            samples: ProgressSamples::default(),
         };
 
         dm.downloads.push(item);
@@ -88,6 +96,7 @@ pub fn update_progress(id: u64, received: u64, total: u64) {
         if let Some(item) = dm.downloads.iter_mut().find(|d| d.id == id) {
             item.received_bytes = received;
             item.total_bytes = total;
+            item.samples.record(Instant::now(), received);
         }
 
         // Notify callbacks
@@ -105,6 +114,10 @@ pub fn set_download_status(id: u64, status: DownloadStatus) {
         let mut dm = dm.borrow_mut();
         if let Some(item) = dm.downloads.iter_mut().find(|d| d.id == id) {
             item.status = status.clone();
+            // Paused downloads keep their graph for when they resume
+            if !matches!(status, DownloadStatus::InProgress | DownloadStatus::Paused) {
+                item.samples.clear();
+            }
             log::info!("Download {} status: {:?}", id, status);
         }
 
@@ -125,6 +138,11 @@ pub fn recent_downloads(limit: usize) -> Vec<DownloadItem> {
     })
 }
 
+/// Get a download by ID
+pub fn get_download(id: u64) -> Option<DownloadItem> {
+    DOWNLOAD_MANAGER.with(|dm| dm.borrow().downloads.iter().find(|d| d.id == id).cloned())
+}
+
 /// Check if there are any active downloads
 pub fn has_active_downloads() -> bool {
     DOWNLOAD_MANAGER.with(|dm| {
@@ -133,6 +151,34 @@ pub fn has_active_downloads() -> bool {
     })
 }
 
+/// Number of downloads in progress
+pub fn active_download_count() -> usize {
+    DOWNLOAD_MANAGER.with(|dm| dm.borrow().downloads.iter().filter(|d| d.is_active()).count())
+}
+
+/// Combined progress of active and paused downloads, None if there are none
+pub fn aggregate_progress() -> Option<f64> {
+    DOWNLOAD_MANAGER.with(|dm| {
+        let dm = dm.borrow();
+        let unfinished: Vec<_> = dm
+            .downloads
+            .iter()
+            .filter(|d| d.is_active() || d.is_paused())
+            .collect();
+        if unfinished.is_empty() {
+            return None;
+        }
+
+        let total_bytes: u64 = unfinished.iter().map(|d| d.total_bytes).sum();
+        let received_bytes: u64 = unfinished.iter().map(|d| d.received_bytes).sum();
+        Some(if total_bytes > 0 {
+            received_bytes as f64 / total_bytes as f64
+        } else {
+            0.0
+        })
+    })
+}
+
 /// Check if there are any downloads
 pub fn has_downloads() -> bool {
     DOWNLOAD_MANAGER.with(|dm| {
@@ -156,6 +202,32 @@ pub fn clear_completed() {
     })
 }
 
+/// Sample active downloads and notify subscribers without a progress update,
+/// so speed, time left and stalls keep showing while data isn't arriving. Returns whether any
+/// download is still active, to know when to stop.
+pub fn refresh() -> bool {
+    DOWNLOAD_MANAGER.with(|dm| {
+        let mut dm = dm.borrow_mut();
+        if !dm.downloads.iter().any(|d| d.is_active()) {
+            return false;
+        }
+
+        // Sample on every refresh too, so a stall shows in the graph as zero
+        let now = Instant::now();
+        for item in dm.downloads.iter_mut().filter(|d| d.is_active()) {
+            item.samples.record(now, item.received_bytes);
+        }
+
+        // Notify callbacks
+        let callbacks = dm.on_changed_callbacks.to_vec();
+        drop(dm);
+        for callback in callbacks {
+            callback();
+        }
+        true
+    })
+}
+
 /// Remove a specific download by ID
 pub fn remove_download(id: u64) {
     DOWNLOAD_MANAGER.with(|dm| {
@@ -174,6 +246,42 @@ pub fn remove_download(id: u64) {
     remove_cancel_callback(id);
 }
 
+/// Open a downloaded file with its default application
+pub fn open_file(path: &Path) {
+    let file = gio::File::for_path(path);
+    let launcher = gtk4::FileLauncher::new(Some(&file));
+    launcher.launch(gtk4::Window::NONE, gio::Cancellable::NONE, |result| {
+        if let Err(e) = result {
+            log::warn!("Failed to open download: {}", e);
+        }
+    });
+}
+
+/// Show a downloaded file in the file manager
+pub fn show_in_folder(path: &Path) {
+    let path = path.to_path_buf();
+    let file = gio::File::for_path(&path);
+    let launcher = gtk4::FileLauncher::new(Some(&file));
+    launcher.open_containing_folder(
+        gtk4::Window::NONE,
+        gio::Cancellable::NONE,
+        move |result| {
+            if let Err(e) = result {
+                log::warn!("Portal open_containing_folder failed: {}, trying xdg-open", e);
+                // Fallback to xdg-open
+                if let Some(parent) = path.parent() {
+                    if let Err(e) = std::process::Command::new("xdg-open")
+                        .arg(parent)
+                        .spawn()
+                    {
+                        log::error!("Failed to open folder with xdg-open: {}", e);
+                    }
+                }
+            }
+        },
+    );
+}
+
 /// Mark a URI as pending "Save As" dialog (called from context menu handler)
 pub fn mark_save_as(uri: &str) {
     PENDING_SAVE_AS.with(|set| {
@@ -187,14 +295,22 @@ pub fn is_save_as(uri: &str) -> bool {
     PENDING_SAVE_AS.with(|set| set.borrow_mut().remove(uri))
 }
 
-/// Get the last directory used for "Save As", or Downloads folder
+/// Get the last directory used for "Save As", or the default download folder
 pub fn last_save_directory() -> PathBuf {
-    LAST_SAVE_DIRECTORY.with(|dir| {
-        dir.borrow().clone().unwrap_or_else(|| {
-            glib::user_special_dir(glib::UserDirectory::Downloads)
-                .unwrap_or_else(|| PathBuf::from("."))
-        })
-    })
+    LAST_SAVE_DIRECTORY.with(|dir| dir.borrow().clone().unwrap_or_else(default_download_directory))
+}
+
+/// The folder downloads are saved to: the one chosen in preferences if it
+/// still exists, otherwise the XDG Downloads folder
+pub fn default_download_directory() -> PathBuf {
+    let settings = gio::Settings::new(crate::config::APP_ID);
+    let configured = PathBuf::from(settings.string("download-directory").as_str());
+    if !configured.as_os_str().is_empty() && configured.is_dir() {
+        return configured;
+    }
+
+    glib::user_special_dir(glib::UserDirectory::Downloads)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".into())))
 }
 
 /// Set the last directory used for "Save As"
@@ -258,6 +374,53 @@ pub fn resume_download(id: u64) {
     });
 }
 
+/// IDs of downloads matching a filter, collected up front since pausing,
+/// resuming and cancelling all notify subscribers
+fn download_ids(filter: impl Fn(&DownloadItem) -> bool) -> Vec<u64> {
+    DOWNLOAD_MANAGER.with(|dm| dm.borrow().downloads.iter().filter(|d| filter(d)).map(|d| d.id).collect())
+}
+
+/// Pause every download that can be resumed later; the rest keep running
+pub fn pause_all() {
+    for id in download_ids(DownloadItem::can_pause) {
+        pause_download(id);
+    }
+}
+
+/// Resume every paused download
+pub fn resume_all() {
+    for id in download_ids(DownloadItem::can_resume) {
+        resume_download(id);
+    }
+}
+
+/// Cancel every download that hasn't finished
+pub fn cancel_all() {
+    for id in download_ids(DownloadItem::is_unfinished) {
+        cancel_download(id);
+    }
+}
+
+/// Whether pause all, resume all and cancel all would each do anything
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkActions {
+    pub can_pause: bool,
+    pub can_resume: bool,
+    pub can_cancel: bool,
+}
+
+/// What the bulk download controls can do right now
+pub fn bulk_actions() -> BulkActions {
+    DOWNLOAD_MANAGER.with(|dm| {
+        let dm = dm.borrow();
+        BulkActions {
+            can_pause: dm.downloads.iter().any(DownloadItem::can_pause),
+            can_resume: dm.downloads.iter().any(DownloadItem::can_resume),
+            can_cancel: dm.downloads.iter().any(DownloadItem::is_unfinished),
+        }
+    })
+}
+
 /// Remove the resume callback for a download
 pub fn remove_resume_callback(id: u64) {
     RESUME_CALLBACKS.with(|callbacks| {
@@ -333,6 +496,8 @@ pub struct DownloadItem {
     pub started_at: SystemTime,
     /// Whether the server supports range requests (resume)
     pub supports_resume: bool,
+    /// Recent progress, drawn as the row's speed graph
+    pub samples: ProgressSamples,
 }
 
 impl DownloadItem {
@@ -360,23 +525,36 @@ impl DownloadItem {
         self.is_paused() && self.supports_resume
     }
 
-    /// Get download speed in bytes per second
+    /// Check if download can be paused without losing what's been received
+    pub fn can_pause(&self) -> bool {
+        self.is_active() && self.supports_resume
+    }
+
+    /// Check if download is in progress or paused
+    pub fn is_unfinished(&self) -> bool {
+        self.is_active() || self.is_paused()
+    }
+
+    /// Get download speed in bytes per second, over the last few seconds
+    /// so a stall shows rather than being averaged away
     pub fn speed_bps(&self) -> f64 {
         if !self.is_active() || self.received_bytes == 0 {
             return 0.0;
         }
+        self.samples.speed(Instant::now(), self.received_bytes)
+    }
 
-        match self.started_at.elapsed() {
-            Ok(elapsed) => {
-                let secs = elapsed.as_secs_f64();
-                if secs > 0.0 {
-                    self.received_bytes as f64 / secs
-                } else {
-                    0.0
-                }
-            }
-            Err(_) => 0.0,
+    /// Check if an active download has received nothing for a while
+    pub fn is_stalled(&self) -> bool {
+        self.is_active() && self.received_bytes > 0 && self.samples.stalled(Instant::now(), self.received_bytes)
+    }
+
+    /// Speed, time left and size of an active download, for its row
+    pub fn progress_text(&self) -> String {
+        if self.is_stalled() {
+            return format!("Stalled - {}", self.size_string());
         }
+        format!("{} - {} left - {}", self.speed_string(), self.eta_string(), self.size_string())
     }
 
     /// Get estimated time remaining in seconds
@@ -471,3 +649,95 @@ fn format_duration(secs: u64) -> String {
         format!("{}s", secs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(id: u64) -> Option<DownloadStatus> {
+        get_download(id).map(|d| d.status)
+    }
+
+    #[test]
+    fn test_refresh_shows_stall() {
+        let id = add_download("https://example.com/big", "big", PathBuf::from("/tmp/big"));
+        let texts = Rc::new(RefCell::new(Vec::new()));
+        let texts_for_changes = Rc::clone(&texts);
+        subscribe_to_changes(move || {
+            if let Some(item) = get_download(id) {
+                texts_for_changes.borrow_mut().push(item.progress_text());
+            }
+        });
+
+        // The last data arrived long enough ago that the download has stalled
+        DOWNLOAD_MANAGER.with(|dm| {
+            let mut dm = dm.borrow_mut();
+            let item = dm.downloads.iter_mut().find(|d| d.id == id).unwrap();
+            item.received_bytes = 1024;
+            item.total_bytes = 4096;
+            item.samples.record(Instant::now() - std::time::Duration::from_secs(10), 1024);
+        });
+
+        assert!(refresh());
+        assert_eq!(texts.borrow().last().map(String::as_str), Some("Stalled - 1 KB / 4 KB"));
+
+        set_download_status(id, DownloadStatus::Completed);
+        texts.borrow_mut().clear();
+        assert!(!refresh());
+        assert!(texts.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_refresh_samples_stall() {
+        let id = add_download("https://example.com/slow", "slow", PathBuf::from("/tmp/slow"));
+        DOWNLOAD_MANAGER.with(|dm| {
+            let mut dm = dm.borrow_mut();
+            let item = dm.downloads.iter_mut().find(|d| d.id == id).unwrap();
+            item.received_bytes = 2048;
+            item.samples.record(Instant::now() - std::time::Duration::from_secs(4), 1024);
+            item.samples.record(Instant::now() - std::time::Duration::from_secs(2), 2048);
+        });
+
+        // No progress since: the graph's latest interval is a zero
+        refresh();
+        let speeds = get_download(id).unwrap().samples.speeds();
+        assert_eq!(speeds.len(), 2);
+        assert_eq!(speeds.last(), Some(&0.0));
+    }
+
+    #[test]
+    fn test_bulk_actions() {
+        let resumable = add_download("https://example.com/a", "a", PathBuf::from("/tmp/a"));
+        set_supports_resume(resumable, true);
+        let streaming = add_download("https://example.com/b", "b", PathBuf::from("/tmp/b"));
+        let finished = add_download("https://example.com/c", "c", PathBuf::from("/tmp/c"));
+        set_download_status(finished, DownloadStatus::Completed);
+        assert_eq!(
+            bulk_actions(),
+            BulkActions {
+                can_pause: true,
+                can_resume: false,
+                can_cancel: true
+            }
+        );
+
+        // Only downloads that can pick up where they left off are paused
+        pause_all();
+        assert_eq!(status(resumable), Some(DownloadStatus::Paused));
+        assert_eq!(status(streaming), Some(DownloadStatus::InProgress));
+        assert_eq!(
+            bulk_actions(),
+            BulkActions {
+                can_pause: false,
+                can_resume: true,
+                can_cancel: true
+            }
+        );
+
+        cancel_all();
+        assert_eq!(status(resumable), Some(DownloadStatus::Cancelled));
+        assert_eq!(status(streaming), Some(DownloadStatus::Cancelled));
+        assert_eq!(status(finished), Some(DownloadStatus::Completed));
+        assert_eq!(bulk_actions(), BulkActions::default());
+    }
+}
@@ -17,6 +17,9 @@ mod imp {
         pub filename_label: RefCell<Option<gtk4::Label>>,
         pub status_label: RefCell<Option<gtk4::Label>>,
         pub progress_bar: RefCell<Option<gtk4::ProgressBar>>,
+        /// Recent speeds drawn by the sparkline, oldest first
+        pub speeds: Rc<RefCell<Vec<f64>>>,
+        pub sparkline: RefCell<Option<gtk4::DrawingArea>>,
         pub status_icon: RefCell<Option<gtk4::Image>>,
         pub pause_button: RefCell<Option<gtk4::Button>>,
         pub action_button: RefCell<Option<gtk4::Button>>,
@@ -105,6 +108,18 @@ mod imp {
             progress_bar.add_css_class("download-progress");
             obj.append(&progress_bar);
             *self.progress_bar.borrow_mut() = Some(progress_bar);
+
+            // Speed over the last few seconds (shown while downloading)
+            let sparkline = gtk4::DrawingArea::new();
+            sparkline.set_content_height(20);
+            sparkline.set_visible(false);
+            sparkline.add_css_class("download-sparkline");
+            let speeds = Rc::clone(&self.speeds);
+            sparkline.set_draw_func(move |area, cr, width, height| {
+                draw_sparkline(area, cr, width, height, &speeds.borrow());
+            });
+            obj.append(&sparkline);
+            *self.sparkline.borrow_mut() = Some(sparkline);
         }
     }
 
@@ -137,13 +152,7 @@ impl DownloadRow {
         if let Some(status) = imp.status_label.borrow().as_ref() {
             match &item.status {
                 DownloadStatus::InProgress => {
-                    let text = format!(
-                        "{} - {} left - {}",
-                        item.speed_string(),
-                        item.eta_string(),
-                        item.size_string()
-                    );
-                    status.set_text(&text);
+                    status.set_text(&item.progress_text());
                     status.set_visible(true);
                 }
                 DownloadStatus::Paused => {
@@ -183,6 +192,14 @@ impl DownloadRow {
             }
         }
 
+        // Update speed graph; it needs two intervals to show a trend
+        if let Some(sparkline) = imp.sparkline.borrow().as_ref() {
+            let speeds = item.samples.speeds();
+            sparkline.set_visible(item.is_active() && speeds.len() >= 2);
+            *imp.speeds.borrow_mut() = speeds;
+            sparkline.queue_draw();
+        }
+
         // Update status icon
         if let Some(icon) = imp.status_icon.borrow().as_ref() {
             match &item.status {
@@ -328,6 +345,38 @@ impl DownloadRow {
     }
 }
 
+/// Draw speeds as a line scaled to the fastest, filled below in a lighter tint
+fn draw_sparkline(area: &gtk4::DrawingArea, cr: &gtk4::cairo::Context, width: i32, height: i32, speeds: &[f64]) {
+    let max = speeds.iter().copied().fold(0.0, f64::max);
+    if speeds.len() < 2 || max <= 0.0 {
+        return;
+    }
+
+    let (width, height) = (width as f64, height as f64);
+    let step = width / (speeds.len() - 1) as f64;
+    let point = |i: usize, speed: f64| (i as f64 * step, height - 1.0 - speed / max * (height - 2.0));
+
+    for (i, speed) in speeds.iter().enumerate() {
+        let (x, y) = point(i, *speed);
+        if i == 0 {
+            cr.move_to(x, y);
+        } else {
+            cr.line_to(x, y);
+        }
+    }
+
+    let color = area.color();
+    cr.set_source_rgba(color.red().into(), color.green().into(), color.blue().into(), 0.8);
+    cr.set_line_width(1.5);
+    let _ = cr.stroke_preserve();
+
+    cr.line_to(width, height);
+    cr.line_to(0.0, height);
+    cr.close_path();
+    cr.set_source_rgba(color.red().into(), color.green().into(), color.blue().into(), 0.15);
+    let _ = cr.fill();
+}
+
 impl Default for DownloadRow {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,165 @@
+//! Recent progress of a download, sampled for its speed and speed graph.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Progress updates arrive for every chunk; keep one sample per interval
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How far back the graph goes. Rows refresh every second, sampling as
+/// they do, so a stall is in there as zero rather than a gap.
+const GRAPH_WINDOW: Duration = Duration::from_secs(30);
+
+/// Samples kept per download at most, one per interval over the window
+const MAX_SAMPLES: usize = 60;
+
+/// How far back the reported speed looks, short enough to show a stall
+const SPEED_WINDOW: Duration = Duration::from_secs(3);
+
+/// Bytes received at recent points in time, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ProgressSamples {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ProgressSamples {
+    /// Record the bytes received so far, unless the last sample is too recent
+    pub fn record(&mut self, now: Instant, received: u64) {
+        if let Some(&(last_time, last_bytes)) = self.samples.back() {
+            if now.saturating_duration_since(last_time) < SAMPLE_INTERVAL {
+                return;
+            }
+            // A download restarted from the beginning starts a new graph
+            if received < last_bytes {
+                self.samples.clear();
+            }
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        if let Some(cutoff) = now.checked_sub(GRAPH_WINDOW) {
+            while self.samples.front().is_some_and(|(time, _)| *time < cutoff) {
+                self.samples.pop_front();
+            }
+        }
+        self.samples.push_back((now, received));
+    }
+
+    /// Speed in bytes per second over the last few seconds, given the bytes
+    /// received by `now`. Falls toward zero while nothing arrives.
+    pub fn speed(&self, now: Instant, received: u64) -> f64 {
+        // Measure from the last sample at least a window old, or the first one
+        let cutoff = now.checked_sub(SPEED_WINDOW);
+        let baseline = self
+            .samples
+            .iter()
+            .rev()
+            .find(|(time, _)| cutoff.is_some_and(|cutoff| *time <= cutoff))
+            .or(self.samples.front());
+        let Some(&(since, bytes)) = baseline else {
+            return 0.0;
+        };
+
+        let secs = now.saturating_duration_since(since).as_secs_f64();
+        if secs > 0.0 {
+            received.saturating_sub(bytes) as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether nothing has arrived for a whole speed window
+    pub fn stalled(&self, now: Instant, received: u64) -> bool {
+        let Some(cutoff) = now.checked_sub(SPEED_WINDOW) else {
+            return false;
+        };
+        self.samples
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= cutoff)
+            .is_some_and(|&(_, bytes)| bytes >= received)
+    }
+
+    /// Speed in bytes per second between each pair of neighbouring samples, oldest first
+    pub fn speeds(&self) -> Vec<f64> {
+        self.samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(&(t0, b0), &(t1, b1))| {
+                let secs = t1.saturating_duration_since(t0).as_secs_f64();
+                if secs > 0.0 {
+                    b1.saturating_sub(b0) as f64 / secs
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Forget all samples, once the download is over
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speeds_follow_each_interval() {
+        let start = Instant::now();
+        let mut samples = ProgressSamples::default();
+        samples.record(start, 0);
+        // Too soon after the first sample, dropped
+        samples.record(start + Duration::from_millis(100), 50_000);
+        samples.record(start + Duration::from_secs(1), 100_000);
+        samples.record(start + Duration::from_secs(2), 100_000);
+        samples.record(start + Duration::from_secs(3), 400_000);
+        assert_eq!(samples.speeds(), vec![100_000.0, 0.0, 300_000.0]);
+
+        samples.clear();
+        assert!(samples.speeds().is_empty());
+    }
+
+    #[test]
+    fn test_speed_drops_when_stalled() {
+        let start = Instant::now();
+        let mut samples = ProgressSamples::default();
+        // 1 MB/s for ten seconds
+        for i in 0..=10 {
+            samples.record(start + Duration::from_secs(i), i * 1_000_000);
+        }
+        let received = 10_000_000;
+        let speed = samples.speed(start + Duration::from_secs(10), received);
+        assert!((speed - 1_000_000.0).abs() < 1.0, "speed {}", speed);
+
+        // Then nothing arrives: the speed falls off, and is zero once the window has passed
+        let stalled = samples.speed(start + Duration::from_millis(12_500), received);
+        assert!(stalled < 300_000.0, "speed {}", stalled);
+        assert_eq!(samples.speed(start + Duration::from_secs(15), received), 0.0);
+        assert!(!samples.stalled(start + Duration::from_millis(12_500), received));
+        assert!(samples.stalled(start + Duration::from_secs(15), received));
+    }
+
+    #[test]
+    fn test_graph_covers_window() {
+        let start = Instant::now();
+        let mut samples = ProgressSamples::default();
+        for i in 0..=40 {
+            samples.record(start + Duration::from_secs(i), i * 1000);
+        }
+        // One a second: the samples from the last 30 seconds, 30 intervals
+        assert_eq!(samples.speeds().len(), 30);
+    }
+
+    #[test]
+    fn test_sample_buffer_is_capped() {
+        let start = Instant::now();
+        let mut samples = ProgressSamples::default();
+        for i in 0..(MAX_SAMPLES as u32 * 2) {
+            samples.record(start + SAMPLE_INTERVAL * i, u64::from(i) * 1000);
+        }
+        assert_eq!(samples.speeds().len(), MAX_SAMPLES - 1);
+    }
+}
@@ -1,7 +1,10 @@
+use super::import::ImportedVisit;
+use super::writer::HistoryWriter;
 use crate::config;
 use rusqlite::{params, Connection, Result};
+use std::collections::HashMap;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
 
 /// A single history entry
@@ -45,9 +48,128 @@ impl HistoryEntry {
     }
 }
 
-/// SQLite-based history storage
+/// How long a connection waits for the other one to release the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema migrations, applied in order; step N brings the database to version N.
+/// Released steps must never change: add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: history table
+    "
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL UNIQUE,
+        title TEXT,
+        visit_count INTEGER DEFAULT 1,
+        last_visit_time INTEGER NOT NULL,
+        first_visit_time INTEGER NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_history_url ON history(url);
+    CREATE INDEX IF NOT EXISTS idx_history_last_visit ON history(last_visit_time DESC);
+    CREATE INDEX IF NOT EXISTS idx_history_visit_count ON history(visit_count DESC);
+    ",
+    // 2: favicons, one per host so pages of a site share it
+    "
+    CREATE TABLE IF NOT EXISTS favicons (
+        host TEXT PRIMARY KEY,
+        png BLOB NOT NULL,
+        updated_time INTEGER NOT NULL
+    );
+    ",
+];
+
+/// Settings shared by the read and write connections
+pub(super) fn configure_connection(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // Safe with WAL: a crash can lose the last commits but not corrupt the database
+    conn.pragma_update(None, "synchronous", "NORMAL")
+}
+
+/// Bring the schema up to date, recording each applied step in schema_version
+fn migrate(conn: &mut Connection) -> Result<()> {
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)")?;
+    let current: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(current.max(0) as usize) {
+        let version = index as i64 + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)?;
+        tx.execute("DELETE FROM schema_version", [])?;
+        tx.execute("INSERT INTO schema_version (version) VALUES (?1)", params![version])?;
+        tx.commit()?;
+        log::info!("Migrated history database to version {}", version);
+    }
+    Ok(())
+}
+
+/// Escape LIKE wildcards so `value` only matches literally (used with `ESCAPE '\'`)
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Whether `url_host` is `host` or one of its subdomains
+fn is_same_site(url_host: &str, host: &str) -> bool {
+    let url_host = url_host.trim_end_matches('.');
+    url_host.eq_ignore_ascii_case(host)
+        || (url_host.len() > host.len()
+            && url_host.as_bytes()[url_host.len() - host.len() - 1] == b'.'
+            && url_host[url_host.len() - host.len()..].eq_ignore_ascii_case(host))
+}
+
+/// When each of `hosts` or one of its subdomains was last visited, as Unix
+/// timestamps in the same order. Reads over a connection of its own, so it
+/// can run off the main thread; visits still queued for the writer aren't seen.
+pub fn last_visits_on_domains(profile_path: &Path, hosts: &[String]) -> Result<Vec<Option<i64>>> {
+    let conn = Connection::open(profile_path.join(config::HISTORY_DB))?;
+    configure_connection(&conn)?;
+
+    let mut last_visits = vec![None; hosts.len()];
+    let mut indices: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, host) in hosts.iter().enumerate() {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if !host.is_empty() {
+            indices.entry(host).or_default().push(index);
+        }
+    }
+    if indices.is_empty() {
+        return Ok(last_visits);
+    }
+
+    let mut stmt = conn.prepare("SELECT url, last_visit_time FROM history")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+    for (url, time) in rows.filter_map(|r| r.ok()) {
+        let Some(url_host) = Url::parse(&url)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_ascii_lowercase()))
+        else {
+            continue;
+        };
+        // The host itself and each parent domain it's a subdomain of
+        let mut domain = url_host.trim_end_matches('.');
+        loop {
+            for &index in indices.get(domain).into_iter().flatten() {
+                last_visits[index] = last_visits[index].max(Some(time));
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => break,
+            }
+        }
+    }
+    Ok(last_visits)
+}
+
+/// SQLite-based history storage.
+/// Reads use `conn` on the calling thread; visits and titles are written by a
+/// background thread (see `writer`).
 pub struct HistoryDatabase {
     conn: Connection,
+    writer: HistoryWriter,
 }
 
 impl std::fmt::Debug for HistoryDatabase {
@@ -60,62 +182,88 @@ impl HistoryDatabase {
     /// Create or open a history database in the given profile directory
     pub fn new(profile_path: &Path) -> Result<Self> {
         let db_path = profile_path.join(config::HISTORY_DB);
-        let conn = Connection::open(&db_path)?;
-
-        // Create tables if they don't exist
-        conn.execute_batch(
-            "
-            CREATE TABLE IF NOT EXISTS history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                url TEXT NOT NULL UNIQUE,
-                title TEXT,
-                visit_count INTEGER DEFAULT 1,
-                last_visit_time INTEGER NOT NULL,
-                first_visit_time INTEGER NOT NULL
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_history_url ON history(url);
-            CREATE INDEX IF NOT EXISTS idx_history_last_visit ON history(last_visit_time DESC);
-            CREATE INDEX IF NOT EXISTS idx_history_visit_count ON history(visit_count DESC);
-            ",
-        )?;
+        let mut conn = Connection::open(&db_path)?;
+
+        // WAL lets the writer thread commit while this connection reads.
+        // The mode is stored in the database file, so the writer inherits it.
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            log::warn!("History database is using {} journaling instead of WAL", journal_mode);
+        }
+        configure_connection(&conn)?;
+        migrate(&mut conn)?;
+
+        // Tables exist before the writer can touch them
+        let writer = HistoryWriter::spawn(&db_path)?;
 
         log::info!("History database opened at {:?}", db_path);
 
-        Ok(Self { conn })
+        Ok(Self { conn, writer })
     }
 
-    /// Record a page visit
-    pub fn record_visit(&self, url: &Url, title: Option<&str>) -> Result<()> {
+    /// Record a page visit. The write happens in the background.
+    pub fn record_visit(&self, url: &Url, title: Option<&str>) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
-        let url_str = url.as_str();
+        self.writer.record_visit(url.as_str(), title, now);
+    }
 
-        self.conn.execute(
-            "INSERT INTO history (url, title, last_visit_time, first_visit_time, visit_count)
-             VALUES (?1, ?2, ?3, ?3, 1)
-             ON CONFLICT(url) DO UPDATE SET
-                 title = COALESCE(?2, title),
-                 visit_count = visit_count + 1,
-                 last_visit_time = ?3",
-            params![url_str, title, now],
-        )?;
+    /// Update the title for a URL (called when page title changes).
+    /// The write happens in the background.
+    pub fn update_title(&self, url: &Url, title: &str) {
+        self.writer.update_title(url.as_str(), title);
+    }
 
-        log::debug!("Recorded visit to {}", url_str);
+    /// Merge pages imported from another browser, keeping the higher visit count
+    /// and the wider visit time range, so importing twice changes nothing.
+    /// The write happens in the background.
+    pub fn import(&self, visits: Vec<ImportedVisit>) {
+        self.writer.import(visits);
+    }
 
-        Ok(())
+    /// Store the favicon of a host as PNG data, replacing the previous one.
+    /// Icons over config::MAX_FAVICON_BYTES are ignored. The write happens in the background.
+    pub fn set_favicon(&self, host: &str, png: Vec<u8>) {
+        if png.is_empty() || png.len() > config::MAX_FAVICON_BYTES {
+            log::debug!("Not storing {} byte favicon for {}", png.len(), host);
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.writer.set_favicon(&host.to_ascii_lowercase(), png, now);
     }
 
-    /// Update the title for a URL (called when page title changes)
-    pub fn update_title(&self, url: &Url, title: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE history SET title = ?1 WHERE url = ?2",
-            params![title, url.as_str()],
-        )?;
-        Ok(())
+    /// The stored favicon of a host, as PNG data
+    pub fn favicon(&self, host: &str) -> Result<Option<Vec<u8>>> {
+        let mut stmt = self.conn.prepare_cached("SELECT png FROM favicons WHERE host = ?1")?;
+        let mut rows = stmt.query(params![host.to_ascii_lowercase()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Wait until all queued visits and title updates are written
+    pub fn flush(&self) {
+        self.writer.flush();
+    }
+
+    /// Write everything still queued and stop the writer thread. Later
+    /// writes are dropped.
+    pub fn shutdown(&self) {
+        self.writer.shutdown();
+    }
+
+    /// The schema version the database has been migrated to
+    pub fn schema_version(&self) -> Result<i64> {
+        self.conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
     }
 
     /// Search history by URL or title
@@ -201,6 +349,7 @@ impl HistoryDatabase {
 
     /// Delete a specific history entry
     pub fn delete_entry(&self, id: i64) -> Result<()> {
+        self.flush();
         self.conn
             .execute("DELETE FROM history WHERE id = ?1", params![id])?;
         Ok(())
@@ -208,21 +357,66 @@ impl HistoryDatabase {
 
     /// Delete entries by URL pattern
     pub fn delete_by_url(&self, url: &str) -> Result<usize> {
+        // Queued visits would otherwise bring the entry back
+        self.flush();
         let count = self
             .conn
             .execute("DELETE FROM history WHERE url = ?1", params![url])?;
         Ok(count)
     }
 
+    /// Delete every entry on a host or its subdomains ("forget this site")
+    pub fn delete_by_domain(&self, host: &str) -> Result<usize> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host.is_empty() {
+            return Ok(0);
+        }
+        self.flush();
+
+        // LIKE narrows it down; the parsed host rules out lookalikes such as
+        // "notexample.com" or the host appearing in a path or query
+        let pattern = format!("%{}%", escape_like(&host));
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, url FROM history WHERE url LIKE ?1 ESCAPE '\\'")?;
+        let ids: Vec<i64> = stmt
+            .query_map(params![pattern], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .filter_map(|r| r.ok())
+            .filter(|(_, url)| {
+                Url::parse(url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(|h| is_same_site(h, &host)))
+                    .unwrap_or(false)
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        let tx = self.conn.unchecked_transaction()?;
+        for id in &ids {
+            tx.execute("DELETE FROM history WHERE id = ?1", params![id])?;
+        }
+        tx.execute(
+            "DELETE FROM favicons WHERE host = ?1 OR host LIKE ?2 ESCAPE '\\'",
+            params![host, format!("%.{}", escape_like(&host))],
+        )?;
+        tx.commit()?;
+
+        log::info!("Deleted {} history entries for {}", ids.len(), host);
+        Ok(ids.len())
+    }
+
     /// Clear all history
     pub fn clear_all(&self) -> Result<()> {
+        self.flush();
         self.conn.execute("DELETE FROM history", [])?;
+        self.conn.execute("DELETE FROM favicons", [])?;
         log::info!("Cleared all history");
         Ok(())
     }
 
     /// Clear history older than a certain time
     pub fn clear_older_than(&self, timestamp: i64) -> Result<usize> {
+        self.flush();
         let count = self.conn.execute(
             "DELETE FROM history WHERE last_visit_time < ?1",
             params![timestamp],
@@ -283,7 +477,8 @@ mod tests {
         let db = HistoryDatabase::new(dir.path()).unwrap();
 
         let url = Url::parse("https://example.com").unwrap();
-        db.record_visit(&url, Some("Example")).unwrap();
+        db.record_visit(&url, Some("Example"));
+        db.flush();
 
         let results = db.search("example", 10).unwrap();
         assert_eq!(results.len(), 1);
@@ -298,9 +493,10 @@ mod tests {
         let db = HistoryDatabase::new(dir.path()).unwrap();
 
         let url = Url::parse("https://example.com").unwrap();
-        db.record_visit(&url, Some("Example")).unwrap();
-        db.record_visit(&url, None).unwrap();
-        db.record_visit(&url, None).unwrap();
+        db.record_visit(&url, Some("Example"));
+        db.record_visit(&url, None);
+        db.record_visit(&url, None);
+        db.flush();
 
         let entry = db.get_by_url(&url).unwrap().unwrap();
         assert_eq!(entry.visit_count, 3);
@@ -313,8 +509,9 @@ mod tests {
 
         let url1 = Url::parse("https://example1.com").unwrap();
         let url2 = Url::parse("https://example2.com").unwrap();
-        db.record_visit(&url1, None).unwrap();
-        db.record_visit(&url2, None).unwrap();
+        db.record_visit(&url1, None);
+        db.record_visit(&url2, None);
+        db.flush();
 
         assert_eq!(db.count().unwrap(), 2);
 
@@ -322,4 +519,138 @@ mod tests {
 
         assert_eq!(db.count().unwrap(), 0);
     }
+
+    #[test]
+    fn test_writes_apply_in_queue_order() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        let url = Url::parse("https://example.com").unwrap();
+        db.record_visit(&url, Some("First"));
+        db.update_title(&url, "Second");
+        db.record_visit(&url, None);
+        db.record_visit(&url, Some("Third"));
+        db.update_title(&url, "Fourth");
+        db.flush();
+
+        let entry = db.get_by_url(&url).unwrap().unwrap();
+        assert_eq!(entry.visit_count, 3);
+        assert_eq!(entry.title.as_deref(), Some("Fourth"));
+
+        // Clearing waits for queued visits, so none survive it
+        db.record_visit(&url, None);
+        db.clear_all().unwrap();
+        db.flush();
+        assert_eq!(db.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_schema_version_and_reopen() {
+        let dir = tempdir().unwrap();
+        let url = Url::parse("https://example.com").unwrap();
+        {
+            let db = HistoryDatabase::new(dir.path()).unwrap();
+            assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len() as i64);
+            let journal_mode: String = db.conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+            assert_eq!(journal_mode, "wal");
+
+            db.record_visit(&url, Some("Example"));
+            db.flush();
+        }
+
+        // Re-opening applies nothing again and keeps the data
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+        assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len() as i64);
+        let rows: i64 = db.conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(db.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_delete_by_domain_includes_subdomains_only() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        for url in [
+            "https://example.com/",
+            "https://example.com/page?q=1",
+            "http://sub.example.com/path",
+            "https://notexample.com/",
+            "https://other.org/?ref=example.com",
+            "https://example.community/",
+        ] {
+            db.record_visit(&Url::parse(url).unwrap(), None);
+        }
+        db.flush();
+
+        assert_eq!(db.delete_by_domain("example.com").unwrap(), 3);
+        let remaining: Vec<String> = db.get_recent(10).unwrap().into_iter().map(|e| e.url).collect();
+        assert_eq!(remaining.len(), 3);
+        assert!(remaining.contains(&"https://notexample.com/".to_string()));
+        assert!(remaining.contains(&"https://other.org/?ref=example.com".to_string()));
+        assert!(remaining.contains(&"https://example.community/".to_string()));
+    }
+
+    #[test]
+    fn test_shutdown_writes_queued_visits() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        db.record_visit(&Url::parse("https://example.com/").unwrap(), None);
+        db.shutdown();
+        assert_eq!(db.count().unwrap(), 1);
+
+        // The writer is gone, later visits are dropped
+        db.record_visit(&Url::parse("https://example.org/").unwrap(), None);
+        assert_eq!(db.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_last_visits_on_domains() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        db.record_visit(&Url::parse("https://www.example.com/").unwrap(), None);
+        db.record_visit(&Url::parse("https://other.org/?ref=example.net").unwrap(), None);
+        db.flush();
+
+        let entry = db.get_by_url(&Url::parse("https://www.example.com/").unwrap()).unwrap().unwrap();
+        let hosts = ["example.com", "example.net", "", "www.example.com."].map(String::from);
+        assert_eq!(
+            last_visits_on_domains(dir.path(), &hosts).unwrap(),
+            vec![Some(entry.last_visit_time), None, None, Some(entry.last_visit_time)]
+        );
+    }
+
+    #[test]
+    fn test_delete_by_subdomain_keeps_parent() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        db.record_visit(&Url::parse("https://example.com/").unwrap(), None);
+        db.record_visit(&Url::parse("https://sub.example.com/").unwrap(), None);
+        db.record_visit(&Url::parse("https://a_b.example.com/").unwrap(), None);
+
+        // Queued visits are flushed first, and "_" is not a wildcard
+        assert_eq!(db.delete_by_domain("SUB.example.com.").unwrap(), 1);
+        assert_eq!(db.delete_by_domain("a_b.example.com").unwrap(), 1);
+        assert_eq!(db.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_favicons_are_stored_per_host() {
+        let dir = tempdir().unwrap();
+        let db = HistoryDatabase::new(dir.path()).unwrap();
+
+        db.set_favicon("Example.com", vec![1, 2, 3]);
+        db.set_favicon("example.com", vec![4, 5]);
+        db.set_favicon("big.example", vec![0; config::MAX_FAVICON_BYTES + 1]);
+        db.flush();
+
+        assert_eq!(db.favicon("example.com").unwrap(), Some(vec![4, 5]));
+        assert_eq!(db.favicon("big.example").unwrap(), None);
+
+        db.delete_by_domain("example.com").unwrap();
+        assert_eq!(db.favicon("example.com").unwrap(), None);
+    }
 }
@@ -0,0 +1,291 @@
+//! Importing history from Firefox and Chromium.
+//!
+//! The other browser's database is copied to a temporary file first (with its
+//! WAL, if any), so it can be read while that browser is running and holds a
+//! lock on it, and the original is never touched.
+
+use rusqlite::{Connection, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Seconds between 1601-01-01 (Chromium's epoch) and the Unix epoch
+const CHROMIUM_EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
+
+/// A browser history can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    Firefox,
+    Chromium,
+}
+
+impl ImportSource {
+    pub const ALL: [ImportSource; 2] = [ImportSource::Firefox, ImportSource::Chromium];
+
+    /// Stable identifier, used as a dialog response ID
+    pub fn id(&self) -> &'static str {
+        match self {
+            ImportSource::Firefox => "firefox",
+            ImportSource::Chromium => "chromium",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportSource::Firefox => "Firefox",
+            ImportSource::Chromium => "Chromium",
+        }
+    }
+
+    /// The history database of this browser's most recently used profile, if installed
+    pub fn locate(&self) -> Option<PathBuf> {
+        let home = directories::BaseDirs::new()?;
+        let candidates: Vec<PathBuf> = match self {
+            ImportSource::Firefox => {
+                let home = home.home_dir();
+                [
+                    home.join(".mozilla/firefox"),
+                    home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"),
+                    home.join("snap/firefox/common/.mozilla/firefox"),
+                ]
+                .iter()
+                .flat_map(|root| profile_dirs(root))
+                .map(|dir| dir.join("places.sqlite"))
+                .collect()
+            }
+            ImportSource::Chromium => {
+                let config = home.config_dir();
+                [
+                    config.join("chromium"),
+                    config.join("google-chrome"),
+                    home.home_dir().join(".var/app/org.chromium.Chromium/config/chromium"),
+                ]
+                .iter()
+                .flat_map(|root| profile_dirs(root))
+                .map(|dir| dir.join("History"))
+                .collect()
+            }
+        };
+
+        candidates
+            .into_iter()
+            .filter_map(|path| Some((modified(&path)?, path)))
+            .max_by_key(|(time, _)| *time)
+            .map(|(_, path)| path)
+    }
+
+    /// Read every web page visited in the database at `path`
+    pub fn read_history(&self, path: &Path) -> Result<Vec<ImportedVisit>, ImportError> {
+        let copy = TempCopy::new(path)?;
+        let conn = Connection::open_with_flags(&copy.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        // Both browsers store microseconds; Firefox since the Unix epoch, Chromium since 1601
+        let (query, epoch_offset) = match self {
+            ImportSource::Firefox => (
+                "SELECT p.url, p.title, p.visit_count, p.last_visit_date,
+                        COALESCE((SELECT MIN(v.visit_date) FROM moz_historyvisits v WHERE v.place_id = p.id),
+                                 p.last_visit_date)
+                 FROM moz_places p
+                 WHERE p.last_visit_date IS NOT NULL AND p.visit_count > 0",
+                0,
+            ),
+            ImportSource::Chromium => (
+                "SELECT u.url, u.title, u.visit_count, u.last_visit_time,
+                        COALESCE((SELECT MIN(v.visit_time) FROM visits v WHERE v.url = u.id),
+                                 u.last_visit_time)
+                 FROM urls u
+                 WHERE u.hidden = 0 AND u.last_visit_time > 0",
+                CHROMIUM_EPOCH_OFFSET_SECS,
+            ),
+        };
+
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ImportedVisit {
+                url: row.get(0)?,
+                title: row.get::<_, Option<String>>(1)?.filter(|t| !t.is_empty()),
+                visit_count: row.get::<_, i64>(2)?.max(1),
+                last_visit_time: row.get::<_, i64>(3)? / 1_000_000 - epoch_offset,
+                first_visit_time: row.get::<_, i64>(4)? / 1_000_000 - epoch_offset,
+            })
+        })?;
+
+        let mut visits = Vec::new();
+        for visit in rows {
+            let visit = visit?;
+            if visit.url.starts_with("http://") || visit.url.starts_with("https://") {
+                visits.push(visit);
+            }
+        }
+        log::info!("Read {} history entries from {} at {:?}", visits.len(), self.label(), path);
+        Ok(visits)
+    }
+}
+
+/// A page from another browser's history
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedVisit {
+    pub url: String,
+    pub title: Option<String>,
+    pub visit_count: i64,
+    /// Seconds since the Unix epoch
+    pub first_visit_time: i64,
+    pub last_visit_time: i64,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Database(rusqlite::Error),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "IO error: {}", e),
+            ImportError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for ImportError {
+    fn from(e: rusqlite::Error) -> Self {
+        ImportError::Database(e)
+    }
+}
+
+/// A copy of a database in the temp directory, removed when dropped
+struct TempCopy {
+    path: PathBuf,
+}
+
+impl TempCopy {
+    fn new(source: &Path) -> std::io::Result<Self> {
+        let name = source.file_name().and_then(|n| n.to_str()).unwrap_or("history");
+        let path = std::env::temp_dir().join(format!("qayeq-import-{}-{}", std::process::id(), name));
+        let copy = Self { path };
+
+        fs::copy(source, &copy.path)?;
+        // Recent writes may still be in the WAL; an old one must not be applied to the new copy
+        let wal = with_suffix(source, "-wal");
+        if wal.exists() {
+            fs::copy(&wal, with_suffix(&copy.path, "-wal"))?;
+        } else {
+            let _ = fs::remove_file(with_suffix(&copy.path, "-wal"));
+        }
+        Ok(copy)
+    }
+}
+
+impl Drop for TempCopy {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(with_suffix(&self.path, suffix));
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Subdirectories of a browser's data directory (its profiles)
+fn profile_dirs(root: &Path) -> Vec<PathBuf> {
+    fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryDatabase;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_import_firefox_places() {
+        let dir = tempdir().unwrap();
+        let places = dir.path().join("places.sqlite");
+        let conn = Connection::open(&places).unwrap();
+        // Like a running Firefox: WAL mode, recent writes not yet checkpointed
+        conn.execute_batch(
+            "PRAGMA journal_mode=WAL;
+             PRAGMA wal_autocheckpoint=0;
+             CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+                 visit_count INTEGER, last_visit_date INTEGER);
+             CREATE TABLE moz_historyvisits (id INTEGER PRIMARY KEY, place_id INTEGER, visit_date INTEGER);
+             INSERT INTO moz_places VALUES (1, 'https://example.com/', 'Example', 3, 1700000300000000);
+             INSERT INTO moz_places VALUES (2, 'https://rust-lang.org/', '', 1, 1700000000000000);
+             INSERT INTO moz_places VALUES (3, 'place:sort=8', 'Recent Tags', 0, NULL);
+             INSERT INTO moz_places VALUES (4, 'file:///etc/hosts', 'hosts', 1, 1700000000000000);
+             INSERT INTO moz_historyvisits VALUES (1, 1, 1700000100000000);
+             INSERT INTO moz_historyvisits VALUES (2, 1, 1700000300000000);",
+        )
+        .unwrap();
+
+        let visits = ImportSource::Firefox.read_history(&places).unwrap();
+        drop(conn);
+        assert_eq!(visits.len(), 2);
+        let example = visits.iter().find(|v| v.url == "https://example.com/").unwrap();
+        assert_eq!(example.title.as_deref(), Some("Example"));
+        assert_eq!(example.visit_count, 3);
+        assert_eq!(example.first_visit_time, 1_700_000_100);
+        assert_eq!(example.last_visit_time, 1_700_000_300);
+        assert!(visits.iter().all(|v| v.title.as_deref() != Some("")));
+
+        let profile = tempdir().unwrap();
+        let db = HistoryDatabase::new(profile.path()).unwrap();
+        db.import(visits.clone());
+        // Importing again must not inflate visit counts
+        db.import(visits);
+        db.flush();
+
+        let results = db.search("example", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].visit_count, 3);
+        assert_eq!(db.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_import_chromium_history() {
+        let dir = tempdir().unwrap();
+        let history = dir.path().join("History");
+        let conn = Connection::open(&history).unwrap();
+        // 13345000000000000 µs after 1601-01-01 is 1700526400 in Unix time
+        conn.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER,
+                 last_visit_time INTEGER, hidden INTEGER);
+             CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER);
+             INSERT INTO urls VALUES (1, 'https://example.org/', 'Example', 2, 13345000000000000, 0);
+             INSERT INTO urls VALUES (2, 'https://hidden.example/', 'Hidden', 1, 13345000000000000, 1);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let visits = ImportSource::Chromium.read_history(&history).unwrap();
+        assert_eq!(visits.len(), 1);
+        assert_eq!(visits[0].url, "https://example.org/");
+        assert_eq!(visits[0].last_visit_time, 1_700_526_400);
+        assert_eq!(visits[0].first_visit_time, 1_700_526_400);
+    }
+}
@@ -1,3 +1,6 @@
 mod database;
+mod import;
+mod writer;
 
-pub use database::HistoryDatabase;
+pub use database::{last_visits_on_domains, HistoryDatabase};
+pub use import::ImportSource;
@@ -0,0 +1,207 @@
+//! Background history writer.
+//!
+//! Threading model: visits, titles and favicons are recorded from WebKit signal
+//! handlers on the GTK main thread, where a slow disk would stall navigation.
+//! They are sent over a channel to a dedicated thread that owns its own
+//! SQLite connection and applies them in the order they were sent. Reads stay
+//! on the main thread's connection; `flush` waits for everything queued so
+//! far, for callers that must see or override pending writes (clearing,
+//! deleting). `shutdown` closes the channel and waits for the thread to
+//! apply what's left, which dropping the `HistoryWriter` also does.
+
+use super::import::ImportedVisit;
+use rusqlite::{params, Connection, Result};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A queued write
+enum WriteOp {
+    Visit {
+        url: String,
+        title: Option<String>,
+        time: i64,
+    },
+    Title {
+        url: String,
+        title: String,
+    },
+    Favicon {
+        host: String,
+        png: Vec<u8>,
+        time: i64,
+    },
+    Import(Vec<ImportedVisit>),
+    /// Reply once every earlier operation has been applied
+    Flush(Sender<()>),
+}
+
+/// Handle to the writer thread
+pub struct HistoryWriter {
+    /// None once shut down, which closes the channel
+    sender: RefCell<Option<Sender<WriteOp>>>,
+    thread: RefCell<Option<JoinHandle<()>>>,
+}
+
+impl HistoryWriter {
+    /// Open a write connection to the database and start the writer thread
+    pub fn spawn(db_path: &Path) -> Result<Self> {
+        let conn = Connection::open(db_path)?;
+        super::database::configure_connection(&conn)?;
+
+        let (sender, receiver) = mpsc::channel::<WriteOp>();
+        let spawned = thread::Builder::new()
+            .name("history-writer".to_string())
+            .spawn(move || {
+                for op in receiver {
+                    let result = match op {
+                        WriteOp::Visit { url, title, time } => insert_visit(&conn, &url, title.as_deref(), time),
+                        WriteOp::Title { url, title } => set_title(&conn, &url, &title),
+                        WriteOp::Favicon { host, png, time } => set_favicon(&conn, &host, &png, time),
+                        WriteOp::Import(visits) => import_visits(&conn, &visits),
+                        WriteOp::Flush(reply) => {
+                            let _ = reply.send(());
+                            Ok(())
+                        }
+                    };
+                    if let Err(e) = result {
+                        log::warn!("Failed to write history: {}", e);
+                    }
+                }
+                log::debug!("History writer stopped");
+            });
+        let thread = match spawned {
+            Ok(thread) => Some(thread),
+            Err(e) => {
+                log::error!("Failed to start history writer: {}", e);
+                None
+            }
+        };
+
+        Ok(Self {
+            sender: RefCell::new(Some(sender)),
+            thread: RefCell::new(thread),
+        })
+    }
+
+    /// Queue a visit to `url` at `time` (seconds since the epoch)
+    pub fn record_visit(&self, url: &str, title: Option<&str>, time: i64) {
+        self.send(WriteOp::Visit {
+            url: url.to_string(),
+            title: title.map(|t| t.to_string()),
+            time,
+        });
+    }
+
+    /// Queue a title change for `url`
+    pub fn update_title(&self, url: &str, title: &str) {
+        self.send(WriteOp::Title {
+            url: url.to_string(),
+            title: title.to_string(),
+        });
+    }
+
+    /// Queue storing the favicon of `host`
+    pub fn set_favicon(&self, host: &str, png: Vec<u8>, time: i64) {
+        self.send(WriteOp::Favicon {
+            host: host.to_string(),
+            png,
+            time,
+        });
+    }
+
+    /// Queue merging pages imported from another browser
+    pub fn import(&self, visits: Vec<ImportedVisit>) {
+        self.send(WriteOp::Import(visits));
+    }
+
+    /// Block until every write queued so far has been applied
+    pub fn flush(&self) {
+        let (reply, done) = mpsc::channel();
+        self.send(WriteOp::Flush(reply));
+        // An error means the thread is gone and nothing is pending
+        let _ = done.recv();
+    }
+
+    /// Close the channel and wait for the thread to write everything queued
+    pub fn shutdown(&self) {
+        self.sender.borrow_mut().take();
+        if let Some(thread) = self.thread.borrow_mut().take() {
+            if thread.join().is_err() {
+                log::error!("History writer panicked");
+            }
+        }
+    }
+
+    fn send(&self, op: WriteOp) {
+        let sent = self.sender.borrow().as_ref().is_some_and(|sender| sender.send(op).is_ok());
+        if !sent {
+            log::warn!("History writer is not running, dropping write");
+        }
+    }
+}
+
+impl Drop for HistoryWriter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn insert_visit(conn: &Connection, url: &str, title: Option<&str>, time: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO history (url, title, last_visit_time, first_visit_time, visit_count)
+         VALUES (?1, ?2, ?3, ?3, 1)
+         ON CONFLICT(url) DO UPDATE SET
+             title = COALESCE(?2, title),
+             visit_count = visit_count + 1,
+             last_visit_time = ?3",
+        params![url, title, time],
+    )?;
+    log::debug!("Recorded visit to {}", url);
+    Ok(())
+}
+
+fn import_visits(conn: &Connection, visits: &[ImportedVisit]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO history (url, title, last_visit_time, first_visit_time, visit_count)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(url) DO UPDATE SET
+                 title = COALESCE(title, ?2),
+                 visit_count = MAX(visit_count, ?5),
+                 last_visit_time = MAX(last_visit_time, ?3),
+                 first_visit_time = MIN(first_visit_time, ?4)",
+        )?;
+        for visit in visits {
+            stmt.execute(params![
+                visit.url,
+                visit.title,
+                visit.last_visit_time,
+                visit.first_visit_time,
+                visit.visit_count
+            ])?;
+        }
+    }
+    tx.commit()?;
+    log::info!("Imported {} history entries", visits.len());
+    Ok(())
+}
+
+fn set_title(conn: &Connection, url: &str, title: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE history SET title = ?1 WHERE url = ?2",
+        params![title, url],
+    )?;
+    Ok(())
+}
+
+fn set_favicon(conn: &Connection, host: &str, png: &[u8], time: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO favicons (host, png, updated_time) VALUES (?1, ?2, ?3)
+         ON CONFLICT(host) DO UPDATE SET png = ?2, updated_time = ?3",
+        params![host, png, time],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,371 @@
+//! HTTP requests made outside WebKit.
+//!
+//! Filter list fetching and download resume go through [`HttpClient`] so
+//! their response handling can be tested against a fake server.
+//!
+//! Callers use the app's [`gio::Cancellable`]; soup is built against a newer
+//! gio, so requests carry a soup cancellable that follows it.
+
+use gtk4::gio;
+use gio::prelude::CancellableExtManual as _;
+use soup::prelude::*;
+use std::fmt;
+
+/// Bytes asked for per body read
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum HttpError {
+    InvalidUrl(String),
+    Request(String),
+    Read(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::InvalidUrl(e) => write!(f, "Invalid URL: {}", e),
+            HttpError::Request(e) => write!(f, "Request failed: {}", e),
+            HttpError::Read(e) => write!(f, "Read error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Status, headers and body of a response
+pub struct Response<B> {
+    pub status: soup::Status,
+    headers: Vec<(String, String)>,
+    pub body: B,
+}
+
+impl<B> Response<B> {
+    pub fn new(status: soup::Status, headers: Vec<(String, String)>, body: B) -> Self {
+        Self { status, headers, body }
+    }
+
+    /// First value of a header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Length from Content-Length, or 0 if it's missing
+    pub fn content_length(&self) -> u64 {
+        self.header("Content-Length")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// A response body read a chunk at a time
+pub trait BodyStream {
+    /// Next chunk of the body, empty at the end
+    async fn read_chunk(&mut self) -> Result<Vec<u8>, HttpError>;
+}
+
+pub trait HttpClient {
+    type Body: BodyStream;
+
+    /// GET a URL and read the whole body
+    async fn get(
+        &self,
+        url: &str,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Response<Vec<u8>>, HttpError>;
+
+    /// GET a URL with a Range header from `start` to `end` (inclusive) or
+    /// the end of the resource, streaming the body
+    async fn get_range(
+        &self,
+        url: &str,
+        start: u64,
+        end: Option<u64>,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Response<Self::Body>, HttpError>;
+}
+
+/// A soup cancellable cancelled along with an app cancellable, for as long
+/// as it's kept
+struct LinkedCancellable {
+    soup: soup::gio::Cancellable,
+    cancellable: gio::Cancellable,
+    handler: Option<gio::CancelledHandlerId>,
+}
+
+impl LinkedCancellable {
+    fn new(cancellable: &gio::Cancellable) -> Self {
+        let soup = soup::gio::Cancellable::new();
+        // Runs at once if already cancelled, and then no handler is kept
+        let linked = soup.clone();
+        let handler = cancellable.connect_cancelled(move |_| linked.cancel());
+        Self {
+            soup,
+            cancellable: cancellable.clone(),
+            handler,
+        }
+    }
+}
+
+impl Drop for LinkedCancellable {
+    fn drop(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            self.cancellable.disconnect_cancelled(handler);
+        }
+    }
+}
+
+/// The real client, a fresh soup session per request
+#[derive(Default)]
+pub struct SoupClient {
+    /// Seconds before a request is abandoned, 0 for no limit
+    timeout: u32,
+    /// Proxy to go through instead of the desktop's
+    proxy_resolver: Option<soup::gio::ProxyResolver>,
+}
+
+impl SoupClient {
+    pub fn with_timeout(timeout: u32) -> Self {
+        Self { timeout, ..Self::default() }
+    }
+
+    pub fn proxy_resolver(mut self, resolver: soup::gio::ProxyResolver) -> Self {
+        self.proxy_resolver = Some(resolver);
+        self
+    }
+
+    fn session(&self) -> soup::Session {
+        let builder = soup::Session::builder().timeout(self.timeout);
+        match &self.proxy_resolver {
+            Some(resolver) => builder.proxy_resolver(resolver).build(),
+            None => builder.build(),
+        }
+    }
+}
+
+/// Value of a Range header for one byte range
+fn range_header(start: u64, end: Option<u64>) -> String {
+    match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    }
+}
+
+/// Collect the response headers of a sent message
+fn response_headers(message: &soup::Message) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    if let Some(response) = message.response_headers() {
+        response.foreach(|name, value| headers.push((name.to_string(), value.to_string())));
+    }
+    headers
+}
+
+impl HttpClient for SoupClient {
+    type Body = SoupBody;
+
+    async fn get(
+        &self,
+        url: &str,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Response<Vec<u8>>, HttpError> {
+        let session = self.session();
+        let message = soup::Message::new("GET", url)
+            .map_err(|e| HttpError::InvalidUrl(e.to_string()))?;
+
+        let request = message.clone();
+        let linked = LinkedCancellable::new(cancellable);
+        let cancellable = linked.soup.clone();
+        let bytes = soup::gio::GioFuture::new(&session, move |session, _, send| {
+            session.send_and_read_async(&request, soup::glib::Priority::DEFAULT, Some(&cancellable), move |result| {
+                send.resolve(result);
+            });
+        })
+        .await
+        .map_err(|e| HttpError::Request(e.to_string()))?;
+
+        Ok(Response::new(message.status(), response_headers(&message), bytes.to_vec()))
+    }
+
+    async fn get_range(
+        &self,
+        url: &str,
+        start: u64,
+        end: Option<u64>,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Response<SoupBody>, HttpError> {
+        let session = self.session();
+        let message = soup::Message::new("GET", url)
+            .map_err(|e| HttpError::InvalidUrl(e.to_string()))?;
+        message
+            .request_headers()
+            .ok_or_else(|| HttpError::Request("No request headers".to_string()))?
+            .append("Range", &range_header(start, end));
+
+        let request = message.clone();
+        let linked = LinkedCancellable::new(cancellable);
+        let cancellable = linked.soup.clone();
+        let stream = soup::gio::GioFuture::new(&session, move |session, _, send| {
+            session.send_async(&request, soup::glib::Priority::DEFAULT, Some(&cancellable), move |result| {
+                send.resolve(result);
+            });
+        })
+        .await
+        .map_err(|e| HttpError::Request(e.to_string()))?;
+
+        Ok(Response::new(
+            message.status(),
+            response_headers(&message),
+            SoupBody { stream },
+        ))
+    }
+}
+
+/// Body of a soup response
+pub struct SoupBody {
+    stream: soup::gio::InputStream,
+}
+
+impl BodyStream for SoupBody {
+    async fn read_chunk(&mut self) -> Result<Vec<u8>, HttpError> {
+        self.stream
+            .read_bytes_future(CHUNK_SIZE, soup::glib::Priority::DEFAULT)
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| HttpError::Read(e.to_string()))
+    }
+}
+
+/// A canned server for tests
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    /// Answers every request with the same response and records what was asked
+    pub struct FakeClient {
+        status: soup::Status,
+        headers: Vec<(String, String)>,
+        chunks: Vec<Vec<u8>>,
+        /// URL and Range header of each request
+        pub requests: RefCell<Vec<(String, Option<String>)>>,
+    }
+
+    impl FakeClient {
+        pub fn new(status: soup::Status, chunks: &[&[u8]]) -> Self {
+            let length: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+            Self {
+                status,
+                headers: vec![("Content-Length".to_string(), length.to_string())],
+                chunks: chunks.iter().map(|chunk| chunk.to_vec()).collect(),
+                requests: RefCell::new(Vec::new()),
+            }
+        }
+
+        pub fn with_header(mut self, name: &str, value: &str) -> Self {
+            self.headers.push((name.to_string(), value.to_string()));
+            self
+        }
+    }
+
+    /// Never answers, failing once the request is cancelled
+    pub struct StalledClient;
+
+    impl HttpClient for StalledClient {
+        type Body = FakeBody;
+
+        async fn get(&self, _url: &str, cancellable: &gio::Cancellable) -> Result<Response<Vec<u8>>, HttpError> {
+            cancellable.future().await;
+            Err(HttpError::Request("Operation was cancelled".to_string()))
+        }
+
+        async fn get_range(
+            &self,
+            _url: &str,
+            _start: u64,
+            _end: Option<u64>,
+            cancellable: &gio::Cancellable,
+        ) -> Result<Response<FakeBody>, HttpError> {
+            cancellable.future().await;
+            Err(HttpError::Request("Operation was cancelled".to_string()))
+        }
+    }
+
+    pub struct FakeBody(VecDeque<Vec<u8>>);
+
+    impl BodyStream for FakeBody {
+        async fn read_chunk(&mut self) -> Result<Vec<u8>, HttpError> {
+            Ok(self.0.pop_front().unwrap_or_default())
+        }
+    }
+
+    impl HttpClient for FakeClient {
+        type Body = FakeBody;
+
+        async fn get(
+            &self,
+            url: &str,
+            _cancellable: &gio::Cancellable,
+        ) -> Result<Response<Vec<u8>>, HttpError> {
+            self.requests.borrow_mut().push((url.to_string(), None));
+            Ok(Response::new(self.status, self.headers.clone(), self.chunks.concat()))
+        }
+
+        async fn get_range(
+            &self,
+            url: &str,
+            start: u64,
+            end: Option<u64>,
+            _cancellable: &gio::Cancellable,
+        ) -> Result<Response<FakeBody>, HttpError> {
+            self.requests.borrow_mut().push((url.to_string(), Some(range_header(start, end))));
+            Ok(Response::new(
+                self.status,
+                self.headers.clone(),
+                FakeBody(self.chunks.iter().cloned().collect()),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gio::prelude::CancellableExt as _;
+
+    #[test]
+    fn test_response_headers() {
+        let response = Response::new(
+            soup::Status::Ok,
+            vec![("Content-Length".to_string(), " 42 ".to_string())],
+            (),
+        );
+        assert_eq!(response.header("content-length"), Some(" 42 "));
+        assert_eq!(response.header("Content-Range"), None);
+        assert_eq!(response.content_length(), 42);
+
+        let response = Response::new(soup::Status::Ok, Vec::new(), ());
+        assert_eq!(response.content_length(), 0);
+    }
+
+    #[test]
+    fn test_linked_cancellable() {
+        let cancellable = gio::Cancellable::new();
+        let linked = LinkedCancellable::new(&cancellable);
+        assert!(!linked.soup.is_cancelled());
+        cancellable.cancel();
+        assert!(linked.soup.is_cancelled());
+
+        // Already cancelled, the soup side starts out cancelled
+        assert!(LinkedCancellable::new(&cancellable).soup.is_cancelled());
+    }
+
+    #[test]
+    fn test_range_header() {
+        assert_eq!(range_header(1024, None), "bytes=1024-");
+        assert_eq!(range_header(0, Some(0)), "bytes=0-0");
+    }
+}
@@ -0,0 +1,27 @@
+//! Per-site JavaScript overrides of the global default.
+
+use crate::site_settings::SiteStore;
+use std::path::Path;
+
+const OVERRIDES_FILE: &str = "javascript.json";
+
+/// Whether JavaScript is enabled, for the hosts where it differs from the default
+#[derive(Debug, Default)]
+pub struct JavaScriptOverrides(SiteStore<bool>);
+
+impl JavaScriptOverrides {
+    /// Load the hosts' choices saved in the data directory
+    pub fn load(data_dir: &Path) -> Self {
+        Self(SiteStore::load(data_dir, OVERRIDES_FILE))
+    }
+
+    /// Get the JavaScript setting chosen for a host
+    pub fn get(&self, host: &str) -> Option<bool> {
+        self.0.get(host)
+    }
+
+    /// Set or clear the JavaScript setting for a host and persist the change
+    pub fn set(&mut self, host: &str, enabled: Option<bool>) {
+        self.0.set(host, enabled);
+    }
+}
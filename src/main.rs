@@ -3,14 +3,23 @@ mod clear_data;
 mod completion;
 mod config;
 mod content_filter;
+mod cookies;
 mod download;
 mod history;
+mod html;
+mod http;
+mod javascript;
 mod preferences;
 mod profile;
+mod qr;
 mod recently_closed;
 mod session;
+mod site_settings;
+mod snippets;
 mod tab;
 mod url_bar;
+mod user_agent;
+mod userscripts;
 mod webview;
 mod window;
 
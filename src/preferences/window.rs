@@ -2,12 +2,19 @@
 
 #![allow(deprecated)] // PreferencesWindow is deprecated since 1.6, but still works
 
+use crate::application::QayeqApplication;
 use crate::config;
+use crate::download;
+use crate::profile::{CookiePolicy, ProfileError, ProxySetting};
+use crate::session::StartupMode;
+use crate::snippets::{is_valid_trigger, Snippet, SnippetManager};
+use crate::window::QayeqWindow;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{gio, glib};
 use libadwaita::prelude::*;
 use libadwaita::subclass::prelude::*;
+use std::rc::Rc;
 
 mod imp {
     use super::*;
@@ -27,9 +34,125 @@ mod imp {
         #[template_child]
         pub new_tab_row: TemplateChild<libadwaita::EntryRow>,
         #[template_child]
-        pub restore_session_row: TemplateChild<libadwaita::SwitchRow>,
+        pub startup_mode_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub open_links_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub startup_pages_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub use_current_pages_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub download_directory_row: TemplateChild<libadwaita::ActionRow>,
+        #[template_child]
+        pub download_directory_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub ask_download_location_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub dangerous_downloads_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub download_notifications_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub https_only_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub javascript_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub mixed_content_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub privacy_signals_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub fingerprinting_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub filter_update_interval_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub update_filters_row: TemplateChild<libadwaita::ActionRow>,
+        #[template_child]
+        pub update_filters_spinner: TemplateChild<gtk4::Spinner>,
+        #[template_child]
+        pub update_filters_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub referrer_policy_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub strip_tracking_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub tracking_parameters_row: TemplateChild<libadwaita::EntryRow>,
+        #[template_child]
+        pub autoplay_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub confirm_leaving_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub popup_limit_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub force_dark_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub minimum_font_size_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub tab_layout_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub pinned_tab_close_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub simplify_url_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub always_show_toolbar_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub always_show_sidebar_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub auto_hide_delay_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub spell_checking_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub spell_checking_languages_row: TemplateChild<libadwaita::EntryRow>,
+        #[template_child]
+        pub smooth_scrolling_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub autoscroll_row: TemplateChild<libadwaita::SwitchRow>,
+        #[template_child]
+        pub hardware_acceleration_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub hibernation_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub load_timeout_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub cache_limit_row: TemplateChild<libadwaita::SpinRow>,
+        #[template_child]
+        pub container_cookies_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub container_proxy_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub container_search_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub site_permissions_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub user_scripts_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub user_scripts_folder_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub user_scripts_container_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub custom_filters_container_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub custom_filters_view: TemplateChild<gtk4::TextView>,
+        #[template_child]
+        pub save_custom_filters_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub snippets_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub snippets_container_row: TemplateChild<libadwaita::ComboRow>,
+        #[template_child]
+        pub add_snippet_button: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub containers_group: TemplateChild<libadwaita::PreferencesGroup>,
+        #[template_child]
+        pub add_container_button: TemplateChild<gtk4::Button>,
 
         pub settings: RefCell<Option<gio::Settings>>,
+        /// Rows of containers_group, removed when the list is rebuilt
+        pub container_rows: RefCell<Vec<gtk4::Widget>>,
+        /// Rows of startup_pages_group, removed when the list is rebuilt
+        pub startup_page_rows: RefCell<Vec<gtk4::Widget>>,
+        /// Rows of snippets_group, removed when the list is rebuilt
+        pub snippet_rows: RefCell<Vec<gtk4::Widget>>,
+        /// Script rows of user_scripts_group, replaced when another container is chosen
+        pub user_script_rows: RefCell<Vec<gtk4::Widget>>,
     }
 
     #[glib::object_subclass]
@@ -55,6 +178,15 @@ mod imp {
             obj.setup_settings();
             obj.bind_settings();
             obj.setup_search_engine_visibility();
+            obj.setup_container_cookie_rows();
+            obj.setup_container_proxy_rows();
+            obj.setup_container_search_rows();
+            obj.setup_site_permission_rows();
+            obj.setup_user_script_rows();
+            obj.setup_custom_filters();
+            obj.setup_snippets();
+            obj.setup_containers_page();
+            obj.setup_startup_pages();
         }
     }
 
@@ -136,10 +268,1216 @@ impl PreferencesWindow {
             .bind("new-tab-url", &*imp.new_tab_row, "text")
             .build();
 
-        // Bind restore session
+        // Bind where links from other apps open
+        let open_links_in = settings.string("open-links-in");
+        let open_links_index = config::OPEN_LINKS_TARGETS
+            .iter()
+            .position(|(id, _)| *id == open_links_in.as_str())
+            .unwrap_or(0) as u32;
+        imp.open_links_row.set_selected(open_links_index);
+        imp.open_links_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::OPEN_LINKS_TARGETS.get(row.selected() as usize) {
+                    let _ = settings.set_string("open-links-in", id);
+                }
+            }
+        ));
+
+        // Bind startup mode, which starts out following the older restore switch
+        let startup_mode = StartupMode::current(settings);
+        let startup_index = config::STARTUP_MODES
+            .iter()
+            .position(|(id, _)| *id == startup_mode.id())
+            .unwrap_or(0) as u32;
+        imp.startup_mode_row.set_selected(startup_index);
+        imp.startup_mode_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::STARTUP_MODES.get(row.selected() as usize) {
+                    let _ = settings.set_string("startup-mode", id);
+                }
+            }
+        ));
+
+        // Download folder, picked with a folder chooser
+        self.update_download_directory_row();
+        imp.download_directory_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.choose_download_directory();
+            }
+        ));
+
+        // Bind always ask where to save
+        settings
+            .bind("ask-download-location", &*imp.ask_download_location_row, "active")
+            .build();
+
+        // Bind dangerous download warning
+        settings
+            .bind("warn-dangerous-downloads", &*imp.dangerous_downloads_row, "active")
+            .build();
+
+        // Bind download notifications
+        settings
+            .bind("download-notifications", &*imp.download_notifications_row, "active")
+            .build();
+
+        // Bind HTTPS-only mode
+        settings
+            .bind("https-only-mode", &*imp.https_only_row, "active")
+            .build();
+
+        // Bind JavaScript default
+        settings
+            .bind("javascript-enabled", &*imp.javascript_row, "active")
+            .build();
+
+        // Bind pop-up limit
+        settings
+            .bind("popup-limit", &*imp.popup_limit_row, "value")
+            .build();
+
+        // Bind mixed content policy
+        let policy = settings.string("mixed-content");
+        let policy_index = config::MIXED_CONTENT_POLICIES
+            .iter()
+            .position(|(id, _)| *id == policy.as_str())
+            .unwrap_or(0) as u32;
+        imp.mixed_content_row.set_selected(policy_index);
+        imp.mixed_content_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::MIXED_CONTENT_POLICIES.get(row.selected() as usize) {
+                    let _ = settings.set_string("mixed-content", id);
+                }
+            }
+        ));
+
+        // Bind Global Privacy Control
+        settings
+            .bind("global-privacy-control", &*imp.privacy_signals_row, "active")
+            .build();
+
         settings
-            .bind("restore-session", &*imp.restore_session_row, "active")
+            .bind("fingerprinting-protection", &*imp.fingerprinting_row, "active")
+            .build();
+
+        // Bind filter list update interval
+        let update_interval = settings.string("filter-update-interval");
+        let update_index = config::FILTER_UPDATE_INTERVALS
+            .iter()
+            .position(|(id, _)| *id == update_interval.as_str())
+            .unwrap_or(1) as u32;
+        imp.filter_update_interval_row.set_selected(update_index);
+        imp.filter_update_interval_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::FILTER_UPDATE_INTERVALS.get(row.selected() as usize) {
+                    let _ = settings.set_string("filter-update-interval", id);
+                }
+            }
+        ));
+
+        // Update filter lists on demand, showing the outcome under the row
+        imp.update_filters_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |button| {
+                let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+                    return;
+                };
+                let imp = window.imp();
+                button.set_sensitive(false);
+                imp.update_filters_spinner.set_visible(true);
+                imp.update_filters_spinner.start();
+                imp.update_filters_row.set_subtitle("Updating…");
+                app.update_filters(glib::clone!(
+                    #[weak]
+                    window,
+                    move |message| {
+                        let imp = window.imp();
+                        imp.update_filters_spinner.stop();
+                        imp.update_filters_spinner.set_visible(false);
+                        imp.update_filters_button.set_sensitive(true);
+                        imp.update_filters_row.set_subtitle(message);
+                    }
+                ));
+            }
+        ));
+
+        // Bind referrer policy
+        let referrer_policy = settings.string("referrer-policy");
+        let referrer_index = config::REFERRER_POLICIES
+            .iter()
+            .position(|(id, _)| *id == referrer_policy.as_str())
+            .unwrap_or(0) as u32;
+        imp.referrer_policy_row.set_selected(referrer_index);
+        imp.referrer_policy_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::REFERRER_POLICIES.get(row.selected() as usize) {
+                    let _ = settings.set_string("referrer-policy", id);
+                }
+            }
+        ));
+
+        // Bind tracking parameter stripping. Extra parameters are saved on apply.
+        let strip_mode = settings.string("strip-tracking-parameters");
+        let strip_index = config::STRIP_TRACKING_MODES
+            .iter()
+            .position(|(id, _)| *id == strip_mode.as_str())
+            .unwrap_or(1) as u32;
+        imp.strip_tracking_row.set_selected(strip_index);
+        imp.strip_tracking_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::STRIP_TRACKING_MODES.get(row.selected() as usize) {
+                    let _ = settings.set_string("strip-tracking-parameters", id);
+                }
+            }
+        ));
+        imp.tracking_parameters_row
+            .set_text(&settings.string("extra-tracking-parameters"));
+        imp.tracking_parameters_row.connect_apply(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                let _ = settings.set_string("extra-tracking-parameters", row.text().trim());
+            }
+        ));
+
+        // Bind autoplay policy
+        let autoplay = settings.string("autoplay");
+        let autoplay_index = config::AUTOPLAY_POLICIES
+            .iter()
+            .position(|(id, _)| *id == autoplay.as_str())
+            .unwrap_or(1) as u32;
+        imp.autoplay_row.set_selected(autoplay_index);
+        imp.autoplay_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::AUTOPLAY_POLICIES.get(row.selected() as usize) {
+                    let _ = settings.set_string("autoplay", id);
+                }
+            }
+        ));
+
+        // Bind beforeunload confirmation
+        settings
+            .bind("confirm-leaving-pages", &*imp.confirm_leaving_row, "active")
+            .build();
+
+        // Bind force dark mode
+        settings
+            .bind("force-dark-mode", &*imp.force_dark_row, "active")
+            .build();
+
+        // Bind minimum font size
+        settings
+            .bind("minimum-font-size", &*imp.minimum_font_size_row, "value")
+            .build();
+
+        // Bind tab layout
+        let layout = settings.string("tab-layout");
+        let layout_index = config::TAB_LAYOUTS
+            .iter()
+            .position(|(id, _)| *id == layout.as_str())
+            .unwrap_or(0) as u32;
+        imp.tab_layout_row.set_selected(layout_index);
+        imp.tab_layout_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::TAB_LAYOUTS.get(row.selected() as usize) {
+                    let _ = settings.set_string("tab-layout", id);
+                }
+            }
+        ));
+
+        // Bind pinned tab close protection
+        let pinned_tab_close = settings.string("pinned-tab-close");
+        let pinned_tab_close_index = config::PINNED_TAB_CLOSE_MODES
+            .iter()
+            .position(|(id, _)| *id == pinned_tab_close.as_str())
+            .unwrap_or(0) as u32;
+        imp.pinned_tab_close_row.set_selected(pinned_tab_close_index);
+        imp.pinned_tab_close_row.connect_selected_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                if let Some((id, _)) = config::PINNED_TAB_CLOSE_MODES.get(row.selected() as usize) {
+                    let _ = settings.set_string("pinned-tab-close", id);
+                }
+            }
+        ));
+
+        settings
+            .bind("simplify-url-bar", &*imp.simplify_url_row, "active")
+            .build();
+
+        // Bind toolbar and sidebar visibility; the delay only matters while the sidebar hides
+        settings
+            .bind("always-show-toolbar", &*imp.always_show_toolbar_row, "active")
+            .build();
+        settings
+            .bind("always-show-sidebar", &*imp.always_show_sidebar_row, "active")
+            .build();
+        settings
+            .bind("auto-hide-delay", &*imp.auto_hide_delay_row, "value")
+            .build();
+        settings
+            .bind("always-show-sidebar", &*imp.auto_hide_delay_row, "sensitive")
+            .invert_boolean()
+            .get_only()
+            .build();
+
+        // Bind spell checking. Languages are saved on apply, not on every keystroke.
+        settings
+            .bind("spell-checking", &*imp.spell_checking_row, "active")
+            .build();
+        settings
+            .bind("spell-checking", &*imp.spell_checking_languages_row, "sensitive")
+            .get()
+            .build();
+        imp.spell_checking_languages_row
+            .set_text(&settings.string("spell-checking-languages"));
+        imp.spell_checking_languages_row.connect_apply(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                let _ = settings.set_string("spell-checking-languages", row.text().trim());
+            }
+        ));
+
+        // Bind smooth scrolling
+        settings
+            .bind("smooth-scrolling", &*imp.smooth_scrolling_row, "active")
+            .build();
+
+        // Bind autoscroll
+        settings
+            .bind("middle-click-autoscroll", &*imp.autoscroll_row, "active")
+            .build();
+
+        // Bind hardware acceleration policy
+        let policy = settings.string("hardware-acceleration");
+        let policy_index = config::HARDWARE_ACCELERATION_POLICIES
+            .iter()
+            .position(|(id, _)| *id == policy.as_str())
+            .unwrap_or(0) as u32;
+        imp.hardware_acceleration_row.set_selected(policy_index);
+
+        imp.hardware_acceleration_row.connect_selected_notify(
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |row| {
+                    let index = row.selected() as usize;
+                    let Some((id, _)) = config::HARDWARE_ACCELERATION_POLICIES.get(index) else {
+                        return;
+                    };
+                    if let Some(settings) = window.imp().settings.borrow().as_ref() {
+                        if settings.string("hardware-acceleration").as_str() == *id {
+                            return;
+                        }
+                        let _ = settings.set_string("hardware-acceleration", id);
+                    }
+                    // Existing webviews keep their policy until they're recreated
+                    let toast = libadwaita::Toast::new("Applies to new tabs. Restart to apply everywhere");
+                    toast.set_timeout(3);
+                    window.add_toast(toast);
+                }
+            ),
+        );
+
+        // Bind tab hibernation delay
+        settings
+            .bind("tab-hibernation-minutes", &*imp.hibernation_row, "value")
+            .build();
+        settings
+            .bind("load-timeout-seconds", &*imp.load_timeout_row, "value")
+            .build();
+        settings
+            .bind("cache-size-limit-mb", &*imp.cache_limit_row, "value")
+            .build();
+    }
+
+    fn update_download_directory_row(&self) {
+        let directory = download::default_download_directory();
+        self.imp()
+            .download_directory_row
+            .set_subtitle(&glib::markup_escape_text(&directory.to_string_lossy()));
+    }
+
+    fn choose_download_directory(&self) {
+        let dialog = gtk4::FileDialog::builder()
+            .title("Download Folder")
+            .initial_folder(&gio::File::for_path(download::default_download_directory()))
+            .build();
+
+        dialog.select_folder(Some(self), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |result| {
+                let Some(path) = result.ok().and_then(|folder| folder.path()) else {
+                    return;
+                };
+                if let Some(settings) = window.imp().settings.borrow().as_ref() {
+                    let _ = settings.set_string("download-directory", &path.to_string_lossy());
+                }
+                window.update_download_directory_row();
+            }
+        ));
+    }
+
+    /// Add a cookie policy row for each container.
+    /// These live in per-profile settings rather than GSettings.
+    fn setup_container_cookie_rows(&self) {
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+
+        let labels: Vec<&str> = CookiePolicy::ALL.iter().map(|p| p.label()).collect();
+        for container in pm.containers() {
+            let settings = pm.container_settings(&container.id);
+            let container_id = container.id;
+
+            let row = libadwaita::ComboRow::builder()
+                .title(container.name)
+                .model(&gtk4::StringList::new(&labels))
+                .build();
+            let selected = CookiePolicy::ALL
+                .iter()
+                .position(|p| *p == settings.cookie_policy)
+                .unwrap_or(0);
+            row.set_selected(selected as u32);
+
+            row.connect_selected_notify(glib::clone!(
+                #[weak]
+                app,
+                move |row| {
+                    let Some(policy) = CookiePolicy::ALL.get(row.selected() as usize) else {
+                        return;
+                    };
+                    let Some(pm) = app.profile_manager() else {
+                        return;
+                    };
+                    let result = pm.update_container_settings(
+                        &container_id,
+                        |profile| profile.set_cookie_policy(*policy),
+                        |settings| settings.cookie_policy = *policy,
+                    );
+                    if let Err(e) = result {
+                        log::error!("Failed to save cookie policy for {}: {}", container_id, e);
+                    }
+                }
+            ));
+            self.imp().container_cookies_group.add(&row);
+        }
+    }
+
+    /// Add a proxy mode row for each container, with an address row for manual proxies.
+    /// Like cookie policies, these live in per-profile settings.
+    fn setup_container_proxy_rows(&self) {
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+
+        for container in pm.containers() {
+            let proxy = pm.container_settings(&container.id).proxy;
+            let container_id = container.id;
+
+            let mode_row = libadwaita::ComboRow::builder()
+                .title(container.name)
+                .model(&gtk4::StringList::new(&["System", "No Proxy", "Manual"]))
+                .build();
+            let address_row = libadwaita::EntryRow::builder()
+                .title("Proxy Address (host:port or socks5://host:port)")
+                .show_apply_button(true)
+                .build();
+            let selected = match &proxy {
+                ProxySetting::System => 0,
+                ProxySetting::NoProxy => 1,
+                ProxySetting::Manual(uri) => {
+                    address_row.set_text(uri);
+                    2
+                }
+            };
+            mode_row.set_selected(selected);
+            address_row.set_visible(selected == 2);
+
+            // A manual proxy is only saved once its address is applied
+            mode_row.connect_selected_notify(glib::clone!(
+                #[weak]
+                app,
+                #[weak]
+                address_row,
+                #[strong]
+                container_id,
+                move |row| {
+                    address_row.set_visible(row.selected() == 2);
+                    match row.selected() {
+                        0 => save_container_proxy(&app, &container_id, ProxySetting::System),
+                        1 => save_container_proxy(&app, &container_id, ProxySetting::NoProxy),
+                        _ => {
+                            if let Some(proxy) = ProxySetting::manual(&address_row.text()) {
+                                save_container_proxy(&app, &container_id, proxy);
+                            }
+                        }
+                    }
+                }
+            ));
+            address_row.connect_apply(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                app,
+                move |row| match ProxySetting::manual(&row.text()) {
+                    Some(proxy) => {
+                        row.remove_css_class("error");
+                        if let ProxySetting::Manual(uri) = &proxy {
+                            row.set_text(uri);
+                        }
+                        save_container_proxy(&app, &container_id, proxy);
+                    }
+                    None => {
+                        row.add_css_class("error");
+                        window.add_toast(libadwaita::Toast::new("Enter a proxy as host:port or scheme://host:port"));
+                    }
+                }
+            ));
+
+            let group = &self.imp().container_proxy_group;
+            group.add(&mode_row);
+            group.add(&address_row);
+        }
+    }
+
+    /// Add a search engine row for each container, with a URL row for a custom engine.
+    /// "Default" follows the search engine chosen above.
+    fn setup_container_search_rows(&self) {
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+
+        // "Default", then the built-in engines; the last one is Custom
+        let labels: Vec<&str> = std::iter::once("Default")
+            .chain(config::SEARCH_ENGINES.iter().map(|(_, name, _)| *name))
+            .collect();
+        let custom_index = config::SEARCH_ENGINES.len() as u32;
+
+        for container in pm.containers() {
+            let search_url = pm.container_settings(&container.id).search_url;
+            let container_id = container.id;
+
+            let engine_row = libadwaita::ComboRow::builder()
+                .title(container.name)
+                .model(&gtk4::StringList::new(&labels))
+                .build();
+            let custom_row = libadwaita::EntryRow::builder()
+                .title("Custom Search URL ({} for the query)")
+                .show_apply_button(true)
+                .build();
+            let selected = match search_url {
+                None => 0,
+                Some(search_url) => config::SEARCH_ENGINES
+                    .iter()
+                    .position(|(_, _, url)| !url.is_empty() && *url == search_url)
+                    .map(|index| index as u32 + 1)
+                    .unwrap_or_else(|| {
+                        custom_row.set_text(&search_url);
+                        custom_index
+                    }),
+            };
+            engine_row.set_selected(selected);
+            custom_row.set_visible(selected == custom_index);
+
+            // A custom engine is only saved once its URL is applied
+            engine_row.connect_selected_notify(glib::clone!(
+                #[weak]
+                app,
+                #[weak]
+                custom_row,
+                #[strong]
+                container_id,
+                move |row| {
+                    let selected = row.selected();
+                    custom_row.set_visible(selected == custom_index);
+                    if selected == 0 {
+                        save_container_search_url(&app, &container_id, None);
+                    } else if selected == custom_index {
+                        let text = custom_row.text();
+                        if text.contains("{}") {
+                            save_container_search_url(&app, &container_id, Some(text.to_string()));
+                        }
+                    } else if let Some((_, _, url)) = config::SEARCH_ENGINES.get(selected as usize - 1) {
+                        save_container_search_url(&app, &container_id, Some(url.to_string()));
+                    }
+                }
+            ));
+            custom_row.connect_apply(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                app,
+                move |row| {
+                    let text = row.text().trim().to_string();
+                    if text.contains("{}") && url::Url::parse(&text.replace("{}", "test")).is_ok() {
+                        row.remove_css_class("error");
+                        save_container_search_url(&app, &container_id, Some(text));
+                    } else {
+                        row.add_css_class("error");
+                        window.add_toast(libadwaita::Toast::new("Enter a search URL with {} for the query"));
+                    }
+                }
+            ));
+
+            let group = &self.imp().container_search_group;
+            group.add(&engine_row);
+            group.add(&custom_row);
+        }
+    }
+
+    /// List remembered site permissions of every container, each revocable
+    fn setup_site_permission_rows(&self) {
+        let group = self.imp().site_permissions_group.clone();
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+
+        let mut has_rows = false;
+        for container in pm.containers() {
+            for (origin, kind, allowed) in pm.container_permissions(&container.id) {
+                let status = if allowed { "Allowed" } else { "Blocked" };
+                let row = libadwaita::ActionRow::builder()
+                    .title(glib::markup_escape_text(&origin))
+                    .subtitle(format!("{}: {} · {}", kind.label(), status, container.name))
+                    .build();
+
+                let revoke_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+                revoke_button.set_valign(gtk4::Align::Center);
+                revoke_button.set_tooltip_text(Some("Revoke"));
+                revoke_button.add_css_class("flat");
+                row.add_suffix(&revoke_button);
+
+                let container_id = container.id.clone();
+                revoke_button.connect_clicked(glib::clone!(
+                    #[weak]
+                    app,
+                    #[weak]
+                    group,
+                    #[weak]
+                    row,
+                    move |_| {
+                        if let Some(pm) = app.profile_manager() {
+                            pm.revoke_container_permission(&container_id, &origin, kind);
+                        }
+                        group.remove(&row);
+                    }
+                ));
+                group.add(&row);
+                has_rows = true;
+            }
+        }
+
+        if !has_rows {
+            let row = libadwaita::ActionRow::builder()
+                .title("No saved permissions")
+                .build();
+            row.add_css_class("dim-label");
+            group.add(&row);
+        }
+    }
+
+    /// List the chosen container's user scripts, each with a switch
+    fn setup_user_script_rows(&self) {
+        let imp = self.imp();
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+        let containers = pm.containers();
+        let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+        imp.user_scripts_container_row.set_model(Some(&gtk4::StringList::new(&names)));
+        let ids: Rc<Vec<String>> = Rc::new(containers.into_iter().map(|c| c.id).collect());
+
+        let show = glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[strong]
+            ids,
+            move |row: &libadwaita::ComboRow| {
+                if let Some(id) = ids.get(row.selected() as usize) {
+                    window.refresh_user_script_rows(id);
+                }
+            }
+        );
+        show(&imp.user_scripts_container_row);
+        imp.user_scripts_container_row.connect_selected_notify(show);
+
+        imp.user_scripts_folder_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            app,
+            move |_| {
+                let selected = window.imp().user_scripts_container_row.selected() as usize;
+                let Some(user_scripts) = ids.get(selected).and_then(|id| app.user_scripts(id)) else {
+                    return;
+                };
+                let folder = gio::File::for_path(user_scripts.directory());
+                let launcher = gtk4::FileLauncher::new(Some(&folder));
+                launcher.launch(Some(&window), gio::Cancellable::NONE, |result| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to open user scripts folder: {}", e);
+                    }
+                });
+            }
+        ));
+    }
+
+    fn refresh_user_script_rows(&self, container_id: &str) {
+        let imp = self.imp();
+        let group = imp.user_scripts_group.clone();
+        for row in imp.user_script_rows.take() {
+            group.remove(&row);
+        }
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let scripts = app.user_scripts(container_id).map(|u| u.scripts()).unwrap_or_default();
+
+        let mut rows: Vec<gtk4::Widget> = Vec::new();
+        for script in scripts.iter() {
+            let row = libadwaita::SwitchRow::builder()
+                .title(glib::markup_escape_text(&script.name))
+                .subtitle(glib::markup_escape_text(
+                    script.description.as_deref().unwrap_or(&script.file_name),
+                ))
+                .active(script.enabled)
+                .build();
+            let container_id = container_id.to_string();
+            let file_name = script.file_name.clone();
+            row.connect_active_notify(glib::clone!(
+                #[weak]
+                app,
+                move |row| {
+                    if let Some(user_scripts) = app.user_scripts(&container_id) {
+                        user_scripts.set_enabled(&file_name, row.is_active());
+                    }
+                }
+            ));
+            group.add(&row);
+            rows.push(row.upcast());
+        }
+
+        if scripts.is_empty() {
+            let row = libadwaita::ActionRow::builder()
+                .title("No user scripts")
+                .subtitle("Add .user.js files to the container's user scripts folder")
+                .build();
+            row.add_css_class("dim-label");
+            group.add(&row);
+            rows.push(row.upcast());
+        }
+        imp.user_script_rows.replace(rows);
+    }
+
+    /// Fill in the chosen container's custom filter rules and save them,
+    /// reporting how many were recognized
+    fn setup_custom_filters(&self) {
+        let imp = self.imp();
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+        let Some(pm) = app.profile_manager() else {
+            return;
+        };
+        let containers = pm.containers();
+        let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+        imp.custom_filters_container_row.set_model(Some(&gtk4::StringList::new(&names)));
+        let ids: Rc<Vec<String>> = Rc::new(containers.into_iter().map(|c| c.id).collect());
+
+        let show = glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            app,
+            #[strong]
+            ids,
+            move |row: &libadwaita::ComboRow| {
+                let imp = window.imp();
+                let text = ids
+                    .get(row.selected() as usize)
+                    .and_then(|id| app.content_filter_manager().and_then(|cfm| cfm.custom_filters(id)));
+                imp.custom_filters_view.set_sensitive(text.is_some());
+                imp.save_custom_filters_button.set_sensitive(text.is_some());
+                imp.custom_filters_view.buffer().set_text(text.as_deref().unwrap_or_default());
+            }
+        );
+        show(&imp.custom_filters_container_row);
+        imp.custom_filters_container_row.connect_selected_notify(show);
+
+        imp.save_custom_filters_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            app,
+            move |_| {
+                let imp = window.imp();
+                let (Some(cfm), Some(pm)) = (app.content_filter_manager(), app.profile_manager()) else {
+                    return;
+                };
+                let Some(id) = ids.get(imp.custom_filters_container_row.selected() as usize) else {
+                    return;
+                };
+                let buffer = imp.custom_filters_view.buffer();
+                let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+                let message = match cfm.set_custom_filters(id, &pm.container_dir(id), &text) {
+                    Ok(check) if check.skipped == 0 => {
+                        format!("Saved {} rules", check.recognized)
+                    }
+                    Ok(check) => format!(
+                        "Saved {} rules, skipped {} unsupported lines",
+                        check.recognized, check.skipped
+                    ),
+                    Err(e) => format!("Couldn't save filters: {}", glib::markup_escape_text(&e.to_string())),
+                };
+                window.add_toast(libadwaita::Toast::new(&message));
+            }
+        ));
+    }
+
+    fn setup_snippets(&self) {
+        let imp = self.imp();
+        let containers = gio::Application::default()
+            .and_downcast::<QayeqApplication>()
+            .and_then(|app| app.profile_manager().map(|pm| pm.containers()))
+            .unwrap_or_default();
+        let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+        imp.snippets_container_row.set_model(Some(&gtk4::StringList::new(&names)));
+        imp.snippets_container_row.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| window.refresh_snippet_rows()
+        ));
+
+        imp.add_snippet_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| window.show_snippet_dialog(None)
+        ));
+        self.refresh_snippet_rows();
+    }
+
+    /// The snippets of the container chosen above the list
+    fn selected_snippets(&self) -> Option<Rc<SnippetManager>> {
+        let app = gio::Application::default().and_downcast::<QayeqApplication>()?;
+        let selected = self.imp().snippets_container_row.selected() as usize;
+        let container = app.profile_manager()?.containers().into_iter().nth(selected)?;
+        app.snippets(&container.id)
+    }
+
+    /// List the chosen container's text snippets; activating one edits it
+    fn refresh_snippet_rows(&self) {
+        let imp = self.imp();
+        for row in imp.snippet_rows.take() {
+            imp.snippets_group.remove(&row);
+        }
+        let Some(snippets) = self.selected_snippets() else {
+            return;
+        };
+        let mut rows: Vec<gtk4::Widget> = Vec::new();
+
+        for snippet in snippets.snippets() {
+            let row = libadwaita::ActionRow::builder()
+                .title(glib::markup_escape_text(&snippet.trigger))
+                .subtitle(glib::markup_escape_text(&snippet.text))
+                .subtitle_lines(1)
+                .activatable(true)
+                .build();
+            let remove_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_valign(gtk4::Align::Center);
+            remove_button.set_tooltip_text(Some("Remove Snippet"));
+            remove_button.add_css_class("flat");
+            row.add_suffix(&remove_button);
+
+            let trigger = snippet.trigger.clone();
+            remove_button.connect_clicked(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[strong]
+                snippets,
+                move |_| {
+                    snippets.remove(&trigger);
+                    window.refresh_snippet_rows();
+                }
+            ));
+            row.connect_activated(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |_| window.show_snippet_dialog(Some(&snippet))
+            ));
+            rows.push(row.upcast());
+        }
+
+        if rows.is_empty() {
+            let row = libadwaita::ActionRow::builder()
+                .title("No text snippets")
+                .build();
+            row.add_css_class("dim-label");
+            rows.push(row.upcast());
+        }
+
+        for row in &rows {
+            imp.snippets_group.add(row);
+        }
+        imp.snippet_rows.replace(rows);
+    }
+
+    /// Ask for a trigger and text, then add the snippet or update `existing`
+    fn show_snippet_dialog(&self, existing: Option<&Snippet>) {
+        let trigger_entry = gtk4::Entry::builder()
+            .placeholder_text("Trigger, e.g. ;addr")
+            .text(existing.map(|s| s.trigger.as_str()).unwrap_or_default())
+            .activates_default(true)
+            .build();
+        let text_entry = gtk4::Entry::builder()
+            .placeholder_text("Text")
+            .text(existing.map(|s| s.text.as_str()).unwrap_or_default())
+            .activates_default(true)
+            .build();
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        content.append(&trigger_entry);
+        content.append(&text_entry);
+
+        let heading = if existing.is_some() { "Edit Snippet" } else { "New Snippet" };
+        let dialog = libadwaita::AlertDialog::new(
+            Some(heading),
+            Some("The trigger is a single word, expanded when typed at the start of a field or after a space."),
+        );
+        dialog.set_extra_child(Some(&content));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("save", "Save");
+        dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+        dialog.set_response_enabled("save", existing.is_some());
+        dialog.set_default_response(Some("save"));
+        dialog.set_close_response("cancel");
+
+        trigger_entry.connect_changed(glib::clone!(
+            #[weak]
+            dialog,
+            move |entry| dialog.set_response_enabled("save", is_valid_trigger(entry.text().trim()))
+        ));
+
+        let previous_trigger = existing.map(|s| s.trigger.clone());
+        dialog.connect_response(None, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, response| {
+                if response != "save" {
+                    return;
+                }
+                let Some(snippets) = window.selected_snippets() else {
+                    return;
+                };
+                let trigger = trigger_entry.text();
+                if !snippets.set(&trigger, &text_entry.text()) {
+                    window.add_toast(libadwaita::Toast::new("Triggers can't contain spaces"));
+                    return;
+                }
+                // Renaming a trigger replaces the old snippet
+                if let Some(previous) = previous_trigger.as_deref() {
+                    if previous != trigger.trim() {
+                        snippets.remove(previous);
+                    }
+                }
+                window.refresh_snippet_rows();
+            }
+        ));
+
+        dialog.present(Some(self));
+    }
+
+    fn setup_startup_pages(&self) {
+        self.imp().use_current_pages_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| window.use_current_pages()
+        ));
+        self.refresh_startup_page_rows();
+    }
+
+    /// List the startup pages, each removable, followed by a row to add one
+    fn refresh_startup_page_rows(&self) {
+        let imp = self.imp();
+        for row in imp.startup_page_rows.take() {
+            imp.startup_pages_group.remove(&row);
+        }
+        let mut rows: Vec<gtk4::Widget> = Vec::new();
+
+        for (index, page) in self.startup_pages().into_iter().enumerate() {
+            let row = libadwaita::ActionRow::builder()
+                .title(glib::markup_escape_text(&page))
+                .build();
+            let remove_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_valign(gtk4::Align::Center);
+            remove_button.set_tooltip_text(Some("Remove"));
+            remove_button.add_css_class("flat");
+            row.add_suffix(&remove_button);
+            remove_button.connect_clicked(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |_| {
+                    let mut pages = window.startup_pages();
+                    if index < pages.len() {
+                        pages.remove(index);
+                    }
+                    window.set_startup_pages(&pages);
+                }
+            ));
+            rows.push(row.upcast());
+        }
+
+        let add_row = libadwaita::EntryRow::builder()
+            .title("Add Page")
+            .show_apply_button(true)
             .build();
+        add_row.connect_apply(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let page = row.text().trim().to_string();
+                if page.is_empty() {
+                    return;
+                }
+                let mut pages = window.startup_pages();
+                pages.push(page);
+                window.set_startup_pages(&pages);
+            }
+        ));
+        rows.push(add_row.upcast());
+
+        for row in &rows {
+            imp.startup_pages_group.add(row);
+        }
+        imp.startup_page_rows.replace(rows);
+    }
+
+    /// Replace the startup pages with the pages open in the browser window
+    fn use_current_pages(&self) {
+        let Some(session) = self
+            .transient_for()
+            .and_downcast::<QayeqWindow>()
+            .and_then(|window| window.get_session_data())
+        else {
+            return;
+        };
+        let pages: Vec<String> = session
+            .tabs
+            .into_iter()
+            .map(|tab| tab.url)
+            .filter(|url| !url.is_empty() && !url.starts_with("about:"))
+            .collect();
+        self.set_startup_pages(&pages);
+    }
+
+    fn startup_pages(&self) -> Vec<String> {
+        self.imp()
+            .settings
+            .borrow()
+            .as_ref()
+            .map(|settings| settings.strv("startup-pages").iter().map(|p| p.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn set_startup_pages(&self, pages: &[String]) {
+        if let Some(settings) = self.imp().settings.borrow().as_ref() {
+            let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+            if let Err(e) = settings.set_strv("startup-pages", pages.as_slice()) {
+                log::warn!("Failed to save startup pages: {}", e);
+            }
+        }
+        self.refresh_startup_page_rows();
+    }
+
+    fn setup_containers_page(&self) {
+        self.imp().add_container_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| window.show_add_container_dialog()
+        ));
+        self.refresh_container_rows();
+    }
+
+    /// List all containers; user containers can be renamed and deleted
+    fn refresh_container_rows(&self) {
+        let imp = self.imp();
+        for row in imp.container_rows.take() {
+            imp.containers_group.remove(&row);
+        }
+        let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+            return;
+        };
+
+        for container in app.containers() {
+            // Same color swatch as the tab sidebar
+            let swatch = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+            swatch.set_width_request(16);
+            swatch.set_valign(gtk4::Align::Center);
+            swatch.add_css_class("container-indicator");
+            swatch.add_css_class(&format!("container-{}", container.id));
+
+            let row: gtk4::Widget = if container.is_default() {
+                let row = libadwaita::ActionRow::builder()
+                    .title(&container.name)
+                    .subtitle("Built-in")
+                    .build();
+                row.add_prefix(&swatch);
+                row.upcast()
+            } else {
+                let row = libadwaita::EntryRow::builder()
+                    .title("Name")
+                    .text(&container.name)
+                    .show_apply_button(true)
+                    .build();
+                row.add_prefix(&swatch);
+
+                let container_id = container.id.clone();
+                row.connect_apply(glib::clone!(
+                    #[weak(rename_to = window)]
+                    self,
+                    #[weak]
+                    app,
+                    move |row| {
+                        match app.rename_container(&container_id, &row.text()) {
+                            Ok(()) => {}
+                            Err(e @ ProfileError::InvalidName(_)) => {
+                                window.add_toast(libadwaita::Toast::new(&glib::markup_escape_text(&e.to_string())));
+                            }
+                            Err(e) => {
+                                log::error!("Failed to rename container {}: {}", container_id, e);
+                                window.add_toast(libadwaita::Toast::new("Could not rename container"));
+                            }
+                        }
+                    }
+                ));
+
+                let delete_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+                delete_button.set_valign(gtk4::Align::Center);
+                delete_button.set_tooltip_text(Some("Delete Container"));
+                delete_button.add_css_class("flat");
+                row.add_suffix(&delete_button);
+
+                let container_id = container.id.clone();
+                let name = container.name.clone();
+                delete_button.connect_clicked(glib::clone!(
+                    #[weak(rename_to = window)]
+                    self,
+                    #[weak]
+                    app,
+                    move |_| match app.delete_container(&container_id) {
+                        Ok(()) => window.refresh_container_rows(),
+                        Err(ProfileError::InUse(_)) => {
+                            let message = format!("Close the tabs in {} before deleting it", name);
+                            window.add_toast(libadwaita::Toast::new(&glib::markup_escape_text(&message)));
+                        }
+                        Err(e) => {
+                            log::error!("Failed to delete container {}: {}", container_id, e);
+                            window.add_toast(libadwaita::Toast::new("Could not delete container"));
+                        }
+                    }
+                ));
+                row.upcast()
+            };
+
+            imp.containers_group.add(&row);
+            imp.container_rows.borrow_mut().push(row);
+        }
+    }
+
+    /// Ask for a name and color, then create a container
+    fn show_add_container_dialog(&self) {
+        let name_entry = gtk4::Entry::builder()
+            .placeholder_text("Name")
+            .activates_default(true)
+            .hexpand(true)
+            .build();
+        let color_button = gtk4::ColorDialogButton::new(Some(gtk4::ColorDialog::new()));
+        if let Ok(color) = gtk4::gdk::RGBA::parse(DEFAULT_USER_CONTAINER_COLOR) {
+            color_button.set_rgba(&color);
+        }
+        let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        content.append(&name_entry);
+        content.append(&color_button);
+
+        let dialog = libadwaita::AlertDialog::new(
+            Some("New Container"),
+            Some("Tabs in the container won't share cookies or site data with other containers."),
+        );
+        dialog.set_extra_child(Some(&content));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("create", "Create");
+        dialog.set_response_appearance("create", libadwaita::ResponseAppearance::Suggested);
+        dialog.set_response_enabled("create", false);
+        dialog.set_default_response(Some("create"));
+        dialog.set_close_response("cancel");
+
+        name_entry.connect_changed(glib::clone!(
+            #[weak]
+            dialog,
+            move |entry| dialog.set_response_enabled("create", !entry.text().trim().is_empty())
+        ));
+
+        dialog.connect_response(None, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, response| {
+                if response != "create" {
+                    return;
+                }
+                let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+                    return;
+                };
+                let color = color_to_hex(&color_button.rgba());
+                match app.create_container(&name_entry.text(), &color) {
+                    Ok(_) => window.refresh_container_rows(),
+                    Err(e @ ProfileError::InvalidName(_)) => {
+                        window.add_toast(libadwaita::Toast::new(&glib::markup_escape_text(&e.to_string())));
+                    }
+                    Err(e) => {
+                        log::error!("Failed to create container: {}", e);
+                        window.add_toast(libadwaita::Toast::new("Could not create container"));
+                    }
+                }
+            }
+        ));
+
+        dialog.present(Some(self));
     }
 
     fn setup_search_engine_visibility(&self) {
@@ -157,8 +1495,50 @@ impl PreferencesWindow {
     }
 }
 
+/// Color preselected for a new container
+const DEFAULT_USER_CONTAINER_COLOR: &str = "#e01b24";
+
+/// Format a color as `#rrggbb`, the form containers store
+fn color_to_hex(color: &gtk4::gdk::RGBA) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.red()),
+        channel(color.green()),
+        channel(color.blue())
+    )
+}
+
 impl Default for PreferencesWindow {
     fn default() -> Self {
         glib::Object::builder().build()
     }
 }
+
+fn save_container_proxy(app: &QayeqApplication, container_id: &str, proxy: ProxySetting) {
+    let Some(pm) = app.profile_manager() else {
+        return;
+    };
+    let result = pm.update_container_settings(
+        container_id,
+        |profile| profile.set_proxy(proxy.clone()),
+        |settings| settings.proxy = proxy.clone(),
+    );
+    if let Err(e) = result {
+        log::error!("Failed to save proxy for {}: {}", container_id, e);
+    }
+}
+
+fn save_container_search_url(app: &QayeqApplication, container_id: &str, search_url: Option<String>) {
+    let Some(pm) = app.profile_manager() else {
+        return;
+    };
+    let result = pm.update_container_settings(
+        container_id,
+        |profile| profile.set_search_url(search_url.clone()),
+        |settings| settings.search_url = search_url.clone(),
+    );
+    if let Err(e) = result {
+        log::error!("Failed to save search engine for {}: {}", container_id, e);
+    }
+}
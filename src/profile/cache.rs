@@ -0,0 +1,110 @@
+//! Keeping a profile's disk cache under the size limit set in preferences.
+//!
+//! WebKit has no hard cap on the disk cache, so the cache is measured
+//! periodically and the sites visited longest ago are evicted until it fits.
+//! Only cached resources are removed; cookies and site storage are kept.
+
+use super::Profile;
+use crate::history;
+use gtk4::{gio, glib};
+use std::rc::Rc;
+use webkit6::{WebsiteData, WebsiteDataTypes};
+
+/// Evict the least recently visited sites from a profile's disk cache until
+/// it takes at most `limit` bytes
+pub fn enforce_cache_limit(profile: &Rc<Profile>, limit: u64) {
+    let Some(data_manager) = profile.network_session().website_data_manager() else {
+        return;
+    };
+    let manager = data_manager.clone();
+    let profile = Rc::clone(profile);
+    data_manager.fetch(WebsiteDataTypes::DISK_CACHE, gio::Cancellable::NONE, move |result| {
+        let records = match result {
+            Ok(records) => records,
+            Err(e) => {
+                log::warn!("Failed to measure the cache of {}: {}", profile.name(), e);
+                return;
+            }
+        };
+        let sizes: Vec<u64> = records.iter().map(|record| record.size(WebsiteDataTypes::DISK_CACHE)).collect();
+        if sizes.iter().sum::<u64>() <= limit {
+            return;
+        }
+
+        // Records are named after the registrable domain, e.g. "example.com"
+        let hosts: Vec<String> = records
+            .iter()
+            .map(|record| record.name().map(|name| name.to_string()).unwrap_or_default())
+            .collect();
+        let path = profile.path().clone();
+        glib::spawn_future_local(async move {
+            let read = gio::spawn_blocking(move || history::last_visits_on_domains(&path, &hosts)).await;
+            let last_visits = match read {
+                Ok(Ok(last_visits)) => last_visits,
+                Ok(Err(e)) => {
+                    log::warn!("Failed to read history for the cache of {}: {}", profile.name(), e);
+                    return;
+                }
+                Err(_) => return,
+            };
+
+            let entries: Vec<(u64, Option<i64>)> = sizes.into_iter().zip(last_visits).collect();
+            let evicted = entries_to_evict(&entries, limit);
+            if evicted.is_empty() {
+                return;
+            }
+
+            let freed: u64 = evicted.iter().map(|&index| entries[index].0).sum();
+            let evicted: Vec<&WebsiteData> = evicted.iter().map(|&index| &records[index]).collect();
+            log::info!(
+                "Cache of {} is over its limit, evicting {} sites ({} bytes)",
+                profile.name(),
+                evicted.len(),
+                freed
+            );
+            let name = profile.name().to_string();
+            manager.remove(WebsiteDataTypes::DISK_CACHE, &evicted, gio::Cancellable::NONE, move |result| {
+                if let Err(e) = result {
+                    log::warn!("Failed to trim the cache of {}: {}", name, e);
+                }
+            });
+        });
+    });
+}
+
+/// Indices of the (size, last visit) entries to evict so the rest add up to at
+/// most `limit` bytes. Sites never visited go first, then the least recently
+/// visited; among equals, larger entries go first.
+fn entries_to_evict(entries: &[(u64, Option<i64>)], limit: u64) -> Vec<usize> {
+    let mut total: u64 = entries.iter().map(|(size, _)| size).sum();
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (size_a, visit_a) = entries[a];
+        let (size_b, visit_b) = entries[b];
+        visit_a.cmp(&visit_b).then(size_b.cmp(&size_a))
+    });
+
+    let mut evicted = Vec::new();
+    for index in order {
+        if total <= limit {
+            break;
+        }
+        total -= entries[index].0;
+        evicted.push(index);
+    }
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_visited_until_under_limit() {
+        let entries = [(400, Some(30)), (300, Some(10)), (200, None), (100, Some(20))];
+        // 1000 bytes against a 500 byte limit: the unvisited site, then the oldest visit
+        assert_eq!(entries_to_evict(&entries, 500), vec![2, 1]);
+        assert_eq!(entries_to_evict(&entries, 1000), Vec::<usize>::new());
+        assert_eq!(entries_to_evict(&entries, 0), vec![2, 1, 3, 0]);
+    }
+}
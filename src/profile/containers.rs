@@ -0,0 +1,258 @@
+//! Containers: the built-in ones from config plus user-defined ones stored in containers.json.
+
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CONTAINERS_FILE: &str = "containers.json";
+
+/// A container as shown in menus and the tab sidebar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Container {
+    /// Profile directory name, also used in the `container-<id>` CSS class
+    pub id: String,
+    pub name: String,
+    /// Color as `#rrggbb`
+    pub color: String,
+}
+
+impl Container {
+    /// Whether this is one of the built-in containers, which can't be renamed or deleted
+    pub fn is_default(&self) -> bool {
+        is_default_container(&self.id)
+    }
+}
+
+/// Whether an ID belongs to a built-in container
+pub fn is_default_container(id: &str) -> bool {
+    config::DEFAULT_CONTAINERS.iter().any(|(default_id, _, _)| *default_id == id)
+}
+
+/// The built-in containers, in config order
+pub fn default_containers() -> Vec<Container> {
+    config::DEFAULT_CONTAINERS
+        .iter()
+        .map(|(id, name, color)| Container {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: color.to_string(),
+        })
+        .collect()
+}
+
+/// Persisted list of user-defined containers
+#[derive(Debug, Default)]
+pub struct UserContainers {
+    path: Option<PathBuf>,
+    containers: Vec<Container>,
+}
+
+impl UserContainers {
+    /// Load user containers from the data directory (empty if none saved yet)
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(CONTAINERS_FILE);
+        let containers: Vec<Container> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| {
+                serde_json::from_str(&contents)
+                    .map_err(|e| log::warn!("Ignoring invalid containers file {:?}: {}", path, e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        // A hand-edited file must not shadow a built-in container, and IDs
+        // name directories and CSS classes
+        let containers = containers
+            .into_iter()
+            .filter(|c| {
+                let usable = !c.is_default() && is_valid_id(&c.id);
+                if !usable {
+                    log::warn!("Ignoring container with unusable ID {:?} in {:?}", c.id, path);
+                }
+                usable
+            })
+            .collect();
+        Self {
+            path: Some(path),
+            containers,
+        }
+    }
+
+    /// User containers in creation order
+    pub fn list(&self) -> &[Container] {
+        &self.containers
+    }
+
+    /// Record a new container and persist the change
+    pub fn add(&mut self, container: Container) -> io::Result<()> {
+        self.containers.push(container);
+        self.save()
+    }
+
+    /// Rename a container. Returns false if there is no such user container.
+    pub fn rename(&mut self, id: &str, name: &str) -> io::Result<bool> {
+        let Some(container) = self.containers.iter_mut().find(|c| c.id == id) else {
+            return Ok(false);
+        };
+        container.name = name.to_string();
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Forget a container. Returns false if there is no such user container.
+    pub fn remove(&mut self, id: &str) -> io::Result<bool> {
+        let before = self.containers.len();
+        self.containers.retain(|c| c.id != id);
+        if self.containers.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.containers)?;
+        fs::write(path, contents)
+    }
+}
+
+/// Derive a container ID from its name: lowercase letters, digits and dashes,
+/// with a number appended if `taken` already uses it
+pub fn container_id_for(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let base = if slug.is_empty() { "container" } else { slug };
+
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|id| !taken(id))
+        .expect("unbounded range always finds a free ID")
+}
+
+/// Whether an ID is in the form `container_id_for` gives: lowercase letters,
+/// digits and dashes
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Whether a color is in the `#rrggbb` form used for containers
+pub fn is_valid_color(color: &str) -> bool {
+    color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// CSS giving user containers their colors, like the built-in rules in style.css
+pub fn container_css(containers: &[Container]) -> String {
+    containers
+        .iter()
+        .filter(|c| !c.is_default() && is_valid_color(&c.color))
+        .map(|c| {
+            format!(
+                ".container-indicator.container-{id} {{ background: {color}; }}\n\
+                 .container-pill.container-{id} > button {{ background: {color}; }}\n",
+                id = c.id,
+                color = c.color
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_container_ids_are_unique_slugs() {
+        let taken = |id: &str| is_default_container(id) || id == "side-project";
+
+        assert_eq!(container_id_for("Side Project", taken), "side-project-2");
+        assert_eq!(container_id_for("  Kids' stuff!! ", taken), "kids-stuff");
+        assert_eq!(container_id_for("Work", taken), "work-2");
+        assert_eq!(container_id_for("日本", taken), "container");
+    }
+
+    #[test]
+    fn test_user_containers_survive_reload() {
+        let dir = tempdir().unwrap();
+        let mut containers = UserContainers::load(dir.path());
+        assert!(containers.list().is_empty());
+
+        let travel = Container {
+            id: "travel".to_string(),
+            name: "Travel".to_string(),
+            color: "#e01b24".to_string(),
+        };
+        containers.add(travel.clone()).unwrap();
+        assert!(containers.rename("travel", "Holidays").unwrap());
+        assert!(!containers.rename("work", "Job").unwrap());
+
+        let reloaded = UserContainers::load(dir.path());
+        assert_eq!(reloaded.list().len(), 1);
+        assert_eq!(reloaded.list()[0].name, "Holidays");
+
+        let mut reloaded = reloaded;
+        assert!(reloaded.remove("travel").unwrap());
+        assert!(UserContainers::load(dir.path()).list().is_empty());
+    }
+
+    #[test]
+    fn test_unusable_ids_are_ignored() {
+        let dir = tempdir().unwrap();
+        let container = |id: &str| Container {
+            id: id.to_string(),
+            name: id.to_string(),
+            color: "#e01b24".to_string(),
+        };
+        let saved = vec![
+            container("travel"),
+            container(".."),
+            container("../travel"),
+            container("a/b"),
+            container(""),
+            container("Travel"),
+            container("work"),
+        ];
+        fs::write(dir.path().join(CONTAINERS_FILE), serde_json::to_string(&saved).unwrap()).unwrap();
+
+        assert_eq!(UserContainers::load(dir.path()).list(), &[container("travel")]);
+    }
+
+    #[test]
+    fn test_css_only_for_valid_user_colors() {
+        let mut containers = default_containers();
+        containers.push(Container {
+            id: "travel".to_string(),
+            name: "Travel".to_string(),
+            color: "#e01b24".to_string(),
+        });
+        containers.push(Container {
+            id: "broken".to_string(),
+            name: "Broken".to_string(),
+            color: "red; } * { color: red".to_string(),
+        });
+
+        let css = container_css(&containers);
+        assert!(css.contains(".container-indicator.container-travel { background: #e01b24; }"));
+        assert!(!css.contains("broken"));
+        assert!(!css.contains("personal"));
+        assert!(is_valid_color("#33d17A"));
+        assert!(!is_valid_color("33d17a"));
+    }
+}
@@ -1,11 +1,18 @@
+use super::containers::{self, Container, UserContainers};
+use super::permissions::{PermissionKind, SitePermissions};
+use crate::site_settings::{SiteSettings, SiteSettingsStore};
+use super::settings::{CookiePolicy, ProfileSettings, ProxySetting};
 use crate::config;
 use crate::history::HistoryDatabase;
-use gtk4::glib;
+use crate::http::SoupClient;
+use gtk4::{gio, glib};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use webkit6::prelude::*;
-use webkit6::{CookieAcceptPolicy, CookiePersistentStorage, NetworkSession};
+use webkit6::{CookiePersistentStorage, NetworkSession};
 
 /// Error type for profile operations
 #[derive(Debug)]
@@ -14,6 +21,14 @@ pub enum ProfileError {
     Database(rusqlite::Error),
     NotFound(String),
     AlreadyExists(String),
+    /// The profile still has open tabs
+    InUse(String),
+    /// A container name that can't be used, and why
+    InvalidName(String),
+    /// A container color that isn't #rrggbb
+    InvalidColor(String),
+    /// A built-in container, which can't be renamed or deleted
+    DefaultContainer(String),
 }
 
 impl std::fmt::Display for ProfileError {
@@ -23,6 +38,10 @@ impl std::fmt::Display for ProfileError {
             ProfileError::Database(e) => write!(f, "Database error: {}", e),
             ProfileError::NotFound(name) => write!(f, "Profile not found: {}", name),
             ProfileError::AlreadyExists(name) => write!(f, "Profile already exists: {}", name),
+            ProfileError::InUse(name) => write!(f, "Profile is in use: {}", name),
+            ProfileError::InvalidName(reason) => write!(f, "{}", reason),
+            ProfileError::InvalidColor(color) => write!(f, "Not a #rrggbb color: {}", color),
+            ProfileError::DefaultContainer(id) => write!(f, "Built-in container can't be changed: {}", id),
         }
     }
 }
@@ -47,6 +66,11 @@ pub struct Profile {
     path: PathBuf,
     history_db: HistoryDatabase,
     network_session: NetworkSession,
+    settings: Rc<RefCell<ProfileSettings>>,
+    permissions: RefCell<SitePermissions>,
+    site_settings: RefCell<SiteSettingsStore>,
+    /// While offline, the session's proxy cuts it off instead of following `settings`
+    offline: Rc<Cell<bool>>,
 }
 
 impl std::fmt::Debug for Profile {
@@ -73,6 +97,10 @@ impl Profile {
         // Open history database
         let history_db = HistoryDatabase::new(&path)?;
 
+        let settings = ProfileSettings::load(&path);
+        let permissions = SitePermissions::load(&path);
+        let site_settings = SiteSettingsStore::load(&path);
+
         // Create NetworkSession with profile-specific paths for cookie/storage persistence
         let network_session = NetworkSession::new(
             data_dir.to_str(),
@@ -81,8 +109,7 @@ impl Profile {
 
         // Configure CookieManager for persistent storage
         if let Some(cookie_manager) = network_session.cookie_manager() {
-            // Accept first-party cookies but block third-party (good privacy default)
-            cookie_manager.set_accept_policy(CookieAcceptPolicy::NoThirdParty);
+            cookie_manager.set_accept_policy(settings.cookie_policy.accept_policy());
 
             // Set persistent storage path for cookies
             let cookies_path = data_dir.join("cookies.sqlite");
@@ -92,14 +119,20 @@ impl Profile {
             );
 
             log::info!(
-                "Profile '{}' cookie manager configured: cookies stored at {:?}",
+                "Profile '{}' cookie manager configured: cookies stored at {:?}, policy {:?}",
                 name,
-                cookies_path
+                cookies_path,
+                settings.cookie_policy
             );
         } else {
             log::warn!("Profile '{}': could not get cookie manager from network session", name);
         }
 
+        settings.proxy.apply(&network_session);
+        if settings.proxy != ProxySetting::System {
+            log::info!("Profile '{}' proxy: {:?}", name, settings.proxy);
+        }
+
         // Enable favicon loading
         if let Some(data_manager) = network_session.website_data_manager() {
             data_manager.set_favicons_enabled(true);
@@ -107,7 +140,15 @@ impl Profile {
         }
 
         // Set up download handling
-        Self::setup_download_handler(&network_session);
+        let settings = Rc::new(RefCell::new(settings));
+        let offline = Rc::new(Cell::new(false));
+        Self::setup_download_handler(
+            &network_session,
+            DownloadNetwork {
+                settings: Rc::clone(&settings),
+                offline: Rc::clone(&offline),
+            },
+        );
 
         log::info!(
             "Profile '{}' network session created with data={:?}, cache={:?}",
@@ -121,6 +162,10 @@ impl Profile {
             path,
             history_db,
             network_session,
+            settings,
+            permissions: RefCell::new(permissions),
+            site_settings: RefCell::new(site_settings),
+            offline,
         })
     }
 
@@ -149,19 +194,136 @@ impl Profile {
         &self.network_session
     }
 
+    /// Which cookies this profile accepts
+    pub fn cookie_policy(&self) -> CookiePolicy {
+        self.settings.borrow().cookie_policy
+    }
+
+    /// Change which cookies this profile accepts.
+    /// Applies to the live session immediately and is saved with the profile.
+    pub fn set_cookie_policy(&self, policy: CookiePolicy) -> Result<(), ProfileError> {
+        if let Some(cookie_manager) = self.network_session.cookie_manager() {
+            cookie_manager.set_accept_policy(policy.accept_policy());
+        }
+
+        let mut settings = self.settings.borrow_mut();
+        settings.cookie_policy = policy;
+        settings.save(&self.path)?;
+        log::info!("Profile '{}' cookie policy set to {:?}", self.name, policy);
+        Ok(())
+    }
+
+    /// How this profile connects to the web
+    pub fn proxy(&self) -> ProxySetting {
+        self.settings.borrow().proxy.clone()
+    }
+
+    /// Change this profile's proxy.
+    /// Applies to the live session's new connections (once back online) and is saved with the profile.
+    pub fn set_proxy(&self, proxy: ProxySetting) -> Result<(), ProfileError> {
+        if !self.offline.get() {
+            proxy.apply(&self.network_session);
+        }
+
+        let mut settings = self.settings.borrow_mut();
+        log::info!("Profile '{}' proxy set to {:?}", self.name, proxy);
+        settings.proxy = proxy;
+        settings.save(&self.path)?;
+        Ok(())
+    }
+
+    /// The search URL template this profile uses instead of the global search engine
+    pub fn search_url(&self) -> Option<String> {
+        self.settings.borrow().search_url.clone()
+    }
+
+    /// Set or clear this profile's own search engine and save it with the profile
+    pub fn set_search_url(&self, search_url: Option<String>) -> Result<(), ProfileError> {
+        let mut settings = self.settings.borrow_mut();
+        log::info!("Profile '{}' search engine set to {:?}", self.name, search_url);
+        settings.search_url = search_url;
+        settings.save(&self.path)?;
+        Ok(())
+    }
+
+    /// Cut the profile's network access, or restore its proxy setting
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.set(offline);
+        if offline {
+            ProxySetting::apply_offline(&self.network_session);
+        } else {
+            self.settings.borrow().proxy.apply(&self.network_session);
+        }
+    }
+
+    /// The remembered decision for a site's permission request, if any
+    pub fn permission_decision(&self, origin: &str, kinds: &[PermissionKind]) -> Option<bool> {
+        self.permissions.borrow().decision(origin, kinds)
+    }
+
+    /// Remember whether a site may use the given permissions
+    pub fn remember_permission(&self, origin: &str, kinds: &[PermissionKind], allowed: bool) {
+        self.permissions.borrow_mut().remember(origin, kinds, allowed);
+    }
+
+    /// Forget a remembered permission so the site is asked again
+    pub fn revoke_permission(&self, origin: &str, kind: PermissionKind) {
+        self.permissions.borrow_mut().revoke(origin, kind);
+    }
+
+    /// All remembered permissions as (origin, permission, allowed)
+    pub fn stored_permissions(&self) -> Vec<(String, PermissionKind, bool)> {
+        self.permissions.borrow().list()
+    }
+
+    /// Get the settings chosen for an origin
+    pub fn site_settings(&self, origin: &str) -> SiteSettings {
+        self.site_settings.borrow().get(origin)
+    }
+
+    /// Replace the settings chosen for an origin
+    pub fn set_site_settings(&self, origin: &str, settings: SiteSettings) {
+        self.site_settings.borrow_mut().set(origin, settings);
+    }
+
+    /// Fetch the cookies that would be sent to `url`.
+    /// The callback runs once WebKit has read them from the cookie store.
+    pub fn cookies_for<F>(&self, url: &url::Url, callback: F)
+    where
+        F: FnOnce(Result<Vec<webkit6::soup::Cookie>, glib::Error>) + 'static,
+    {
+        let Some(cookie_manager) = self.network_session.cookie_manager() else {
+            callback(Ok(Vec::new()));
+            return;
+        };
+        cookie_manager.cookies(url.as_str(), None::<&gtk4::gio::Cancellable>, callback);
+    }
+
+    /// Delete a single cookie. Takes effect immediately in every tab of this profile.
+    pub fn delete_cookie<F>(&self, cookie: &webkit6::soup::Cookie, callback: F)
+    where
+        F: FnOnce(Result<(), glib::Error>) + 'static,
+    {
+        let Some(cookie_manager) = self.network_session.cookie_manager() else {
+            callback(Ok(()));
+            return;
+        };
+        cookie_manager.delete_cookie(cookie, None::<&gtk4::gio::Cancellable>, callback);
+    }
+
     /// Set up download handling for the network session
-    fn setup_download_handler(network_session: &NetworkSession) {
+    fn setup_download_handler(network_session: &NetworkSession, network: DownloadNetwork) {
         use crate::download;
-        use std::cell::Cell;
-        use std::rc::Rc;
 
-        network_session.connect_download_started(|_session, wk_download| {
+        network_session.connect_download_started(move |_session, wk_download| {
             let uri = wk_download.request().and_then(|r| r.uri()).map(|u| u.to_string());
             log::info!("Download started: {:?}", uri);
 
 
-            // Check if this download was triggered by context menu "Save As"
-            let show_dialog = uri.as_ref().map(|u| download::is_save_as(u)).unwrap_or(false);
+            // Ask where to save if triggered by context menu "Save As" or if the user
+            // wants to choose for every download
+            let save_as = uri.as_ref().map(|u| download::is_save_as(u)).unwrap_or(false);
+            let show_dialog = save_as || gio::Settings::new(config::APP_ID).boolean("ask-download-location");
 
             // Track download ID across closures
             let download_id: Rc<Cell<Option<u64>>> = Rc::new(Cell::new(None));
@@ -171,128 +333,45 @@ impl Profile {
             let download_id_for_fail = Rc::clone(&download_id);
 
             let uri_for_decide = uri.clone();
+            let network = network.clone();
 
             // Connect to decide-destination
             wk_download.connect_decide_destination(move |wk_download, suggested_filename| {
                 log::info!("Download decide destination: {} (dialog={})", suggested_filename, show_dialog);
-
-                if show_dialog {
-                    // Show file chooser dialog (uses XDG portals)
-                    let initial_dir = download::last_save_directory();
-
-                    let dialog = gtk4::FileDialog::builder()
-                        .title("Save As")
-                        .initial_name(suggested_filename)
-                        .initial_folder(&gtk4::gio::File::for_path(&initial_dir))
-                        .build();
-
-                    let download_clone = wk_download.clone();
-                    let download_for_cancel = wk_download.clone();
-                    let download_id_clone = Rc::clone(&download_id_for_decide);
-                    let uri_clone = uri_for_decide.clone();
-                    let filename = suggested_filename.to_string();
-
-                    let app = gtk4::gio::Application::default()
-                        .and_then(|a| a.downcast::<gtk4::Application>().ok());
-                    let window = app.as_ref().and_then(|a| a.active_window());
-
-                    dialog.save(
-                        window.as_ref(),
-                        gtk4::gio::Cancellable::NONE,
-                        move |result| {
-                            match result {
-                                Ok(file) => {
-                                    if let Some(path) = file.path() {
-                                        // Remember the directory for next time
-                                        if let Some(parent) = path.parent() {
-                                            download::set_last_save_directory(parent.to_path_buf());
-                                        }
-
-                                        // Register with global download manager
-                                        let id = download::add_download(
-                                            uri_clone.as_deref().unwrap_or(""),
-                                            &filename,
-                                            path.clone(),
-                                        );
-                                        download_id_clone.set(Some(id));
-
-                                        // Register cancel callback
-                                        let download_for_cancel = download_for_cancel.clone();
-                                        download::register_cancel_callback(id, move || {
-                                            download_for_cancel.cancel();
-                                        });
-
-                                        let path_str = path.to_string_lossy().to_string();
-                                        log::info!("Saving download to: {}", path_str);
-                                        download_clone.set_allow_overwrite(true);
-                                        download_clone.set_destination(&path_str);
-                                    } else {
-                                        log::error!("Could not get path from selected file");
-                                        download_clone.cancel();
-                                    }
-                                }
-                                Err(e) => {
-                                    log::info!("Download save cancelled: {}", e);
-                                    download_clone.cancel();
-                                }
-                            }
-                        },
+                // Never trust the server's name: it could point outside the download folder
+                let filename = download::sanitize_filename(suggested_filename, uri_for_decide.as_deref());
+
+                let settings = gio::Settings::new(config::APP_ID);
+                let mime_type = wk_download.response().and_then(|r| r.mime_type());
+                let extensions: Vec<String> = settings
+                    .strv("dangerous-download-extensions")
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect();
+                if settings.boolean("warn-dangerous-downloads")
+                    && download::is_dangerous(&filename, mime_type.as_deref(), &extensions)
+                {
+                    log::info!("Download {} may be dangerous, asking before saving", filename);
+                    confirm_dangerous_download(
+                        wk_download,
+                        filename,
+                        show_dialog,
+                        uri_for_decide.clone(),
+                        Rc::clone(&download_id_for_decide),
+                        network.clone(),
                     );
-
-                    true // We're handling it - download waits until we set destination
                 } else {
-                    // Auto-save to Downloads folder
-                    let downloads_dir = glib::user_special_dir(glib::UserDirectory::Downloads)
-                        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".into())));
-
-                    // Generate unique filename if file already exists
-                    let destination = unique_filename(&downloads_dir, suggested_filename);
-                    let final_filename = destination
-                        .file_name()
-                        .map(|s| s.to_string_lossy().to_string())
-                        .unwrap_or_else(|| suggested_filename.to_string());
-
-                    // Register with global download manager
-                    let id = download::add_download(
-                        uri_for_decide.as_deref().unwrap_or(""),
-                        &final_filename,
-                        destination.clone(),
+                    choose_destination(
+                        wk_download,
+                        &filename,
+                        show_dialog,
+                        uri_for_decide.clone(),
+                        Rc::clone(&download_id_for_decide),
+                        network.clone(),
                     );
-                    download_id_for_decide.set(Some(id));
-
-                    // Register cancel callback - preserves file if pausing
-                    let download_for_cancel = wk_download.clone();
-                    let dest_for_cancel = destination.clone();
-                    download::register_cancel_callback(id, move || {
-                        // If we're pausing (not cancelling), backup the partial file
-                        // because WebKit will delete it on cancel
-                        if download::is_paused(id) {
-                            let backup_path = dest_for_cancel.with_extension("part");
-                            if let Err(e) = std::fs::copy(&dest_for_cancel, &backup_path) {
-                                log::warn!("Failed to backup partial download: {}", e);
-                            } else {
-                                log::info!("Backed up partial download to {:?}", backup_path);
-                            }
-                        }
-                        download_for_cancel.cancel();
-                    });
-
-                    // Register resume callback with Range-based HTTP resume
-                    download::register_resume_callback(id, move || {
-                        if let Some((url, dest, received_bytes)) = download::get_download_for_resume(id) {
-                            log::info!("Resuming download {} from byte {}", id, received_bytes);
-                            resume_download_with_range(id, &url, &dest, received_bytes);
-                        }
-                    });
-
-                    let path_str = destination.to_string_lossy().to_string();
-                    log::info!("Auto-saving download to: {}", path_str);
-                    // Allow overwrite since unique_filename already guarantees a unique path
-                    wk_download.set_allow_overwrite(true);
-                    wk_download.set_destination(&path_str);
-
-                    true // We've set the destination
                 }
+
+                true // We're handling it - download waits until we set destination
             });
 
             // Track if we've checked for resume support
@@ -312,7 +391,10 @@ impl Profile {
                             if let Some(headers) = response.http_headers() {
                                 if let Some(accept_ranges) = headers.one("Accept-Ranges") {
                                     let supports_resume = accept_ranges.to_lowercase() != "none";
-                                    download::set_supports_resume(id, supports_resume);
+                                    // Only ever turn resume on, the range probe may already have
+                                    if supports_resume {
+                                        download::set_supports_resume(id, true);
+                                    }
                                     log::info!("Download {} Accept-Ranges: {} (resume={})",
                                         id, accept_ranges, supports_resume);
                                 }
@@ -324,38 +406,20 @@ impl Profile {
 
             // Handle completion - only if still in progress (not paused/cancelled)
             wk_download.connect_finished(move |wk_download| {
-                if let Some(id) = download_id_for_finish.get() {
-                    // Only mark as completed if we weren't paused/cancelled
-                    // Check if download actually completed (no error, or received all data)
-                    let was_cancelled = wk_download.response()
-                        .map(|r| {
-                            let expected = r.content_length() as u64;
-                            let received = wk_download.received_data_length();
-                            expected > 0 && received < expected
-                        })
-                        .unwrap_or(false);
-
-                    if !was_cancelled && !download::is_paused(id) {
-                        download::set_download_status(id, download::DownloadStatus::Completed);
-                        download::remove_cancel_callback(id);
-                        download::remove_resume_callback(id);
-                    }
-                }
+                // Check if download actually completed (no error, or received all data)
+                let was_cancelled = wk_download.response()
+                    .map(|r| {
+                        let expected = r.content_length() as u64;
+                        let received = wk_download.received_data_length();
+                        expected > 0 && received < expected
+                    })
+                    .unwrap_or(false);
+                webkit_download_finished(&download_id_for_finish, was_cancelled);
             });
 
             // Handle failures - but not if we're pausing (that also triggers failed)
             wk_download.connect_failed(move |_wk_download, error| {
-                if let Some(id) = download_id_for_fail.get() {
-                    // Check if this was a pause or cancel (status already set)
-                    if !download::is_paused(id) && download::is_active(id) {
-                        download::set_download_status(
-                            id,
-                            download::DownloadStatus::Failed(error.to_string()),
-                        );
-                    }
-                    download::remove_cancel_callback(id);
-                    // Don't remove resume callback - we might want to resume
-                }
+                webkit_download_failed(&download_id_for_fail, &error.to_string());
             });
         });
 
@@ -369,10 +433,14 @@ impl Profile {
 pub struct ProfileManager {
     base_dir: PathBuf,
     profiles_dir: PathBuf,
-    /// Currently loaded profiles (lazy-loaded on demand)
-    profiles: HashMap<String, Profile>,
+    /// Currently loaded profiles; each container is loaded the first time it's used
+    profiles: RefCell<HashMap<String, Rc<Profile>>>,
+    /// Containers created by the user, on top of config::DEFAULT_CONTAINERS
+    user_containers: RefCell<UserContainers>,
     /// Default container for new tabs
     default_container: String,
+    /// "Work Offline": every profile, including ones loaded later, is cut off
+    offline: Cell<bool>,
 }
 
 impl ProfileManager {
@@ -393,24 +461,24 @@ impl ProfileManager {
         // Create directories if they don't exist
         fs::create_dir_all(&profiles_dir)?;
 
-        let mut manager = Self {
+        let user_containers = UserContainers::load(&base_dir);
+        let manager = Self {
             base_dir,
             profiles_dir,
-            profiles: HashMap::new(),
+            profiles: RefCell::new(HashMap::new()),
+            user_containers: RefCell::new(user_containers),
             default_container: config::DEFAULT_CONTAINER.to_string(),
+            offline: Cell::new(false),
         };
 
-        // Ensure all default containers exist
-        for (container_id, container_name, _color) in config::DEFAULT_CONTAINERS {
-            if !manager.profile_exists(container_id) {
-                log::info!("Creating container profile '{}' ({})", container_name, container_id);
-                manager.create_profile(container_id)?;
+        // Ensure all containers exist
+        for container in manager.containers() {
+            if !manager.profile_exists(&container.id) {
+                log::info!("Creating container profile '{}' ({})", container.name, container.id);
+                manager.create_profile(&container.id)?;
             }
         }
 
-        // Pre-load the default container
-        manager.load_profile(config::DEFAULT_CONTAINER)?;
-
         Ok(manager)
     }
 
@@ -420,7 +488,7 @@ impl ProfileManager {
     }
 
     /// Create a new profile directory (does not load it)
-    pub fn create_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+    pub fn create_profile(&self, name: &str) -> Result<(), ProfileError> {
         let profile_path = self.profiles_dir.join(name);
 
         if profile_path.exists() {
@@ -436,37 +504,44 @@ impl ProfileManager {
     }
 
     /// Load a profile into memory (if not already loaded)
-    fn load_profile(&mut self, name: &str) -> Result<(), ProfileError> {
-        if self.profiles.contains_key(name) {
+    fn load_profile(&self, name: &str) -> Result<(), ProfileError> {
+        if self.profiles.borrow().contains_key(name) {
             return Ok(()); // Already loaded
         }
 
-        let profile_path = self.profiles_dir.join(name);
+        let profile_path = self.container_dir(name);
         if !profile_path.exists() {
             return Err(ProfileError::NotFound(name.to_string()));
         }
 
         let profile = Profile::open(name, profile_path)?;
-        self.profiles.insert(name.to_string(), profile);
+        if self.offline.get() {
+            profile.set_offline(true);
+        }
+        self.profiles.borrow_mut().insert(name.to_string(), Rc::new(profile));
         log::info!("Loaded profile/container '{}'", name);
 
         Ok(())
     }
 
     /// Get a profile by name, loading it if necessary
-    pub fn get_or_load_profile(&mut self, name: &str) -> Result<&Profile, ProfileError> {
+    pub fn get_or_load_profile(&self, name: &str) -> Result<Rc<Profile>, ProfileError> {
         self.load_profile(name)?;
-        self.profiles.get(name).ok_or_else(|| ProfileError::NotFound(name.to_string()))
+        self.get_profile(name).ok_or_else(|| ProfileError::NotFound(name.to_string()))
     }
 
     /// Get a loaded profile by name (without loading)
-    pub fn get_profile(&self, name: &str) -> Option<&Profile> {
-        self.profiles.get(name)
+    pub fn get_profile(&self, name: &str) -> Option<Rc<Profile>> {
+        self.profiles.borrow().get(name).cloned()
     }
 
-    /// Get mutable access to a profile
-    pub fn get_profile_mut(&mut self, name: &str) -> Option<&mut Profile> {
-        self.profiles.get_mut(name)
+    /// Find the loaded profile that owns a network session
+    pub fn profile_for_session(&self, session: &NetworkSession) -> Option<Rc<Profile>> {
+        self.profiles
+            .borrow()
+            .values()
+            .find(|p| p.network_session() == session)
+            .cloned()
     }
 
     /// List all available profiles on disk
@@ -487,8 +562,8 @@ impl ProfileManager {
     }
 
     /// List currently loaded profiles
-    pub fn loaded_profiles(&self) -> Vec<&str> {
-        self.profiles.keys().map(|s| s.as_str()).collect()
+    pub fn loaded_profiles(&self) -> Vec<String> {
+        self.profiles.borrow().keys().cloned().collect()
     }
 
     /// Get the default container name
@@ -505,20 +580,104 @@ impl ProfileManager {
         Ok(())
     }
 
-    /// Get the current/default profile (backwards compatibility)
-    pub fn current_profile(&self) -> Option<&Profile> {
-        self.profiles.get(&self.default_container)
+    /// Whether every profile has been cut off from the network
+    pub fn is_offline(&self) -> bool {
+        self.offline.get()
     }
 
-    /// Get mutable access to the current/default profile (backwards compatibility)
-    pub fn current_profile_mut(&mut self) -> Option<&mut Profile> {
-        self.profiles.get_mut(&self.default_container)
+    /// Work offline, or go back online, in every profile
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.set(offline);
+        for profile in self.profiles.borrow().values() {
+            profile.set_offline(offline);
+        }
+        log::info!("Working {}", if offline { "offline" } else { "online" });
+    }
+
+    /// Finish writing each loaded profile's history before the app exits
+    pub fn shutdown(&self) {
+        for profile in self.profiles.borrow().values() {
+            profile.history().shutdown();
+        }
+    }
+
+    /// Get the current/default profile (backwards compatibility), loading it if necessary
+    pub fn current_profile(&self) -> Option<Rc<Profile>> {
+        self.get_or_load_profile(&self.default_container)
+            .map_err(|e| log::error!("Failed to load profile '{}': {}", self.default_container, e))
+            .ok()
+    }
+
+    /// All containers: the built-in ones first, then the user's in creation order
+    pub fn containers(&self) -> Vec<Container> {
+        let mut containers = containers::default_containers();
+        containers.extend(self.user_containers.borrow().list().iter().cloned());
+        containers
+    }
+
+    /// Look up a container by ID
+    pub fn container(&self, id: &str) -> Option<Container> {
+        self.containers().into_iter().find(|c| c.id == id)
+    }
+
+    /// Create a user container with its own profile, loaded when a tab first uses it
+    pub fn create_container(&self, name: &str, color: &str) -> Result<Container, ProfileError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ProfileError::InvalidName("A container needs a name".to_string()));
+        }
+        if !containers::is_valid_color(color) {
+            return Err(ProfileError::InvalidColor(color.to_string()));
+        }
+
+        let id = containers::container_id_for(name, |id| {
+            containers::is_default_container(id) || self.container(id).is_some() || self.profile_exists(id)
+        });
+        self.create_profile(&id)?;
+
+        let container = Container {
+            id,
+            name: name.to_string(),
+            color: color.to_string(),
+        };
+        self.user_containers.borrow_mut().add(container.clone())?;
+        log::info!("Created container '{}' ({})", container.name, container.id);
+        Ok(container)
+    }
+
+    /// Rename a user container (the built-in ones keep their names)
+    pub fn rename_container(&self, id: &str, name: &str) -> Result<(), ProfileError> {
+        let name = name.trim();
+        if containers::is_default_container(id) {
+            return Err(ProfileError::DefaultContainer(id.to_string()));
+        }
+        if name.is_empty() {
+            return Err(ProfileError::InvalidName("A container needs a name".to_string()));
+        }
+        if !self.user_containers.borrow_mut().rename(id, name)? {
+            return Err(ProfileError::NotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete a user container and its data. The caller makes sure no tab uses it.
+    pub fn delete_container(&self, id: &str) -> Result<(), ProfileError> {
+        if containers::is_default_container(id) {
+            return Err(ProfileError::DefaultContainer(id.to_string()));
+        }
+
+        // Unload first; delete_profile refuses loaded profiles
+        self.profiles.borrow_mut().remove(id);
+        self.delete_profile(id)?;
+        self.user_containers.borrow_mut().remove(id)?;
+        log::info!("Deleted container '{}'", id);
+        Ok(())
     }
 
     /// Delete a profile (cannot delete loaded profiles or default containers)
-    pub fn delete_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+    pub fn delete_profile(&self, name: &str) -> Result<(), ProfileError> {
         // Don't allow deleting any of the default containers
-        if config::DEFAULT_CONTAINERS.iter().any(|(id, _, _)| *id == name) {
+        if containers::is_default_container(name) {
             return Err(ProfileError::Io(std::io::Error::new(
                 std::io::ErrorKind::PermissionDenied,
                 "Cannot delete a default container",
@@ -526,7 +685,7 @@ impl ProfileManager {
         }
 
         // Don't allow deleting currently loaded profiles (they may have open tabs)
-        if self.profiles.contains_key(name) {
+        if self.profiles.borrow().contains_key(name) {
             return Err(ProfileError::Io(std::io::Error::new(
                 std::io::ErrorKind::PermissionDenied,
                 "Cannot delete a loaded profile (close all tabs using it first)",
@@ -554,6 +713,62 @@ impl ProfileManager {
     pub fn profiles_dir(&self) -> &PathBuf {
         &self.profiles_dir
     }
+
+    /// Get the directory a container's profile is kept in
+    pub fn container_dir(&self, id: &str) -> PathBuf {
+        self.profiles_dir.join(id)
+    }
+
+    /// A container's settings, read from its directory so its profile isn't
+    /// loaded just to show them
+    pub fn container_settings(&self, id: &str) -> ProfileSettings {
+        match self.get_profile(id) {
+            Some(profile) => profile.settings.borrow().clone(),
+            None => ProfileSettings::load(&self.container_dir(id)),
+        }
+    }
+
+    /// Change a container's settings. A loaded profile is changed through
+    /// `loaded`, which also updates its live session; otherwise `change` is
+    /// applied to the saved settings.
+    pub fn update_container_settings(
+        &self,
+        id: &str,
+        loaded: impl FnOnce(&Profile) -> Result<(), ProfileError>,
+        change: impl FnOnce(&mut ProfileSettings),
+    ) -> Result<(), ProfileError> {
+        match self.get_profile(id) {
+            Some(profile) => loaded(&profile),
+            None => Ok(ProfileSettings::update(&self.container_dir(id), change)?),
+        }
+    }
+
+    /// A container's remembered site permissions, without loading its profile
+    pub fn container_permissions(&self, id: &str) -> Vec<(String, PermissionKind, bool)> {
+        match self.get_profile(id) {
+            Some(profile) => profile.stored_permissions(),
+            None => SitePermissions::load(&self.container_dir(id)).list(),
+        }
+    }
+
+    /// Forget a container's remembered permission, without loading its profile
+    pub fn revoke_container_permission(&self, id: &str, origin: &str, kind: PermissionKind) {
+        match self.get_profile(id) {
+            Some(profile) => profile.revoke_permission(origin, kind),
+            None => SitePermissions::load(&self.container_dir(id)).revoke(origin, kind),
+        }
+    }
+}
+
+/// Run `f` with the profile that owns `session`, or the current profile when
+/// there's no session or no loaded profile owns it
+pub fn with_session_profile<R>(session: Option<&NetworkSession>, f: impl FnOnce(&Profile) -> R) -> Option<R> {
+    let app = gio::Application::default().and_downcast::<crate::application::QayeqApplication>()?;
+    let pm = app.profile_manager()?;
+    let profile = session
+        .and_then(|session| pm.profile_for_session(session))
+        .or_else(|| pm.current_profile())?;
+    Some(f(&profile))
 }
 
 // Convenience function for getting the data directory
@@ -573,13 +788,315 @@ mod dirs {
     }
 }
 
+/// What a profile's own soup requests, download probes and resumes, need
+/// to reach the network the way its NetworkSession does
+#[derive(Clone)]
+struct DownloadNetwork {
+    settings: Rc<RefCell<ProfileSettings>>,
+    offline: Rc<Cell<bool>>,
+}
+
+impl DownloadNetwork {
+    /// A client going through the profile's proxy, or None while offline
+    fn client(&self, timeout: u32) -> Option<SoupClient> {
+        if self.offline.get() {
+            return None;
+        }
+        let client = SoupClient::with_timeout(timeout);
+        Some(match self.settings.borrow().proxy.proxy_resolver() {
+            Some(resolver) => client.proxy_resolver(resolver),
+            None => client,
+        })
+    }
+}
+
+/// Ask before saving a download that could run code. Discarding cancels it.
+fn confirm_dangerous_download(
+    wk_download: &webkit6::Download,
+    filename: String,
+    show_dialog: bool,
+    uri: Option<String>,
+    download_id: Rc<Cell<Option<u64>>>,
+    network: DownloadNetwork,
+) {
+    use libadwaita::prelude::*;
+
+    let app = gtk4::gio::Application::default()
+        .and_then(|a| a.downcast::<gtk4::Application>().ok());
+    let window = app.as_ref().and_then(|a| a.active_window());
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Keep Dangerous File?"),
+        Some(&format!("“{}” may harm your computer. Only keep it if you trust where it came from.", filename)),
+    );
+    dialog.add_response("discard", "Discard");
+    dialog.add_response("keep", "Keep");
+    dialog.set_response_appearance("discard", libadwaita::ResponseAppearance::Destructive);
+    dialog.set_default_response(Some("discard"));
+    dialog.set_close_response("discard");
+
+    let wk_download = wk_download.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response == "keep" {
+            choose_destination(
+                &wk_download,
+                &filename,
+                show_dialog,
+                uri.clone(),
+                Rc::clone(&download_id),
+                network.clone(),
+            );
+        } else {
+            log::info!("Discarded dangerous download: {}", filename);
+            wk_download.cancel();
+        }
+    });
+
+    dialog.present(window.as_ref());
+}
+
+/// Pick where a download goes: ask with a file chooser or save it to the download folder
+fn choose_destination(
+    wk_download: &webkit6::Download,
+    suggested_filename: &str,
+    show_dialog: bool,
+    uri: Option<String>,
+    download_id: Rc<Cell<Option<u64>>>,
+    network: DownloadNetwork,
+) {
+    use crate::download;
+
+    if show_dialog {
+        // Show file chooser dialog (uses XDG portals)
+        let initial_dir = download::last_save_directory();
+
+        let dialog = gtk4::FileDialog::builder()
+            .title("Save As")
+            .initial_name(suggested_filename)
+            .initial_folder(&gtk4::gio::File::for_path(&initial_dir))
+            .build();
+
+        let download_clone = wk_download.clone();
+        let download_for_cancel = wk_download.clone();
+        let download_id_clone = Rc::clone(&download_id);
+        let uri_clone = uri;
+        let filename = suggested_filename.to_string();
+
+        let app =
+            gtk4::gio::Application::default().and_then(|a| a.downcast::<gtk4::Application>().ok());
+        let window = app.as_ref().and_then(|a| a.active_window());
+
+        dialog.save(
+            window.as_ref(),
+            gtk4::gio::Cancellable::NONE,
+            move |result| {
+                match result {
+                    Ok(file) => {
+                        if let Some(path) = file.path() {
+                            // Remember the directory for next time
+                            if let Some(parent) = path.parent() {
+                                download::set_last_save_directory(parent.to_path_buf());
+                            }
+
+                            // Register with global download manager
+                            let id = download::add_download(
+                                uri_clone.as_deref().unwrap_or(""),
+                                &filename,
+                                path.clone(),
+                            );
+                            download_id_clone.set(Some(id));
+
+                            // Register cancel callback
+                            let download_for_cancel = download_for_cancel.clone();
+                            download::register_cancel_callback(id, move || {
+                                download_for_cancel.cancel();
+                            });
+
+                            let path_str = path.to_string_lossy().to_string();
+                            log::info!("Saving download to: {}", path_str);
+                            download_clone.set_allow_overwrite(true);
+                            download_clone.set_destination(&path_str);
+                        } else {
+                            log::error!("Could not get path from selected file");
+                            download_clone.cancel();
+                        }
+                    }
+                    Err(e) => {
+                        log::info!("Download save cancelled: {}", e);
+                        download_clone.cancel();
+                    }
+                }
+            },
+        );
+    } else {
+        // Auto-save to the download folder
+        let downloads_dir = download::default_download_directory();
+
+        // Generate unique filename if file already exists
+        let destination = unique_filename(&downloads_dir, suggested_filename);
+        let final_filename = destination
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| suggested_filename.to_string());
+
+        // Register with global download manager
+        let id = download::add_download(
+            uri.as_deref().unwrap_or(""),
+            &final_filename,
+            destination.clone(),
+        );
+        download_id.set(Some(id));
+
+        // Register cancel callback - preserves file if pausing
+        let download_for_cancel = wk_download.clone();
+        let dest_for_cancel = destination.clone();
+        download::register_cancel_callback(id, move || {
+            // If we're pausing (not cancelling), backup the partial file
+            // because WebKit will delete it on cancel
+            if download::is_paused(id) {
+                let backup_path = dest_for_cancel.with_extension("part");
+                if let Err(e) = std::fs::copy(&dest_for_cancel, &backup_path) {
+                    log::warn!("Failed to backup partial download: {}", e);
+                } else {
+                    log::info!("Backed up partial download to {:?}", backup_path);
+                }
+            }
+            download_for_cancel.cancel();
+        });
+
+        // Register resume callback with Range-based HTTP resume
+        if let Some(uri) = uri.as_deref() {
+            probe_resume_support(&network, id, uri);
+        }
+        register_range_resume(id, download_id, move || network.client(0));
+
+        let path_str = destination.to_string_lossy().to_string();
+        log::info!("Auto-saving download to: {}", path_str);
+        // Allow overwrite since unique_filename already guarantees a unique path
+        wk_download.set_allow_overwrite(true);
+        wk_download.set_destination(&path_str);
+    }
+}
+
+/// Resume a download with a Range request through a client from `client`,
+/// which gives None while the profile is offline
+fn register_range_resume<C, F>(id: u64, download_id: Rc<Cell<Option<u64>>>, client: F)
+where
+    C: crate::http::HttpClient + 'static,
+    F: Fn() -> Option<C> + 'static,
+{
+    use crate::download;
+
+    download::register_resume_callback(id, move || {
+        if let Some((url, dest, received_bytes)) = download::get_download_for_resume(id) {
+            log::info!("Resuming download {} from byte {}", id, received_bytes);
+            let Some(client) = client() else {
+                log::warn!("Not resuming download {} while offline", id);
+                return;
+            };
+            // The soup request owns the download from here on, detach
+            // the cancelled WebKit download so its signals are ignored
+            download_id.set(None);
+            resume_download_with_range(client, id, &url, &dest, received_bytes);
+        }
+    });
+}
+
+/// The WebKit download finished. `download_id` is cleared once a download
+/// is handed to the soup resume path, so a late signal can't touch it.
+fn webkit_download_finished(download_id: &Cell<Option<u64>>, was_cancelled: bool) {
+    use crate::download;
+
+    if let Some(id) = download_id.get() {
+        // Only mark as completed if we weren't paused/cancelled
+        if !was_cancelled && !download::is_paused(id) {
+            download::set_download_status(id, download::DownloadStatus::Completed);
+            download::remove_cancel_callback(id);
+            download::remove_resume_callback(id);
+        }
+    }
+}
+
+/// The WebKit download failed, which pausing and cancelling also cause
+fn webkit_download_failed(download_id: &Cell<Option<u64>>, error: &str) {
+    use crate::download;
+
+    if let Some(id) = download_id.get() {
+        // Check if this was a pause or cancel (status already set)
+        if !download::is_paused(id) && download::is_active(id) {
+            download::set_download_status(id, download::DownloadStatus::Failed(error.to_string()));
+        }
+        download::remove_cancel_callback(id);
+        // Don't remove resume callback - we might want to resume
+    }
+}
+
+/// Seconds the range probe may take, it only matters early in a download
+const RANGE_PROBE_TIMEOUT_SECS: u32 = 10;
+
+/// Ask the server for a single byte to learn whether the download can be
+/// resumed, since plenty of servers honour ranges without sending
+/// Accept-Ranges. It goes through the profile's proxy and is skipped while
+/// offline. If the probe fails the header check still applies.
+fn probe_resume_support(network: &DownloadNetwork, id: u64, url: &str) {
+    use crate::download;
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return;
+    }
+    let Some(client) = network.client(RANGE_PROBE_TIMEOUT_SECS) else {
+        return;
+    };
+
+    let url = url.to_string();
+    glib::spawn_future_local(async move {
+        let cancellable = gio::Cancellable::new();
+        match probe_range_support(&client, &url, &cancellable).await {
+            Some(supports_resume) => {
+                log::info!("Download {} range probe: resume={}", id, supports_resume);
+                if supports_resume {
+                    download::set_supports_resume(id, true);
+                }
+            }
+            None => log::debug!("Download {} range probe failed", id),
+        }
+    });
+}
+
+/// Whether a response to a Range request shows the server honours ranges
+fn accepts_ranges<B>(response: &crate::http::Response<B>) -> bool {
+    response.status == soup::Status::PartialContent
+        || response
+            .header("Content-Range")
+            .is_some_and(|range| range.trim_start().starts_with("bytes"))
+}
+
+/// Request the first byte of a URL, returning whether ranges are supported
+/// or None if the server couldn't be asked
+async fn probe_range_support<C: crate::http::HttpClient>(
+    client: &C,
+    url: &str,
+    cancellable: &gio::Cancellable,
+) -> Option<bool> {
+    // The body is dropped unread, so a server ignoring the range costs little
+    match client.get_range(url, 0, Some(0), cancellable).await {
+        Ok(response) => Some(accepts_ranges(&response)),
+        Err(e) => {
+            log::debug!("Range probe for {} failed: {}", url, e);
+            None
+        }
+    }
+}
+
 /// Resume a download using HTTP Range request
-fn resume_download_with_range(id: u64, url: &str, dest: &PathBuf, start_byte: u64) {
+fn resume_download_with_range<C: crate::http::HttpClient + 'static>(
+    client: C,
+    id: u64,
+    url: &str,
+    dest: &PathBuf,
+    start_byte: u64,
+) {
     use crate::download;
-    use soup::gio::prelude::CancellableExt;
-    use soup::prelude::*;
-    use std::fs::OpenOptions;
-    use std::io::Write;
 
     let url = url.to_string();
     let dest = dest.clone();
@@ -596,7 +1113,7 @@ fn resume_download_with_range(id: u64, url: &str, dest: &PathBuf, start_byte: u6
     }
 
     // Create cancellable for this download
-    let cancellable = soup::gio::Cancellable::new();
+    let cancellable = gio::Cancellable::new();
     let cancellable_for_callback = cancellable.clone();
     let dest_for_callback = dest.clone();
 
@@ -619,106 +1136,121 @@ fn resume_download_with_range(id: u64, url: &str, dest: &PathBuf, start_byte: u6
     download::set_download_status(id, download::DownloadStatus::InProgress);
 
     glib::spawn_future_local(async move {
-        let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
-            // Create HTTP request with Range header
-            let msg = soup::Message::new("GET", &url)?;
-            msg.request_headers()
-                .ok_or("No request headers")?
-                .append("Range", &format!("bytes={}-", start_byte));
-
-            let session = soup::Session::new();
-
-            // Send request and get input stream (with cancellable)
-            let input_stream = session
-                .send_future(&msg, soup::glib::Priority::DEFAULT)
-                .await?;
-
-            // Check response status
-            let status = msg.status();
-            if status != soup::Status::PartialContent && status != soup::Status::Ok {
-                return Err(format!("Server returned status {:?}", status).into());
-            }
+        let result = resume_from(&client, id, &url, &dest, start_byte, &cancellable, |received, total| {
+            download::update_progress(id, received, total);
+        })
+        .await;
 
-            // Get content length from response
-            let content_length = msg.response_headers()
-                .map(|h: soup::MessageHeaders| h.content_length())
-                .unwrap_or(0);
-            let total_bytes = if status == soup::Status::PartialContent {
-                // For 206, content-length is the remaining bytes
-                start_byte + content_length as u64
-            } else {
-                // For 200, server doesn't support Range - we're getting the whole file
-                content_length as u64
-            };
+        finish_resumed_download(id, result.map_err(|e| e.to_string()));
+    });
+}
 
-            // Open destination file for appending (or writing if server sent full file)
-            let mut file = if status == soup::Status::PartialContent {
-                OpenOptions::new().append(true).open(&dest)?
-            } else {
-                // Server didn't support Range, start from beginning
-                download::update_progress(id, 0, total_bytes);
-                OpenOptions::new().write(true).truncate(true).create(true).open(&dest)?
-            };
+/// Record how a resumed download ended, unless it was paused or cancelled
+fn finish_resumed_download(id: u64, result: Result<(), String>) {
+    use crate::download;
 
-            // Read and write in chunks
-            let mut received = if status == soup::Status::PartialContent { start_byte } else { 0 };
-            let mut buffer = vec![0u8; 65536]; // 64KB chunks
+    // Only update status if not already paused/cancelled
+    if !download::is_paused(id) && download::is_active(id) {
+        match result {
+            Ok(()) => {
+                log::info!("Download {} resumed and completed", id);
+                download::set_download_status(id, download::DownloadStatus::Completed);
+                download::remove_cancel_callback(id);
+                download::remove_resume_callback(id);
+            }
+            Err(e) => {
+                log::error!("Resume download {} failed: {}", id, e);
+                download::set_download_status(id, download::DownloadStatus::Failed(e));
+            }
+        }
+    }
+}
 
-            loop {
-                // Check for cancellation before each read
-                if cancellable.is_cancelled() {
-                    log::info!("Download {} cancelled during read loop", id);
-                    return Ok(()); // Exit cleanly, status already set by cancel callback
-                }
+/// Where a resumed download continues from, given the server's answer to
+/// its Range request
+#[derive(Debug, PartialEq)]
+struct ResumePlan {
+    /// Whether to append to the partial file rather than start over
+    append: bool,
+    received: u64,
+    total: u64,
+}
 
-                // read_future returns (buffer, bytes_read) or (buffer, error)
-                let result = input_stream
-                    .read_future(buffer, soup::glib::Priority::DEFAULT)
-                    .await;
-
-                let (buf, bytes_read) = match result {
-                    Ok((b, n)) => (b, n),
-                    Err((_b, e)) => {
-                        // Check if this was due to cancellation
-                        if cancellable.is_cancelled() {
-                            log::info!("Download {} cancelled", id);
-                            return Ok(());
-                        }
-                        return Err(format!("Read error: {}", e).into());
-                    }
-                };
-                buffer = buf; // Take buffer back
+fn resume_plan(status: soup::Status, start_byte: u64, content_length: u64) -> Result<ResumePlan, String> {
+    match status {
+        // For 206, content-length is the remaining bytes
+        soup::Status::PartialContent => Ok(ResumePlan {
+            append: true,
+            received: start_byte,
+            total: start_byte + content_length,
+        }),
+        // For 200, server doesn't support Range - we're getting the whole file
+        soup::Status::Ok => Ok(ResumePlan {
+            append: false,
+            received: 0,
+            total: content_length,
+        }),
+        _ => Err(format!("Server returned status {:?}", status)),
+    }
+}
 
-                if bytes_read == 0 {
-                    break; // EOF
-                }
+/// Fetch the rest of a download into `dest`, reporting (received, total)
+/// as it goes. Returns Ok early if cancelled.
+async fn resume_from<C: crate::http::HttpClient>(
+    client: &C,
+    id: u64,
+    url: &str,
+    dest: &Path,
+    start_byte: u64,
+    cancellable: &gio::Cancellable,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use crate::http::BodyStream;
+    use std::fs::OpenOptions;
+    use std::io::Write;
 
-                file.write_all(&buffer[..bytes_read])?;
-                received += bytes_read as u64;
+    let mut response = client.get_range(url, start_byte, None, cancellable).await?;
+    let plan = resume_plan(response.status, start_byte, response.content_length())?;
 
-                // Update progress
-                download::update_progress(id, received, total_bytes);
-            }
+    // Open destination file for appending (or writing if server sent full file)
+    let mut file = if plan.append {
+        OpenOptions::new().append(true).open(dest)?
+    } else {
+        // Server didn't support Range, start from beginning
+        progress(0, plan.total);
+        OpenOptions::new().write(true).truncate(true).create(true).open(dest)?
+    };
 
-            Ok(())
-        }.await;
+    let mut received = plan.received;
+    loop {
+        // Check for cancellation before each read
+        if cancellable.is_cancelled() {
+            log::info!("Download {} cancelled during read loop", id);
+            return Ok(()); // Exit cleanly, status already set by cancel callback
+        }
 
-        // Only update status if not already paused/cancelled
-        if !download::is_paused(id) && download::is_active(id) {
-            match result {
-                Ok(()) => {
-                    log::info!("Download {} resumed and completed", id);
-                    download::set_download_status(id, download::DownloadStatus::Completed);
-                    download::remove_cancel_callback(id);
-                    download::remove_resume_callback(id);
-                }
-                Err(e) => {
-                    log::error!("Resume download {} failed: {}", id, e);
-                    download::set_download_status(id, download::DownloadStatus::Failed(e.to_string()));
+        let chunk = match response.body.read_chunk().await {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // Check if this was due to cancellation
+                if cancellable.is_cancelled() {
+                    log::info!("Download {} cancelled", id);
+                    return Ok(());
                 }
+                return Err(e.into());
             }
+        };
+
+        if chunk.is_empty() {
+            break; // EOF
         }
-    });
+
+        file.write_all(&chunk)?;
+        received += chunk.len() as u64;
+        progress(received, plan.total);
+    }
+
+    Ok(())
 }
 
 /// Generate a unique filename by adding a counter if the file already exists.
@@ -760,3 +1292,158 @@ fn unique_filename(dir: &PathBuf, filename: &str) -> PathBuf {
     };
     dir.join(&new_filename)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::FakeClient;
+    use tempfile::tempdir;
+
+    fn resume(client: &FakeClient, dest: &Path, start_byte: u64) -> (Result<(), String>, Vec<(u64, u64)>) {
+        let cancellable = gio::Cancellable::new();
+        let mut updates = Vec::new();
+        let result = soup::glib::MainContext::new().block_on(resume_from(
+            client,
+            1,
+            "https://example.com/file.bin",
+            dest,
+            start_byte,
+            &cancellable,
+            |received, total| updates.push((received, total)),
+        ));
+        (result.map_err(|e| e.to_string()), updates)
+    }
+
+    #[test]
+    fn test_download_network_offline() {
+        let network = DownloadNetwork {
+            settings: Rc::new(RefCell::new(ProfileSettings::default())),
+            offline: Rc::new(Cell::new(true)),
+        };
+        assert!(network.client(0).is_none());
+        network.offline.set(false);
+        assert!(network.client(0).is_some());
+    }
+
+    #[test]
+    fn test_probe_range_support() {
+        let cancellable = gio::Cancellable::new();
+        let context = soup::glib::MainContext::new();
+        let url = "https://example.com/file.bin";
+
+        let partial = FakeClient::new(soup::Status::PartialContent, &[b"h"]);
+        assert_eq!(context.block_on(probe_range_support(&partial, url, &cancellable)), Some(true));
+        assert_eq!(partial.requests.borrow()[0], (url.to_string(), Some("bytes=0-0".to_string())));
+
+        let full = FakeClient::new(soup::Status::Ok, &[b"hello world"]);
+        assert_eq!(context.block_on(probe_range_support(&full, url, &cancellable)), Some(false));
+
+        let unsatisfiable = FakeClient::new(soup::Status::RequestedRangeNotSatisfiable, &[])
+            .with_header("Content-Range", "bytes */0");
+        assert_eq!(context.block_on(probe_range_support(&unsatisfiable, url, &cancellable)), Some(true));
+    }
+
+    #[test]
+    fn test_resume_after_full_response_finishes_once() {
+        use crate::download::{self, DownloadStatus};
+
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("file.bin");
+        fs::write(&dest, b"partial").unwrap();
+
+        let id = download::add_download("https://example.com/file.bin", "file.bin", dest.clone());
+        download::update_progress(id, 7, 11);
+        let download_id = Rc::new(Cell::new(Some(id)));
+        // The server ignores the range and sends the whole file
+        register_range_resume(id, Rc::clone(&download_id), || {
+            Some(FakeClient::new(soup::Status::Ok, &[b"hello world"]))
+        });
+
+        let finished = Rc::new(Cell::new(0));
+        let finished_for_changes = Rc::clone(&finished);
+        download::subscribe_to_changes(move || {
+            let status = download::get_download(id).map(|d| d.status);
+            if matches!(status, Some(DownloadStatus::Completed | DownloadStatus::Failed(_))) {
+                finished_for_changes.set(finished_for_changes.get() + 1);
+            }
+        });
+
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                // Pausing cancels the WebKit download, which reports a failure
+                download::pause_download(id);
+                webkit_download_failed(&download_id, "Download was cancelled");
+
+                download::resume_download(id);
+                while download::is_active(id) {
+                    context.iteration(true);
+                }
+
+                // A late signal from the WebKit download must not finish it again
+                webkit_download_finished(&download_id, false);
+                webkit_download_failed(&download_id, "Download was cancelled");
+            })
+            .unwrap();
+
+        assert_eq!(finished.get(), 1);
+        assert_eq!(download::get_download(id).map(|d| d.status), Some(DownloadStatus::Completed));
+        assert_eq!(fs::read(&dest).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn test_resume_plan() {
+        assert_eq!(
+            resume_plan(soup::Status::PartialContent, 100, 50),
+            Ok(ResumePlan { append: true, received: 100, total: 150 })
+        );
+        assert_eq!(
+            resume_plan(soup::Status::Ok, 100, 150),
+            Ok(ResumePlan { append: false, received: 0, total: 150 })
+        );
+        assert!(resume_plan(soup::Status::RequestedRangeNotSatisfiable, 100, 0).is_err());
+    }
+
+    #[test]
+    fn test_resume_appends_partial_content() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("file.bin");
+        fs::write(&dest, b"hello ").unwrap();
+
+        let client = FakeClient::new(soup::Status::PartialContent, &[b"wor", b"ld"]);
+        let (result, updates) = resume(&client, &dest, 6);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(fs::read(&dest).unwrap(), b"hello world");
+        assert_eq!(updates, vec![(9, 11), (11, 11)]);
+        assert_eq!(client.requests.borrow()[0].1.as_deref(), Some("bytes=6-"));
+    }
+
+    #[test]
+    fn test_resume_restarts_on_full_response() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("file.bin");
+        fs::write(&dest, b"stale partial data").unwrap();
+
+        let client = FakeClient::new(soup::Status::Ok, &[b"hello ", b"world"]);
+        let (result, updates) = resume(&client, &dest, 18);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(fs::read(&dest).unwrap(), b"hello world");
+        assert_eq!(updates, vec![(0, 11), (6, 11), (11, 11)]);
+    }
+
+    #[test]
+    fn test_resume_fails_on_error_status() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("file.bin");
+        fs::write(&dest, b"partial").unwrap();
+
+        let client = FakeClient::new(soup::Status::NotFound, &[b"Not Found"]);
+        let (result, updates) = resume(&client, &dest, 7);
+
+        assert_eq!(result, Err("Server returned status NotFound".to_string()));
+        assert_eq!(fs::read(&dest).unwrap(), b"partial");
+        assert!(updates.is_empty());
+    }
+}
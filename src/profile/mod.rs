@@ -1,3 +1,11 @@
+mod cache;
+mod containers;
 mod manager;
+mod permissions;
+mod settings;
 
-pub use manager::ProfileManager;
+pub use cache::enforce_cache_limit;
+pub use containers::{container_css, Container};
+pub use manager::{with_session_profile, Profile, ProfileError, ProfileManager};
+pub use permissions::PermissionKind;
+pub use settings::{CookiePolicy, ProxySetting};
@@ -0,0 +1,151 @@
+//! Remembered site permission decisions, stored per profile.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const PERMISSIONS_FILE: &str = "permissions.json";
+
+/// A permission a site can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionKind {
+    Geolocation,
+    Notifications,
+    Camera,
+    Microphone,
+}
+
+impl PermissionKind {
+    /// Human-readable name
+    pub fn label(&self) -> &'static str {
+        match self {
+            PermissionKind::Geolocation => "Location",
+            PermissionKind::Notifications => "Notifications",
+            PermissionKind::Camera => "Camera",
+            PermissionKind::Microphone => "Microphone",
+        }
+    }
+}
+
+/// Per-origin allow/block decisions
+#[derive(Debug, Default)]
+pub struct SitePermissions {
+    path: Option<PathBuf>,
+    /// origin -> permission -> allowed
+    sites: BTreeMap<String, BTreeMap<PermissionKind, bool>>,
+}
+
+impl SitePermissions {
+    /// Load decisions from a profile directory (empty if none saved yet)
+    pub fn load(profile_path: &Path) -> Self {
+        let path = profile_path.join(PERMISSIONS_FILE);
+        let sites = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            path: Some(path),
+            sites,
+        }
+    }
+
+    /// The remembered decision for a request covering `kinds`.
+    /// Blocked if any of them was blocked, allowed only if all were allowed.
+    pub fn decision(&self, origin: &str, kinds: &[PermissionKind]) -> Option<bool> {
+        let site = self.sites.get(origin)?;
+        let decisions: Vec<Option<bool>> = kinds.iter().map(|k| site.get(k).copied()).collect();
+
+        if decisions.contains(&Some(false)) {
+            Some(false)
+        } else if !decisions.is_empty() && decisions.iter().all(|d| *d == Some(true)) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Remember a decision for every permission in `kinds` and persist it
+    pub fn remember(&mut self, origin: &str, kinds: &[PermissionKind], allowed: bool) {
+        let site = self.sites.entry(origin.to_string()).or_default();
+        for kind in kinds {
+            site.insert(*kind, allowed);
+        }
+        self.save_logged();
+    }
+
+    /// Forget a stored decision and persist the change
+    pub fn revoke(&mut self, origin: &str, kind: PermissionKind) {
+        if let Some(site) = self.sites.get_mut(origin) {
+            site.remove(&kind);
+            if site.is_empty() {
+                self.sites.remove(origin);
+            }
+        }
+        self.save_logged();
+    }
+
+    /// All stored decisions as (origin, permission, allowed)
+    pub fn list(&self) -> Vec<(String, PermissionKind, bool)> {
+        self.sites
+            .iter()
+            .flat_map(|(origin, site)| {
+                site.iter()
+                    .map(move |(kind, allowed)| (origin.clone(), *kind, *allowed))
+            })
+            .collect()
+    }
+
+    fn save_logged(&self) {
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save site permissions: {}", e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.sites)?;
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const ORIGIN: &str = "https://meet.example";
+
+    #[test]
+    fn test_media_needs_every_kind_allowed() {
+        let mut permissions = SitePermissions::default();
+        let both = [PermissionKind::Camera, PermissionKind::Microphone];
+
+        permissions.remember(ORIGIN, &[PermissionKind::Camera], true);
+        assert_eq!(permissions.decision(ORIGIN, &[PermissionKind::Camera]), Some(true));
+        assert_eq!(permissions.decision(ORIGIN, &both), None);
+
+        permissions.remember(ORIGIN, &[PermissionKind::Microphone], false);
+        assert_eq!(permissions.decision(ORIGIN, &both), Some(false));
+    }
+
+    #[test]
+    fn test_revoke_and_reload() {
+        let dir = tempdir().unwrap();
+        let mut permissions = SitePermissions::load(dir.path());
+        permissions.remember(ORIGIN, &[PermissionKind::Geolocation, PermissionKind::Notifications], true);
+        permissions.revoke(ORIGIN, PermissionKind::Geolocation);
+
+        let reloaded = SitePermissions::load(dir.path());
+        assert_eq!(
+            reloaded.list(),
+            vec![(ORIGIN.to_string(), PermissionKind::Notifications, true)]
+        );
+        assert_eq!(reloaded.decision("https://other.example", &[PermissionKind::Notifications]), None);
+    }
+}
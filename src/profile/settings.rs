@@ -0,0 +1,277 @@
+//! Per-profile settings stored alongside the profile's data.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Proxy schemes WebKit's network process understands
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks", "socks4", "socks4a", "socks5", "socks5h"];
+
+/// Hosts that never go through a manual proxy
+const PROXY_IGNORE_HOSTS: &[&str] = &["localhost", "127.0.0.0/8", "::1"];
+
+/// Proxy for a profile that's offline: nothing listens on port 1 of the
+/// loopback interface, so every connection is refused at once
+const OFFLINE_PROXY_URI: &str = "http://127.0.0.1:1";
+
+/// Which cookies a profile accepts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CookiePolicy {
+    AcceptAll,
+    /// Accept first-party cookies but block third-party (good privacy default)
+    #[default]
+    BlockThirdParty,
+    BlockAll,
+}
+
+impl CookiePolicy {
+    /// All policies, in the order shown in preferences
+    pub const ALL: [CookiePolicy; 3] = [
+        CookiePolicy::AcceptAll,
+        CookiePolicy::BlockThirdParty,
+        CookiePolicy::BlockAll,
+    ];
+
+    /// Human-readable name
+    pub fn label(&self) -> &'static str {
+        match self {
+            CookiePolicy::AcceptAll => "Accept all",
+            CookiePolicy::BlockThirdParty => "Block third-party",
+            CookiePolicy::BlockAll => "Block all",
+        }
+    }
+
+    /// The equivalent WebKit policy
+    pub fn accept_policy(&self) -> webkit6::CookieAcceptPolicy {
+        match self {
+            CookiePolicy::AcceptAll => webkit6::CookieAcceptPolicy::Always,
+            CookiePolicy::BlockThirdParty => webkit6::CookieAcceptPolicy::NoThirdParty,
+            CookiePolicy::BlockAll => webkit6::CookieAcceptPolicy::Never,
+        }
+    }
+}
+
+/// How a profile connects to the web
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxySetting {
+    /// Follow the desktop's proxy settings
+    #[default]
+    System,
+    /// Connect directly, whatever the desktop says
+    NoProxy,
+    /// A proxy URI such as "http://proxy.example:8080" or "socks5://localhost:1080"
+    Manual(String),
+}
+
+impl ProxySetting {
+    /// A manual proxy from user input, "host:port" or "scheme://host:port".
+    /// Returns None unless it names a supported scheme, a host and a port.
+    pub fn manual(input: &str) -> Option<Self> {
+        let input = input.trim().trim_end_matches('/');
+        let uri = if input.contains("://") {
+            input.to_string()
+        } else {
+            format!("http://{}", input)
+        };
+        let url = url::Url::parse(&uri).ok()?;
+        if !PROXY_SCHEMES.contains(&url.scheme()) || !url.path().trim_start_matches('/').is_empty() {
+            return None;
+        }
+        // Proxy credentials are asked for when the proxy wants them, not stored here
+        if !url.username().is_empty() || url.password().is_some() {
+            return None;
+        }
+        // Url drops default ports, so read the port from the input itself
+        let (_, port) = uri.rsplit_once(':')?;
+        let port: u16 = port.parse().ok()?;
+        let host = url.host_str()?;
+        Some(ProxySetting::Manual(format!("{}://{}:{}", url.scheme(), host, port)))
+    }
+
+    /// Apply to a profile's network session; takes effect for new connections
+    pub fn apply(&self, session: &webkit6::NetworkSession) {
+        match self {
+            ProxySetting::System => session.set_proxy_settings(webkit6::NetworkProxyMode::Default, None),
+            ProxySetting::NoProxy => session.set_proxy_settings(webkit6::NetworkProxyMode::NoProxy, None),
+            ProxySetting::Manual(uri) => {
+                let settings = webkit6::NetworkProxySettings::new(Some(uri), PROXY_IGNORE_HOSTS);
+                session.set_proxy_settings(webkit6::NetworkProxyMode::Custom, Some(&settings));
+            }
+        }
+    }
+
+    /// The same route for a libsoup session, or None to keep its default
+    /// resolver, which follows the desktop like WebKit's default mode
+    pub fn proxy_resolver(&self) -> Option<soup::gio::ProxyResolver> {
+        match self {
+            ProxySetting::System => None,
+            ProxySetting::NoProxy => Some(soup::gio::SimpleProxyResolver::new(None, &[] as &[&str])),
+            ProxySetting::Manual(uri) => Some(soup::gio::SimpleProxyResolver::new(Some(uri), PROXY_IGNORE_HOSTS)),
+        }
+    }
+
+    /// Cut a network session off by routing all of it, local hosts included, to a
+    /// proxy that refuses connections. WebKitGTK has no offline mode for a session.
+    pub fn apply_offline(session: &webkit6::NetworkSession) {
+        let settings = webkit6::NetworkProxySettings::new(Some(OFFLINE_PROXY_URI), &[]);
+        session.set_proxy_settings(webkit6::NetworkProxyMode::Custom, Some(&settings));
+    }
+}
+
+/// Settings for a single profile/container
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    #[serde(default)]
+    pub cookie_policy: CookiePolicy,
+    #[serde(default)]
+    pub proxy: ProxySetting,
+    /// Search URL template (with `{}` for the query) used instead of the global search engine
+    #[serde(default)]
+    pub search_url: Option<String>,
+}
+
+impl ProfileSettings {
+    /// Load settings from a profile directory, falling back to defaults
+    pub fn load(profile_path: &Path) -> Self {
+        let settings_path = profile_path.join(SETTINGS_FILE);
+        let Ok(contents) = fs::read_to_string(&settings_path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid profile settings {:?}: {}", settings_path, e);
+            Self::default()
+        })
+    }
+
+    /// Save settings to a profile directory
+    pub fn save(&self, profile_path: &Path) -> io::Result<()> {
+        let settings_path = profile_path.join(SETTINGS_FILE);
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&settings_path, contents)
+    }
+
+    /// Change the settings saved in a profile directory that no loaded profile uses
+    pub fn update(profile_path: &Path, change: impl FnOnce(&mut Self)) -> io::Result<()> {
+        fs::create_dir_all(profile_path)?;
+        let mut settings = Self::load(profile_path);
+        change(&mut settings);
+        settings.save(profile_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cookie_policy_survives_reload() {
+        let dir = tempdir().unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()).cookie_policy, CookiePolicy::BlockThirdParty);
+
+        let settings = ProfileSettings {
+            cookie_policy: CookiePolicy::BlockAll,
+            ..Default::default()
+        };
+        settings.save(dir.path()).unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()), settings);
+    }
+
+    #[test]
+    fn test_search_url_survives_reload() {
+        let dir = tempdir().unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()).search_url, None);
+
+        let settings = ProfileSettings {
+            search_url: Some("https://search.example.internal/?q={}".to_string()),
+            ..Default::default()
+        };
+        settings.save(dir.path()).unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()), settings);
+    }
+
+    #[test]
+    fn test_update_unloaded_profile() {
+        let dir = tempdir().unwrap();
+        let profile_path = dir.path().join("work");
+        ProfileSettings::update(&profile_path, |s| s.cookie_policy = CookiePolicy::BlockAll).unwrap();
+        ProfileSettings::update(&profile_path, |s| s.proxy = ProxySetting::NoProxy).unwrap();
+
+        let settings = ProfileSettings::load(&profile_path);
+        assert_eq!(settings.cookie_policy, CookiePolicy::BlockAll);
+        assert_eq!(settings.proxy, ProxySetting::NoProxy);
+    }
+
+    #[test]
+    fn test_proxy_resolver_matches_setting() {
+        use soup::gio::prelude::ProxyResolverExt;
+
+        let lookup = |setting: ProxySetting| {
+            setting.proxy_resolver().map(|resolver| {
+                resolver
+                    .lookup("https://example.com/", soup::gio::Cancellable::NONE)
+                    .unwrap()
+                    .iter()
+                    .map(|proxy| proxy.to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(lookup(ProxySetting::System), None);
+        assert_eq!(lookup(ProxySetting::NoProxy), Some(vec!["direct://".to_string()]));
+        assert_eq!(
+            lookup(ProxySetting::Manual("socks5://127.0.0.1:1080".to_string())),
+            Some(vec!["socks5://127.0.0.1:1080".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_manual_proxy_input() {
+        assert_eq!(
+            ProxySetting::manual("proxy.example:8080"),
+            Some(ProxySetting::Manual("http://proxy.example:8080".to_string()))
+        );
+        assert_eq!(
+            ProxySetting::manual(" socks5://127.0.0.1:1080/ "),
+            Some(ProxySetting::Manual("socks5://127.0.0.1:1080".to_string()))
+        );
+        assert_eq!(
+            ProxySetting::manual("http://proxy.example:80"),
+            Some(ProxySetting::Manual("http://proxy.example:80".to_string()))
+        );
+        assert_eq!(ProxySetting::manual("proxy.example"), None);
+        assert_eq!(ProxySetting::manual("ftp://proxy.example:21"), None);
+        assert_eq!(ProxySetting::manual("http://proxy.example:8080/path"), None);
+        assert_eq!(ProxySetting::manual("proxy.example:99999"), None);
+        assert_eq!(ProxySetting::manual(""), None);
+    }
+
+    #[test]
+    fn test_proxy_survives_reload() {
+        let dir = tempdir().unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()).proxy, ProxySetting::System);
+
+        // Settings saved before proxies existed keep loading
+        fs::write(dir.path().join(SETTINGS_FILE), "{\"cookie_policy\":\"accept-all\"}").unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()).cookie_policy, CookiePolicy::AcceptAll);
+
+        let settings = ProfileSettings {
+            proxy: ProxySetting::Manual("socks5://localhost:1080".to_string()),
+            ..Default::default()
+        };
+        settings.save(dir.path()).unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()), settings);
+    }
+
+    #[test]
+    fn test_invalid_settings_use_defaults() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(SETTINGS_FILE), "{\"cookie_policy\":\"sometimes\"}").unwrap();
+        assert_eq!(ProfileSettings::load(dir.path()), ProfileSettings::default());
+    }
+}
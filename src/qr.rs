@@ -0,0 +1,74 @@
+//! QR codes for sending the current page to another device.
+
+use gtk4::{gdk, glib};
+use qrcode::{Color, EcLevel, QrCode};
+
+/// Light modules around the code, as the QR spec asks for
+const QUIET_ZONE: usize = 4;
+
+/// Pixels per module when rendering
+const MODULE_PIXELS: usize = 6;
+
+/// Encode text as a QR code, dropping to lower error correction for long text.
+/// Returns None if it doesn't fit even the largest version.
+pub fn encode(text: &str) -> Option<QrCode> {
+    [EcLevel::M, EcLevel::L]
+        .into_iter()
+        .find_map(|level| QrCode::with_error_correction_level(text, level).ok())
+}
+
+/// Render a QR code as a black on white texture
+pub fn to_texture(code: &QrCode) -> gdk::MemoryTexture {
+    let size = (code.width() + 2 * QUIET_ZONE) * MODULE_PIXELS;
+    let modules: Vec<bool> = code.to_colors().into_iter().map(|c| c == Color::Dark).collect();
+    let pixels = rasterize(&modules, code.width(), MODULE_PIXELS);
+    gdk::MemoryTexture::new(
+        size as i32,
+        size as i32,
+        gdk::MemoryFormat::G8,
+        &glib::Bytes::from_owned(pixels),
+        size,
+    )
+}
+
+/// Grayscale pixels of a square module matrix (true is dark), with the quiet zone
+fn rasterize(modules: &[bool], width: usize, scale: usize) -> Vec<u8> {
+    let size = (width + 2 * QUIET_ZONE) * scale;
+    let mut pixels = vec![0xff; size * size];
+    for (i, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+        let (x, y) = (i % width + QUIET_ZONE, i / width + QUIET_ZONE);
+        for row in y * scale..(y + 1) * scale {
+            pixels[row * size + x * scale..row * size + (x + 1) * scale].fill(0);
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize_adds_quiet_zone() {
+        // 2x2 matrix with the top-left and bottom-right modules dark
+        let pixels = rasterize(&[true, false, false, true], 2, 1);
+        let size = 2 + 2 * QUIET_ZONE;
+        assert_eq!(pixels.len(), size * size);
+
+        let dark: Vec<(usize, usize)> = (0..pixels.len())
+            .filter(|i| pixels[*i] == 0)
+            .map(|i| (i % size, i / size))
+            .collect();
+        assert_eq!(dark, vec![(QUIET_ZONE, QUIET_ZONE), (QUIET_ZONE + 1, QUIET_ZONE + 1)]);
+    }
+
+    #[test]
+    fn test_long_urls_fall_back_to_lower_error_correction() {
+        let url = format!("https://example.com/?q={}", "a".repeat(2400));
+        let code = encode(&url).unwrap();
+        assert_eq!(code.error_correction_level(), EcLevel::L);
+
+        assert_eq!(encode("https://example.com/").unwrap().error_correction_level(), EcLevel::M);
+        assert!(encode(&"a".repeat(4000)).is_none());
+    }
+}
@@ -9,6 +9,9 @@ pub const MAX_CLOSED_TABS: usize = 25;
 /// Maximum number of recently closed windows to remember globally.
 pub const MAX_CLOSED_WINDOWS: usize = 10;
 
+/// Number of closed tabs and windows listed in the "Recently Closed" menu.
+pub const MENU_ENTRY_LIMIT: usize = 10;
+
 /// A tab that was closed and can be reopened.
 #[derive(Clone, Debug)]
 pub struct ClosedTab {
@@ -40,7 +43,9 @@ impl RecentlyClosedTabs {
     }
 
     /// Record a closed tab. Oldest tabs are removed when limit is reached.
+    /// Reclosing a URL already in the list moves it to the top instead of duplicating it.
     pub fn push(&mut self, tab: ClosedTab) {
+        self.tabs.retain(|t| t.url != tab.url || t.container != tab.container);
         self.tabs.push_front(tab);
         while self.tabs.len() > MAX_CLOSED_TABS {
             self.tabs.pop_back();
@@ -52,6 +57,11 @@ impl RecentlyClosedTabs {
         self.tabs.pop_front()
     }
 
+    /// Get and remove the closed tab at `index` (0 is the most recent).
+    pub fn reopen_at(&mut self, index: usize) -> Option<ClosedTab> {
+        self.tabs.remove(index)
+    }
+
     /// Get all recently closed tabs (most recent first).
     pub fn list(&self) -> impl Iterator<Item = &ClosedTab> {
         self.tabs.iter()
@@ -89,6 +99,11 @@ impl RecentlyClosedWindows {
         self.windows.pop_front()
     }
 
+    /// Get and remove the closed window at `index` (0 is the most recent).
+    pub fn reopen_at(&mut self, index: usize) -> Option<ClosedWindow> {
+        self.windows.remove(index)
+    }
+
     /// Get all recently closed windows (most recent first).
     pub fn list(&self) -> impl Iterator<Item = &ClosedWindow> {
         self.windows.iter()
@@ -99,3 +114,85 @@ impl RecentlyClosedWindows {
         self.windows.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed_tab(url: &str, container: &str) -> ClosedTab {
+        ClosedTab {
+            url: url.to_string(),
+            title: None,
+            container: container.to_string(),
+            closed_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_closed_tabs_capped() {
+        let mut closed = RecentlyClosedTabs::new();
+        for i in 0..MAX_CLOSED_TABS + 5 {
+            closed.push(closed_tab(&format!("https://example.com/{}", i), "personal"));
+        }
+
+        assert_eq!(closed.list().count(), MAX_CLOSED_TABS);
+        // The oldest entries were dropped
+        let last = closed.list().last().unwrap();
+        assert_eq!(last.url, "https://example.com/5");
+    }
+
+    #[test]
+    fn test_reclosed_tab_moves_to_top() {
+        let mut closed = RecentlyClosedTabs::new();
+        closed.push(closed_tab("https://a.example/", "personal"));
+        closed.push(closed_tab("https://b.example/", "personal"));
+        closed.push(closed_tab("https://a.example/", "personal"));
+
+        let urls: Vec<_> = closed.list().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.example/", "https://b.example/"]);
+    }
+
+    #[test]
+    fn test_reopen_at_index() {
+        let mut closed = RecentlyClosedTabs::new();
+        for url in ["https://a.example/", "https://b.example/", "https://c.example/"] {
+            closed.push(closed_tab(url, "personal"));
+        }
+
+        assert_eq!(closed.reopen_at(1).unwrap().url, "https://b.example/");
+        assert!(closed.reopen_at(5).is_none());
+        let urls: Vec<_> = closed.list().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["https://c.example/", "https://a.example/"]);
+    }
+
+    #[test]
+    fn test_same_url_in_other_container_kept() {
+        let mut closed = RecentlyClosedTabs::new();
+        closed.push(closed_tab("https://a.example/", "personal"));
+        closed.push(closed_tab("https://a.example/", "work"));
+
+        assert_eq!(closed.list().count(), 2);
+        assert_eq!(closed.pop().unwrap().container, "work");
+    }
+
+    #[test]
+    fn test_closed_windows_capped_and_reopened_by_index() {
+        let mut closed = RecentlyClosedWindows::new();
+        for i in 0..MAX_CLOSED_WINDOWS + 2 {
+            closed.push(ClosedWindow {
+                tabs: vec![
+                    closed_tab(&format!("https://example.com/{}", i), "personal"),
+                    closed_tab("https://example.org/", "work"),
+                ],
+                active_tab_index: 1,
+                closed_at: SystemTime::now(),
+            });
+        }
+        assert_eq!(closed.list().count(), MAX_CLOSED_WINDOWS);
+
+        let window = closed.reopen_at(2).unwrap();
+        assert_eq!(window.tabs[0].url, format!("https://example.com/{}", MAX_CLOSED_WINDOWS - 1));
+        assert_eq!(window.active_tab_index, 1);
+        assert_eq!(closed.list().count(), MAX_CLOSED_WINDOWS - 1);
+    }
+}
@@ -0,0 +1,123 @@
+//! Dialog listing the sessions saved by name, to reopen or delete them.
+
+use super::NamedSession;
+use gtk4::glib;
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Callback receiving the session picked to open
+type OpenCallback = Rc<dyn Fn(NamedSession)>;
+
+/// Dialog for reopening a saved session.
+pub struct SessionsDialog {
+    dialog: libadwaita::Dialog,
+}
+
+impl SessionsDialog {
+    /// Create a dialog for the sessions saved in the profile at `profile_path`.
+    pub fn new<F: Fn(NamedSession) + 'static>(profile_path: &Path, on_open: F) -> Self {
+        let dialog = libadwaita::Dialog::builder()
+            .title("Saved Sessions")
+            .content_width(420)
+            .content_height(420)
+            .build();
+
+        let placeholder = gtk4::Label::new(Some("No saved sessions"));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(24);
+        placeholder.set_margin_bottom(24);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+        list.set_placeholder(Some(&placeholder));
+
+        let on_open: OpenCallback = Rc::new(on_open);
+        for name in NamedSession::list(profile_path) {
+            list.append(&session_row(&dialog, &list, profile_path, &name, &on_open));
+        }
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&list);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .vexpand(true)
+            .child(&content)
+            .build();
+
+        let toolbar_view = libadwaita::ToolbarView::new();
+        toolbar_view.add_top_bar(&libadwaita::HeaderBar::new());
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: &impl IsA<gtk4::Widget>) {
+        self.dialog.present(Some(parent));
+    }
+}
+
+/// Build a row for one saved session: activating it opens the session, the button deletes it
+fn session_row(
+    dialog: &libadwaita::Dialog,
+    list: &gtk4::ListBox,
+    profile_path: &Path,
+    name: &str,
+    on_open: &OpenCallback,
+) -> libadwaita::ActionRow {
+    let subtitle = match NamedSession::load(profile_path, name) {
+        Ok(saved) => format!("{} windows, {} tabs", saved.session.windows.len(), saved.tab_count()),
+        Err(e) => {
+            log::warn!("Failed to read saved session '{}': {}", name, e);
+            "Couldn't read this session".to_string()
+        }
+    };
+    let row = libadwaita::ActionRow::builder()
+        .title(glib::markup_escape_text(name))
+        .subtitle(subtitle)
+        .activatable(true)
+        .build();
+
+    let delete_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+    delete_button.set_valign(gtk4::Align::Center);
+    delete_button.set_tooltip_text(Some("Delete Session"));
+    delete_button.add_css_class("flat");
+    row.add_suffix(&delete_button);
+
+    let profile_path: PathBuf = profile_path.to_path_buf();
+    let name = name.to_string();
+
+    let dialog = dialog.clone();
+    let on_open = Rc::clone(on_open);
+    let open_path = profile_path.clone();
+    let open_name = name.clone();
+    row.connect_activated(move |_| match NamedSession::load(&open_path, &open_name) {
+        Ok(saved) => {
+            dialog.close();
+            on_open(saved);
+        }
+        Err(e) => log::error!("Failed to open saved session '{}': {}", open_name, e),
+    });
+
+    let list = list.clone();
+    let row_weak = row.downgrade();
+    delete_button.connect_clicked(move |_| match NamedSession::delete(&profile_path, &name) {
+        Ok(()) => {
+            log::info!("Deleted saved session '{}'", name);
+            if let Some(row) = row_weak.upgrade() {
+                list.remove(&row);
+            }
+        }
+        Err(e) => log::error!("Failed to delete saved session '{}': {}", name, e),
+    });
+
+    row
+}
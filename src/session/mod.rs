@@ -0,0 +1,655 @@
+//! Session persistence for saving and restoring browser tabs.
+
+mod dialog;
+
+pub use dialog::SessionsDialog;
+
+use gtk4::gio;
+use gtk4::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+const SESSION_FILE: &str = "session.json";
+
+/// The session file as it was at startup, when it wasn't restored then
+const PREVIOUS_SESSION_FILE: &str = "session-previous.json";
+
+/// Written on clean shutdown
+const CLEAN_EXIT_MARKER: &str = "clean-exit";
+
+/// Written at startup; there without a clean exit marker means the last run
+/// ended unexpectedly
+const RUNNING_MARKER: &str = "running";
+
+/// Directory in the profile holding sessions saved by name
+const NAMED_SESSIONS_DIR: &str = "sessions";
+
+/// Header row of CSV session exports
+const CSV_HEADER: &str = "window,url,title,container";
+
+/// Record that the app is shutting down cleanly
+pub fn mark_clean_exit(data_dir: &Path) -> io::Result<()> {
+    fs::write(data_dir.join(CLEAN_EXIT_MARKER), "")
+}
+
+/// Whether the last run ended without shutting down cleanly, then mark this
+/// run as running so a crash of it is noticed next time. A first run, or
+/// one after a version that didn't write the running marker, isn't a crash.
+pub fn begin_run(data_dir: &Path) -> bool {
+    let clean_marker = data_dir.join(CLEAN_EXIT_MARKER);
+    let crashed = data_dir.join(RUNNING_MARKER).exists() && !clean_marker.exists();
+    if clean_marker.exists() {
+        if let Err(e) = fs::remove_file(&clean_marker) {
+            log::warn!("Failed to remove clean exit marker: {}", e);
+        }
+    }
+    if let Err(e) = fs::write(data_dir.join(RUNNING_MARKER), "") {
+        log::warn!("Failed to write running marker: {}", e);
+    }
+    crashed
+}
+
+/// What the browser opens on startup, from the `startup-mode` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupMode {
+    /// Reopen the windows of the last session
+    RestoreSession,
+    /// One window showing the homepage
+    Homepage,
+    /// One window with the startup pages as tabs
+    StartupPages,
+}
+
+impl StartupMode {
+    /// The configured startup mode
+    pub fn current(settings: &gio::Settings) -> Self {
+        let chosen = settings.user_value("startup-mode").is_some();
+        let mode = chosen.then(|| settings.string("startup-mode"));
+        Self::from_values(mode.as_deref(), settings.boolean("restore-session"))
+    }
+
+    /// The mode for a `startup-mode` value, or for the older `restore-session`
+    /// switch when no mode has been chosen yet
+    fn from_values(mode: Option<&str>, restore_session: bool) -> Self {
+        match mode {
+            Some("session") => Self::RestoreSession,
+            Some("pages") => Self::StartupPages,
+            Some(_) => Self::Homepage,
+            None if restore_session => Self::RestoreSession,
+            None => Self::Homepage,
+        }
+    }
+
+    /// The mode's `startup-mode` value
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::RestoreSession => "session",
+            Self::Homepage => "homepage",
+            Self::StartupPages => "pages",
+        }
+    }
+}
+
+/// A saved tab entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTab {
+    pub url: String,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    /// Vertical scroll offset of the page when the session was saved
+    #[serde(default)]
+    pub scroll_y: Option<f64>,
+    /// ID of the tab group the tab belongs to, one of its window's `groups`
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Whether the tab was pinned
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// A named group of tabs in a window's sidebar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTabGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// A saved window with its tabs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedWindow {
+    pub tabs: Vec<SavedTab>,
+    pub active_tab_index: usize,
+    #[serde(default)]
+    pub window_width: Option<i32>,
+    #[serde(default)]
+    pub window_height: Option<i32>,
+    #[serde(default)]
+    pub groups: Vec<SavedTabGroup>,
+}
+
+impl SavedWindow {
+    /// A window with a tab for each URL, in the default container, the first one active
+    pub fn with_pages(urls: impl IntoIterator<Item = String>) -> Self {
+        let tabs = urls
+            .into_iter()
+            .map(|url| SavedTab {
+                url,
+                title: None,
+                container: None,
+                scroll_y: None,
+                group: None,
+                pinned: false,
+            })
+            .collect();
+        Self {
+            tabs,
+            ..Default::default()
+        }
+    }
+}
+
+/// Multi-window session state to persist across restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MultiWindowSession {
+    pub windows: Vec<SavedWindow>,
+}
+
+impl MultiWindowSession {
+    /// Create an empty multi-window session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load multi-window session from a profile directory.
+    /// Handles migration from legacy single-window format.
+    pub fn load(profile_path: &Path) -> io::Result<Self> {
+        Self::load_file(&profile_path.join(SESSION_FILE))
+    }
+
+    fn load_file(session_path: &Path) -> io::Result<Self> {
+        if !session_path.exists() {
+            return Ok(Self::new());
+        }
+
+        let contents = fs::read_to_string(session_path)?;
+
+        // Try to parse as multi-window format first
+        if let Ok(multi) = serde_json::from_str::<MultiWindowSession>(&contents) {
+            if !multi.windows.is_empty() {
+                return Ok(multi);
+            }
+        }
+
+        // Fall back to legacy single-window format
+        if let Ok(legacy) = serde_json::from_str::<Session>(&contents) {
+            if !legacy.tabs.is_empty() {
+                let window = SavedWindow {
+                    tabs: legacy.tabs,
+                    active_tab_index: legacy.active_tab_index,
+                    window_width: legacy.window_width,
+                    window_height: legacy.window_height,
+                    groups: Vec::new(),
+                };
+                return Ok(MultiWindowSession {
+                    windows: vec![window],
+                });
+            }
+        }
+
+        Ok(Self::new())
+    }
+
+    /// Save multi-window session to a profile directory
+    pub fn save(&self, profile_path: &Path) -> io::Result<()> {
+        let session_path = profile_path.join(SESSION_FILE);
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&session_path, contents)?;
+        log::debug!("Multi-window session saved to {:?}", session_path);
+        Ok(())
+    }
+
+    /// Move the session file aside without reading it, so the new session
+    /// doesn't overwrite it and it can still be restored later
+    pub fn set_aside(profile_path: &Path) -> io::Result<()> {
+        let session_path = profile_path.join(SESSION_FILE);
+        if session_path.exists() {
+            fs::rename(&session_path, profile_path.join(PREVIOUS_SESSION_FILE))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a session was set aside at startup
+    pub fn has_previous(profile_path: &Path) -> bool {
+        profile_path.join(PREVIOUS_SESSION_FILE).exists()
+    }
+
+    /// Load the session set aside at startup, removing it so it's only restored once
+    pub fn take_previous(profile_path: &Path) -> io::Result<Self> {
+        let previous_path = profile_path.join(PREVIOUS_SESSION_FILE);
+        let session = Self::load_file(&previous_path)?;
+        if previous_path.exists() {
+            fs::remove_file(&previous_path)?;
+        }
+        Ok(session)
+    }
+
+    /// Check if there are any windows to restore
+    pub fn has_windows(&self) -> bool {
+        self.windows.iter().any(|w| !w.tabs.is_empty())
+    }
+
+    /// Add a window to the session
+    pub fn add_window(&mut self, window: SavedWindow) {
+        self.windows.push(window);
+    }
+
+    /// Export as pretty-printed JSON, the same format as the session file
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Export as a flat CSV with one row per tab, numbered by window
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for (index, window) in self.windows.iter().enumerate() {
+            for tab in &window.tabs {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    index + 1,
+                    csv_field(&tab.url),
+                    csv_field(tab.title.as_deref().unwrap_or_default()),
+                    csv_field(tab.container.as_deref().unwrap_or_default()),
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Parse an exported session, either JSON or CSV
+    pub fn from_export(contents: &str) -> io::Result<Self> {
+        if contents.trim_start().starts_with('{') {
+            return serde_json::from_str(contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+
+        let mut lines = contents.lines();
+        if lines.next().map(str::trim) != Some(CSV_HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a session export",
+            ));
+        }
+
+        let mut session = Self::new();
+        let mut current_window = None;
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let fields = parse_csv_line(line);
+            let [window, url, title, container] = fields.as_slice() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Malformed CSV row: {}", line),
+                ));
+            };
+
+            if current_window.as_ref() != Some(window) {
+                session.add_window(SavedWindow::default());
+                current_window = Some(window.clone());
+            }
+            if let Some(saved_window) = session.windows.last_mut() {
+                saved_window.tabs.push(SavedTab {
+                    url: url.clone(),
+                    title: Some(title.clone()).filter(|t| !t.is_empty()),
+                    container: Some(container.clone()).filter(|c| !c.is_empty()),
+                    scroll_y: None,
+                    group: None,
+                    pinned: false,
+                });
+            }
+        }
+        Ok(session)
+    }
+
+    /// Drop tabs whose URL doesn't parse and windows left empty.
+    /// Returns the number of tabs skipped.
+    pub fn retain_valid_tabs(&mut self) -> usize {
+        let mut skipped = 0;
+        for window in &mut self.windows {
+            let active_url = window.tabs.get(window.active_tab_index).map(|t| t.url.clone());
+            let before = window.tabs.len();
+            window.tabs.retain(|tab| Url::parse(&tab.url).is_ok());
+            skipped += before - window.tabs.len();
+
+            // Keep the same tab active if it survived
+            window.active_tab_index = active_url
+                .and_then(|url| window.tabs.iter().position(|t| t.url == url))
+                .unwrap_or(0);
+        }
+        self.windows.retain(|w| !w.tabs.is_empty());
+        skipped
+    }
+}
+
+/// Windows saved under a name, to reopen later
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamedSession {
+    pub name: String,
+    #[serde(flatten)]
+    pub session: MultiWindowSession,
+}
+
+impl NamedSession {
+    /// Names of the sessions saved in a profile, sorted
+    pub fn list(profile_path: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(profile_path.join(NAMED_SESSIONS_DIR)) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?.to_string();
+                urlencoding::decode(&stem).ok().map(|name| name.into_owned())
+            })
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names
+    }
+
+    pub fn exists(profile_path: &Path, name: &str) -> bool {
+        named_session_path(profile_path, name).exists()
+    }
+
+    pub fn load(profile_path: &Path, name: &str) -> io::Result<Self> {
+        let contents = fs::read_to_string(named_session_path(profile_path, name))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Save the session, replacing any saved under the same name
+    pub fn save(&self, profile_path: &Path) -> io::Result<()> {
+        fs::create_dir_all(profile_path.join(NAMED_SESSIONS_DIR))?;
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(named_session_path(profile_path, &self.name), contents)
+    }
+
+    pub fn delete(profile_path: &Path, name: &str) -> io::Result<()> {
+        fs::remove_file(named_session_path(profile_path, name))
+    }
+
+    /// Number of tabs across all windows
+    pub fn tab_count(&self) -> usize {
+        self.session.windows.iter().map(|window| window.tabs.len()).sum()
+    }
+}
+
+/// File of a named session; the name is percent-encoded so any name is a safe file name
+fn named_session_path(profile_path: &Path, name: &str) -> PathBuf {
+    profile_path
+        .join(NAMED_SESSIONS_DIR)
+        .join(format!("{}.json", urlencoding::encode(name)))
+}
+
+/// Quote a CSV field if it contains a separator or quote
+fn csv_field(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Split a CSV line into fields, handling quoted fields
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Session state to persist across restarts (legacy single-window format)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<SavedTab>,
+    pub active_tab_index: usize,
+    #[serde(default)]
+    pub window_width: Option<i32>,
+    #[serde(default)]
+    pub window_height: Option<i32>,
+}
+
+impl Session {
+    /// Create an empty session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load session from a profile directory
+    pub fn load(profile_path: &Path) -> io::Result<Self> {
+        let session_path = profile_path.join(SESSION_FILE);
+
+        if !session_path.exists() {
+            return Ok(Self::new());
+        }
+
+        let contents = fs::read_to_string(&session_path)?;
+        serde_json::from_str(&contents).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })
+    }
+
+    /// Save session to a profile directory
+    pub fn save(&self, profile_path: &Path) -> io::Result<()> {
+        let session_path = profile_path.join(SESSION_FILE);
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&session_path, contents)?;
+        log::debug!("Session saved to {:?}", session_path);
+        Ok(())
+    }
+
+    /// Check if there are any tabs to restore
+    pub fn has_tabs(&self) -> bool {
+        !self.tabs.is_empty()
+    }
+
+    /// Add a tab to the session
+    pub fn add_tab(&mut self, url: String, title: Option<String>, container: Option<String>) {
+        self.tabs.push(SavedTab { url, title, container, scroll_y: None, group: None, pinned: false });
+    }
+
+    /// Clear all tabs
+    pub fn clear(&mut self) {
+        self.tabs.clear();
+        self.active_tab_index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_startup_mode_falls_back_to_restore_switch() {
+        assert_eq!(StartupMode::from_values(Some("pages"), true), StartupMode::StartupPages);
+        assert_eq!(StartupMode::from_values(Some("homepage"), true), StartupMode::Homepage);
+        assert_eq!(StartupMode::from_values(Some("session"), false), StartupMode::RestoreSession);
+        // Before a mode is chosen, the old switch still decides
+        assert_eq!(StartupMode::from_values(None, true), StartupMode::RestoreSession);
+        assert_eq!(StartupMode::from_values(None, false), StartupMode::Homepage);
+    }
+
+    #[test]
+    fn test_legacy_session_keeps_container() {
+        let dir = tempdir().unwrap();
+
+        let mut session = Session::new();
+        session.add_tab("https://example.com/".to_string(), None, Some("work".to_string()));
+        session.add_tab("https://example.org/".to_string(), None, None);
+        session.save(dir.path()).unwrap();
+
+        let restored = Session::load(dir.path()).unwrap();
+        assert_eq!(restored.tabs.len(), 2);
+        assert_eq!(restored.tabs[0].container.as_deref(), Some("work"));
+        assert_eq!(restored.tabs[1].container, None);
+    }
+
+    #[test]
+    fn test_legacy_session_migrates_container() {
+        let dir = tempdir().unwrap();
+
+        let mut session = Session::new();
+        session.add_tab("https://example.com/".to_string(), None, Some("banking".to_string()));
+        session.save(dir.path()).unwrap();
+
+        let multi = MultiWindowSession::load(dir.path()).unwrap();
+        assert_eq!(multi.windows.len(), 1);
+        assert_eq!(multi.windows[0].tabs[0].container.as_deref(), Some("banking"));
+    }
+
+    fn export_fixture() -> MultiWindowSession {
+        let tab = |url: &str, title: Option<&str>| SavedTab {
+            url: url.to_string(),
+            title: title.map(str::to_string),
+            container: Some("work".to_string()),
+            scroll_y: None,
+            group: None,
+            pinned: false,
+        };
+        let mut session = MultiWindowSession::new();
+        for i in 0..3 {
+            session.add_window(SavedWindow {
+                tabs: vec![
+                    tab(&format!("https://example.com/{}", i), Some("Hello, \"world\"")),
+                    tab("https://example.org/", None),
+                ],
+                active_tab_index: 1,
+                ..Default::default()
+            });
+        }
+        session
+    }
+
+    #[test]
+    fn test_export_keeps_windows() {
+        let session = export_fixture();
+
+        let from_json = MultiWindowSession::from_export(&session.to_json().unwrap()).unwrap();
+        assert_eq!(from_json.windows.len(), 3);
+        assert_eq!(from_json.windows[2].active_tab_index, 1);
+
+        let from_csv = MultiWindowSession::from_export(&session.to_csv()).unwrap();
+        assert_eq!(from_csv.windows.len(), 3);
+        assert_eq!(from_csv.windows[1].tabs[0].url, "https://example.com/1");
+        assert_eq!(from_csv.windows[1].tabs[0].title.as_deref(), Some("Hello, \"world\""));
+        assert_eq!(from_csv.windows[1].tabs[1].title, None);
+        assert_eq!(from_csv.windows[1].tabs[1].container.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn test_import_skips_invalid_urls() {
+        let csv = "window,url,title,container\n1,not a url,,\n1,https://example.com/,,\n2,also bad,,\n";
+        let mut session = MultiWindowSession::from_export(csv).unwrap();
+        assert_eq!(session.retain_valid_tabs(), 2);
+        assert_eq!(session.windows.len(), 1);
+        assert_eq!(session.windows[0].tabs[0].url, "https://example.com/");
+
+        assert!(MultiWindowSession::from_export("garbage").is_err());
+    }
+
+    #[test]
+    fn test_set_aside_session_survives_new_session() {
+        let dir = tempdir().unwrap();
+        let mut old = MultiWindowSession::new();
+        old.add_window(SavedWindow {
+            tabs: vec![SavedTab {
+                url: "https://example.com/old".to_string(),
+                title: None,
+                container: None,
+                scroll_y: None,
+                group: None,
+                pinned: false,
+            }],
+            ..Default::default()
+        });
+        old.save(dir.path()).unwrap();
+
+        MultiWindowSession::set_aside(dir.path()).unwrap();
+        assert!(!MultiWindowSession::load(dir.path()).unwrap().has_windows());
+        export_fixture().save(dir.path()).unwrap();
+
+        assert!(MultiWindowSession::has_previous(dir.path()));
+        let previous = MultiWindowSession::take_previous(dir.path()).unwrap();
+        assert_eq!(previous.windows[0].tabs[0].url, "https://example.com/old");
+        assert!(!MultiWindowSession::has_previous(dir.path()));
+        assert_eq!(MultiWindowSession::load(dir.path()).unwrap().windows.len(), 3);
+    }
+
+    #[test]
+    fn test_named_sessions() {
+        let dir = tempdir().unwrap();
+        assert!(NamedSession::list(dir.path()).is_empty());
+
+        for name in ["work/research", "Articles"] {
+            NamedSession { name: name.to_string(), session: export_fixture() }
+                .save(dir.path())
+                .unwrap();
+        }
+        assert_eq!(NamedSession::list(dir.path()), vec!["Articles", "work/research"]);
+        assert!(NamedSession::exists(dir.path(), "work/research"));
+
+        let loaded = NamedSession::load(dir.path(), "work/research").unwrap();
+        assert_eq!(loaded.name, "work/research");
+        assert_eq!(loaded.tab_count(), 6);
+
+        NamedSession::delete(dir.path(), "Articles").unwrap();
+        assert_eq!(NamedSession::list(dir.path()), vec!["work/research"]);
+    }
+
+    #[test]
+    fn test_crash_needs_running_marker() {
+        let dir = tempdir().unwrap();
+        // First run, and a clean exit from a version without the running marker
+        assert!(!begin_run(dir.path()));
+        fs::remove_file(dir.path().join(RUNNING_MARKER)).unwrap();
+        mark_clean_exit(dir.path()).unwrap();
+        assert!(!begin_run(dir.path()));
+
+        // Clean exit, then a run that never got to shut down
+        mark_clean_exit(dir.path()).unwrap();
+        assert!(!begin_run(dir.path()));
+        assert!(begin_run(dir.path()));
+        assert!(begin_run(dir.path()));
+    }
+
+    #[test]
+    fn test_legacy_session_without_container_field() {
+        let dir = tempdir().unwrap();
+        let json = r#"{"tabs":[{"url":"https://example.com/","title":null}],"active_tab_index":0}"#;
+        fs::write(dir.path().join(SESSION_FILE), json).unwrap();
+
+        let restored = Session::load(dir.path()).unwrap();
+        assert_eq!(restored.tabs[0].container, None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Per-site settings, keyed by origin.
+//!
+//! One store for everything a site can have set just for it. Zoom lives here;
+//! other per-site choices get a field on [`SiteSettings`] as they move over.
+//! Each profile (container) keeps its own store in its directory.
+//! [`SiteStore`] does the saving, here and for the other per-site overrides.
+
+mod store;
+
+pub use store::SiteStore;
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+const SITE_SETTINGS_FILE: &str = "site-settings.json";
+
+/// Settings chosen for one origin. Unset fields follow the global defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteSettings {
+    /// Page zoom level, 1.0 being 100%
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<f64>,
+}
+
+impl SiteSettings {
+    /// Whether nothing is set, so the site follows the defaults
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A profile's settings by origin
+#[derive(Debug, Default)]
+pub struct SiteSettingsStore(SiteStore<SiteSettings>);
+
+impl SiteSettingsStore {
+    /// Load a profile's site settings from its directory (empty if none saved yet)
+    pub fn load(profile_dir: &Path) -> Self {
+        Self(SiteStore::load(profile_dir, SITE_SETTINGS_FILE))
+    }
+
+    /// Get the settings for an origin, the defaults if none are set
+    pub fn get(&self, origin: &str) -> SiteSettings {
+        self.0.get(origin).unwrap_or_default()
+    }
+
+    /// Replace the settings for an origin and persist the change.
+    /// Empty settings remove the origin's entry.
+    pub fn set(&mut self, origin: &str, settings: SiteSettings) {
+        self.0.set(origin, Some(settings).filter(|s| !s.is_empty()));
+    }
+}
+
+/// The origin site settings are stored under, None for URLs without one
+/// (such as data: and about: pages)
+pub fn origin_key(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_set_persists_and_clears() {
+        let dir = tempdir().unwrap();
+        let mut store = SiteSettingsStore::load(dir.path());
+        store.set("https://example.com", SiteSettings { zoom: Some(1.25) });
+        store.set("https://other.org", SiteSettings { zoom: Some(0.8) });
+        store.set("https://other.org", SiteSettings::default());
+
+        let reloaded = SiteSettingsStore::load(dir.path());
+        assert_eq!(reloaded.get("https://example.com").zoom, Some(1.25));
+        assert_eq!(reloaded.get("https://other.org"), SiteSettings::default());
+        assert_eq!(reloaded.get("https://unknown.net").zoom, None);
+    }
+
+    #[test]
+    fn test_origin_key() {
+        let key = |s: &str| origin_key(&Url::parse(s).unwrap());
+        assert_eq!(key("https://example.com/a/b?q=1").as_deref(), Some("https://example.com"));
+        assert_eq!(key("http://localhost:8080/").as_deref(), Some("http://localhost:8080"));
+        assert_eq!(key("https://example.com:443/").as_deref(), Some("https://example.com"));
+        assert_eq!(key("data:text/plain,hi"), None);
+        assert_eq!(key("about:blank"), None);
+    }
+}
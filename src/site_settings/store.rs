@@ -0,0 +1,93 @@
+//! Persisted map of site -> value, the storage behind every per-site setting.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Values chosen for sites (hosts or origins), saved as one JSON file
+#[derive(Debug)]
+pub struct SiteStore<V> {
+    path: Option<PathBuf>,
+    sites: HashMap<String, V>,
+}
+
+impl<V> Default for SiteStore<V> {
+    fn default() -> Self {
+        Self {
+            path: None,
+            sites: HashMap::new(),
+        }
+    }
+}
+
+impl<V: Clone + PartialEq + Serialize + DeserializeOwned> SiteStore<V> {
+    /// Load `file_name` from a directory (empty if none saved yet)
+    pub fn load(dir: &Path, file_name: &str) -> Self {
+        let path = dir.join(file_name);
+        let sites = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            path: Some(path),
+            sites,
+        }
+    }
+
+    /// The value chosen for a site, if any
+    pub fn get(&self, site: &str) -> Option<V> {
+        self.sites.get(site).cloned()
+    }
+
+    /// Set or clear a site's value, persisting it if that changed anything
+    pub fn set(&mut self, site: &str, value: Option<V>) {
+        let previous = match value {
+            Some(value) => self.sites.insert(site.to_string(), value),
+            None => self.sites.remove(site),
+        };
+        if previous.as_ref() == self.sites.get(site) {
+            return;
+        }
+
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save {:?}: {}", self.path, e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.sites)?;
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_set_persists_and_clears() {
+        let dir = tempdir().unwrap();
+        let mut store: SiteStore<bool> = SiteStore::load(dir.path(), "sites.json");
+        store.set("example.com", Some(false));
+        store.set("other.org", Some(true));
+        store.set("other.org", None);
+
+        let reloaded: SiteStore<bool> = SiteStore::load(dir.path(), "sites.json");
+        assert_eq!(reloaded.get("example.com"), Some(false));
+        assert_eq!(reloaded.get("other.org"), None);
+
+        // Clearing a site that has nothing set doesn't write the file
+        let dir = tempdir().unwrap();
+        let mut store: SiteStore<bool> = SiteStore::load(dir.path(), "sites.json");
+        store.set("example.com", None);
+        assert!(!dir.path().join("sites.json").exists());
+    }
+}
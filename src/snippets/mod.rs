@@ -0,0 +1,184 @@
+//! Text snippets expanded in form fields
+//!
+//! Typing a snippet's trigger (e.g. `;addr`) in a text field, text area or
+//! editable element replaces it with the snippet's text. Each container has
+//! its own snippets, expanded only in its tabs. The expansion script runs in
+//! its own script world, so pages can't read the snippet table.
+
+mod store;
+
+pub use store::{is_valid_trigger, split_shared, Snippet};
+
+use crate::content_filter::ContentManagers;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use store::SnippetStore;
+use webkit6::{UserContentInjectedFrames, UserScript, UserScriptInjectionTime};
+
+/// Script world the expansion script runs in, apart from page scripts
+const SCRIPT_WORLD: &str = "qayeq-snippets";
+
+/// Replaced with the snippet table, a JSON object of trigger -> text
+const SNIPPETS_PLACEHOLDER: &str = "__QAYEQ_SNIPPETS__";
+
+/// Expands triggers as they're typed. Each input event checks the text right
+/// before the caret, so a trigger typed over several events (one per key)
+/// expands once its last character arrives. A trigger only expands at the
+/// start of the text or after whitespace, so `a;addr` is left alone.
+const EXPANSION_SCRIPT: &str = r#"
+(function() {
+    'use strict';
+    const snippets = __QAYEQ_SNIPPETS__;
+    const triggers = Object.keys(snippets).sort((a, b) => b.length - a.length);
+    if (triggers.length === 0) return;
+
+    const TEXT_INPUT_TYPES = ['text', 'search', 'email', 'url', 'tel', ''];
+    let expanding = false;
+
+    function matchTrigger(before) {
+        for (const trigger of triggers) {
+            if (!before.endsWith(trigger)) continue;
+            const boundary = before.length - trigger.length;
+            if (boundary === 0 || /\s/.test(before[boundary - 1])) return trigger;
+        }
+        return null;
+    }
+
+    function expandInField(field) {
+        if (field instanceof HTMLInputElement) {
+            // Never expand in password fields, or in fields without a caret
+            const type = (field.getAttribute('type') || '').toLowerCase();
+            if (!TEXT_INPUT_TYPES.includes(type)) return;
+        }
+        if (field.readOnly || field.selectionStart !== field.selectionEnd) return;
+
+        const caret = field.selectionStart;
+        if (caret === null) return;
+        const trigger = matchTrigger(field.value.slice(0, caret));
+        if (!trigger) return;
+
+        field.setRangeText(snippets[trigger], caret - trigger.length, caret, 'end');
+        // Let the page's own listeners see the new value
+        field.dispatchEvent(new InputEvent('input', { bubbles: true, inputType: 'insertReplacementText' }));
+    }
+
+    function expandInEditable() {
+        const selection = window.getSelection();
+        if (!selection || selection.rangeCount === 0 || !selection.isCollapsed) return;
+        const node = selection.anchorNode;
+        if (!node || node.nodeType !== Node.TEXT_NODE) return;
+
+        const caret = selection.anchorOffset;
+        const trigger = matchTrigger(node.data.slice(0, caret));
+        if (!trigger) return;
+
+        const range = document.createRange();
+        range.setStart(node, caret - trigger.length);
+        range.setEnd(node, caret);
+        selection.removeAllRanges();
+        selection.addRange(range);
+        // insertText keeps undo working and fires the page's input events
+        document.execCommand('insertText', false, snippets[trigger]);
+    }
+
+    document.addEventListener('input', function(event) {
+        if (expanding || event.isComposing) return;
+        const target = event.target;
+        expanding = true;
+        try {
+            if (target instanceof HTMLInputElement || target instanceof HTMLTextAreaElement) {
+                expandInField(target);
+            } else if (target instanceof HTMLElement && target.isContentEditable) {
+                expandInEditable();
+            }
+        } finally {
+            expanding = false;
+        }
+    }, true);
+})();
+"#;
+
+/// Manages a container's snippet table and the script expanding it
+pub struct SnippetManager {
+    container: String,
+    store: RefCell<SnippetStore>,
+    content_managers: ContentManagers,
+    injected: RefCell<Option<UserScript>>,
+}
+
+impl SnippetManager {
+    /// Create a manager injecting into the container's webviews among `managers`,
+    /// with its snippets saved in the container's `profile_dir`
+    pub fn new(container: &str, profile_dir: &Path, managers: &ContentManagers) -> Rc<Self> {
+        Rc::new(Self {
+            container: container.to_string(),
+            store: RefCell::new(SnippetStore::load(profile_dir)),
+            content_managers: managers.clone(),
+            injected: RefCell::new(None),
+        })
+    }
+
+    /// Inject the expansion script with the saved snippets
+    pub fn initialize(&self) {
+        self.reinject();
+        log::info!(
+            "Loaded {} text snippets for container '{}'",
+            self.store.borrow().snippets().len(),
+            self.container
+        );
+    }
+
+    /// All snippets, in the order they were added
+    pub fn snippets(&self) -> Vec<Snippet> {
+        self.store.borrow().snippets().to_vec()
+    }
+
+    /// Add or update a snippet. Returns false for an invalid trigger. Open
+    /// pages keep the snippets they were loaded with.
+    pub fn set(&self, trigger: &str, text: &str) -> bool {
+        let saved = self.store.borrow_mut().set(trigger, text);
+        if saved {
+            self.reinject();
+        }
+        saved
+    }
+
+    /// Remove a snippet
+    pub fn remove(&self, trigger: &str) {
+        self.store.borrow_mut().remove(trigger);
+        self.reinject();
+    }
+
+    /// Take the expansion script out of the container's webviews, for a
+    /// container that's being deleted
+    pub fn unload(&self) {
+        if let Some(script) = self.injected.take() {
+            self.content_managers.remove_script(&script);
+        }
+    }
+
+    /// Swap the injected script for one with the current snippet table
+    fn reinject(&self) {
+        if let Some(script) = self.injected.take() {
+            self.content_managers.remove_script(&script);
+        }
+
+        let store = self.store.borrow();
+        if store.snippets().is_empty() {
+            return;
+        }
+
+        let source = EXPANSION_SCRIPT.replace(SNIPPETS_PLACEHOLDER, &store.to_json());
+        let script = UserScript::for_world(
+            &source,
+            UserContentInjectedFrames::AllFrames,
+            UserScriptInjectionTime::End,
+            SCRIPT_WORLD,
+            &[],
+            &[],
+        );
+        self.content_managers.add_container_script(&self.container, &script);
+        self.injected.replace(Some(script));
+    }
+}
@@ -0,0 +1,180 @@
+//! Persisted table of text snippets.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const SNIPPETS_FILE: &str = "snippets.json";
+
+/// A text expansion: typing `trigger` in a form field replaces it with `text`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub trigger: String,
+    pub text: String,
+}
+
+/// Snippets saved in a container's directory, by trigger
+#[derive(Debug, Default)]
+pub struct SnippetStore {
+    path: Option<PathBuf>,
+    snippets: Vec<Snippet>,
+}
+
+impl SnippetStore {
+    /// Load snippets from a container's directory (empty if none saved yet)
+    pub fn load(profile_dir: &Path) -> Self {
+        let path = profile_dir.join(SNIPPETS_FILE);
+        let snippets = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Vec<Snippet>>(&contents).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|snippet| is_valid_trigger(&snippet.trigger))
+            .collect();
+
+        Self {
+            path: Some(path),
+            snippets,
+        }
+    }
+
+    /// All snippets, in the order they were added
+    pub fn snippets(&self) -> &[Snippet] {
+        &self.snippets
+    }
+
+    /// Add a snippet, or replace the text of the one with the same trigger,
+    /// and persist the change. Returns false for an invalid trigger.
+    pub fn set(&mut self, trigger: &str, text: &str) -> bool {
+        let trigger = trigger.trim();
+        if !is_valid_trigger(trigger) {
+            return false;
+        }
+        match self.snippets.iter_mut().find(|snippet| snippet.trigger == trigger) {
+            Some(snippet) => snippet.text = text.to_string(),
+            None => self.snippets.push(Snippet {
+                trigger: trigger.to_string(),
+                text: text.to_string(),
+            }),
+        }
+        self.persist();
+        true
+    }
+
+    /// Remove the snippet with a trigger and persist the change
+    pub fn remove(&mut self, trigger: &str) {
+        let count = self.snippets.len();
+        self.snippets.retain(|snippet| snippet.trigger != trigger);
+        if self.snippets.len() != count {
+            self.persist();
+        }
+    }
+
+    /// The snippets as a JSON object of trigger -> text, for the page script
+    pub fn to_json(&self) -> String {
+        let table: BTreeMap<&str, &str> = self
+            .snippets
+            .iter()
+            .map(|snippet| (snippet.trigger.as_str(), snippet.text.as_str()))
+            .collect();
+        serde_json::to_string(&table).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save snippets: {}", e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.snippets)?;
+        fs::write(path, contents)
+    }
+}
+
+/// Give each container a copy of the snippets saved in the data directory
+/// before snippets were kept per container, then remove the shared file.
+/// Containers that already have snippets of their own keep them.
+pub fn split_shared(data_dir: &Path, profile_dirs: &[PathBuf]) -> io::Result<()> {
+    let shared = data_dir.join(SNIPPETS_FILE);
+    if !shared.exists() {
+        return Ok(());
+    }
+    for dir in profile_dirs {
+        let path = dir.join(SNIPPETS_FILE);
+        if !path.exists() {
+            fs::create_dir_all(dir)?;
+            fs::copy(&shared, &path)?;
+        }
+    }
+    fs::remove_file(&shared)?;
+    log::info!("Copied shared text snippets into {} containers", profile_dirs.len());
+    Ok(())
+}
+
+/// Triggers are a single word, so they can be told apart from the text around them
+pub fn is_valid_trigger(trigger: &str) -> bool {
+    !trigger.is_empty() && !trigger.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_set_replaces_and_persists() {
+        let dir = tempdir().unwrap();
+        let mut store = SnippetStore::load(dir.path());
+        assert!(store.set(";addr", "1 Main St"));
+        assert!(store.set(" ;sig ", "Regards"));
+        assert!(store.set(";addr", "2 High St"));
+        assert!(!store.set("two words", "nope"));
+        assert!(!store.set("", "nope"));
+
+        let reloaded = SnippetStore::load(dir.path());
+        let saved: Vec<(&str, &str)> = reloaded
+            .snippets()
+            .iter()
+            .map(|snippet| (snippet.trigger.as_str(), snippet.text.as_str()))
+            .collect();
+        assert_eq!(saved, vec![(";addr", "2 High St"), (";sig", "Regards")]);
+        assert_eq!(reloaded.to_json(), r#"{";addr":"2 High St",";sig":"Regards"}"#);
+    }
+
+    #[test]
+    fn test_split_shared_snippets() {
+        let dir = tempdir().unwrap();
+        let (personal, work) = (dir.path().join("personal"), dir.path().join("work"));
+        SnippetStore::load(dir.path()).set(";addr", "1 Main St");
+        fs::create_dir(&work).unwrap();
+        SnippetStore::load(&work).set(";sig", "Regards");
+
+        split_shared(dir.path(), &[personal.clone(), work.clone()]).unwrap();
+        assert_eq!(SnippetStore::load(&personal).snippets()[0].trigger, ";addr");
+        // A container's own snippets aren't replaced
+        assert_eq!(SnippetStore::load(&work).snippets()[0].trigger, ";sig");
+        assert!(SnippetStore::load(dir.path()).snippets().is_empty());
+        // Nothing left to split the next time
+        split_shared(dir.path(), &[personal, work]).unwrap();
+    }
+
+    #[test]
+    fn test_remove() {
+        let dir = tempdir().unwrap();
+        let mut store = SnippetStore::load(dir.path());
+        store.set(";a", "A");
+        store.set(";b", "B");
+        store.remove(";a");
+        store.remove(";missing");
+
+        let reloaded = SnippetStore::load(dir.path());
+        assert_eq!(reloaded.snippets().len(), 1);
+        assert_eq!(reloaded.snippets()[0].trigger, ";b");
+    }
+}
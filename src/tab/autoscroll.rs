@@ -0,0 +1,143 @@
+//! Middle-click autoscroll.
+//!
+//! WebKitGTK doesn't implement autoscroll, so it's done from the outside: a
+//! middle press on the page (but not on a link, which opens it in a new tab,
+//! or a text field, which pastes) sets an origin, and a timer scrolls the page
+//! by script at a speed proportional to the pointer's distance from it. The
+//! controllers run in the capture phase so the page never sees the presses
+//! that start and end autoscrolling.
+
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, glib};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+use webkit6::prelude::*;
+
+/// Time between scroll steps (about 60 per second)
+const STEP_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Distance from the origin, in pixels, within which the page doesn't move
+const DEAD_ZONE: f64 = 8.0;
+
+/// Pixels scrolled per step for each pixel beyond the dead zone
+const SPEED_PER_PIXEL: f64 = 0.15;
+
+#[derive(Default)]
+struct AutoscrollState {
+    /// Where autoscroll started, while it's active
+    origin: Cell<Option<(f64, f64)>>,
+    pointer: Cell<(f64, f64)>,
+    /// Whether the pointer is over a link or editable content
+    over_target: Cell<bool>,
+    timer: RefCell<Option<glib::SourceId>>,
+}
+
+impl AutoscrollState {
+    fn stop(&self, webview: &webkit6::WebView) {
+        self.origin.set(None);
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            timer.remove();
+        }
+        webview.set_cursor(None);
+    }
+}
+
+/// Let a middle click on the page start autoscrolling, while the preference is on
+pub fn setup_autoscroll(webview: &webkit6::WebView, settings: &gio::Settings) {
+    let state = Rc::new(AutoscrollState::default());
+
+    let target_state = Rc::clone(&state);
+    webview.connect_mouse_target_changed(move |_, hit, _| {
+        target_state
+            .over_target
+            .set(hit.context_is_link() || hit.context_is_editable());
+    });
+
+    let motion = gtk4::EventControllerMotion::new();
+    let motion_state = Rc::clone(&state);
+    motion.connect_motion(move |_, x, y| motion_state.pointer.set((x, y)));
+    webview.add_controller(motion);
+
+    // Any button: a press while active only ends autoscrolling
+    let click = gtk4::GestureClick::new();
+    click.set_button(0);
+    click.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let settings = settings.clone();
+    click.connect_pressed(move |gesture, _, x, y| {
+        let Some(webview) = gesture.widget().and_downcast::<webkit6::WebView>() else {
+            return;
+        };
+        if state.origin.get().is_some() {
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+            state.stop(&webview);
+            return;
+        }
+        let starts = gesture.current_button() == gdk::BUTTON_MIDDLE
+            && !state.over_target.get()
+            && settings.boolean("middle-click-autoscroll");
+        if !starts {
+            // Let the page have the click
+            gesture.set_state(gtk4::EventSequenceState::Denied);
+            return;
+        }
+
+        gesture.set_state(gtk4::EventSequenceState::Claimed);
+        state.origin.set(Some((x, y)));
+        state.pointer.set((x, y));
+        webview.set_cursor_from_name(Some("all-scroll"));
+
+        let timer_state = Rc::clone(&state);
+        let webview_weak = webview.downgrade();
+        let timer = glib::timeout_add_local(STEP_INTERVAL, move || {
+            let (Some(webview), Some(origin)) = (webview_weak.upgrade(), timer_state.origin.get()) else {
+                // The timer is ending either way; don't let stop() remove it again
+                timer_state.timer.borrow_mut().take();
+                return glib::ControlFlow::Break;
+            };
+            if !webview.is_mapped() {
+                timer_state.timer.borrow_mut().take();
+                timer_state.stop(&webview);
+                return glib::ControlFlow::Break;
+            }
+
+            let (x, y) = timer_state.pointer.get();
+            let (dx, dy) = (step(x - origin.0), step(y - origin.1));
+            if dx != 0.0 || dy != 0.0 {
+                webview.evaluate_javascript(
+                    &format!("window.scrollBy({}, {})", dx, dy),
+                    None,
+                    None,
+                    None::<&gio::Cancellable>,
+                    |_| {},
+                );
+            }
+            glib::ControlFlow::Continue
+        });
+        state.timer.replace(Some(timer));
+    });
+    webview.add_controller(click);
+}
+
+/// Scroll distance for one step, given the pointer's offset from the origin on one axis
+fn step(offset: f64) -> f64 {
+    let beyond = offset.abs() - DEAD_ZONE;
+    if beyond <= 0.0 {
+        return 0.0;
+    }
+    (beyond * SPEED_PER_PIXEL).round().max(1.0).copysign(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_speed_grows_with_distance() {
+        assert_eq!(step(0.0), 0.0);
+        assert_eq!(step(-DEAD_ZONE), 0.0);
+        assert_eq!(step(DEAD_ZONE + 1.0), 1.0);
+        assert_eq!(step(-(DEAD_ZONE + 1.0)), -1.0);
+        assert!(step(DEAD_ZONE + 200.0) > step(DEAD_ZONE + 50.0));
+    }
+}
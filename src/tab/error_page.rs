@@ -0,0 +1,179 @@
+//! Error pages shown in place of a page that failed to load.
+
+use crate::html;
+use gtk4::{gio, glib};
+
+/// Placeholders in the bundled page
+const TITLE_PLACEHOLDER: &str = "{{TITLE}}";
+const URL_PLACEHOLDER: &str = "{{URL}}";
+const REASON_PLACEHOLDER: &str = "{{REASON}}";
+const ACTIONS_PLACEHOLDER: &str = "{{ACTIONS}}";
+
+/// Fragment of the "Go Back" link, which the tab manager follows itself so it
+/// works on sites with JavaScript turned off. A `qayeq://` link wouldn't do:
+/// the page has the failing site's origin, which can't link to local schemes.
+const GO_BACK_FRAGMENT: &str = "qayeq-go-back";
+
+/// Why a page failed to load
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadFailure {
+    /// The host name couldn't be resolved
+    NotFound,
+    /// The server refused the connection
+    Refused,
+    TimedOut,
+    /// No network connection
+    Offline,
+    /// The site's certificate isn't trusted, with a human-readable reason
+    Tls(String),
+    /// Anything else, with WebKit's message
+    Other(String),
+}
+
+impl LoadFailure {
+    /// Classify a load error
+    pub fn from_error(error: &glib::Error) -> Self {
+        if error.kind::<gio::ResolverError>().is_some() {
+            return LoadFailure::NotFound;
+        }
+        match error.kind::<gio::IOErrorEnum>() {
+            Some(gio::IOErrorEnum::ConnectionRefused) => LoadFailure::Refused,
+            Some(gio::IOErrorEnum::TimedOut) => LoadFailure::TimedOut,
+            Some(gio::IOErrorEnum::NetworkUnreachable | gio::IOErrorEnum::HostUnreachable) => {
+                LoadFailure::Offline
+            }
+            _ => LoadFailure::Other(error.message().to_string()),
+        }
+    }
+
+    /// Describe the problems with a certificate
+    pub fn from_tls_errors(errors: gio::TlsCertificateFlags) -> Self {
+        let reason = if errors.contains(gio::TlsCertificateFlags::BAD_IDENTITY) {
+            "The certificate belongs to a different site."
+        } else if errors.contains(gio::TlsCertificateFlags::EXPIRED) {
+            "The certificate has expired."
+        } else if errors.contains(gio::TlsCertificateFlags::NOT_ACTIVATED) {
+            "The certificate isn't valid yet. Check that your clock is set correctly."
+        } else if errors.contains(gio::TlsCertificateFlags::REVOKED) {
+            "The certificate has been revoked."
+        } else if errors.contains(gio::TlsCertificateFlags::UNKNOWN_CA) {
+            "The certificate isn't issued by a trusted authority."
+        } else if errors.contains(gio::TlsCertificateFlags::INSECURE) {
+            "The certificate uses insecure encryption."
+        } else {
+            "The certificate couldn't be verified."
+        };
+        LoadFailure::Tls(reason.to_string())
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            LoadFailure::NotFound => "Server not found",
+            LoadFailure::Refused => "Connection refused",
+            LoadFailure::TimedOut => "Connection timed out",
+            LoadFailure::Offline => "You're offline",
+            LoadFailure::Tls(_) => "This connection isn't secure",
+            LoadFailure::Other(_) => "Page couldn't be loaded",
+        }
+    }
+
+    fn reason(&self) -> String {
+        match self {
+            LoadFailure::NotFound => {
+                "The site's address couldn't be found. Check it for typos.".to_string()
+            }
+            LoadFailure::Refused => {
+                "The server isn't accepting connections. It may be down or restarting.".to_string()
+            }
+            LoadFailure::TimedOut => "The server took too long to respond.".to_string(),
+            LoadFailure::Offline => "Check your network connection and try again.".to_string(),
+            LoadFailure::Tls(reason) => format!(
+                "{} Someone could be impersonating the site to steal passwords or other information.",
+                reason
+            ),
+            LoadFailure::Other(message) => message.clone(),
+        }
+    }
+}
+
+/// Whether a navigation is the error page's "Go Back" link
+pub fn is_go_back_link(uri: &str) -> bool {
+    url::Url::parse(uri).is_ok_and(|url| url.fragment() == Some(GO_BACK_FRAGMENT))
+}
+
+/// HTML for the error page of a failed load, from the bundled template
+pub fn error_page(failing_uri: &str, failure: &LoadFailure) -> String {
+    let template = match gio::resources_lookup_data(
+        "/dev/myyc/qayeq/error.html",
+        gio::ResourceLookupFlags::NONE,
+    ) {
+        Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+        Err(e) => {
+            log::error!("Failed to load error page: {}", e);
+            format!("<h1>{}</h1><p>{}</p>", TITLE_PLACEHOLDER, REASON_PLACEHOLDER)
+        }
+    };
+    render(&template, failing_uri, failure)
+}
+
+fn render(template: &str, failing_uri: &str, failure: &LoadFailure) -> String {
+    let url = html::escape(failing_uri);
+    // "Retry" and "Proceed anyway" load the failing URL again; the manager tells
+    // "Proceed anyway" apart because it's clicked while the error page is showing
+    let actions = match failure {
+        LoadFailure::Tls(_) => format!(
+            r##"<a class="button" href="#{}">Go Back</a>
+<a class="proceed" href="{}">Proceed anyway (unsafe)</a>"##,
+            GO_BACK_FRAGMENT, url
+        ),
+        _ => format!(r#"<a class="button" href="{}">Retry</a>"#, url),
+    };
+
+    template
+        .replace(TITLE_PLACEHOLDER, failure.title())
+        .replace(URL_PLACEHOLDER, &url)
+        .replace(REASON_PLACEHOLDER, &html::escape(&failure.reason()))
+        .replace(ACTIONS_PLACEHOLDER, &actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "<title>{{TITLE}}</title>{{URL}}|{{REASON}}|{{ACTIONS}}";
+
+    #[test]
+    fn test_classify_errors() {
+        let refused = glib::Error::new(gio::IOErrorEnum::ConnectionRefused, "Connection refused");
+        assert_eq!(LoadFailure::from_error(&refused), LoadFailure::Refused);
+
+        let dns = glib::Error::new(gio::ResolverError::NotFound, "Name or service not known");
+        assert_eq!(LoadFailure::from_error(&dns), LoadFailure::NotFound);
+
+        let tls = LoadFailure::from_tls_errors(gio::TlsCertificateFlags::UNKNOWN_CA | gio::TlsCertificateFlags::EXPIRED);
+        assert_eq!(tls, LoadFailure::Tls("The certificate has expired.".to_string()));
+    }
+
+    #[test]
+    fn test_render_escapes_url() {
+        let html = render(TEMPLATE, "https://example.com/?a=<b>", &LoadFailure::Refused);
+        assert!(html.starts_with("<title>Connection refused</title>https://example.com/?a=&lt;b&gt;|"));
+        assert!(html.contains(r#"href="https://example.com/?a=&lt;b&gt;">Retry</a>"#));
+
+        let tls = render(TEMPLATE, "https://example.com/", &LoadFailure::Tls("Expired.".to_string()));
+        assert!(tls.contains("Proceed anyway"));
+        assert!(!tls.contains("Retry"));
+    }
+
+    #[test]
+    fn test_go_back_link_works_without_javascript() {
+        let tls = render(TEMPLATE, "https://example.com/", &LoadFailure::Tls("Expired.".to_string()));
+        assert!(!tls.contains("javascript:"));
+        assert!(tls.contains(r##"href="#qayeq-go-back">Go Back</a>"##));
+
+        // The link resolves against the failing page's URL
+        assert!(is_go_back_link("https://example.com/path#qayeq-go-back"));
+        assert!(!is_go_back_link("https://example.com/path#section"));
+        assert!(!is_go_back_link("https://example.com/"));
+    }
+}
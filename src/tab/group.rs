@@ -0,0 +1,143 @@
+//! Named, collapsible groups of tabs in the sidebar.
+//!
+//! A group is a header row in the tab list followed by its member tabs.
+//! Collapsing a group only hides the member rows; their webviews stay alive.
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use std::cell::{Cell, RefCell};
+
+/// A tab group's header row and state
+pub struct TabGroup {
+    name: RefCell<String>,
+    collapsed: Cell<bool>,
+    header: gtk4::FlowBoxChild,
+    label: gtk4::Label,
+    arrow: gtk4::Image,
+}
+
+impl TabGroup {
+    pub fn new(name: &str) -> Self {
+        let arrow = gtk4::Image::from_icon_name("pan-down-symbolic");
+        let label = gtk4::Label::new(Some(name));
+        label.set_hexpand(true);
+        label.set_xalign(0.0);
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        label.add_css_class("heading");
+
+        let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        content.append(&arrow);
+        content.append(&label);
+
+        let header = gtk4::FlowBoxChild::new();
+        header.add_css_class("tab-group-header");
+        header.set_focusable(false);
+        header.set_child(Some(&content));
+
+        let group = Self {
+            name: RefCell::new(name.to_string()),
+            collapsed: Cell::new(false),
+            header,
+            label,
+            arrow,
+        };
+        group.update_tooltip();
+        group
+    }
+
+    pub fn header(&self) -> &gtk4::FlowBoxChild {
+        &self.header
+    }
+
+    pub fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    pub fn set_name(&self, name: &str) {
+        self.name.replace(name.to_string());
+        self.label.set_text(name);
+        self.update_tooltip();
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed.get()
+    }
+
+    /// Update the header for the group's collapsed state; the caller hides the members
+    pub fn set_collapsed(&self, collapsed: bool) {
+        self.collapsed.set(collapsed);
+        self.arrow.set_icon_name(Some(if collapsed {
+            "pan-end-symbolic"
+        } else {
+            "pan-down-symbolic"
+        }));
+        self.update_tooltip();
+    }
+
+    fn update_tooltip(&self) {
+        let action = if self.collapsed.get() { "Expand" } else { "Collapse" };
+        self.header
+            .set_tooltip_text(Some(&format!("{} “{}”", action, self.name.borrow())));
+    }
+}
+
+/// A name for a new group that isn't taken yet: "Group 1", "Group 2", ...
+pub fn next_group_name<'a>(existing: impl IntoIterator<Item = &'a str>) -> String {
+    let existing: Vec<&str> = existing.into_iter().collect();
+    (1..)
+        .map(|n| format!("Group {}", n))
+        .find(|name| !existing.contains(&name.as_str()))
+        .unwrap_or_default()
+}
+
+/// Ask for a group's new name, calling `on_rename` with it unless cancelled
+pub fn prompt_rename(
+    parent: &impl IsA<gtk4::Widget>,
+    current: &str,
+    on_rename: impl Fn(String) + 'static,
+) {
+    let dialog = libadwaita::AlertDialog::new(Some("Rename Group"), None);
+    let entry = gtk4::Entry::new();
+    entry.set_text(current);
+    entry.set_placeholder_text(Some("Group name"));
+    entry.set_activates_default(true);
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("rename", "Rename");
+    dialog.set_response_appearance("rename", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("rename"));
+    dialog.set_close_response("cancel");
+
+    let dialog_weak = dialog.downgrade();
+    entry.connect_changed(move |entry| {
+        if let Some(dialog) = dialog_weak.upgrade() {
+            dialog.set_response_enabled("rename", !entry.text().trim().is_empty());
+        }
+    });
+
+    dialog.connect_response(None, glib::clone!(
+        #[weak]
+        entry,
+        move |_, response| {
+            let name = entry.text().trim().to_string();
+            if response == "rename" && !name.is_empty() {
+                on_rename(name);
+            }
+        }
+    ));
+    dialog.present(Some(parent));
+    entry.grab_focus();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_group_name_skips_taken_names() {
+        assert_eq!(next_group_name([]), "Group 1");
+        assert_eq!(next_group_name(["Group 1", "Reading"]), "Group 2");
+        assert_eq!(next_group_name(["Group 2"]), "Group 1");
+    }
+}
@@ -0,0 +1,238 @@
+//! HTTPS-only mode: upgrade plain HTTP navigations and warn before falling back.
+
+use crate::html;
+use std::collections::{HashMap, HashSet};
+use url::{Host, Url};
+
+/// Tracks HTTPS upgrades across the tabs of a window
+#[derive(Debug, Default)]
+pub struct HttpsOnlyState {
+    /// Host each tab's in-flight HTTPS upgrade started from. Keyed by tab
+    /// as the upgraded load may be redirected to another host.
+    upgrading: HashMap<u32, String>,
+    /// Hosts the fallback warning was shown for
+    warned: HashSet<String>,
+    /// Hosts the user chose to visit over HTTP this session
+    allowed_http: HashSet<String>,
+}
+
+/// What to do with a plain HTTP navigation
+#[derive(Debug, PartialEq)]
+pub enum HttpDecision {
+    /// Load the page as requested
+    Allow,
+    /// Load the HTTPS variant instead
+    Upgrade(Url),
+    /// The upgrade already failed (e.g. HTTPS redirected back to HTTP)
+    Fallback,
+}
+
+impl HttpsOnlyState {
+    /// Decide how to handle a navigation to `url` in a tab while HTTPS-only mode is on
+    pub fn decide(&mut self, tab_id: u32, url: &Url) -> HttpDecision {
+        let Some(https_url) = upgrade_url(url) else {
+            return HttpDecision::Allow;
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+
+        if self.allowed_http.contains(&host) {
+            return HttpDecision::Allow;
+        }
+
+        // Following the warning page's "continue" link means the user accepted HTTP
+        if self.warned.remove(&host) {
+            log::info!("Allowing HTTP for {} after warning", host);
+            self.allowed_http.insert(host);
+            return HttpDecision::Allow;
+        }
+
+        // An HTTP navigation while upgrading means HTTPS sent us back to HTTP
+        if self.upgrading.get(&tab_id) == Some(&host) {
+            self.upgrading.remove(&tab_id);
+            return HttpDecision::Fallback;
+        }
+
+        self.upgrading.insert(tab_id, host);
+        HttpDecision::Upgrade(https_url)
+    }
+
+    /// Mark a tab's upgraded load as successful once it commits, wherever
+    /// it was redirected to
+    pub fn upgrade_committed(&mut self, tab_id: u32, url: &Url) {
+        if url.scheme() == "https" {
+            self.upgrading.remove(&tab_id);
+        }
+    }
+
+    /// Whether an HTTPS upgrade is in flight in a tab and this is its load
+    pub fn is_upgrading(&self, tab_id: u32, url: &Url) -> bool {
+        url.scheme() == "https" && self.upgrading.contains_key(&tab_id)
+    }
+
+    /// Handle a failed load. Returns true if it was a failed HTTPS upgrade
+    /// and the fallback warning should be shown.
+    pub fn upgrade_failed(&mut self, tab_id: u32, url: &Url) -> bool {
+        if url.scheme() != "https" {
+            return false;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        if self.upgrading.remove(&tab_id).is_some() {
+            self.warned.insert(host.to_string());
+            return true;
+        }
+        false
+    }
+
+    /// Forget a tab's in-flight upgrade without warning (e.g. the user
+    /// navigated away or closed the tab)
+    pub fn upgrade_cancelled(&mut self, tab_id: u32) {
+        self.upgrading.remove(&tab_id);
+    }
+
+    /// Record that the warning was shown for a host
+    pub fn mark_warned(&mut self, tab_id: u32, host: &str) {
+        self.upgrading.remove(&tab_id);
+        self.warned.insert(host.to_string());
+    }
+}
+
+/// The HTTPS variant of an HTTP URL, or None if it shouldn't be upgraded.
+/// Localhost and literal IP addresses are exempt.
+pub fn upgrade_url(url: &Url) -> Option<Url> {
+    if url.scheme() != "http" {
+        return None;
+    }
+
+    match url.host()? {
+        Host::Ipv4(_) | Host::Ipv6(_) => return None,
+        Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.');
+            if domain.eq_ignore_ascii_case("localhost") || domain.ends_with(".localhost") {
+                return None;
+            }
+        }
+    }
+
+    let mut https_url = url.clone();
+    https_url.set_scheme("https").ok()?;
+    // An explicit :80 doesn't make sense for HTTPS
+    if url.port() == Some(80) {
+        https_url.set_port(None).ok()?;
+    }
+    Some(https_url)
+}
+
+/// HTML for the warning shown when a site can't be loaded over HTTPS
+pub fn fallback_page(http_url: &Url) -> String {
+    let host = html::escape(http_url.host_str().unwrap_or_default());
+    let url = html::escape(http_url.as_str());
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="color-scheme" content="light dark">
+<title>Site doesn't support HTTPS</title>
+<style>
+  body {{ font-family: "Adwaita Sans", Cantarell, system-ui, sans-serif; max-width: 560px; margin: 15vh auto; padding: 0 24px; }}
+  h1 {{ font-size: 22px; }}
+  p {{ line-height: 1.5; }}
+  a.button {{ display: inline-block; margin-top: 12px; padding: 8px 16px; border-radius: 6px; background: #c01c28; color: #fff; text-decoration: none; }}
+</style>
+</head>
+<body>
+<h1>This site doesn't support HTTPS</h1>
+<p><strong>{host}</strong> couldn't be loaded over a secure connection. If you continue, anyone on your network may be able to see or change what you send and receive.</p>
+<a class="button" href="{url}">Continue to HTTP site</a>
+</body>
+</html>"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_plain_http() {
+        let url = Url::parse("http://example.com/path?q=1").unwrap();
+        assert_eq!(
+            upgrade_url(&url).unwrap().as_str(),
+            "https://example.com/path?q=1"
+        );
+    }
+
+    #[test]
+    fn test_exempt_hosts() {
+        for uri in [
+            "http://localhost:8080/",
+            "http://app.localhost/",
+            "http://192.168.1.1/",
+            "http://[::1]/",
+            "https://example.com/",
+        ] {
+            assert!(upgrade_url(&Url::parse(uri).unwrap()).is_none(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_fallback_after_warning() {
+        let mut state = HttpsOnlyState::default();
+        let http = Url::parse("http://example.com/").unwrap();
+        let https = Url::parse("https://example.com/").unwrap();
+
+        assert_eq!(state.decide(1, &http), HttpDecision::Upgrade(https.clone()));
+        assert!(state.upgrade_failed(1, &https));
+        // Following the warning's link allows HTTP from then on
+        assert_eq!(state.decide(1, &http), HttpDecision::Allow);
+        assert_eq!(state.decide(1, &http), HttpDecision::Allow);
+    }
+
+    #[test]
+    fn test_redirect_back_to_http() {
+        let mut state = HttpsOnlyState::default();
+        let http = Url::parse("http://example.com/").unwrap();
+
+        assert!(matches!(state.decide(1, &http), HttpDecision::Upgrade(_)));
+        assert_eq!(state.decide(1, &http), HttpDecision::Fallback);
+    }
+
+    #[test]
+    fn test_cancelled_upgrade_keeps_warning() {
+        let mut state = HttpsOnlyState::default();
+        let http = Url::parse("http://example.com/").unwrap();
+        let https = Url::parse("https://example.com/").unwrap();
+
+        // An upgrade that never got a chance (e.g. offline) is retried next time
+        assert_eq!(state.decide(1, &http), HttpDecision::Upgrade(https.clone()));
+        state.upgrade_cancelled(1);
+        assert_eq!(state.decide(1, &http), HttpDecision::Upgrade(https.clone()));
+        assert!(state.upgrade_failed(1, &https));
+    }
+
+    #[test]
+    fn test_upgrade_redirected_to_other_host() {
+        let mut state = HttpsOnlyState::default();
+        let http = Url::parse("http://a.example/").unwrap();
+        let redirected = Url::parse("https://b.example/").unwrap();
+
+        // https://a.example redirected to https://b.example, which committed
+        assert!(matches!(state.decide(1, &http), HttpDecision::Upgrade(_)));
+        state.upgrade_committed(1, &redirected);
+        assert!(!state.is_upgrading(1, &redirected));
+        // The next visit to a.example is upgraded again, not a fallback
+        assert!(matches!(state.decide(1, &http), HttpDecision::Upgrade(_)));
+    }
+
+    #[test]
+    fn test_upgrades_are_per_tab() {
+        let mut state = HttpsOnlyState::default();
+        let http = Url::parse("http://example.com/").unwrap();
+
+        assert!(matches!(state.decide(1, &http), HttpDecision::Upgrade(_)));
+        assert!(matches!(state.decide(2, &http), HttpDecision::Upgrade(_)));
+        assert_eq!(state.decide(1, &http), HttpDecision::Fallback);
+    }
+}
@@ -1,49 +1,173 @@
 use crate::application::QayeqApplication;
+use crate::content_filter::ContentManagers;
 use crate::download;
+use crate::profile::Profile;
 use crate::recently_closed::ClosedTab;
+use crate::session::{SavedTab, SavedTabGroup};
+use crate::site_settings;
+use crate::tab::autoscroll;
+use crate::tab::error_page::{self, LoadFailure};
+use crate::tab::group::{self, TabGroup};
+use crate::tab::https_only::{self, HttpDecision, HttpsOnlyState};
+use crate::tab::popups::{PopupDecision, PopupLimiter};
+use crate::tab::progress::LoadProgress;
+use crate::tab::security::SecurityLevel;
 use crate::tab::TabRow;
+use crate::url_bar;
+use crate::user_agent::UserAgentMode;
 use crate::webview::{self, WebViewExt as QayeqWebViewExt};
 use gtk4::prelude::*;
-use gtk4::glib;
+use gtk4::{gio, glib};
+use libadwaita::prelude::*;
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::rc::Rc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use url::Url;
 use webkit6::prelude::*;
-use webkit6::{NetworkSession, UserContentManager};
+use webkit6::NetworkSession;
+
+/// Pauses every video and audio element of a page
+const PAUSE_MEDIA_SCRIPT: &str = "document.querySelectorAll('video,audio').forEach(m => m.pause())";
+
+/// Leaves the page's fullscreen element, as if a site script asked to
+const EXIT_FULLSCREEN_SCRIPT: &str = "if (document.fullscreenElement) document.exitFullscreen()";
+
+/// How long a window close waits on pages that haven't answered, so a hung
+/// page can't keep its window open. Pages showing the leave dialog wait for it.
+const WINDOW_CLOSE_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// Manages browser tabs
 pub struct TabManager {
-    tabs_list: gtk4::ListBox,
+    tabs_list: gtk4::FlowBox,
     content_stack: gtk4::Stack,
     tabs: RefCell<HashMap<u32, Tab>>,
     active_tab_id: Cell<Option<u32>>,
     next_tab_id: Cell<u32>,
+    /// Tab groups in the sidebar, by group ID
+    groups: RefCell<HashMap<String, TabGroup>>,
+    next_group_id: Cell<u32>,
     /// URL entry to update when URL changes
     url_entry: gtk4::Entry,
     /// Progress bar for page load progress
-    load_progress: gtk4::ProgressBar,
+    load_progress: LoadProgress,
     /// Window reference for title updates
     window: glib::WeakRef<libadwaita::ApplicationWindow>,
     /// Back button to update sensitivity
     back_button: gtk4::Button,
     /// Forward button to update sensitivity
     forward_button: gtk4::Button,
+    /// Refresh button, which stops the load instead while the active tab is loading
+    refresh_button: gtk4::Button,
     /// Application reference for history recording
     app: glib::WeakRef<QayeqApplication>,
     /// Network session for cookie/storage persistence
     network_session: NetworkSession,
-    /// Content manager for ad blocking (optional)
-    user_content_manager: Option<UserContentManager>,
+    /// Content managers for ad blocking, one per webview (optional)
+    content_managers: Option<ContentManagers>,
     /// Callback for tab count changes
     on_tab_count_changed: RefCell<Option<Box<dyn Fn(usize)>>>,
     /// Callback for when webview gets focus (user clicked on web content)
     on_webview_focus: RefCell<Option<Box<dyn Fn()>>>,
     /// Callback for session changes (tab added/closed, navigation finished)
     on_session_changed: RefCell<Option<Box<dyn Fn()>>>,
+    /// Callback for when a tab's pop-ups start being blocked (receives the tab ID)
+    on_popups_blocked: RefCell<Option<TabCallback>>,
+    /// Callback for when the active tab's container changes (receives the container ID)
+    on_active_container_changed: RefCell<Option<ContainerCallback>>,
+    /// Callback for when the active tab's connection security changes
+    on_security_changed: RefCell<Option<SecurityCallback>>,
+    /// Callback for when another tab becomes active, or the active tab's page
+    /// commits or finishes loading
+    on_page_changed: RefCell<Option<Box<dyn Fn()>>>,
+    /// Callback for when the pointer enters or leaves a link on the active tab
+    on_link_hovered: RefCell<Option<LinkCallback>>,
+    /// Callback for when a tab has been loading longer than the load timeout (receives the tab ID)
+    on_load_timed_out: RefCell<Option<TabCallback>>,
+    /// Callback for when a tab closes after its page agreed to be left
+    on_tab_closed: RefCell<Option<ClosedTabCallback>>,
+    /// Callback for when tabs start or stop playing audio (receives whether any tab is)
+    on_media_playing_changed: RefCell<Option<MediaCallback>>,
+    /// Callback for when the active tab's page enters or leaves fullscreen
+    on_fullscreen_changed: RefCell<Option<FullscreenCallback>>,
+    /// Tab whose page is showing an element fullscreen, through the Fullscreen API
+    fullscreen_tab: Cell<Option<u32>>,
+    /// Tabs still to agree to a window close, when their pages may warn before being left
+    window_close: RefCell<Option<WindowClose>>,
+    /// HTTPS-only mode upgrade tracking shared by all tabs
+    https_only: RefCell<HttpsOnlyState>,
+    /// Certificates of tabs showing a TLS error page, kept for "Proceed anyway"
+    tls_errors: RefCell<HashMap<u32, TlsError>>,
+    /// Tabs whose current page loaded HTTP resources into an HTTPS page
+    insecure_content: RefCell<HashSet<u32>>,
+    /// Tabs loading their page once without mixed content blocking
+    insecure_content_allowed: RefCell<HashMap<u32, InsecureContentLoad>>,
+    /// Tabs showing an error page in place of the page that failed to load
+    error_pages: RefCell<HashMap<u32, ErrorPageLoad>>,
+    /// Timers of tabs whose load started while the load timeout was enabled
+    load_timeouts: RefCell<HashMap<u32, glib::SourceId>>,
+    /// Pop-up flood protection shared by all tabs
+    popups: RefCell<PopupLimiter>,
+    /// App settings, kept to follow preference changes that affect live tabs
+    settings: gio::Settings,
+}
+
+/// A window close waiting on pages that may warn before being left
+struct WindowClose {
+    pending: HashSet<u32>,
+    /// Tabs showing the leave dialog, which the timeout leaves to the user
+    asking: HashSet<u32>,
+    on_confirmed: Box<dyn FnOnce()>,
+    timeout: Option<glib::SourceId>,
+}
+
+/// A certificate the user may choose to trust from the TLS error page
+struct TlsError {
+    uri: String,
+    host: String,
+    certificate: gio::TlsCertificate,
+}
+
+/// Progress of a one-time load with mixed content blocking lifted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsecureContentLoad {
+    /// Reloading with blocking off in this tab
+    Reloading,
+    /// The page was loaded with its insecure content, until the tab navigates away
+    Loaded,
+}
+
+/// Progress of an error page replacing a failed load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorPageLoad {
+    /// The error page was requested and hasn't been committed yet
+    Loading,
+    /// The error page is displayed, until the tab commits another page
+    Shown,
 }
 
+/// Callback receiving a tab ID
+type TabCallback = Box<dyn Fn(u32)>;
+
+/// Callback receiving a container ID
+type ContainerCallback = Box<dyn Fn(&str)>;
+
+/// Callback receiving the active tab's connection security
+type SecurityCallback = Box<dyn Fn(SecurityLevel)>;
+
+/// Callback receiving the hovered link's URI, or None once off links
+type LinkCallback = Box<dyn Fn(Option<&str>)>;
+
+/// Callback receiving a tab that was closed
+type ClosedTabCallback = Box<dyn Fn(ClosedTab)>;
+
+/// Callback receiving whether any tab is playing audio
+type MediaCallback = Box<dyn Fn(bool)>;
+
+/// Callback receiving whether the active tab's page entered or left fullscreen
+type FullscreenCallback = Box<dyn Fn(bool)>;
+
 /// Represents a single tab
 struct Tab {
     id: u32,
@@ -53,42 +177,180 @@ struct Tab {
     profile_id: String,
     /// The tab that opened this tab (for returning when closed)
     parent_tab_id: Option<u32>,
+    /// Last known scroll offset of the page, for session restore
+    scroll: Rc<ScrollState>,
+    /// URL to load when the tab is first shown (restored and hibernated tabs load lazily)
+    pending_url: PendingLoad,
+    /// When the tab was last the active tab, for idle hibernation
+    last_active: Cell<Instant>,
+}
+
+impl Tab {
+    /// The tab's URL, including the deferred URL of a tab that hasn't loaded yet
+    fn url(&self) -> Option<String> {
+        if let Some(url) = self.pending_url.url() {
+            return Some(url.to_string());
+        }
+        self.webview.uri().map(|uri| uri.to_string())
+    }
+
+    /// The tab's title, falling back to the sidebar title for unloaded tabs
+    fn title(&self) -> Option<String> {
+        if self.pending_url.is_pending() {
+            let title = self.row.title();
+            return if title.is_empty() { None } else { Some(title) };
+        }
+        self.webview.title().map(|t| t.to_string())
+    }
+
+    /// Whether the tab's page has been deferred and not loaded yet
+    fn is_suspended(&self) -> bool {
+        self.pending_url.is_pending()
+    }
+}
+
+/// A page a tab loads when it's first shown rather than straight away
+#[derive(Default)]
+struct PendingLoad(RefCell<Option<Url>>);
+
+impl PendingLoad {
+    /// Wait with `url` until the tab is shown
+    fn defer(&self, url: Url) {
+        *self.0.borrow_mut() = Some(url);
+    }
+
+    fn url(&self) -> Option<Url> {
+        self.0.borrow().clone()
+    }
+
+    fn is_pending(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// The URL to load now that the tab is shown, once
+    fn take_for_show(&self) -> Option<Url> {
+        self.0.borrow_mut().take()
+    }
+}
+
+/// How a tab reopened from a saved session gets its page
+#[derive(Debug, PartialEq)]
+enum RestoredPage {
+    /// The active tab loads straight away
+    Load(Url),
+    /// The other tabs wait until they're first switched to
+    Defer(Url),
+    /// A saved URL that doesn't parse leaves the tab empty
+    Blank,
+}
+
+impl RestoredPage {
+    fn for_saved(url: &str, active: bool) -> Self {
+        match Url::parse(url) {
+            Ok(url) if active => RestoredPage::Load(url),
+            Ok(url) => RestoredPage::Defer(url),
+            Err(_) => RestoredPage::Blank,
+        }
+    }
+}
+
+/// An open tab as listed in the tab search
+#[derive(Debug, Clone)]
+pub struct TabSummary {
+    pub tab_id: u32,
+    pub title: String,
+    pub url: String,
+    pub container: String,
+}
+
+/// Scroll offset tracking for a tab
+#[derive(Default)]
+struct ScrollState {
+    /// Last captured vertical scroll offset
+    offset: Cell<Option<f64>>,
+    /// True while a restored offset is waiting to be applied
+    restore_pending: Cell<bool>,
 }
 
 impl TabManager {
     pub fn new(
-        tabs_list: gtk4::ListBox,
+        tabs_list: gtk4::FlowBox,
         content_stack: gtk4::Stack,
         url_entry: gtk4::Entry,
         load_progress: gtk4::ProgressBar,
         _window: &libadwaita::ApplicationWindow,
         back_button: gtk4::Button,
         forward_button: gtk4::Button,
+        refresh_button: gtk4::Button,
         app: &QayeqApplication,
         network_session: NetworkSession,
-        user_content_manager: Option<UserContentManager>,
+        content_managers: Option<ContentManagers>,
     ) -> Rc<Self> {
         let app_weak = glib::WeakRef::new();
         app_weak.set(Some(app));
 
-        Rc::new(Self {
+        let manager = Rc::new(Self {
             tabs_list,
             content_stack,
             tabs: RefCell::new(HashMap::new()),
             active_tab_id: Cell::new(None),
             next_tab_id: Cell::new(1),
+            groups: RefCell::new(HashMap::new()),
+            next_group_id: Cell::new(1),
             url_entry,
-            load_progress,
+            load_progress: LoadProgress::new(load_progress),
             window: glib::WeakRef::new(),
             back_button,
             forward_button,
+            refresh_button,
             app: app_weak,
             network_session,
-            user_content_manager,
+            content_managers,
             on_tab_count_changed: RefCell::new(None),
             on_webview_focus: RefCell::new(None),
             on_session_changed: RefCell::new(None),
-        })
+            on_popups_blocked: RefCell::new(None),
+            on_active_container_changed: RefCell::new(None),
+            on_security_changed: RefCell::new(None),
+            on_page_changed: RefCell::new(None),
+            on_link_hovered: RefCell::new(None),
+            on_load_timed_out: RefCell::new(None),
+            on_tab_closed: RefCell::new(None),
+            on_media_playing_changed: RefCell::new(None),
+            on_fullscreen_changed: RefCell::new(None),
+            fullscreen_tab: Cell::new(None),
+            window_close: RefCell::new(None),
+            https_only: RefCell::new(HttpsOnlyState::default()),
+            tls_errors: RefCell::new(HashMap::new()),
+            insecure_content: RefCell::new(HashSet::new()),
+            insecure_content_allowed: RefCell::new(HashMap::new()),
+            error_pages: RefCell::new(HashMap::new()),
+            load_timeouts: RefCell::new(HashMap::new()),
+            popups: RefCell::new(PopupLimiter::default()),
+            settings: gio::Settings::new(crate::config::APP_ID),
+        });
+
+        // New tabs read the preference in create_webview; existing ones are updated here
+        let manager_weak = Rc::downgrade(&manager);
+        manager.settings.connect_changed(Some("smooth-scrolling"), move |_, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.for_each_webview_settings(webview::apply_scroll_settings);
+            }
+        });
+        let manager_weak = Rc::downgrade(&manager);
+        manager.settings.connect_changed(Some("minimum-font-size"), move |_, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.for_each_webview_settings(webview::apply_font_settings);
+            }
+        });
+        let manager_weak = Rc::downgrade(&manager);
+        manager.settings.connect_changed(Some("caret-browsing"), move |_, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.for_each_webview_settings(webview::apply_caret_browsing);
+            }
+        });
+
+        manager
     }
 
     pub fn set_window(&self, window: &libadwaita::ApplicationWindow) {
@@ -107,6 +369,48 @@ impl TabManager {
         *self.on_session_changed.borrow_mut() = Some(Box::new(callback));
     }
 
+    pub fn set_on_popups_blocked<F: Fn(u32) + 'static>(&self, callback: F) {
+        *self.on_popups_blocked.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_active_container_changed<F: Fn(&str) + 'static>(&self, callback: F) {
+        *self.on_active_container_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_security_changed<F: Fn(SecurityLevel) + 'static>(&self, callback: F) {
+        *self.on_security_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_page_changed<F: Fn() + 'static>(&self, callback: F) {
+        *self.on_page_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_link_hovered<F: Fn(Option<&str>) + 'static>(&self, callback: F) {
+        *self.on_link_hovered.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_load_timed_out<F: Fn(u32) + 'static>(&self, callback: F) {
+        *self.on_load_timed_out.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_tab_closed<F: Fn(ClosedTab) + 'static>(&self, callback: F) {
+        *self.on_tab_closed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_media_playing_changed<F: Fn(bool) + 'static>(&self, callback: F) {
+        *self.on_media_playing_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn set_on_fullscreen_changed<F: Fn(bool) + 'static>(&self, callback: F) {
+        *self.on_fullscreen_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    /// Let a tab open pop-ups without the flood limit
+    pub fn allow_popups(&self, tab_id: u32) {
+        log::info!("Allowing pop-ups from tab {}", tab_id);
+        self.popups.borrow_mut().allow(tab_id);
+    }
+
     fn notify_tab_count_changed(&self) {
         let count = self.tabs.borrow().len();
         if let Some(callback) = self.on_tab_count_changed.borrow().as_ref() {
@@ -126,6 +430,60 @@ impl TabManager {
         }
     }
 
+    /// A page entered or left fullscreen; only the active tab's page may take
+    /// over the window. Returns whether the change was passed on to the window.
+    fn notify_fullscreen_changed(&self, tab_id: u32, fullscreen: bool) -> bool {
+        if fullscreen {
+            if self.active_tab_id.get() != Some(tab_id) {
+                return false;
+            }
+            self.fullscreen_tab.set(Some(tab_id));
+        } else if self.fullscreen_tab.get() == Some(tab_id) {
+            self.fullscreen_tab.set(None);
+        } else {
+            return false;
+        }
+        if let Some(callback) = self.on_fullscreen_changed.borrow().as_ref() {
+            callback(fullscreen);
+        }
+        true
+    }
+
+    /// Make the page showing an element fullscreen leave it. WebKit then
+    /// reports the page leaving fullscreen as if the site had left it.
+    pub fn exit_page_fullscreen(&self) {
+        let webview = self
+            .fullscreen_tab
+            .get()
+            .and_then(|tab_id| self.tabs.borrow().get(&tab_id).map(|tab| tab.webview.clone()));
+        if let Some(webview) = webview {
+            webview.evaluate_javascript(
+                EXIT_FULLSCREEN_SCRIPT,
+                None,
+                None,
+                None::<&gio::Cancellable>,
+                |result| {
+                    if let Err(e) = result {
+                        log::debug!("Failed to leave fullscreen: {}", e);
+                    }
+                },
+            );
+        }
+    }
+
+    fn notify_media_playing_changed(&self) {
+        let playing = self.is_media_playing();
+        if let Some(callback) = self.on_media_playing_changed.borrow().as_ref() {
+            callback(playing);
+        }
+    }
+
+    fn notify_link_hovered(&self, link: Option<&str>) {
+        if let Some(callback) = self.on_link_hovered.borrow().as_ref() {
+            callback(link);
+        }
+    }
+
     /// Create a new tab, optionally in a specific container
     /// If container is None, uses the default container from ProfileManager
     /// If parent_tab_id is Some, closing this tab will return to the parent tab
@@ -135,6 +493,84 @@ impl TabManager {
 
     /// Create a new tab with a parent tab reference
     fn new_tab_with_parent(self: &Rc<Self>, url: Option<&Url>, container: Option<&str>, parent_tab_id: Option<u32>) -> u32 {
+        // Tabs opened from another tab go right after it, and after the tabs it opened
+        // before, so links opened one after another stay in click order
+        let index = parent_tab_id.map_or(-1, |id| self.child_tab_index(id));
+        let (tab_id, tab_row) = self.create_tab(container, parent_tab_id, index);
+
+        // Tabs opened from a grouped tab join its group. The row is already inside
+        // the group, so it keeps its place rather than moving to the group's end.
+        let parent_group = parent_tab_id
+            .and_then(|id| self.tabs.borrow().get(&id).and_then(|tab| tab.row.group_id()));
+        if let Some(group_id) = parent_group {
+            tab_row.set_group(Some(&group_id));
+            tab_row.set_visible(!self.is_group_collapsed(&group_id));
+        }
+
+        // Select the new tab
+        self.tabs_list.select_child(&tab_row);
+        self.switch_to_tab(tab_id);
+
+        // Navigate to URL if provided, otherwise to the configured new tab page
+        if let Some(url) = url {
+            self.navigate_to_in_tab(tab_id, url);
+        } else if let Some(url) = configured_url("new-tab-url") {
+            self.navigate_to_in_tab(tab_id, &url);
+        }
+
+        self.notify_tab_count_changed();
+        self.notify_session_changed();
+        tab_id
+    }
+
+    /// Create a restored tab in the background without loading its page.
+    /// The URL is loaded the first time the tab is switched to.
+    fn new_suspended_tab(self: &Rc<Self>, url: &Url, title: Option<&str>, container: Option<&str>) -> u32 {
+        let (tab_id, tab_row) = self.create_tab(container, None, -1);
+
+        // Show the saved title and cached favicon until the page actually loads
+        tab_row.set_title(title.filter(|t| !t.is_empty()).unwrap_or(url.as_str()));
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            tab.pending_url.defer(url.clone());
+            load_cached_favicon(&tab.webview, url, &tab_row);
+        }
+
+        self.notify_tab_count_changed();
+        self.notify_session_changed();
+        tab_id
+    }
+
+    /// Reopen a tab from a saved session. Only the `active` one loads its page
+    /// now; the others load theirs when first switched to.
+    pub fn restore_tab(self: &Rc<Self>, url: &str, title: Option<&str>, container: Option<&str>, active: bool) -> u32 {
+        match RestoredPage::for_saved(url, active) {
+            RestoredPage::Load(url) => self.new_tab(Some(&url), container),
+            RestoredPage::Defer(url) => self.new_suspended_tab(&url, title, container),
+            RestoredPage::Blank => self.new_tab(None, container),
+        }
+    }
+
+    /// Sidebar position for a new tab opened from `parent_id`: after the parent
+    /// and the run of tabs it already opened, or at the end if it's gone
+    fn child_tab_index(&self, parent_id: u32) -> i32 {
+        let tabs = self.tabs.borrow();
+        let Some(parent_index) = tabs.get(&parent_id).map(|tab| tab.row.index()) else {
+            return -1;
+        };
+        let mut parents = Vec::new();
+        while let Some(child) = self.tabs_list.child_at_index(parents.len() as i32) {
+            let parent = child
+                .downcast_ref::<TabRow>()
+                .and_then(|row| tabs.get(&row.tab_id()))
+                .and_then(|tab| tab.parent_tab_id);
+            parents.push(parent);
+        }
+        index_after_children(&parents, parent_index.max(0) as usize, parent_id) as i32
+    }
+
+    /// Build a tab's row and webview and add them to the content stack and to the
+    /// sidebar at `index` (-1 to append)
+    fn create_tab(self: &Rc<Self>, container: Option<&str>, parent_tab_id: Option<u32>, index: i32) -> (u32, TabRow) {
         let tab_id = self.next_tab_id.get();
         self.next_tab_id.set(tab_id + 1);
 
@@ -142,11 +578,12 @@ impl TabManager {
         let (profile_id, network_session) = if let Some(app) = self.app.upgrade() {
             if let Some(pm) = app.profile_manager() {
                 let container_name = container.unwrap_or_else(|| pm.default_container());
-                if let Some(profile) = pm.get_profile(container_name) {
-                    (container_name.to_string(), profile.network_session().clone())
-                } else {
-                    log::warn!("Container '{}' not loaded, using default network session", container_name);
-                    (container_name.to_string(), self.network_session.clone())
+                match pm.get_or_load_profile(container_name) {
+                    Ok(profile) => (container_name.to_string(), profile.network_session().clone()),
+                    Err(e) => {
+                        log::warn!("Container '{}' not loaded ({}), using default network session", container_name, e);
+                        (container_name.to_string(), self.network_session.clone())
+                    }
                 }
             } else {
                 (crate::config::DEFAULT_CONTAINER.to_string(), self.network_session.clone())
@@ -155,66 +592,315 @@ impl TabManager {
             (crate::config::DEFAULT_CONTAINER.to_string(), self.network_session.clone())
         };
 
-        // Create tab row for sidebar (TabRow extends ListBoxRow directly)
+        // Create tab row for sidebar (TabRow extends FlowBoxChild directly)
         let tab_row = TabRow::new(tab_id);
         tab_row.set_container(&profile_id);
+        tab_row.set_horizontal(self.horizontal_tabs());
 
         // Create WebKit webview with the container's network session
-        let webview = webview::create_webview(
-            &network_session,
-            self.user_content_manager.as_ref(),
-        );
+        let user_content_manager = self.content_managers.as_ref().map(|m| m.for_webview(&profile_id));
+        let webview = webview::create_webview(&network_session, user_content_manager.as_ref());
+
+        let scroll = Rc::new(ScrollState::default());
 
         // Connect WebKit signals
-        self.setup_webview_signals(tab_id, &webview, &tab_row, &profile_id);
+        self.setup_webview_signals(tab_id, &webview, &tab_row, &profile_id, &scroll);
 
         // Set up close button handler
         let manager = Rc::clone(self);
         tab_row.connect_close_clicked(move |row| {
-            manager.close_tab(row.tab_id());
+            manager.request_close_tab(row.tab_id(), false);
         });
 
+        self.setup_tab_context_menu(&tab_row);
+
         // Add to content stack first
         self.content_stack.add_named(&webview, Some(&tab_id.to_string()));
 
-        // Store tab before appending to list (to avoid borrow issues when selection triggers)
+        // Store tab before adding it to the list (to avoid borrow issues when selection triggers)
         let tab = Tab {
             id: tab_id,
             row: tab_row.clone(),
             webview,
             profile_id: profile_id.clone(),
             parent_tab_id,
+            scroll,
+            pending_url: PendingLoad::default(),
+            last_active: Cell::new(Instant::now()),
         };
         self.tabs.borrow_mut().insert(tab_id, tab);
         log::debug!("Created tab {} in container '{}' (parent: {:?})", tab_id, profile_id, parent_tab_id);
 
         // Now add to tabs list (this may trigger selection signal)
-        self.tabs_list.append(&tab_row);
+        self.tabs_list.insert(&tab_row, index);
 
-        // Select the new tab
-        self.tabs_list.select_row(Some(&tab_row));
-        self.switch_to_tab(tab_id);
+        (tab_id, tab_row)
+    }
 
-        // Navigate to URL if provided
-        if let Some(url) = url {
-            self.navigate_to_in_tab(tab_id, url);
-        }
+    /// Right-click menu on a tab's sidebar row
+    fn setup_tab_context_menu(self: &Rc<Self>, tab_row: &TabRow) {
+        // Actions live on the row so the menu always targets the tab it was opened on
+        let actions = gio::SimpleActionGroup::new();
+        let move_action = gio::SimpleAction::new("move-to-container", Some(glib::VariantTy::STRING));
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        move_action.connect_activate(move |_, param| {
+            let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) else {
+                return;
+            };
+            if let Some(container) = param.and_then(|p| p.get::<String>()) {
+                manager.move_tab_to_container(row.tab_id(), &container);
+            }
+        });
+        actions.add_action(&move_action);
 
-        self.notify_tab_count_changed();
-        self.notify_session_changed();
-        tab_id
+        let new_group_action = gio::SimpleAction::new("new-group", None);
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        new_group_action.connect_activate(move |_, _| {
+            if let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) {
+                manager.add_tab_to_new_group(row.tab_id());
+            }
+        });
+        actions.add_action(&new_group_action);
+
+        let add_to_group_action = gio::SimpleAction::new("add-to-group", Some(glib::VariantTy::STRING));
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        add_to_group_action.connect_activate(move |_, param| {
+            let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) else {
+                return;
+            };
+            if let Some(group_id) = param.and_then(|p| p.get::<String>()) {
+                manager.add_tab_to_group(row.tab_id(), &group_id);
+            }
+        });
+        actions.add_action(&add_to_group_action);
+
+        let remove_from_group_action = gio::SimpleAction::new("remove-from-group", None);
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        remove_from_group_action.connect_activate(move |_, _| {
+            if let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) {
+                manager.remove_tab_from_group(row.tab_id());
+            }
+        });
+        actions.add_action(&remove_from_group_action);
+
+        let pause_media_action = gio::SimpleAction::new("pause-media", None);
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        pause_media_action.connect_activate(move |_, _| {
+            if let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) {
+                manager.pause_media(row.tab_id());
+            }
+        });
+        actions.add_action(&pause_media_action);
+
+        let toggle_pin_action = gio::SimpleAction::new("toggle-pin", None);
+        let manager_weak = Rc::downgrade(self);
+        let row_weak = tab_row.downgrade();
+        toggle_pin_action.connect_activate(move |_, _| {
+            if let (Some(manager), Some(row)) = (manager_weak.upgrade(), row_weak.upgrade()) {
+                manager.set_tab_pinned(row.tab_id(), !row.is_pinned());
+            }
+        });
+        actions.add_action(&toggle_pin_action);
+        tab_row.insert_action_group("tab", Some(&actions));
+
+        let gesture = gtk4::GestureClick::new();
+        gesture.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        let manager_weak = Rc::downgrade(self);
+        gesture.connect_pressed(move |gesture, _, x, y| {
+            let (Some(manager), Some(row)) = (
+                manager_weak.upgrade(),
+                gesture.widget().and_then(|w| w.downcast::<TabRow>().ok()),
+            ) else {
+                return;
+            };
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+
+            let containers = gio::Menu::new();
+            let current = row.container_id();
+            let app = gio::Application::default().and_downcast::<QayeqApplication>();
+            for container in app.map(|app| app.containers()).unwrap_or_default() {
+                if container.id != current {
+                    containers.append(
+                        Some(&container.name),
+                        Some(&format!("tab.move-to-container::{}", container.id)),
+                    );
+                }
+            }
+            let menu = gio::Menu::new();
+            let pinning = gio::Menu::new();
+            pinning.append(Some(if row.is_pinned() { "Unpin Tab" } else { "Pin Tab" }), Some("tab.toggle-pin"));
+            menu.append_section(None, &pinning);
+            menu.append_submenu(Some("Move to Container"), &containers);
+
+            let current_group = row.group_id();
+            let other_groups = gio::Menu::new();
+            for (group_id, name) in manager.group_names() {
+                if Some(&group_id) != current_group.as_ref() {
+                    other_groups.append(Some(&name), Some(&format!("tab.add-to-group::{}", group_id)));
+                }
+            }
+            let grouping = gio::Menu::new();
+            grouping.append(Some("Add Tab to New Group"), Some("tab.new-group"));
+            if other_groups.n_items() > 0 {
+                grouping.append_submenu(Some("Add Tab to Group"), &other_groups);
+            }
+            if current_group.is_some() {
+                grouping.append(Some("Remove from Group"), Some("tab.remove-from-group"));
+            }
+            menu.append_section(None, &grouping);
+
+            if manager.is_playing_audio(row.tab_id()) {
+                let media = gio::Menu::new();
+                media.append(Some("Pause Media"), Some("tab.pause-media"));
+                menu.append_section(None, &media);
+            }
+
+            popup_menu_at(&row, &menu, x, y);
+        });
+        tab_row.add_controller(gesture);
+    }
+
+    /// Clicking a group's header collapses or expands the group; right-clicking
+    /// offers to rename or close it
+    fn setup_group_header(self: &Rc<Self>, group_id: &str, header: &gtk4::FlowBoxChild) {
+        let click = gtk4::GestureClick::new();
+        click.set_button(gtk4::gdk::BUTTON_PRIMARY);
+        let manager_weak = Rc::downgrade(self);
+        let id = group_id.to_string();
+        click.connect_pressed(move |gesture, _, _, _| {
+            // Claimed so the tab list doesn't select the header like a tab
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+            if let Some(manager) = manager_weak.upgrade() {
+                let collapsed = manager.is_group_collapsed(&id);
+                manager.set_group_collapsed(&id, !collapsed);
+            }
+        });
+        header.add_controller(click);
+
+        let actions = gio::SimpleActionGroup::new();
+        let rename_action = gio::SimpleAction::new("rename", None);
+        let manager_weak = Rc::downgrade(self);
+        let id = group_id.to_string();
+        rename_action.connect_activate(move |_, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.prompt_rename_group(&id);
+            }
+        });
+        actions.add_action(&rename_action);
+
+        let close_action = gio::SimpleAction::new("close", None);
+        let manager_weak = Rc::downgrade(self);
+        let id = group_id.to_string();
+        close_action.connect_activate(move |_, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.close_group(&id);
+            }
+        });
+        actions.add_action(&close_action);
+        header.insert_action_group("group", Some(&actions));
+
+        let menu_gesture = gtk4::GestureClick::new();
+        menu_gesture.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        menu_gesture.connect_pressed(|gesture, _, x, y| {
+            let Some(header) = gesture.widget() else {
+                return;
+            };
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+            let menu = gio::Menu::new();
+            menu.append(Some("Rename Group…"), Some("group.rename"));
+            menu.append(Some("Close Group"), Some("group.close"));
+            popup_menu_at(&header, &menu, x, y);
+        });
+        header.add_controller(menu_gesture);
     }
 
-    fn setup_webview_signals(self: &Rc<Self>, tab_id: u32, webview: &webkit6::WebView, tab_row: &TabRow, profile_id: &str) {
+    fn setup_webview_signals(
+        self: &Rc<Self>,
+        tab_id: u32,
+        webview: &webkit6::WebView,
+        tab_row: &TabRow,
+        profile_id: &str,
+        scroll: &Rc<ScrollState>,
+    ) {
+        // The page agreed to close, after any beforeunload confirmation
+        let manager_weak = Rc::downgrade(self);
+        webview.connect_close(move |_| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.webview_closed(tab_id);
+            }
+        });
+        let manager_weak = Rc::downgrade(self);
+        webview.connect_is_playing_audio_notify(move |_| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.notify_media_playing_changed();
+            }
+        });
+
+        // Fullscreen API requests: WebKit shows the element over the whole
+        // webview, and the window goes fullscreen around it
+        let manager_weak = Rc::downgrade(self);
+        webview.connect_enter_fullscreen(move |_| {
+            // Returning true refuses the request, for pages in background tabs
+            manager_weak
+                .upgrade()
+                .is_some_and(|manager| !manager.notify_fullscreen_changed(tab_id, true))
+        });
+        let manager_weak = Rc::downgrade(self);
+        webview.connect_leave_fullscreen(move |_| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.notify_fullscreen_changed(tab_id, false);
+            }
+            false
+        });
+
+        let manager_weak = Rc::downgrade(self);
+        let manager_for_asked = Rc::downgrade(self);
+        webview::connect_beforeunload(
+            webview,
+            move |_| {
+                if let Some(manager) = manager_for_asked.upgrade() {
+                    if let Some(close) = manager.window_close.borrow_mut().as_mut() {
+                        close.asking.insert(tab_id);
+                    }
+                }
+            },
+            move |_, leave| {
+                // Staying on a page keeps its window open too
+                if let (false, Some(manager)) = (leave, manager_weak.upgrade()) {
+                    manager.end_window_close();
+                }
+            },
+        );
+
+        // Per-site settings follow the page's origin
+        let manager_weak = Rc::downgrade(self);
+        let tab_profile_id = profile_id.to_string();
+        webview.connect_notify_local(Some("uri"), move |wv, _| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.apply_site_settings(wv, &tab_profile_id);
+            }
+        });
+
         // URL changed - use connect_notify_local for "uri" property (local = not Send+Sync)
         let url_entry = self.url_entry.clone();
         let content_stack = self.content_stack.clone();
         let tid_str = tab_id.to_string();
+        let app = self.app.clone();
         webview.connect_notify_local(Some("uri"), move |wv, _| {
             if let Some(uri) = wv.uri() {
                 log::info!("URI changed to: {}", uri);
             }
 
+            // Apply the site's JavaScript setting before its page loads
+            if let Some(app) = app.upgrade() {
+                webview::apply_javascript(wv, javascript_enabled_for(&app, wv.uri().as_deref()));
+            }
+
             let is_active = content_stack
                 .visible_child_name()
                 .map(|n| n.as_str() == tid_str)
@@ -222,7 +908,7 @@ impl TabManager {
 
             if is_active {
                 if let Some(uri) = wv.uri() {
-                    url_entry.set_text(&uri);
+                    url_entry.set_text(&url_bar_text(&uri));
                 }
             }
         });
@@ -270,22 +956,104 @@ impl TabManager {
             }
 
             match event {
-                LoadEvent::Started => {
-                    progress_bar.set_fraction(0.1);
-                    progress_bar.set_visible(true);
-                }
-                LoadEvent::Committed => {
-                    let progress = wv.estimated_load_progress();
-                    progress_bar.set_fraction(progress.max(0.3));
-                }
-                LoadEvent::Finished => {
-                    progress_bar.set_visible(false);
-                    progress_bar.set_fraction(0.0);
+                LoadEvent::Started => progress_bar.start(),
+                LoadEvent::Committed => progress_bar.set_progress(wv.estimated_load_progress().max(0.3)),
+                LoadEvent::Finished => progress_bar.finish(),
+                _ => progress_bar.set_progress(wv.estimated_load_progress()),
+            }
+        });
+
+        // Offer to stop loads that run past the timeout; each new load restarts it
+        let manager = Rc::downgrade(self);
+        webview.connect_load_changed(move |_, event| {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            match event {
+                webkit6::LoadEvent::Started => manager.start_load_timeout(tab_id),
+                webkit6::LoadEvent::Finished => manager.cancel_load_timeout(tab_id),
+                _ => {}
+            }
+        });
+
+        // Hovered link, for the status label; background tabs can't be hovered
+        let manager = Rc::downgrade(self);
+        webview.connect_mouse_target_changed(move |_, hit, _| {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            if manager.active_tab_id.get() != Some(tab_id) {
+                return;
+            }
+            let link = hit.link_uri().filter(|_| hit.context_is_link());
+            manager.notify_link_hovered(link.as_deref());
+        });
+
+        // Connection security: reset for each page, re-checked once it finishes
+        let manager = Rc::downgrade(self);
+        webview.connect_load_changed(move |wv, event| {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            match event {
+                webkit6::LoadEvent::Committed => {
+                    manager.insecure_content.borrow_mut().remove(&tab_id);
+                    let loaded = manager.insecure_content_allowed.borrow().get(&tab_id) == Some(&InsecureContentLoad::Loaded);
+                    if loaded {
+                        manager.end_insecure_content_load(tab_id, wv);
+                    }
+                    // An error page is committed like any other page; the one after it replaces it
+                    let mut error_pages = manager.error_pages.borrow_mut();
+                    match error_pages.get(&tab_id) {
+                        Some(ErrorPageLoad::Loading) => {
+                            error_pages.insert(tab_id, ErrorPageLoad::Shown);
+                        }
+                        Some(ErrorPageLoad::Shown) => {
+                            error_pages.remove(&tab_id);
+                        }
+                        None => {}
+                    }
+                    drop(error_pages);
+                    manager.notify_security_changed(tab_id);
+                    manager.notify_page_changed(tab_id);
                 }
-                _ => {
-                    let progress = wv.estimated_load_progress();
-                    progress_bar.set_fraction(progress);
+                webkit6::LoadEvent::Finished => {
+                    manager.finish_insecure_content_load(tab_id);
+                    manager.notify_security_changed(tab_id);
+                    manager.notify_page_changed(tab_id);
                 }
+                _ => {}
+            }
+        });
+        let manager = Rc::downgrade(self);
+        webview.connect_insecure_content_detected(move |_, _| {
+            if let Some(manager) = manager.upgrade() {
+                manager.insecure_content.borrow_mut().insert(tab_id);
+                manager.notify_security_changed(tab_id);
+            }
+        });
+        // Blocked loads aren't reported as insecure content, so notice them as they start
+        let manager = Rc::downgrade(self);
+        webview.connect_resource_load_started(move |wv, _, request| {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            let page_is_https = wv.uri().is_some_and(|uri| uri.starts_with("https:"));
+            let resource_is_http = request.uri().is_some_and(|uri| uri.starts_with("http:"));
+            if page_is_https
+                && resource_is_http
+                && manager.mixed_content_blocked()
+                && manager.insecure_content.borrow_mut().insert(tab_id)
+            {
+                manager.notify_security_changed(tab_id);
+            }
+        });
+
+        // Forget the previous page's scroll offset once a new page commits
+        let scroll_state = Rc::clone(scroll);
+        webview.connect_load_changed(move |_, event| {
+            if event == webkit6::LoadEvent::Committed && !scroll_state.restore_pending.get() {
+                scroll_state.offset.set(None);
             }
         });
 
@@ -293,6 +1061,7 @@ impl TabManager {
         let row = tab_row.clone();
         let back_btn = self.back_button.clone();
         let forward_btn = self.forward_button.clone();
+        let refresh_btn = self.refresh_button.clone();
         let content_stack = self.content_stack.clone();
         let app_ref = self.app.clone();
         let tid_str = tab_id.to_string();
@@ -311,6 +1080,7 @@ impl TabManager {
             if is_active {
                 back_btn.set_sensitive(wv.can_go_back());
                 forward_btn.set_sensitive(wv.can_go_forward());
+                update_refresh_button(&refresh_btn, is_loading);
             }
 
             // Record history and save session when navigation STARTS (not finishes)
@@ -318,15 +1088,14 @@ impl TabManager {
             if is_loading {
                 if let Some(uri) = wv.uri() {
                     // Skip internal pages
-                    if !uri.starts_with("about:") {
+                    if !uri.starts_with("about:") && !webview::is_internal_uri(&uri) {
                         if let Ok(url) = Url::parse(&uri) {
                             // Record history immediately so back button works
                             if let Some(app) = app_ref.upgrade() {
                                 if let Some(pm) = app.profile_manager() {
                                     if let Some(profile) = pm.get_profile(&tab_profile_id) {
-                                        if let Err(e) = profile.history().record_visit(&url, None) {
-                                            log::warn!("Failed to record history for profile '{}': {}", tab_profile_id, e);
-                                        }
+                                        // Queued for the history writer thread, so this doesn't block
+                                        profile.history().record_visit(&url, None);
                                     }
                                 }
                             }
@@ -340,11 +1109,23 @@ impl TabManager {
             }
         });
 
-        // Favicon changed
+        // Favicon changed; also stored per host for URL bar completions
         let row = tab_row.clone();
+        let app_ref = self.app.clone();
+        let tab_profile_id = profile_id.to_string();
         webview.connect_notify_local(Some("favicon"), move |wv, _| {
             if let Some(favicon) = wv.favicon() {
                 row.set_favicon(Some(&favicon));
+                let host = wv
+                    .uri()
+                    .filter(|uri| !uri.starts_with("about:") && !webview::is_internal_uri(uri))
+                    .and_then(|uri| Url::parse(&uri).ok())
+                    .and_then(|url| url.host_str().map(|h| h.to_string()));
+                if let (Some(host), Some(app)) = (host, app_ref.upgrade()) {
+                    if let Some(profile) = app.profile_manager().and_then(|pm| pm.get_profile(&tab_profile_id)) {
+                        profile.history().set_favicon(&host, favicon.save_to_png_bytes().to_vec());
+                    }
+                }
             } else {
                 row.set_favicon(None);
             }
@@ -360,17 +1141,23 @@ impl TabManager {
         });
         webview.add_controller(focus_controller);
 
+        // Middle-click autoscroll, which WebKitGTK doesn't do itself
+        autoscroll::setup_autoscroll(webview, &self.settings);
 
         // Context menu - detect "Save As" actions to show file dialog
         webview.connect_context_menu(move |_wv, context_menu, hit_test_result| {
             // Check for download-related context menu items
             use webkit6::ContextMenuAction;
 
+            let mut has_inspect_element = false;
             let n_items = context_menu.n_items();
             for i in 0..n_items {
                 if let Some(item) = context_menu.item_at_position(i) {
                     let action = item.stock_action();
                     match action {
+                        ContextMenuAction::InspectElement => {
+                            has_inspect_element = true;
+                        }
                         ContextMenuAction::DownloadImageToDisk => {
                             // User is about to save an image
                             if let Some(uri) = hit_test_result.image_uri() {
@@ -393,13 +1180,98 @@ impl TabManager {
                     }
                 }
             }
+
+            // Always offer the inspector, even on menus WebKit builds without it
+            if !has_inspect_element {
+                context_menu.append(&webkit6::ContextMenuItem::new_separator());
+                context_menu.append(&webkit6::ContextMenuItem::from_stock_action(
+                    ContextMenuAction::InspectElement,
+                ));
+            }
             false // Allow context menu to show
         });
 
+        // HTTPS-only mode: a committed upgrade succeeded, a failed one offers an HTTP fallback
+        let manager = Rc::downgrade(self);
+        webview.connect_load_changed(move |wv, event| {
+            if event != webkit6::LoadEvent::Committed {
+                return;
+            }
+            if let (Some(manager), Some(url)) = (manager.upgrade(), wv.current_url()) {
+                manager.https_only.borrow_mut().upgrade_committed(tab_id, &url);
+            }
+        });
+        let manager = Rc::downgrade(self);
+        webview.connect_load_failed(move |wv, _event, failing_uri, error| {
+            let (Some(manager), Ok(url)) = (manager.upgrade(), Url::parse(failing_uri)) else {
+                return false;
+            };
+            if error.matches(webkit6::NetworkError::Cancelled) {
+                if url.scheme() == "https" {
+                    manager.https_only.borrow_mut().upgrade_cancelled(tab_id);
+                }
+                return false;
+            }
+            // Offline, every load fails at the proxy; say so rather than "connection refused"
+            let offline = manager
+                .app
+                .upgrade()
+                .is_some_and(|app| app.profile_manager().is_some_and(|pm| pm.is_offline()));
+            // An upgrade that failed only because we're offline says nothing about
+            // the host's HTTPS, so it mustn't count towards the HTTP fallback
+            let upgrade_failed = if offline {
+                manager.https_only.borrow_mut().upgrade_cancelled(tab_id);
+                false
+            } else {
+                manager.https_only.borrow_mut().upgrade_failed(tab_id, &url)
+            };
+            if !upgrade_failed {
+                let shown = show_error_page(wv, failing_uri, error, offline);
+                if shown {
+                    manager.error_pages.borrow_mut().insert(tab_id, ErrorPageLoad::Loading);
+                }
+                return shown;
+            }
+
+            log::info!("HTTPS upgrade failed for {}: {}", failing_uri, error);
+            let mut http_url = url.clone();
+            let _ = http_url.set_scheme("http");
+            wv.load_alternate_html(&https_only::fallback_page(&http_url), failing_uri, None);
+            manager.error_pages.borrow_mut().insert(tab_id, ErrorPageLoad::Loading);
+            true
+        });
+
+        // Untrusted certificates get an error page that can trust them for this session
+        let manager = Rc::downgrade(self);
+        webview.connect_load_failed_with_tls_errors(move |wv, failing_uri, certificate, errors| {
+            let (Some(manager), Ok(url)) = (manager.upgrade(), Url::parse(failing_uri)) else {
+                return false;
+            };
+            // A failed HTTPS upgrade gets the HTTP fallback page from load-failed instead
+            if manager.https_only.borrow().is_upgrading(tab_id, &url) {
+                return false;
+            }
+
+            log::warn!("TLS errors loading {}: {:?}", failing_uri, errors);
+            manager.tls_errors.borrow_mut().insert(
+                tab_id,
+                TlsError {
+                    uri: failing_uri.to_string(),
+                    host: url.host_str().unwrap_or_default().to_string(),
+                    certificate: certificate.clone(),
+                },
+            );
+            let page = error_page::error_page(failing_uri, &LoadFailure::from_tls_errors(errors));
+            wv.load_alternate_html(&page, failing_uri, None);
+            manager.error_pages.borrow_mut().insert(tab_id, ErrorPageLoad::Loading);
+            true
+        });
+
         // Handle navigation policy decisions, including target="_blank" links
         let manager = Rc::downgrade(self);
         let parent_tab_id = tab_id; // Capture current tab ID for parent reference
-        webview.connect_decide_policy(move |_wv, decision, decision_type| {
+        let settings = gio::Settings::new(crate::config::APP_ID);
+        webview.connect_decide_policy(move |wv, decision, decision_type| {
             use webkit6::PolicyDecisionType;
 
             match decision_type {
@@ -415,6 +1287,48 @@ impl TabManager {
                                     uri,
                                     nav_action.mouse_button()
                                 );
+
+                                // "Go Back" and "Proceed anyway" on the TLS error page
+                                if let Some(manager) = manager.upgrade() {
+                                    if manager.go_back_from_error_page(wv, parent_tab_id, &uri, nav_type) {
+                                        decision.ignore();
+                                        return true;
+                                    }
+                                    manager.accept_tls_error(wv, parent_tab_id, &uri, nav_type);
+                                }
+
+                                // Apply the site's requested user agent before the request goes out
+                                if let (Some(manager), Ok(url)) = (manager.upgrade(), Url::parse(&uri)) {
+                                    if let (Some(app), Some(host)) = (manager.app.upgrade(), url.host_str()) {
+                                        let mode = app.site_user_agent(host);
+                                        webview::apply_user_agent(wv, mode.map(|m| m.user_agent()));
+                                    }
+                                }
+
+                                // HTTPS-only mode: upgrade plain HTTP, warn before falling back
+                                if settings.boolean("https-only-mode") && uri.starts_with("http:") {
+                                    if let (Some(manager), Ok(url)) = (manager.upgrade(), Url::parse(&uri)) {
+                                        let https_decision = manager.https_only.borrow_mut().decide(parent_tab_id, &url);
+                                        match https_decision {
+                                            HttpDecision::Allow => {}
+                                            HttpDecision::Upgrade(https_url) => {
+                                                log::info!("Upgrading to HTTPS: {}", https_url);
+                                                decision.ignore();
+                                                wv.load_uri(https_url.as_str());
+                                                return true;
+                                            }
+                                            HttpDecision::Fallback => {
+                                                log::info!("HTTPS redirected back to HTTP: {}", uri);
+                                                decision.ignore();
+                                                if let Some(host) = url.host_str() {
+                                                    manager.https_only.borrow_mut().mark_warned(parent_tab_id, host);
+                                                }
+                                                wv.load_alternate_html(&https_only::fallback_page(&url), &uri, None);
+                                                return true;
+                                            }
+                                        }
+                                    }
+                                }
                             }
                         }
                     }
@@ -460,6 +1374,22 @@ impl TabManager {
                 PolicyDecisionType::NewWindowAction => {
                     // Handle target="_blank" and window.open() - open in new tab with parent reference
                     log::info!("New window policy decision - opening in new tab from tab {}", parent_tab_id);
+
+                    // Suppress floods of pop-ups from one tab
+                    if let Some(manager) = manager.upgrade() {
+                        let limit = settings.int("popup-limit").max(1) as u32;
+                        let popup_decision = manager.popups.borrow_mut().request(parent_tab_id, limit, Instant::now());
+                        if let PopupDecision::Block { first } = popup_decision {
+                            log::info!("Blocked pop-up from tab {}", parent_tab_id);
+                            if first {
+                                if let Some(callback) = manager.on_popups_blocked.borrow().as_ref() {
+                                    callback(parent_tab_id);
+                                }
+                            }
+                            decision.ignore();
+                            return true;
+                        }
+                    }
                     if let Some(nav_decision) = decision.downcast_ref::<webkit6::NavigationPolicyDecision>() {
                         if let Some(mut nav_action) = nav_decision.navigation_action() {
                             if let Some(mut request) = nav_action.request() {
@@ -485,16 +1415,97 @@ impl TabManager {
         });
     }
 
+    /// Trust a tab's rejected certificate when its error page's "Proceed anyway"
+    /// link is followed. Other navigations leave it untrusted.
+    fn accept_tls_error(&self, webview: &webkit6::WebView, tab_id: u32, uri: &str, nav_type: webkit6::NavigationType) {
+        let showing_error_page = webview.uri().is_some_and(|current| current == uri);
+        if nav_type != webkit6::NavigationType::LinkClicked || !showing_error_page {
+            return;
+        }
+        let Some(tls_error) = self.tls_errors.borrow_mut().remove(&tab_id).filter(|e| e.uri == uri) else {
+            return;
+        };
+        let Some(session) = webview.network_session() else {
+            return;
+        };
+
+        log::warn!("User accepted an untrusted certificate for {}", tls_error.host);
+        session.allow_tls_certificate_for_host(&tls_error.certificate, &tls_error.host);
+    }
+
+    /// Follow an error page's "Go Back" link. Returns whether the navigation was one.
+    fn go_back_from_error_page(
+        &self,
+        webview: &webkit6::WebView,
+        tab_id: u32,
+        uri: &str,
+        nav_type: webkit6::NavigationType,
+    ) -> bool {
+        let showing_error_page = matches!(self.error_pages.borrow().get(&tab_id), Some(ErrorPageLoad::Shown));
+        if nav_type != webkit6::NavigationType::LinkClicked || !showing_error_page || !error_page::is_go_back_link(uri) {
+            return false;
+        }
+        webview.go_back();
+        true
+    }
+
+    /// Move a tab to another container. A webview's network session is fixed, so the
+    /// page is reopened in a new tab at the same position and the original is closed
+    /// without being recorded as recently closed. Returns the new tab's ID.
+    pub fn move_tab_to_container(self: &Rc<Self>, tab_id: u32, container: &str) -> Option<u32> {
+        let (url, title, index, parent_tab_id, group_id, pinned) = {
+            let tabs = self.tabs.borrow();
+            let tab = tabs.get(&tab_id)?;
+            if tab.profile_id == container {
+                return None;
+            }
+            (tab.url(), tab.title(), tab.row.index(), tab.parent_tab_id, tab.row.group_id(), tab.row.is_pinned())
+        };
+        let was_active = self.active_tab_id.get() == Some(tab_id);
+        let url = url.and_then(|u| Url::parse(&u).ok());
+
+        let (new_id, new_row) = self.create_tab(Some(container), parent_tab_id, index);
+        if let Some(group_id) = &group_id {
+            new_row.set_group(Some(group_id));
+            new_row.set_visible(!self.is_group_collapsed(group_id));
+        }
+        new_row.set_pinned(pinned);
+
+        if let Some(title) = title.as_deref().filter(|t| !t.is_empty()) {
+            new_row.set_title(title);
+        }
+        if let Some(url) = &url {
+            if was_active {
+                self.navigate_to_in_tab(new_id, url);
+            } else if let Some(tab) = self.tabs.borrow().get(&new_id) {
+                // Background tabs load when they're next shown, like restored ones
+                tab.pending_url.defer(url.clone());
+                load_cached_favicon(&tab.webview, url, &new_row);
+            }
+        }
+
+        // Switch first so closing the original doesn't pick another tab
+        if was_active {
+            self.tabs_list.select_child(&new_row);
+            self.switch_to_tab(new_id);
+        }
+        // An internal move, so the ClosedTab is not recorded
+        let _ = self.close_tab(tab_id);
+
+        log::info!("Moved tab {} to container '{}' as tab {}", tab_id, container, new_id);
+        Some(new_id)
+    }
+
     /// Close a tab and return its data for recently closed tracking.
     pub fn close_tab(&self, tab_id: u32) -> Option<ClosedTab> {
         // Extract tab data and determine if we need to switch tabs
-        let (closed_tab, need_switch, parent_tab_id) = {
+        let (closed_tab, need_switch, parent_tab_id, group_id) = {
             let mut tabs = self.tabs.borrow_mut();
             if let Some(tab) = tabs.remove(&tab_id) {
                 // Capture tab state before removing
                 let title = tab.row.title();
                 let closed = ClosedTab {
-                    url: tab.webview.uri().map(|s| s.to_string()).unwrap_or_default(),
+                    url: tab.url().unwrap_or_default(),
                     title: if title.is_empty() { None } else { Some(title) },
                     container: tab.profile_id.clone(),
                     closed_at: SystemTime::now(),
@@ -502,16 +1513,30 @@ impl TabManager {
 
                 self.tabs_list.remove(&tab.row);
                 self.content_stack.remove(&tab.webview);
+                self.tls_errors.borrow_mut().remove(&tab_id);
+                self.insecure_content.borrow_mut().remove(&tab_id);
+                self.end_insecure_content_load(tab_id, &tab.webview);
+                self.error_pages.borrow_mut().remove(&tab_id);
+                self.https_only.borrow_mut().upgrade_cancelled(tab_id);
+                self.cancel_load_timeout(tab_id);
+                self.popups.borrow_mut().remove(tab_id);
 
                 // Check if we need to switch tabs after releasing the borrow
                 let need_switch = self.active_tab_id.get() == Some(tab_id);
-                (Some(closed), need_switch, tab.parent_tab_id)
+                (Some(closed), need_switch, tab.parent_tab_id, tab.row.group_id())
             } else {
-                (None, false, None)
+                (None, false, None, None)
             }
         };
         // RefCell borrow is released here
 
+        if let Some(group_id) = group_id {
+            self.remove_group_if_empty(&group_id);
+        }
+        if closed_tab.is_some() {
+            self.settle_window_close(Some(tab_id));
+        }
+
         // Now we can safely trigger selection which may call switch_to_tab
         if need_switch {
             // Try to switch to parent tab first, then fall back to first tab
@@ -522,11 +1547,11 @@ impl TabManager {
                 None
             }.or_else(|| {
                 // Fall back to first tab
-                self.tabs_list.row_at_index(0).and_then(|row| row.downcast::<TabRow>().ok())
+                self.first_tab_row()
             });
 
             if let Some(row) = target_row {
-                self.tabs_list.select_row(Some(&row));
+                self.tabs_list.select_child(&row);
             } else {
                 self.active_tab_id.set(None);
             }
@@ -534,6 +1559,11 @@ impl TabManager {
 
         self.notify_tab_count_changed();
         self.notify_session_changed();
+        if closed_tab.is_some() {
+            self.notify_media_playing_changed();
+            // A closed page can't report leaving fullscreen itself
+            self.notify_fullscreen_changed(tab_id, false);
+        }
         closed_tab
     }
 
@@ -545,25 +1575,285 @@ impl TabManager {
         None
     }
 
+    /// Ask a tab's page to close, running its `beforeunload` handler. The tab
+    /// closes when the webview emits `close`, which a page that warns about
+    /// unsaved changes only allows once the user agrees to leave.
+    pub fn try_close_tab(&self, tab_id: u32) {
+        let webview = self.tabs.borrow().get(&tab_id).map(|tab| tab.webview.clone());
+        if let Some(webview) = webview {
+            webview.try_close();
+        }
+    }
+
+    pub fn try_close_current_tab(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            self.try_close_tab(tab_id);
+        }
+    }
+
+    /// Close a tab from its close button or a shortcut. Pinned tabs are protected
+    /// as set in preferences, unless `force` (Ctrl+Shift+W) is set: closing asks
+    /// first, or only the forced shortcut closes them.
+    pub fn request_close_tab(self: &Rc<Self>, tab_id: u32, force: bool) {
+        if force {
+            self.try_close_tab(tab_id);
+            return;
+        }
+        self.close_guarding_pinned(vec![tab_id], |manager, tab_ids| {
+            for tab_id in tab_ids {
+                manager.try_close_tab(tab_id);
+            }
+        });
+    }
+
+    /// Close tabs as the protection of pinned tabs set in preferences allows.
+    /// `close` gets the tabs to close: all of them, once confirmed if any is
+    /// pinned, or only the unpinned ones when pinned tabs need the forced
+    /// shortcut.
+    fn close_guarding_pinned(
+        self: &Rc<Self>,
+        tab_ids: Vec<u32>,
+        close: impl FnOnce(&Rc<Self>, Vec<u32>) + 'static,
+    ) {
+        let pinned: Vec<TabRow> = {
+            let tabs = self.tabs.borrow();
+            tab_ids
+                .iter()
+                .filter_map(|tab_id| tabs.get(tab_id))
+                .filter(|tab| tab.row.is_pinned())
+                .map(|tab| tab.row.clone())
+                .collect()
+        };
+        if pinned.is_empty() {
+            close(self, tab_ids);
+            return;
+        }
+
+        match self.settings.string("pinned-tab-close").as_str() {
+            "confirm" => self.confirm_close_pinned(&pinned, move |manager| close(manager, tab_ids)),
+            "shortcut" => {
+                let unpinned: Vec<u32> = tab_ids
+                    .into_iter()
+                    .filter(|tab_id| !pinned.iter().any(|row| row.tab_id() == *tab_id))
+                    .collect();
+                if !unpinned.is_empty() {
+                    close(self, unpinned);
+                } else if let Some(window) = self.window.upgrade() {
+                    window.error_bell();
+                }
+            }
+            _ => close(self, tab_ids),
+        }
+    }
+
+    /// How the active tab is protected from being closed: the
+    /// `pinned-tab-close` setting if it's pinned, otherwise None
+    pub fn current_tab_close_protection(&self) -> Option<glib::GString> {
+        let tabs = self.tabs.borrow();
+        let tab = tabs.get(&self.active_tab_id.get()?)?;
+        tab.row.is_pinned().then(|| self.settings.string("pinned-tab-close"))
+    }
+
+    pub fn request_close_current_tab(self: &Rc<Self>, force: bool) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            self.request_close_tab(tab_id, force);
+        }
+    }
+
+    /// Ask before closing pinned tabs, calling `on_close` if the user agrees
+    fn confirm_close_pinned(self: &Rc<Self>, pinned: &[TabRow], on_close: impl FnOnce(&Rc<Self>) + 'static) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let (heading, body, label) = match pinned {
+            [row] => {
+                let title = row.title();
+                let title = if title.is_empty() { "This tab" } else { title.as_str() };
+                (
+                    "Close Pinned Tab?",
+                    format!("{} is pinned. It can be reopened from recently closed tabs.", title),
+                    "Close Tab",
+                )
+            }
+            _ => (
+                "Close Pinned Tabs?",
+                format!(
+                    "{} of these tabs are pinned. They can be reopened from recently closed tabs.",
+                    pinned.len()
+                ),
+                "Close Tabs",
+            ),
+        };
+        let dialog = libadwaita::AlertDialog::new(Some(heading), Some(&body));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("close", label);
+        dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let manager_weak = Rc::downgrade(self);
+        let on_close = RefCell::new(Some(on_close));
+        dialog.connect_response(None, move |_, response| {
+            if response == "close" {
+                if let (Some(manager), Some(on_close)) = (manager_weak.upgrade(), on_close.take()) {
+                    on_close(&manager);
+                }
+            }
+        });
+        dialog.present(Some(&window));
+    }
+
+    /// Pin or unpin a tab, protecting it from being closed by accident
+    pub fn set_tab_pinned(&self, tab_id: u32, pinned: bool) {
+        let row = self.tabs.borrow().get(&tab_id).map(|tab| tab.row.clone());
+        if let Some(row) = row.filter(|row| row.is_pinned() != pinned) {
+            row.set_pinned(pinned);
+            self.notify_session_changed();
+        }
+    }
+
+    /// Ask every tab's page whether it can be left, calling `on_confirmed` once all
+    /// have agreed. The tabs stay open; the window closes them along with itself.
+    /// Staying on any page cancels the request, and pages that don't answer in
+    /// time are taken as agreeing.
+    pub fn confirm_close_all(self: &Rc<Self>, on_confirmed: impl FnOnce() + 'static) {
+        let webviews: Vec<(u32, webkit6::WebView)> = self
+            .tabs
+            .borrow()
+            .iter()
+            .map(|(&id, tab)| (id, tab.webview.clone()))
+            .collect();
+        let manager_weak = Rc::downgrade(self);
+        let timeout = glib::timeout_add_local_once(WINDOW_CLOSE_TIMEOUT, move || {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.window_close_timed_out();
+            }
+        });
+        *self.window_close.borrow_mut() = Some(WindowClose {
+            pending: webviews.iter().map(|(id, _)| *id).collect(),
+            asking: HashSet::new(),
+            on_confirmed: Box::new(on_confirmed),
+            timeout: Some(timeout),
+        });
+        if webviews.is_empty() {
+            self.settle_window_close(None);
+        }
+        // Pages without a handler, and unloaded ones, answer right away
+        for (_, webview) in webviews {
+            webview.try_close();
+        }
+    }
+
+    /// A tab's page agreed to close: part of a window close, or a tab close
+    fn webview_closed(&self, tab_id: u32) {
+        let for_window = self
+            .window_close
+            .borrow()
+            .as_ref()
+            .is_some_and(|close| close.pending.contains(&tab_id));
+        if for_window {
+            self.settle_window_close(Some(tab_id));
+            return;
+        }
+        if let Some(closed_tab) = self.close_tab(tab_id) {
+            if let Some(callback) = self.on_tab_closed.borrow().as_ref() {
+                callback(closed_tab);
+            }
+        }
+    }
+
+    /// Drop the window close request along with its timeout
+    fn end_window_close(&self) -> Option<WindowClose> {
+        let mut close = self.window_close.borrow_mut().take()?;
+        if let Some(timeout) = close.timeout.take() {
+            timeout.remove();
+        }
+        Some(close)
+    }
+
+    /// Give up on pages that never answered the window close, as if they agreed
+    fn window_close_timed_out(&self) {
+        let unanswered: Vec<u32> = {
+            let mut window_close = self.window_close.borrow_mut();
+            let Some(close) = window_close.as_mut() else {
+                return;
+            };
+            // The source has fired, so there's nothing to remove later
+            close.timeout = None;
+            close.pending.difference(&close.asking).copied().collect()
+        };
+        if !unanswered.is_empty() {
+            log::warn!("Closing window without an answer from {} pages", unanswered.len());
+        }
+        for tab_id in unanswered {
+            self.settle_window_close(Some(tab_id));
+        }
+    }
+
+    /// Stop waiting on a tab for the window close, finishing it if it was the last
+    fn settle_window_close(&self, tab_id: Option<u32>) {
+        {
+            let mut window_close = self.window_close.borrow_mut();
+            let Some(close) = window_close.as_mut() else {
+                return;
+            };
+            if let Some(tab_id) = tab_id {
+                close.pending.remove(&tab_id);
+            }
+            if !close.pending.is_empty() {
+                return;
+            }
+        }
+        if let Some(close) = self.end_window_close() {
+            (close.on_confirmed)();
+        }
+    }
+
     pub fn switch_to_tab(&self, tab_id: u32) {
         if self.tabs.borrow().contains_key(&tab_id) {
+            // A tab in a collapsed group is shown by expanding the group
+            let group_id = self.tabs.borrow().get(&tab_id).and_then(|tab| tab.row.group_id());
+            if let Some(group_id) = group_id {
+                self.set_group_collapsed(&group_id, false);
+            }
+
+            // A page shown fullscreen doesn't stay fullscreen behind another tab
+            if self.fullscreen_tab.get().is_some_and(|id| id != tab_id) {
+                self.exit_page_fullscreen();
+            }
+
             // Remove active class from all tabs, add to new active
+            let previous_id = self.active_tab_id.get();
             for tab in self.tabs.borrow().values() {
                 tab.row.set_active(tab.id == tab_id);
+                if tab.id == tab_id || Some(tab.id) == previous_id {
+                    tab.last_active.set(Instant::now());
+                }
             }
 
             self.active_tab_id.set(Some(tab_id));
             self.content_stack.set_visible_child_name(&tab_id.to_string());
 
-            // Select the row in the ListBox
+            // Restored tabs defer loading until they're first shown
+            let pending_url = self
+                .tabs
+                .borrow()
+                .get(&tab_id)
+                .and_then(|tab| tab.pending_url.take_for_show());
+            if let Some(url) = &pending_url {
+                log::debug!("Loading deferred tab {}: {}", tab_id, url);
+                self.navigate_to_in_tab(tab_id, url);
+            }
+
+            // Select the row in the tab list
             if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                self.tabs_list.select_row(Some(&tab.row));
+                self.tabs_list.select_child(&tab.row);
             }
 
             // Update URL bar, title, and progress for the new active tab
             if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                if let Some(uri) = tab.webview.uri() {
-                    self.url_entry.set_text(&uri);
+                if let Some(uri) = tab.url() {
+                    self.url_entry.set_text(&url_bar_text(&uri));
                 } else {
                     self.url_entry.set_text("");
                 }
@@ -579,160 +1869,1359 @@ impl TabManager {
 
                 self.back_button.set_sensitive(tab.webview.can_go_back());
                 self.forward_button.set_sensitive(tab.webview.can_go_forward());
+                update_refresh_button(&self.refresh_button, tab.webview.is_loading());
 
-                // Update progress bar for the switched tab
-                let is_loading = tab.webview.is_loading();
-                if is_loading {
-                    self.load_progress.set_fraction(tab.webview.estimated_load_progress());
-                    self.load_progress.set_visible(true);
-                } else {
-                    self.load_progress.set_visible(false);
+                if let Some(callback) = self.on_active_container_changed.borrow().as_ref() {
+                    callback(&tab.profile_id);
+                }
+                if let Some(callback) = self.on_security_changed.borrow().as_ref() {
+                    callback(self.security_level_of(tab));
+                }
+                if let Some(callback) = self.on_page_changed.borrow().as_ref() {
+                    callback();
                 }
+                self.notify_link_hovered(None);
+
+                // Update progress bar for the switched tab
+                self.load_progress
+                    .show_tab(tab.webview.is_loading(), tab.webview.estimated_load_progress());
             }
         }
     }
 
-    pub fn tab_count(&self) -> usize {
-        self.tabs.borrow().len()
+    /// Switch to the tab at a position in the sidebar, not counting group headers
+    pub fn switch_to_tab_at(&self, index: usize) {
+        let mut positions: Vec<(i32, u32)> = self
+            .tabs
+            .borrow()
+            .values()
+            .map(|tab| (tab.row.index(), tab.id))
+            .collect();
+        positions.sort_unstable();
+        if let Some(&(_, tab_id)) = positions.get(index) {
+            self.switch_to_tab(tab_id);
+        }
     }
 
-    pub fn navigate_to(&self, url: &Url) {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            self.navigate_to_in_tab(tab_id, url);
-        }
+    /// The first tab in the sidebar
+    fn first_tab_row(&self) -> Option<TabRow> {
+        self.tabs
+            .borrow()
+            .values()
+            .min_by_key(|tab| tab.row.index())
+            .map(|tab| tab.row.clone())
     }
 
-    fn navigate_to_in_tab(&self, tab_id: u32, url: &Url) {
-        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-            tab.webview.load_url(url);
+    /// Select the active tab's row again, when the tab list selected something else
+    pub fn select_active_tab(&self) {
+        let row = self
+            .active_tab_id
+            .get()
+            .and_then(|id| self.tabs.borrow().get(&id).map(|tab| tab.row.clone()));
+        match row {
+            Some(row) => self.tabs_list.select_child(&row),
+            None => self.tabs_list.unselect_all(),
         }
     }
 
-    pub fn reload_current(&self) {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                tab.webview.reload();
-            }
-        }
+    /// Put a tab in a new group, whose header takes the tab's place in the sidebar.
+    /// Returns the new group's ID.
+    pub fn add_tab_to_new_group(self: &Rc<Self>, tab_id: u32) -> Option<String> {
+        let index = self.tabs.borrow().get(&tab_id)?.row.index();
+        let names: Vec<String> = self.groups.borrow().values().map(TabGroup::name).collect();
+        let name = group::next_group_name(names.iter().map(String::as_str));
+        let group_id = self.create_group(None, &name, index);
+        self.add_tab_to_group(tab_id, &group_id);
+        log::debug!("Created tab group '{}' with tab {}", name, tab_id);
+        Some(group_id)
     }
 
-    pub fn go_back(&self) {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                tab.webview.go_back();
-            }
+    /// Recreate a saved group at the end of the sidebar, for session restore.
+    /// Returns the group's ID, which is the saved one unless it's taken.
+    pub fn restore_group(self: &Rc<Self>, saved: &SavedTabGroup) -> String {
+        self.create_group(Some(&saved.id), &saved.name, -1)
+    }
+
+    /// Insert a group's header into the sidebar, using `id` if it's free
+    fn create_group(self: &Rc<Self>, id: Option<&str>, name: &str, index: i32) -> String {
+        let id = match id.filter(|id| !self.groups.borrow().contains_key(*id)) {
+            Some(id) => id.to_string(),
+            None => loop {
+                let n = self.next_group_id.get();
+                self.next_group_id.set(n + 1);
+                let id = format!("group-{}", n);
+                if !self.groups.borrow().contains_key(&id) {
+                    break id;
+                }
+            },
+        };
+        let group = TabGroup::new(name);
+        self.setup_group_header(&id, group.header());
+        self.tabs_list.insert(group.header(), index);
+        self.groups.borrow_mut().insert(id.clone(), group);
+        id
+    }
+
+    /// Move a tab into a group, after the group's other tabs
+    pub fn add_tab_to_group(&self, tab_id: u32, group_id: &str) {
+        let Some(row) = self.tabs.borrow().get(&tab_id).map(|tab| tab.row.clone()) else {
+            return;
+        };
+        let previous = row.group_id();
+        if !self.groups.borrow().contains_key(group_id) || previous.as_deref() == Some(group_id) {
+            return;
+        }
+
+        let index = self.group_end_index(group_id);
+        self.move_row(&row, index);
+        row.set_group(Some(group_id));
+        row.set_visible(!self.is_group_collapsed(group_id));
+        if let Some(previous) = previous {
+            self.remove_group_if_empty(&previous);
         }
+        self.notify_session_changed();
     }
 
-    pub fn go_forward(&self) {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                tab.webview.go_forward();
-            }
+    /// Take a tab out of its group, placing it just after the group
+    pub fn remove_tab_from_group(&self, tab_id: u32) {
+        let Some(row) = self.tabs.borrow().get(&tab_id).map(|tab| tab.row.clone()) else {
+            return;
+        };
+        let Some(group_id) = row.group_id() else {
+            return;
+        };
+
+        let index = self.group_end_index(&group_id);
+        row.set_group(None);
+        row.set_visible(true);
+        self.move_row(&row, index);
+        self.remove_group_if_empty(&group_id);
+        self.notify_session_changed();
+    }
+
+    pub fn rename_group(&self, group_id: &str, name: &str) {
+        if let Some(group) = self.groups.borrow().get(group_id) {
+            group.set_name(name);
         }
+        self.notify_session_changed();
     }
 
-    pub fn can_go_back(&self) -> bool {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                return tab.webview.can_go_back();
+    fn prompt_rename_group(self: &Rc<Self>, group_id: &str) {
+        let name = self.groups.borrow().get(group_id).map(TabGroup::name);
+        let (Some(window), Some(name)) = (self.window.upgrade(), name) else {
+            return;
+        };
+        let manager_weak = Rc::downgrade(self);
+        let id = group_id.to_string();
+        group::prompt_rename(&window, &name, move |name| {
+            if let Some(manager) = manager_weak.upgrade() {
+                manager.rename_group(&id, &name);
             }
-        }
-        false
+        });
     }
 
-    pub fn can_go_forward(&self) -> bool {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                return tab.webview.can_go_forward();
+    fn is_group_collapsed(&self, group_id: &str) -> bool {
+        self.groups.borrow().get(group_id).is_some_and(TabGroup::is_collapsed)
+    }
+
+    /// Show or hide a group's tabs; hidden tabs keep their pages loaded
+    pub fn set_group_collapsed(&self, group_id: &str, collapsed: bool) {
+        match self.groups.borrow().get(group_id) {
+            Some(group) if group.is_collapsed() != collapsed => group.set_collapsed(collapsed),
+            _ => return,
+        }
+        for tab in self.tabs.borrow().values() {
+            if tab.row.group_id().as_deref() == Some(group_id) {
+                tab.row.set_visible(!collapsed);
             }
         }
-        false
+        self.notify_session_changed();
     }
 
-    pub fn current_url(&self) -> Option<Url> {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                return tab.webview.current_url();
+    /// Close a group and all its tabs, each once its page agrees to be left.
+    /// Pinned tabs in it are protected as when closed on their own.
+    pub fn close_group(self: &Rc<Self>, group_id: &str) {
+        let mut members: Vec<(i32, u32)> = self
+            .tabs
+            .borrow()
+            .values()
+            .filter(|tab| tab.row.group_id().as_deref() == Some(group_id))
+            .map(|tab| (tab.row.index(), tab.id))
+            .collect();
+        members.sort_unstable();
+
+        let tab_ids = members.into_iter().map(|(_, tab_id)| tab_id).collect();
+        self.close_guarding_pinned(tab_ids, |manager, tab_ids| {
+            // Don't leave the window without tabs
+            if tab_ids.len() == manager.tab_count() {
+                manager.new_tab(None, None);
+            }
+            // The header goes with the group's last tab
+            for tab_id in tab_ids {
+                manager.try_close_tab(tab_id);
             }
+        });
+    }
+
+    /// Remove a group's header once its last tab has left
+    fn remove_group_if_empty(&self, group_id: &str) {
+        let has_members = self
+            .tabs
+            .borrow()
+            .values()
+            .any(|tab| tab.row.group_id().as_deref() == Some(group_id));
+        if has_members {
+            return;
+        }
+        if let Some(group) = self.groups.borrow_mut().remove(group_id) {
+            self.tabs_list.remove(group.header());
         }
-        None
     }
 
-    pub fn current_title(&self) -> Option<String> {
-        if let Some(tab_id) = self.active_tab_id.get() {
-            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
-                return tab.webview.title().map(|s| s.to_string());
+    /// Sidebar position just past a group's header and tabs
+    fn group_end_index(&self, group_id: &str) -> i32 {
+        let Some(mut index) = self.groups.borrow().get(group_id).map(|g| g.header().index() + 1) else {
+            return -1;
+        };
+        while let Some(row) = self.tabs_list.child_at_index(index).and_downcast::<TabRow>() {
+            if row.group_id().as_deref() != Some(group_id) {
+                break;
             }
+            index += 1;
         }
-        None
+        index
     }
 
-    /// Get all tabs with their URLs and titles for session saving
-    /// Returns (url, title) pairs in tab order, plus the active tab index
-    pub fn get_all_tabs(&self) -> (Vec<(String, Option<String>)>, usize) {
-        let tabs = self.tabs.borrow();
-        let active_id = self.active_tab_id.get();
+    /// Move a tab's row to a sidebar position, counted before the move
+    fn move_row(&self, row: &TabRow, index: i32) {
+        let current = row.index();
+        let index = if current < index { index - 1 } else { index };
+        if current == index {
+            return;
+        }
+        self.tabs_list.remove(row);
+        self.tabs_list.insert(row, index);
+        if self.active_tab_id.get() == Some(row.tab_id()) {
+            self.tabs_list.select_child(row);
+        }
+    }
 
-        // Collect tabs with their IDs for sorting
-        let mut tab_data: Vec<(u32, String, Option<String>)> = tabs
+    /// Groups as (ID, name) in sidebar order
+    fn group_names(&self) -> Vec<(String, String)> {
+        let groups = self.groups.borrow();
+        let mut names: Vec<(i32, String, String)> = groups
             .iter()
-            .filter_map(|(&id, tab)| {
-                tab.webview.uri().map(|uri| {
-                    let title = tab.webview.title().map(|t| t.to_string());
-                    (id, uri.to_string(), title)
-                })
-            })
+            .map(|(id, group)| (group.header().index(), id.clone(), group.name()))
             .collect();
+        names.sort_unstable();
+        names.into_iter().map(|(_, id, name)| (id, name)).collect()
+    }
 
-        // Sort by tab ID to maintain order
-        tab_data.sort_by_key(|(id, _, _)| *id);
-
-        // Find active tab index
-        let active_index = tab_data
-            .iter()
-            .position(|(id, _, _)| Some(*id) == active_id)
-            .unwrap_or(0);
-
-        let tabs_vec: Vec<(String, Option<String>)> = tab_data
+    /// The window's tab groups for session saving, in sidebar order
+    pub fn saved_groups(&self) -> Vec<SavedTabGroup> {
+        let groups = self.groups.borrow();
+        self.group_names()
             .into_iter()
-            .map(|(_, url, title)| (url, title))
-            .collect();
-
-        (tabs_vec, active_index)
+            .map(|(id, name)| SavedTabGroup {
+                collapsed: groups.get(&id).is_some_and(TabGroup::is_collapsed),
+                id,
+                name,
+            })
+            .collect()
     }
 
-    /// Get all tabs with their container info (for recording closed windows).
-    /// Returns (url, title, container) tuples and active tab index.
-    pub fn get_all_tabs_with_containers(&self) -> (Vec<(String, Option<String>, String)>, usize) {
+    /// Update the settings of every open tab, for preferences that can change live.
+    /// Hibernated tabs get the current preferences when their webview is recreated.
+    fn for_each_webview_settings(&self, apply: impl Fn(&webkit6::Settings)) {
+        for tab in self.tabs.borrow().values() {
+            if let Some(settings) = WebViewExt::settings(&tab.webview) {
+                apply(&settings);
+            }
+        }
+    }
+
+    /// Lay the tab list out as a horizontal strip instead of a vertical sidebar
+    pub fn set_horizontal_tabs(&self, horizontal: bool) {
+        // With one tab per line, a vertical FlowBox puts every tab on a single row
+        self.tabs_list.set_orientation(if horizontal {
+            gtk4::Orientation::Vertical
+        } else {
+            gtk4::Orientation::Horizontal
+        });
+        for tab in self.tabs.borrow().values() {
+            tab.row.set_horizontal(horizontal);
+        }
+    }
+
+    fn horizontal_tabs(&self) -> bool {
+        self.tabs_list.orientation() == gtk4::Orientation::Vertical
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.borrow().len()
+    }
+
+    pub fn navigate_to(&self, url: &Url) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            self.navigate_to_in_tab(tab_id, url);
+        }
+    }
+
+    fn navigate_to_in_tab(&self, tab_id: u32, url: &Url) {
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            tab.webview.load_url(&without_tracking_params(url, true));
+        }
+    }
+
+    /// The active tab's address to copy, cleaned of tracking parameters unless
+    /// that's turned off
+    pub fn current_url_for_copy(&self) -> Option<Url> {
+        self.current_url().map(|url| without_tracking_params(&url, false))
+    }
+
+    /// Query the current scroll offset of every tab so it can be saved with the session
+    pub fn capture_scroll_positions(&self) {
+        for tab in self.tabs.borrow().values() {
+            if tab.scroll.restore_pending.get() {
+                continue;
+            }
+            let scroll = Rc::clone(&tab.scroll);
+            tab.webview.evaluate_javascript(
+                "window.scrollY",
+                None,
+                None,
+                None::<&gio::Cancellable>,
+                move |result| {
+                    // A restore may have been scheduled while the query was in flight
+                    if scroll.restore_pending.get() {
+                        return;
+                    }
+                    match result {
+                        Ok(value) if value.is_number() => scroll.offset.set(Some(value.to_double())),
+                        Ok(_) => {}
+                        Err(e) => log::debug!("Failed to read scroll position: {}", e),
+                    }
+                },
+            );
+        }
+    }
+
+    /// Scroll a restored tab back to a saved offset once its page finishes loading.
+    /// The offset is applied once, and not at all if the user has already scrolled.
+    pub fn restore_scroll_position(&self, tab_id: u32, offset: f64) {
+        if offset <= 0.0 {
+            return;
+        }
+        let tabs = self.tabs.borrow();
+        let Some(tab) = tabs.get(&tab_id) else {
+            return;
+        };
+
+        tab.scroll.offset.set(Some(offset));
+        tab.scroll.restore_pending.set(true);
+
+        let scroll = Rc::clone(&tab.scroll);
+        let handler_id: Rc<RefCell<Option<glib::SignalHandlerId>>> = Rc::new(RefCell::new(None));
+        let handler_id_inner = Rc::clone(&handler_id);
+        let id = tab.webview.connect_load_changed(move |wv, event| {
+            if event != webkit6::LoadEvent::Finished {
+                return;
+            }
+            // One-shot: later navigations in this tab must not jump around
+            if let Some(id) = handler_id_inner.borrow_mut().take() {
+                wv.disconnect(id);
+            }
+            scroll.restore_pending.set(false);
+
+            wv.evaluate_javascript(
+                &scroll_restore_script(offset),
+                None,
+                None,
+                None::<&gio::Cancellable>,
+                |result| {
+                    if let Err(e) = result {
+                        log::debug!("Failed to restore scroll position: {}", e);
+                    }
+                },
+            );
+        });
+        *handler_id.borrow_mut() = Some(id);
+    }
+
+    /// Show or close the web inspector of the active tab.
+    /// Each tab's webview has its own inspector, so this follows tab switches.
+    pub fn toggle_inspector(&self) {
+        let Some(tab_id) = self.active_tab_id.get() else {
+            return;
+        };
+        let tabs = self.tabs.borrow();
+        let Some(inspector) = tabs.get(&tab_id).and_then(|tab| tab.webview.inspector()) else {
+            return;
+        };
+
+        // The inspector only has a web view of its own while it's open
+        if inspector.web_view().is_some() {
+            inspector.close();
+        } else {
+            inspector.show();
+        }
+    }
+
+    /// Show the system print dialog for the active tab.
+    /// A page that's still loading prints as currently rendered.
+    pub fn print(&self) {
+        let Some(tab_id) = self.active_tab_id.get() else {
+            return;
+        };
+        let tabs = self.tabs.borrow();
+        let Some(tab) = tabs.get(&tab_id) else {
+            return;
+        };
+        if tab.webview.is_loading() {
+            log::info!("Printing tab {} while it's still loading", tab_id);
+        }
+
+        let operation = webkit6::PrintOperation::new(&tab.webview);
+        operation.connect_failed(|_, error| {
+            log::warn!("Printing failed: {}", error);
+        });
+        operation.run_dialog(self.window.upgrade().as_ref());
+    }
+
+    /// Whether any tab is playing audio
+    pub fn is_media_playing(&self) -> bool {
+        self.tabs.borrow().values().any(|tab| tab.webview.is_playing_audio())
+    }
+
+    fn is_playing_audio(&self, tab_id: u32) -> bool {
+        self.tabs
+            .borrow()
+            .get(&tab_id)
+            .is_some_and(|tab| tab.webview.is_playing_audio())
+    }
+
+    /// Pause the video and audio elements of a tab's page. Media in frames from
+    /// other sites, such as embedded players, is out of reach and keeps playing.
+    pub fn pause_media(&self, tab_id: u32) {
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            pause_webview_media(&tab.webview);
+        }
+    }
+
+    /// Pause the video and audio elements of every tab's page
+    pub fn pause_all_media(&self) {
+        for tab in self.tabs.borrow().values() {
+            if !tab.is_suspended() {
+                pause_webview_media(&tab.webview);
+            }
+        }
+    }
+
+    /// Export the active tab to a PDF file without showing the print dialog.
+    /// `on_done` is called once the file has been written or the export
+    /// failed, including when there's no active tab.
+    pub fn export_pdf<F: Fn(Result<(), String>) + 'static>(&self, path: &Path, on_done: F) {
+        let tabs = self.tabs.borrow();
+        let Some(tab) = self.active_tab_id.get().and_then(|tab_id| tabs.get(&tab_id)) else {
+            on_done(Err("No page to export".to_string()));
+            return;
+        };
+
+        // An output URI sends the job to GTK's file backend, whatever its printer is called
+        let print_settings = gtk4::PrintSettings::new();
+        print_settings.set(gtk4::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+        print_settings.set(
+            gtk4::PRINT_SETTINGS_OUTPUT_URI,
+            Some(gio::File::for_path(path).uri().as_str()),
+        );
+
+        let operation = webkit6::PrintOperation::new(&tab.webview);
+        operation.set_print_settings(&print_settings);
+
+        let on_done = Rc::new(on_done);
+        let on_failed = Rc::clone(&on_done);
+        // "failed" is followed by "finished", so only report the first outcome
+        let reported = Rc::new(Cell::new(false));
+        let reported_failed = Rc::clone(&reported);
+        operation.connect_failed(move |_, error| {
+            log::warn!("PDF export failed: {}", error);
+            reported_failed.set(true);
+            on_failed(Err(error.to_string()));
+        });
+        operation.connect_finished(move |_| {
+            if !reported.get() {
+                on_done(Ok(()));
+            }
+        });
+        operation.print();
+    }
+
+    /// Whether the active tab shows a web page that can be saved: not an
+    /// internal page, a blank tab or an error page
+    pub fn can_save_page(&self) -> bool {
+        let Some(tab_id) = self.active_tab_id.get() else {
+            return false;
+        };
+        let tabs = self.tabs.borrow();
+        let Some(tab) = tabs.get(&tab_id) else {
+            return false;
+        };
+        if tab.is_suspended() || self.error_pages.borrow().contains_key(&tab_id) {
+            return false;
+        }
+        tab.webview
+            .uri()
+            .is_some_and(|uri| !uri.starts_with("about:") && !webview::is_internal_uri(&uri))
+    }
+
+    /// Save the active tab's page, with its resources, as a single MHTML file.
+    /// `on_done` is called once the file has been written or saving failed,
+    /// including when there's no active tab.
+    pub fn save_page<F: FnOnce(Result<(), glib::Error>) + 'static>(&self, path: &Path, on_done: F) {
+        let tabs = self.tabs.borrow();
+        let Some(tab) = self.active_tab_id.get().and_then(|tab_id| tabs.get(&tab_id)) else {
+            on_done(Err(glib::Error::new(gio::IOErrorEnum::NotFound, "No page to save")));
+            return;
+        };
+
+        tab.webview.save_to_file(
+            &gio::File::for_path(path),
+            webkit6::SaveMode::Mhtml,
+            gio::Cancellable::NONE,
+            move |result| {
+                if let Err(e) = &result {
+                    log::warn!("Saving page failed: {}", e);
+                }
+                on_done(result);
+            },
+        );
+    }
+
+    /// Snapshot the active tab's viewport or whole document
+    pub fn capture_snapshot<F>(&self, region: webkit6::SnapshotRegion, callback: F)
+    where
+        F: FnOnce(Result<gtk4::gdk::Texture, glib::Error>) + 'static,
+    {
+        let webview = self
+            .active_tab_id
+            .get()
+            .and_then(|id| self.tabs.borrow().get(&id).map(|tab| tab.webview.clone()));
+        let Some(webview) = webview else {
+            callback(Err(glib::Error::new(gio::IOErrorEnum::NotFound, "No active tab")));
+            return;
+        };
+
+        webview.snapshot(
+            region,
+            webkit6::SnapshotOptions::NONE,
+            gio::Cancellable::NONE,
+            callback,
+        );
+    }
+
+    /// Override the user agent of a single tab, or restore the default with None.
+    /// Tabs sharing a network session keep their own user agent.
+    /// The change only takes effect on the next load.
+    pub fn set_user_agent(&self, tab_id: u32, user_agent: Option<&str>) {
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            webview::apply_user_agent(&tab.webview, user_agent);
+        }
+    }
+
+    /// Switch the active tab's site to `mode`, or back to the default user agent
+    /// if it's already using it. The choice is remembered per host and the page reloads.
+    /// Returns the mode now in effect, or None if there's no site to apply it to.
+    pub fn toggle_site_user_agent(&self, mode: UserAgentMode) -> Option<Option<UserAgentMode>> {
+        let tab_id = self.active_tab_id.get()?;
+        let url = self.current_url()?;
+        let host = url.host_str()?;
+        let app = self.app.upgrade()?;
+
+        let new_mode = if app.site_user_agent(host) == Some(mode) {
+            None
+        } else {
+            Some(mode)
+        };
+        app.set_site_user_agent(host, new_mode);
+        log::info!("User agent for {} set to {:?}", host, new_mode);
+
+        self.set_user_agent(tab_id, new_mode.map(|m| m.user_agent()));
+        self.reload_current();
+        Some(new_mode)
+    }
+
+    /// The loaded profile (container) with the given name
+    fn profile(&self, profile_id: &str) -> Option<Rc<Profile>> {
+        self.app.upgrade()?.profile_manager()?.get_profile(profile_id)
+    }
+
+    /// Apply the settings the tab's container has for the origin a webview
+    /// now shows, or the defaults if it has none
+    fn apply_site_settings(&self, wv: &webkit6::WebView, profile_id: &str) {
+        let Some(profile) = self.profile(profile_id) else {
+            return;
+        };
+        let settings = wv
+            .current_url()
+            .and_then(|url| site_settings::origin_key(&url))
+            .map(|origin| profile.site_settings(&origin))
+            .unwrap_or_default();
+
+        let zoom = settings.zoom.unwrap_or(1.0);
+        if wv.zoom_level() != zoom {
+            wv.set_zoom_level(zoom);
+        }
+    }
+
+    /// Zoom the active tab one level in (`step` > 0) or out (`step` < 0), or
+    /// reset it to 100% for 0. The level is remembered for the site in the
+    /// tab's container and applied to the site's other open tabs there.
+    /// Returns the new level.
+    pub fn zoom_current(&self, step: i32) -> Option<f64> {
+        let tabs = self.tabs.borrow();
+        let active = tabs.get(&self.active_tab_id.get()?)?;
+        let webview = &active.webview;
+        let level = if step == 0 {
+            1.0
+        } else {
+            step_zoom(webview.zoom_level(), step)
+        };
+
+        let Some(origin) = webview.current_url().and_then(|url| site_settings::origin_key(&url)) else {
+            webview.set_zoom_level(level);
+            return Some(level);
+        };
+        if let Some(profile) = self.profile(&active.profile_id) {
+            let mut settings = profile.site_settings(&origin);
+            settings.zoom = (level != 1.0).then_some(level);
+            profile.set_site_settings(&origin, settings);
+        }
+        for tab in tabs.values().filter(|tab| tab.profile_id == active.profile_id) {
+            let same_site = tab.webview.current_url().and_then(|url| site_settings::origin_key(&url));
+            if same_site.as_deref() == Some(origin.as_str()) {
+                tab.webview.set_zoom_level(level);
+            }
+        }
+        Some(level)
+    }
+
+    /// Whether JavaScript is enabled on the active tab's site, None for non-web pages
+    pub fn site_javascript_enabled(&self) -> Option<bool> {
+        let url = self.current_url().filter(|url| matches!(url.scheme(), "http" | "https"))?;
+        let app = self.app.upgrade()?;
+        Some(app.site_javascript_enabled(url.host_str()?))
+    }
+
+    /// Enable or disable JavaScript on the active tab's site, reloading if it changed
+    pub fn set_site_javascript(&self, enabled: bool) {
+        if self.site_javascript_enabled() == Some(enabled) {
+            return;
+        }
+        let (Some(tab_id), Some(url), Some(app)) = (self.active_tab_id.get(), self.current_url(), self.app.upgrade()) else {
+            return;
+        };
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        app.set_site_javascript(host, enabled);
+        log::info!("JavaScript {} on {}", if enabled { "enabled" } else { "disabled" }, host);
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            webview::apply_javascript(&tab.webview, enabled);
+        }
+        self.reload_current();
+    }
+
+    /// Navigate the active tab to the configured homepage, if one is set
+    pub fn go_home(&self) {
+        if let Some(url) = configured_url("homepage") {
+            self.navigate_to(&url);
+        }
+    }
+
+    /// Start timing a tab's load, if the load timeout is enabled
+    fn start_load_timeout(self: &Rc<Self>, tab_id: u32) {
+        self.cancel_load_timeout(tab_id);
+        let seconds = self.settings.int("load-timeout-seconds");
+        if seconds <= 0 {
+            return;
+        }
+        let manager = Rc::downgrade(self);
+        let timer = glib::timeout_add_seconds_local_once(seconds as u32, move || {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            // The timer is done; don't let cancel_load_timeout() remove it again
+            manager.load_timeouts.borrow_mut().remove(&tab_id);
+            let still_loading = manager
+                .tabs
+                .borrow()
+                .get(&tab_id)
+                .is_some_and(|tab| tab.webview.is_loading());
+            if still_loading {
+                log::info!("Tab {} is still loading after {}s", tab_id, seconds);
+                if let Some(callback) = manager.on_load_timed_out.borrow().as_ref() {
+                    callback(tab_id);
+                }
+            }
+        });
+        self.load_timeouts.borrow_mut().insert(tab_id, timer);
+    }
+
+    fn cancel_load_timeout(&self, tab_id: u32) {
+        if let Some(timer) = self.load_timeouts.borrow_mut().remove(&tab_id) {
+            timer.remove();
+        }
+    }
+
+    /// Stop a tab's load, if it's still loading
+    pub fn stop_loading(&self, tab_id: u32) {
+        if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+            if tab.webview.is_loading() {
+                tab.webview.stop_loading();
+            }
+        }
+    }
+
+    /// The title shown for a tab
+    pub fn tab_title(&self, tab_id: u32) -> Option<String> {
+        self.tabs.borrow().get(&tab_id).map(|tab| tab.row.title())
+    }
+
+    /// Whether the active tab is loading a page
+    pub fn is_loading_current(&self) -> bool {
+        self.active_tab_id
+            .get()
+            .and_then(|tab_id| self.tabs.borrow().get(&tab_id).map(|tab| tab.webview.is_loading()))
+            .unwrap_or(false)
+    }
+
+    pub fn stop_current(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            self.stop_loading(tab_id);
+        }
+    }
+
+    pub fn reload_current(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                tab.webview.reload();
+            }
+        }
+    }
+
+    pub fn go_back(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                tab.webview.go_back();
+            }
+        }
+    }
+
+    pub fn go_forward(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                tab.webview.go_forward();
+            }
+        }
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                return tab.webview.can_go_back();
+            }
+        }
+        false
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                return tab.webview.can_go_forward();
+            }
+        }
+        false
+    }
+
+    /// The active tab's URL, including one not loaded yet, and its container
+    pub fn current_tab_location(&self) -> Option<(Option<Url>, String)> {
+        let tab_id = self.active_tab_id.get()?;
+        let tabs = self.tabs.borrow();
+        let tab = tabs.get(&tab_id)?;
+        let url = tab.url().and_then(|url| Url::parse(&url).ok());
+        Some((url, tab.profile_id.clone()))
+    }
+
+    /// Whether the active tab is pinned
+    pub fn is_current_tab_pinned(&self) -> bool {
+        let Some(tab_id) = self.active_tab_id.get() else {
+            return false;
+        };
+        self.tabs.borrow().get(&tab_id).is_some_and(|tab| tab.row.is_pinned())
+    }
+
+    pub fn current_url(&self) -> Option<Url> {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                return tab.webview.current_url();
+            }
+        }
+        None
+    }
+
+    /// Move keyboard focus to the active tab's page
+    pub fn focus_current_webview(&self) {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                tab.webview.grab_focus();
+            }
+        }
+    }
+
+    /// Connection security of the active tab's page
+    pub fn current_security_level(&self) -> SecurityLevel {
+        self.active_tab_id
+            .get()
+            .and_then(|tab_id| self.tabs.borrow().get(&tab_id).map(|tab| self.security_level_of(tab)))
+            .unwrap_or(SecurityLevel::Local)
+    }
+
+    /// Server certificate of the active tab's page, if it was loaded over TLS
+    pub fn current_certificate(&self) -> Option<gio::TlsCertificate> {
+        let tab_id = self.active_tab_id.get()?;
+        let tabs = self.tabs.borrow();
+        let tab = tabs.get(&tab_id)?;
+        if tab.is_suspended() {
+            return None;
+        }
+        tab.webview.tls_info().map(|(certificate, _)| certificate)
+    }
+
+    fn security_level_of(&self, tab: &Tab) -> SecurityLevel {
+        if tab.is_suspended() {
+            return SecurityLevel::Local;
+        }
+        let Some(uri) = tab.webview.uri() else {
+            return SecurityLevel::Local;
+        };
+        let tls_errors = tab.webview.tls_info().map(|(_, errors)| errors);
+        let mut level = SecurityLevel::for_page(&uri, tls_errors, self.insecure_content.borrow().contains(&tab.id));
+        let allowed = self.insecure_content_allowed.borrow().contains_key(&tab.id);
+        if level == SecurityLevel::MixedContent && self.mixed_content_blocked() && !allowed {
+            level = SecurityLevel::MixedContentBlocked;
+        }
+        // A lookalike host doesn't hide a worse connection, so report whichever is more severe
+        if Url::parse(&uri).is_ok_and(|url| url_bar::is_lookalike_host(&url)) {
+            level = level.max(SecurityLevel::LookalikeDomain);
+        }
+        level
+    }
+
+    fn mixed_content_blocked(&self) -> bool {
+        self.app
+            .upgrade()
+            .and_then(|app| app.content_filter_manager().map(|cfm| cfm.mixed_content_blocked()))
+            .unwrap_or(false)
+    }
+
+    /// Reload the active tab with its blocked insecure content. Blocking is lifted
+    /// for that tab only, until it leaves the page, so other tabs and later pages stay protected.
+    pub fn load_insecure_content(&self) {
+        let (Some(tab_id), Some(app)) = (self.active_tab_id.get(), self.app.upgrade()) else {
+            return;
+        };
+        let Some(cfm) = app.content_filter_manager() else {
+            return;
+        };
+        let Some(ucm) = self.tabs.borrow().get(&tab_id).and_then(|tab| tab.webview.user_content_manager()) else {
+            return;
+        };
+
+        log::info!("Loading insecure content in tab {}", tab_id);
+        cfm.set_mixed_content_allowed(&ucm, true);
+        self.insecure_content_allowed
+            .borrow_mut()
+            .insert(tab_id, InsecureContentLoad::Reloading);
+        self.reload_current();
+    }
+
+    /// Note that a tab's reload with insecure content is done; blocking stays lifted
+    /// for the page's later loads
+    fn finish_insecure_content_load(&self, tab_id: u32) {
+        if let Some(load) = self.insecure_content_allowed.borrow_mut().get_mut(&tab_id) {
+            *load = InsecureContentLoad::Loaded;
+        }
+    }
+
+    /// Block mixed content in a tab again, once it leaves the page it was allowed on
+    fn end_insecure_content_load(&self, tab_id: u32, webview: &webkit6::WebView) {
+        if self.insecure_content_allowed.borrow_mut().remove(&tab_id).is_none() {
+            return;
+        }
+        let Some(ucm) = webview.user_content_manager() else {
+            return;
+        };
+        if let Some(cfm) = self.app.upgrade().as_ref().and_then(|app| app.content_filter_manager()) {
+            cfm.set_mixed_content_allowed(&ucm, false);
+        }
+    }
+
+    /// Tell the window about a security change, if it's on the active tab
+    fn notify_security_changed(&self, tab_id: u32) {
+        if self.active_tab_id.get() != Some(tab_id) {
+            return;
+        }
+        let level = self.current_security_level();
+        if let Some(callback) = self.on_security_changed.borrow().as_ref() {
+            callback(level);
+        }
+    }
+
+    /// Tell the window the active tab's page changed, if it's the active tab
+    fn notify_page_changed(&self, tab_id: u32) {
+        if self.active_tab_id.get() != Some(tab_id) {
+            return;
+        }
+        if let Some(callback) = self.on_page_changed.borrow().as_ref() {
+            callback();
+        }
+    }
+
+    /// Network session of the active tab, identifying its container
+    pub fn current_network_session(&self) -> Option<NetworkSession> {
+        let tab_id = self.active_tab_id.get()?;
+        self.tabs.borrow().get(&tab_id)?.webview.network_session()
+    }
+
+    pub fn current_title(&self) -> Option<String> {
+        if let Some(tab_id) = self.active_tab_id.get() {
+            if let Some(tab) = self.tabs.borrow().get(&tab_id) {
+                return tab.webview.title().map(|s| s.to_string());
+            }
+        }
+        None
+    }
+
+    /// Free a background tab's webview, keeping its row, title and favicon.
+    /// The tab's page is reloaded in a fresh webview the next time it's shown.
+    /// Returns false if the tab is active, already suspended, or has no page.
+    pub fn hibernate_tab(self: &Rc<Self>, tab_id: u32) -> bool {
+        if self.active_tab_id.get() == Some(tab_id) {
+            return false;
+        }
+
+        let (old_webview, url, profile_id, row, scroll) = {
+            let tabs = self.tabs.borrow();
+            let Some(tab) = tabs.get(&tab_id) else {
+                return false;
+            };
+            if tab.is_suspended() {
+                return false;
+            }
+            let Some(url) = tab.webview.uri().and_then(|uri| Url::parse(&uri).ok()) else {
+                return false;
+            };
+            (tab.webview.clone(), url, tab.profile_id.clone(), tab.row.clone(), Rc::clone(&tab.scroll))
+        };
+
+        // The page is loaded again on waking, with insecure content blocked
+        self.end_insecure_content_load(tab_id, &old_webview);
+
+        // An unloaded webview doesn't hold a web process, so swapping it in frees the page
+        let network_session = old_webview
+            .network_session()
+            .unwrap_or_else(|| self.network_session.clone());
+        let user_content_manager = self.content_managers.as_ref().map(|m| m.for_webview(&profile_id));
+        let webview = webview::create_webview(&network_session, user_content_manager.as_ref());
+        self.setup_webview_signals(tab_id, &webview, &row, &profile_id, &scroll);
+
+        self.content_stack.remove(&old_webview);
+        self.content_stack.add_named(&webview, Some(&tab_id.to_string()));
+
+        if let Some(tab) = self.tabs.borrow_mut().get_mut(&tab_id) {
+            tab.webview = webview;
+            tab.pending_url.defer(url);
+        }
+        row.set_loading(false);
+
+        // Come back to the same spot when the tab is woken
+        if let Some(offset) = scroll.offset.get() {
+            self.restore_scroll_position(tab_id, offset);
+        }
+
+        log::info!("Hibernated tab {}", tab_id);
+        true
+    }
+
+    /// Hibernate background tabs that haven't been shown for at least `idle`.
+    /// Tabs that are playing audio are left alone.
+    pub fn hibernate_idle_tabs(self: &Rc<Self>, idle: Duration) {
+        let active_id = self.active_tab_id.get();
+        let idle_tabs: Vec<u32> = self
+            .tabs
+            .borrow()
+            .values()
+            .filter(|tab| {
+                Some(tab.id) != active_id
+                    && !tab.is_suspended()
+                    && !tab.webview.is_playing_audio()
+                    && tab.last_active.get().elapsed() >= idle
+            })
+            .map(|tab| tab.id)
+            .collect();
+
+        for tab_id in idle_tabs {
+            self.hibernate_tab(tab_id);
+        }
+    }
+
+    /// Number of tabs whose page hasn't been loaded yet
+    pub fn suspended_tab_count(&self) -> usize {
+        self.tabs.borrow().values().filter(|tab| tab.is_suspended()).count()
+    }
+
+    /// Get all tabs with their URLs and titles for session saving
+    /// Returns (url, title) pairs in tab order, plus the active tab index
+    pub fn get_all_tabs(&self) -> (Vec<(String, Option<String>)>, usize) {
+        let (tabs, active_index) = self.get_saved_tabs();
+        let tabs_vec = tabs.into_iter().map(|saved| (saved.url, saved.title)).collect();
+        (tabs_vec, active_index)
+    }
+
+    /// Get all tabs as session entries, including container and scroll offset.
+    /// Returns saved tabs in tab order, plus the active tab index.
+    pub fn get_saved_tabs(&self) -> (Vec<SavedTab>, usize) {
         let tabs = self.tabs.borrow();
         let active_id = self.active_tab_id.get();
 
         // Collect tabs with their IDs for sorting
-        let mut tab_data: Vec<(u32, String, Option<String>, String)> = tabs
+        let mut tab_data: Vec<(u32, SavedTab)> = tabs
             .iter()
             .filter_map(|(&id, tab)| {
-                tab.webview.uri().map(|uri| {
-                    let title = tab.webview.title().map(|t| t.to_string());
-                    (id, uri.to_string(), title, tab.profile_id.clone())
+                tab.url().map(|url| {
+                    let saved = SavedTab {
+                        url,
+                        title: tab.title(),
+                        container: Some(tab.profile_id.clone()),
+                        scroll_y: tab.scroll.offset.get().filter(|y| *y > 0.0),
+                        group: tab.row.group_id(),
+                        pinned: tab.row.is_pinned(),
+                    };
+                    (id, saved)
                 })
             })
             .collect();
 
-        // Sort by tab ID to maintain order
-        tab_data.sort_by_key(|(id, _, _, _)| *id);
+        // Sort by sidebar position, since grouping tabs and opening links moves them
+        tab_data.sort_by_key(|(id, _)| tabs.get(id).map_or(i32::MAX, |tab| tab.row.index()));
 
         // Find active tab index
         let active_index = tab_data
             .iter()
-            .position(|(id, _, _, _)| Some(*id) == active_id)
+            .position(|(id, _)| Some(*id) == active_id)
             .unwrap_or(0);
 
-        let tabs_vec: Vec<(String, Option<String>, String)> = tab_data
-            .into_iter()
-            .map(|(_, url, title, container)| (url, title, container))
+        let tabs_vec = tab_data.into_iter().map(|(_, saved)| saved).collect();
+
+        (tabs_vec, active_index)
+    }
+
+    /// All tabs in sidebar order, for the tab search
+    pub fn tab_summaries(&self) -> Vec<TabSummary> {
+        let tabs = self.tabs.borrow();
+        let mut summaries: Vec<(i32, TabSummary)> = tabs
+            .iter()
+            .map(|(&tab_id, tab)| {
+                let url = tab.url().unwrap_or_default();
+                let summary = TabSummary {
+                    tab_id,
+                    title: tab.title().filter(|t| !t.is_empty()).unwrap_or_else(|| url.clone()),
+                    url: display_uri(&url).to_string(),
+                    container: tab.profile_id.clone(),
+                };
+                (tab.row.index(), summary)
+            })
             .collect();
+        summaries.sort_by_key(|(index, _)| *index);
+        summaries.into_iter().map(|(_, summary)| summary).collect()
+    }
 
+    /// Whether any tab belongs to the container
+    pub fn has_tabs_in_container(&self, container_id: &str) -> bool {
+        self.tabs.borrow().values().any(|tab| tab.profile_id == container_id)
+    }
+
+    /// Get all tabs with their container info (for recording closed windows).
+    /// Returns (url, title, container) tuples and active tab index.
+    pub fn get_all_tabs_with_containers(&self) -> (Vec<(String, Option<String>, String)>, usize) {
+        let (tabs, active_index) = self.get_saved_tabs();
+        let tabs_vec = tabs
+            .into_iter()
+            .map(|saved| (saved.url, saved.title, saved.container.unwrap_or_default()))
+            .collect();
         (tabs_vec, active_index)
     }
 }
+
+/// Replace a failed load with the error page, keeping the failing URI in the URL bar.
+/// Returns false for failures that aren't worth a page (downloads, stopped loads).
+fn show_error_page(webview: &webkit6::WebView, failing_uri: &str, error: &glib::Error, offline: bool) -> bool {
+    if error.matches(webkit6::NetworkError::Cancelled) || error.kind::<webkit6::PolicyError>().is_some() {
+        return false;
+    }
+
+    log::info!("Load failed for {}: {}", failing_uri, error);
+    let failure = if offline {
+        LoadFailure::Offline
+    } else {
+        LoadFailure::from_error(error)
+    };
+    let page = error_page::error_page(failing_uri, &failure);
+    webview.load_alternate_html(&page, failing_uri, None);
+    true
+}
+
+/// Whether JavaScript should run for a page. Only web pages follow the
+/// per-site setting; internal pages such as the new tab page need it.
+fn javascript_enabled_for(app: &QayeqApplication, uri: Option<&str>) -> bool {
+    let Some(url) = uri.and_then(|uri| Url::parse(uri).ok()) else {
+        return true;
+    };
+    match (url.scheme(), url.host_str()) {
+        ("http" | "https", Some(host)) => app.site_javascript_enabled(host),
+        _ => true,
+    }
+}
+
+/// URI to show in the URL bar; the new tab page shows an empty bar
+fn display_uri(uri: &str) -> &str {
+    if uri == crate::config::NEW_TAB_URL {
+        ""
+    } else {
+        uri
+    }
+}
+
+/// Text for the URL bar; lookalike hosts are shown in their punycode form
+fn url_bar_text(uri: &str) -> String {
+    match Url::parse(uri) {
+        Ok(url) if url_bar::is_lookalike_host(&url) => url.to_string(),
+        _ => display_uri(uri).to_string(),
+    }
+}
+
+/// Read a page URL from settings. Empty values and about:blank mean no page.
+fn configured_url(key: &str) -> Option<Url> {
+    let settings = gio::Settings::new(crate::config::APP_ID);
+    let value = settings.string(key);
+    let value = value.trim();
+    if value.is_empty() || value == "about:blank" {
+        return None;
+    }
+
+    if let Ok(url) = Url::parse(value) {
+        return Some(url);
+    }
+    // Allow bare domains like "example.com"
+    match url_bar::parse_input(value) {
+        url_bar::UrlBarInput::Url(url) => Some(url),
+        url_bar::UrlBarInput::Search(_) => {
+            log::warn!("Ignoring invalid '{}' setting: {}", key, value);
+            None
+        }
+    }
+}
+
+/// Show the favicon cached for a page in the favicon database, if any
+fn load_cached_favicon(webview: &webkit6::WebView, url: &Url, row: &TabRow) {
+    let Some(database) = webview
+        .network_session()
+        .and_then(|session| session.website_data_manager())
+        .and_then(|manager| manager.favicon_database())
+    else {
+        return;
+    };
+
+    let row = row.clone();
+    database.favicon(url.as_str(), None::<&gio::Cancellable>, move |result| {
+        if let Ok(texture) = result {
+            row.set_favicon(Some(&texture));
+        }
+    });
+}
+
+/// Build a script that scrolls to `offset` once the document is tall enough.
+/// Gives up if the user scrolls first, so late-loading content isn't fought over.
+fn scroll_restore_script(offset: f64) -> String {
+    format!(
+        r#"(function(y) {{
+    var attempts = 0;
+    function apply() {{
+        if (window.scrollY !== 0) return;
+        var maxScroll = document.documentElement.scrollHeight - window.innerHeight;
+        if (maxScroll >= y || attempts >= 20) {{
+            window.scrollTo(0, y);
+            return;
+        }}
+        attempts++;
+        setTimeout(apply, 250);
+    }}
+    apply();
+}})({});"#,
+        offset
+    )
+}
+
+/// Run the pause media script in a page
+fn pause_webview_media(webview: &webkit6::WebView) {
+    webview.evaluate_javascript(
+        PAUSE_MEDIA_SCRIPT,
+        None,
+        None,
+        None::<&gio::Cancellable>,
+        |result| {
+            if let Err(e) = result {
+                log::debug!("Failed to pause media: {}", e);
+            }
+        },
+    );
+}
+
+/// Show the refresh button as a stop button while the active tab is loading
+fn update_refresh_button(button: &gtk4::Button, loading: bool) {
+    if loading {
+        button.set_icon_name("process-stop-symbolic");
+        button.set_tooltip_text(Some("Stop"));
+    } else {
+        button.set_icon_name("view-refresh-symbolic");
+        button.set_tooltip_text(Some("Refresh"));
+    }
+}
+
+/// Pop up a context menu at a point in a sidebar row
+fn popup_menu_at(widget: &impl IsA<gtk4::Widget>, menu: &gio::Menu, x: f64, y: f64) {
+    let popover = gtk4::PopoverMenu::from_model(Some(menu));
+    popover.set_parent(widget);
+    popover.set_has_arrow(false);
+    popover.set_pointing_to(Some(&gtk4::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+    popover.connect_closed(|popover| {
+        // Unparent once the menu item's action has run
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+    popover.popup();
+}
+
+/// Position after the parent at `parent_index` and the run of rows right after
+/// it that it opened. `parents` has each sidebar row's parent tab, in order.
+fn index_after_children(parents: &[Option<u32>], parent_index: usize, parent_id: u32) -> usize {
+    let mut index = parent_index + 1;
+    while parents.get(index).copied().flatten() == Some(parent_id) {
+        index += 1;
+    }
+    index.min(parents.len())
+}
+
+/// `url` without tracking parameters, if the preference strips them when
+/// navigating (`navigating`) or when copying
+fn without_tracking_params(url: &Url, navigating: bool) -> Url {
+    let settings = gio::Settings::new(crate::config::APP_ID);
+    let strip = match settings.string("strip-tracking-parameters").as_str() {
+        "always" => true,
+        "copy" => !navigating,
+        _ => false,
+    };
+    if !strip {
+        return url.clone();
+    }
+    let patterns = url_bar::tracking_parameters(&settings.string("extra-tracking-parameters"));
+    url_bar::strip_tracking_params(url, &patterns)
+}
+
+/// The next zoom level from `current`: the closest larger one in
+/// `config::ZOOM_LEVELS` for a positive step, the closest smaller one for a
+/// negative step, stopping at either end
+fn step_zoom(current: f64, step: i32) -> f64 {
+    let levels = crate::config::ZOOM_LEVELS;
+    // Levels are rounded, so treat a stored 0.6666 as 0.67
+    let epsilon = 0.01;
+    if step > 0 {
+        levels
+            .iter()
+            .copied()
+            .find(|level| *level > current + epsilon)
+            .unwrap_or(levels[levels.len() - 1])
+    } else {
+        levels
+            .iter()
+            .rev()
+            .copied()
+            .find(|level| *level < current - epsilon)
+            .unwrap_or(levels[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_from_one_tab_stack_in_click_order() {
+        // Tabs 1, 2, 3 with no parents; open two links from tab 1
+        let mut parents: Vec<Option<u32>> = vec![None, None, None];
+        let first = index_after_children(&parents, 0, 1);
+        assert_eq!(first, 1);
+        parents.insert(first, Some(1));
+        let second = index_after_children(&parents, 0, 1);
+        assert_eq!(second, 2);
+        parents.insert(second, Some(1));
+        assert_eq!(parents, vec![None, Some(1), Some(1), None, None]);
+
+        // A link from the last tab goes at the end
+        assert_eq!(index_after_children(&parents, 4, 3), 5);
+    }
+
+    #[test]
+    fn test_only_the_active_restored_tab_loads() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let saved = ["https://one.example/", "https://two.example/", "https://three.example/", "not a url"];
+        let pages: Vec<RestoredPage> = saved
+            .iter()
+            .enumerate()
+            .map(|(index, saved_url)| RestoredPage::for_saved(saved_url, index == 1))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![
+                RestoredPage::Defer(url("https://one.example/")),
+                RestoredPage::Load(url("https://two.example/")),
+                RestoredPage::Defer(url("https://three.example/")),
+                RestoredPage::Blank,
+            ]
+        );
+
+        // Deferred tabs keep their URL until they're selected, then load it once
+        let (first, third) = (PendingLoad::default(), PendingLoad::default());
+        first.defer(url("https://one.example/"));
+        third.defer(url("https://three.example/"));
+        assert_eq!(first.take_for_show(), Some(url("https://one.example/")));
+        assert!(!first.is_pending());
+        assert_eq!(first.take_for_show(), None);
+        assert!(third.is_pending());
+        assert_eq!(third.url(), Some(url("https://three.example/")));
+    }
+
+    #[test]
+    fn test_step_zoom() {
+        assert_eq!(step_zoom(1.0, 1), 1.1);
+        assert_eq!(step_zoom(1.0, -1), 0.9);
+        // Levels set elsewhere snap to the next one in the list
+        assert_eq!(step_zoom(1.2, 1), 1.25);
+        assert_eq!(step_zoom(1.2, -1), 1.1);
+        assert_eq!(step_zoom(0.6666, -1), 0.5);
+        // The ends hold
+        assert_eq!(step_zoom(3.0, 1), 3.0);
+        assert_eq!(step_zoom(0.3, -1), 0.3);
+    }
+}
@@ -1,5 +1,15 @@
+mod autoscroll;
+mod error_page;
+mod group;
+mod https_only;
 mod manager;
+mod popups;
+mod progress;
 mod row;
+mod search;
+mod security;
 
-pub use manager::TabManager;
+pub use manager::{TabManager, TabSummary};
 pub use row::TabRow;
+pub use search::TabSearchDialog;
+pub use security::{certificate_details, SecurityLevel};
@@ -0,0 +1,107 @@
+//! Pop-up flood protection: limit how many new windows a tab can open in a burst.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Requests closer together than this count as one burst
+pub const BURST_WINDOW: Duration = Duration::from_secs(3);
+
+/// What to do with a tab's request to open a new window
+#[derive(Debug, PartialEq)]
+pub enum PopupDecision {
+    Allow,
+    /// Suppress it; `first` is true for the first one blocked in a burst
+    Block { first: bool },
+}
+
+#[derive(Debug)]
+struct Burst {
+    count: u32,
+    last: Instant,
+    blocked: bool,
+}
+
+/// Tracks new-window requests per originating tab
+#[derive(Debug, Default)]
+pub struct PopupLimiter {
+    bursts: HashMap<u32, Burst>,
+    /// Tabs the user allowed to open pop-ups freely
+    allowed: HashSet<u32>,
+}
+
+impl PopupLimiter {
+    /// Decide on a new-window request from `tab_id`, allowing at most `limit` per burst
+    pub fn request(&mut self, tab_id: u32, limit: u32, now: Instant) -> PopupDecision {
+        if self.allowed.contains(&tab_id) {
+            return PopupDecision::Allow;
+        }
+
+        let burst = self.bursts.entry(tab_id).or_insert(Burst {
+            count: 0,
+            last: now,
+            blocked: false,
+        });
+        // A quiet period starts a new burst
+        if now.duration_since(burst.last) > BURST_WINDOW {
+            burst.count = 0;
+            burst.blocked = false;
+        }
+        burst.last = now;
+        burst.count += 1;
+
+        if burst.count <= limit {
+            return PopupDecision::Allow;
+        }
+        let first = !burst.blocked;
+        burst.blocked = true;
+        PopupDecision::Block { first }
+    }
+
+    /// Let a tab open pop-ups without limit
+    pub fn allow(&mut self, tab_id: u32) {
+        self.bursts.remove(&tab_id);
+        self.allowed.insert(tab_id);
+    }
+
+    /// Forget a closed tab
+    pub fn remove(&mut self, tab_id: u32) {
+        self.bursts.remove(&tab_id);
+        self.allowed.remove(&tab_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_after_limit_until_quiet() {
+        let mut limiter = PopupLimiter::default();
+        let start = Instant::now();
+        let soon = start + Duration::from_millis(100);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.request(1, 3, soon), PopupDecision::Allow);
+        }
+        assert_eq!(limiter.request(1, 3, soon), PopupDecision::Block { first: true });
+        assert_eq!(limiter.request(1, 3, soon), PopupDecision::Block { first: false });
+        // Other tabs have their own count
+        assert_eq!(limiter.request(2, 3, soon), PopupDecision::Allow);
+
+        let later = soon + BURST_WINDOW + Duration::from_secs(1);
+        assert_eq!(limiter.request(1, 3, later), PopupDecision::Allow);
+    }
+
+    #[test]
+    fn test_allowed_tab_is_not_limited() {
+        let mut limiter = PopupLimiter::default();
+        let now = Instant::now();
+
+        assert_eq!(limiter.request(1, 0, now), PopupDecision::Block { first: true });
+        limiter.allow(1);
+        assert_eq!(limiter.request(1, 0, now), PopupDecision::Allow);
+
+        limiter.remove(1);
+        assert_eq!(limiter.request(1, 0, now), PopupDecision::Block { first: true });
+    }
+}
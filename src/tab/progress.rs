@@ -0,0 +1,172 @@
+//! Page load progress bar.
+//!
+//! WebKit reports progress in a few coarse jumps, so the bar eases toward
+//! each new value on the frame clock instead of snapping to it. Until the
+//! first response is committed there's nothing meaningful to show, so the bar
+//! pulses instead.
+
+use gtk4::prelude::*;
+use gtk4::glib;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Time between pulses before the page is committed
+const PULSE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How much of the remaining distance to the target is covered per second
+const EASE_RATE: f64 = 8.0;
+
+/// Distance to the target below which the bar just settles on it
+const SETTLE_DISTANCE: f64 = 0.001;
+
+#[derive(Default)]
+struct ProgressState {
+    /// The fraction shown, tracked here since pulsing leaves the bar's own fraction meaningless
+    current: Cell<f64>,
+    target: Cell<f64>,
+    /// Frame time of the previous animation step, in microseconds
+    last_frame: Cell<Option<i64>>,
+    tick: RefCell<Option<gtk4::TickCallbackId>>,
+    pulse: RefCell<Option<glib::SourceId>>,
+}
+
+impl ProgressState {
+    fn stop_tick(&self) {
+        self.last_frame.set(None);
+        if let Some(tick) = self.tick.borrow_mut().take() {
+            tick.remove();
+        }
+    }
+
+    fn stop_pulse(&self) {
+        if let Some(pulse) = self.pulse.borrow_mut().take() {
+            pulse.remove();
+        }
+    }
+}
+
+/// The window's load progress bar, shared by all tabs but showing the active one
+#[derive(Clone)]
+pub struct LoadProgress {
+    bar: gtk4::ProgressBar,
+    state: Rc<ProgressState>,
+}
+
+impl LoadProgress {
+    pub fn new(bar: gtk4::ProgressBar) -> Self {
+        Self {
+            bar,
+            state: Rc::default(),
+        }
+    }
+
+    /// Pulse until the load reports real progress
+    pub fn start(&self) {
+        self.state.stop_tick();
+        self.state.current.set(0.0);
+        self.state.target.set(0.0);
+        self.bar.set_visible(true);
+        if self.state.pulse.borrow().is_some() {
+            return;
+        }
+        self.bar.pulse();
+        // The bar is in the toolbar, which may be hidden; keep pulsing so it's right when shown
+        let bar = self.bar.downgrade();
+        let pulse = glib::timeout_add_local(PULSE_INTERVAL, move || match bar.upgrade() {
+            Some(bar) => {
+                bar.pulse();
+                glib::ControlFlow::Continue
+            }
+            None => glib::ControlFlow::Break,
+        });
+        self.state.pulse.replace(Some(pulse));
+    }
+
+    /// Ease the bar toward a fraction; it never moves backwards during a load
+    pub fn set_progress(&self, fraction: f64) {
+        self.state.stop_pulse();
+        self.bar.set_visible(true);
+        self.state
+            .target
+            .set(fraction.clamp(0.0, 1.0).max(self.state.target.get()));
+        if self.state.tick.borrow().is_some() {
+            return;
+        }
+        self.bar.set_fraction(self.state.current.get());
+        let state = Rc::downgrade(&self.state);
+        let tick = self.bar.add_tick_callback(move |bar, clock| {
+            let Some(state) = state.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            let now = clock.frame_time();
+            let elapsed = state
+                .last_frame
+                .replace(Some(now))
+                .map_or(0.0, |last| (now - last) as f64 / 1_000_000.0);
+            let current = ease_toward(state.current.get(), state.target.get(), elapsed);
+            state.current.set(current);
+            bar.set_fraction(current);
+            if current == state.target.get() {
+                // The callback is removed by returning Break
+                state.tick.borrow_mut().take();
+                state.last_frame.set(None);
+                return glib::ControlFlow::Break;
+            }
+            glib::ControlFlow::Continue
+        });
+        self.state.tick.replace(Some(tick));
+    }
+
+    /// Hide the bar and drop any animation, when the load ends
+    pub fn finish(&self) {
+        self.state.stop_pulse();
+        self.state.stop_tick();
+        self.state.current.set(0.0);
+        self.state.target.set(0.0);
+        self.bar.set_fraction(0.0);
+        self.bar.set_visible(false);
+    }
+
+    /// Show another tab's load state as it is, without animating from the previous tab's
+    pub fn show_tab(&self, loading: bool, fraction: f64) {
+        self.finish();
+        if !loading {
+            return;
+        }
+        if fraction > 0.0 {
+            self.state.current.set(fraction);
+            self.state.target.set(fraction);
+            self.bar.set_fraction(fraction);
+            self.bar.set_visible(true);
+        } else {
+            self.start();
+        }
+    }
+}
+
+/// The fraction to show after `elapsed` seconds of easing from `current` toward `target`
+fn ease_toward(current: f64, target: f64, elapsed: f64) -> f64 {
+    let next = current + (target - current) * (elapsed * EASE_RATE).min(1.0);
+    if (target - next).abs() < SETTLE_DISTANCE {
+        target
+    } else {
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ease_toward_approaches_and_settles() {
+        assert_eq!(ease_toward(0.0, 0.5, 0.0), 0.0);
+        let step = ease_toward(0.0, 0.5, 0.016);
+        assert!(step > 0.0 && step < 0.5);
+        assert!(ease_toward(step, 0.5, 0.016) > step);
+        // A long frame gap lands on the target rather than overshooting
+        assert_eq!(ease_toward(0.2, 0.5, 10.0), 0.5);
+        assert_eq!(ease_toward(0.4995, 0.5, 0.016), 0.5);
+    }
+}
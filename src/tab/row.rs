@@ -13,8 +13,11 @@ mod imp {
         pub favicon: RefCell<Option<gtk4::gdk::Texture>>,
         pub is_loading: Cell<bool>,
         pub container_id: RefCell<String>,
+        pub group_id: RefCell<Option<String>>,
+        pub pinned: Cell<bool>,
 
         // Child widgets
+        pub layout_box: RefCell<Option<gtk4::Box>>,
         pub content_box: RefCell<Option<gtk4::Box>>,
         pub container_indicator: RefCell<Option<gtk4::Box>>,
         pub icon_stack: RefCell<Option<gtk4::Stack>>,
@@ -28,7 +31,7 @@ mod imp {
     impl ObjectSubclass for TabRow {
         const NAME: &'static str = "QayeqTabRow";
         type Type = super::TabRow;
-        type ParentType = gtk4::ListBoxRow;
+        type ParentType = gtk4::FlowBoxChild;
     }
 
     impl ObjectImpl for TabRow {
@@ -38,23 +41,29 @@ mod imp {
             let obj = self.obj();
             obj.add_css_class("tab-row");
 
-            // Content box - compact padding
-            let content_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-            content_box.set_margin_start(4);
-            content_box.set_margin_end(4);
-            content_box.set_margin_top(4);
-            content_box.set_margin_bottom(4);
-            content_box.set_valign(gtk4::Align::Center);
-            obj.set_child(Some(&content_box));
+            // Layout box - compact padding; its orientation follows the tab list's
+            let layout_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+            layout_box.set_margin_start(4);
+            layout_box.set_margin_end(4);
+            layout_box.set_margin_top(4);
+            layout_box.set_margin_bottom(4);
+            layout_box.set_valign(gtk4::Align::Center);
+            obj.set_child(Some(&layout_box));
 
             // Container indicator - small colored bar
             let container_indicator = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
             container_indicator.set_width_request(3);
             container_indicator.set_margin_end(6);
             container_indicator.add_css_class("container-indicator");
-            content_box.append(&container_indicator);
+            layout_box.append(&container_indicator);
             *self.container_indicator.borrow_mut() = Some(container_indicator);
 
+            // Content box - favicon, title and close button
+            let content_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+            content_box.set_valign(gtk4::Align::Center);
+            layout_box.append(&content_box);
+            *self.layout_box.borrow_mut() = Some(layout_box);
+
             // Favicon / spinner container
             let icon_stack = gtk4::Stack::new();
             icon_stack.set_transition_type(gtk4::StackTransitionType::Crossfade);
@@ -99,13 +108,13 @@ mod imp {
     }
 
     impl WidgetImpl for TabRow {}
-    impl ListBoxRowImpl for TabRow {}
+    impl FlowBoxChildImpl for TabRow {}
 }
 
 glib::wrapper! {
     pub struct TabRow(ObjectSubclass<imp::TabRow>)
-        @extends gtk4::ListBoxRow, gtk4::Widget,
-        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget, gtk4::Actionable;
+        @extends gtk4::FlowBoxChild, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
 }
 
 impl TabRow {
@@ -117,14 +126,11 @@ impl TabRow {
 
     /// Set the container this tab belongs to and update the visual indicator
     pub fn set_container(&self, container_id: &str) {
-        *self.imp().container_id.borrow_mut() = container_id.to_string();
+        let previous = self.imp().container_id.replace(container_id.to_string());
 
-        // Apply color via CSS class (container-$id)
+        // Apply color via CSS class (container-$id); user containers get theirs from generated CSS
         if let Some(indicator) = self.imp().container_indicator.borrow().as_ref() {
-            // Remove any existing container color classes
-            for (id, _, _) in crate::config::DEFAULT_CONTAINERS {
-                indicator.remove_css_class(&format!("container-{}", id));
-            }
+            indicator.remove_css_class(&format!("container-{}", previous));
             indicator.add_css_class(&format!("container-{}", container_id));
         }
     }
@@ -133,6 +139,38 @@ impl TabRow {
         self.imp().container_id.borrow().clone()
     }
 
+    /// Set the tab group this tab belongs to, indenting it under the group's header
+    pub fn set_group(&self, group_id: Option<&str>) {
+        self.imp().group_id.replace(group_id.map(str::to_string));
+        if group_id.is_some() {
+            self.add_css_class("grouped");
+        } else {
+            self.remove_css_class("grouped");
+        }
+    }
+
+    pub fn group_id(&self) -> Option<String> {
+        self.imp().group_id.borrow().clone()
+    }
+
+    /// Pin or unpin the tab; the close button shows a pin while it's pinned
+    pub fn set_pinned(&self, pinned: bool) {
+        self.imp().pinned.set(pinned);
+        if pinned {
+            self.add_css_class("pinned");
+        } else {
+            self.remove_css_class("pinned");
+        }
+        if let Some(button) = self.imp().close_button.borrow().as_ref() {
+            button.set_icon_name(if pinned { "view-pin-symbolic" } else { "window-close-symbolic" });
+            button.set_tooltip_text(Some(if pinned { "Close Pinned Tab" } else { "Close Tab" }));
+        }
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.imp().pinned.get()
+    }
+
     pub fn tab_id(&self) -> u32 {
         self.imp().tab_id.get()
     }
@@ -199,6 +237,35 @@ impl TabRow {
         }
     }
 
+    /// Lay the row out for a horizontal tab strip, with the container
+    /// indicator as a bar above the tab instead of beside it
+    pub fn set_horizontal(&self, horizontal: bool) {
+        let imp = self.imp();
+        if let Some(layout_box) = imp.layout_box.borrow().as_ref() {
+            layout_box.set_orientation(if horizontal {
+                gtk4::Orientation::Vertical
+            } else {
+                gtk4::Orientation::Horizontal
+            });
+        }
+        if let Some(indicator) = imp.container_indicator.borrow().as_ref() {
+            if horizontal {
+                indicator.set_size_request(-1, 3);
+                indicator.set_margin_end(0);
+                indicator.set_margin_bottom(4);
+            } else {
+                indicator.set_size_request(3, -1);
+                indicator.set_margin_end(6);
+                indicator.set_margin_bottom(0);
+            }
+        }
+        if horizontal {
+            self.add_css_class("horizontal");
+        } else {
+            self.remove_css_class("horizontal");
+        }
+    }
+
     pub fn set_active(&self, active: bool) {
         if active {
             self.add_css_class("active");
@@ -0,0 +1,222 @@
+//! Tab search dialog: filter open tabs by title or URL and switch to one.
+
+use super::TabSummary;
+use gtk4::glib;
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Dialog listing the window's tabs with a live filter.
+pub struct TabSearchDialog {
+    dialog: libadwaita::Dialog,
+    entry: gtk4::SearchEntry,
+    list: gtk4::ListBox,
+    tabs: Rc<Vec<TabSummary>>,
+    /// Tab IDs of the rows currently shown, in row order
+    visible: Rc<RefCell<Vec<u32>>>,
+}
+
+impl TabSearchDialog {
+    pub fn new(tabs: Vec<TabSummary>) -> Self {
+        let dialog = libadwaita::Dialog::builder()
+            .title("Search Tabs")
+            .content_width(480)
+            .content_height(420)
+            .build();
+
+        let entry = gtk4::SearchEntry::new();
+        entry.set_placeholder_text(Some("Search tabs by title or address"));
+
+        let placeholder = gtk4::Label::new(Some("No matching tabs"));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(24);
+        placeholder.set_margin_bottom(24);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::Browse);
+        list.add_css_class("boxed-list");
+        list.set_placeholder(Some(&placeholder));
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&entry);
+        content.append(&list);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .vexpand(true)
+            .child(&content)
+            .build();
+
+        let toolbar_view = libadwaita::ToolbarView::new();
+        toolbar_view.add_top_bar(&libadwaita::HeaderBar::new());
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+        dialog.set_focus(Some(&entry));
+
+        Self {
+            dialog,
+            entry,
+            list,
+            tabs: Rc::new(tabs),
+            visible: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Present the dialog; `on_select` is called with the chosen tab's ID
+    pub fn present<F>(&self, parent: &impl IsA<gtk4::Widget>, on_select: F)
+    where
+        F: Fn(u32) + 'static,
+    {
+        let on_select = Rc::new(on_select);
+        refresh_list(&self.list, &self.tabs, &self.visible, "");
+
+        // Live filter as the user types
+        let list = self.list.clone();
+        let tabs = Rc::clone(&self.tabs);
+        let visible = Rc::clone(&self.visible);
+        self.entry.connect_search_changed(move |entry| {
+            refresh_list(&list, &tabs, &visible, &entry.text());
+        });
+
+        // Up/Down move the selection while typing
+        let key_controller = gtk4::EventControllerKey::new();
+        let list = self.list.clone();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            let step = match key {
+                gtk4::gdk::Key::Down => 1,
+                gtk4::gdk::Key::Up => -1,
+                _ => return glib::Propagation::Proceed,
+            };
+            let current = list.selected_row().map(|row| row.index()).unwrap_or(-1);
+            if let Some(row) = list.row_at_index((current + step).max(0)) {
+                list.select_row(Some(&row));
+            }
+            glib::Propagation::Stop
+        });
+        self.entry.add_controller(key_controller);
+
+        // Enter switches to the selected (or first) tab
+        let dialog = self.dialog.clone();
+        let list = self.list.clone();
+        let visible = Rc::clone(&self.visible);
+        let select = Rc::clone(&on_select);
+        self.entry.connect_activate(move |_| {
+            let index = list.selected_row().map(|row| row.index()).unwrap_or(0);
+            let tab_id = visible.borrow().get(index as usize).copied();
+            if let Some(tab_id) = tab_id {
+                dialog.close();
+                select(tab_id);
+            }
+        });
+
+        let dialog = self.dialog.clone();
+        let visible = Rc::clone(&self.visible);
+        self.list.connect_row_activated(move |_, row| {
+            let tab_id = visible.borrow().get(row.index() as usize).copied();
+            if let Some(tab_id) = tab_id {
+                dialog.close();
+                on_select(tab_id);
+            }
+        });
+
+        self.dialog.present(Some(parent));
+    }
+}
+
+/// Rebuild the rows for the tabs matching `query`
+fn refresh_list(list: &gtk4::ListBox, tabs: &[TabSummary], visible: &RefCell<Vec<u32>>, query: &str) {
+    list.remove_all();
+    let mut visible = visible.borrow_mut();
+    visible.clear();
+
+    let query = query.trim();
+    for tab in tabs.iter().filter(|tab| matches(tab, query)) {
+        let row = libadwaita::ActionRow::builder()
+            .title(highlight_markup(&tab.title, query))
+            .subtitle(highlight_markup(&tab.url, query))
+            .activatable(true)
+            .build();
+
+        let indicator = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+        indicator.set_width_request(3);
+        indicator.add_css_class("container-indicator");
+        indicator.add_css_class(&format!("container-{}", tab.container));
+        row.add_prefix(&indicator);
+
+        list.append(&row);
+        visible.push(tab.tab_id);
+    }
+
+    if let Some(first) = list.row_at_index(0) {
+        list.select_row(Some(&first));
+    }
+}
+
+/// Case-insensitive match against a tab's title or URL
+fn matches(tab: &TabSummary, query: &str) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    let query = query.to_lowercase();
+    tab.title.to_lowercase().contains(&query) || tab.url.to_lowercase().contains(&query)
+}
+
+/// Pango markup for `text` with each case-insensitive match of `query` in bold
+fn highlight_markup(text: &str, query: &str) -> String {
+    let lower = text.to_lowercase();
+    let query = query.to_lowercase();
+    // Lowercasing can change byte offsets for some scripts; skip highlighting then
+    if query.is_empty() || lower.len() != text.len() {
+        return glib::markup_escape_text(text).into();
+    }
+
+    let mut markup = String::new();
+    let mut last = 0;
+    for (start, matched) in lower.match_indices(&query) {
+        let end = start + matched.len();
+        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return glib::markup_escape_text(text).into();
+        }
+        markup.push_str(&glib::markup_escape_text(&text[last..start]));
+        markup.push_str("<b>");
+        markup.push_str(&glib::markup_escape_text(&text[start..end]));
+        markup.push_str("</b>");
+        last = end;
+    }
+    markup.push_str(&glib::markup_escape_text(&text[last..]));
+    markup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab(title: &str, url: &str) -> TabSummary {
+        TabSummary {
+            tab_id: 1,
+            title: title.to_string(),
+            url: url.to_string(),
+            container: "personal".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_matches_title_or_url() {
+        let rust = tab("The Rust Book", "https://doc.rust-lang.org/book/");
+        assert!(matches(&rust, "rust BOOK"));
+        assert!(matches(&rust, "doc.rust"));
+        assert!(matches(&rust, ""));
+        assert!(!matches(&rust, "python"));
+    }
+
+    #[test]
+    fn test_highlight_markup() {
+        assert_eq!(highlight_markup("Rust & rust", "RUST"), "<b>Rust</b> &amp; <b>rust</b>");
+        assert_eq!(highlight_markup("<none>", "x"), "&lt;none&gt;");
+    }
+}
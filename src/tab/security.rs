@@ -0,0 +1,153 @@
+//! Connection security of a page, for the indicator before the URL entry.
+
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+
+/// How a page was loaded, from the user's point of view, ordered from least
+/// to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecurityLevel {
+    /// Internal pages, blank tabs and tabs that haven't loaded yet
+    Local,
+    /// HTTPS with a certificate that verified
+    Secure,
+    /// HTTPS, with the page's HTTP resources blocked
+    MixedContentBlocked,
+    /// HTTPS, but the page also loaded resources over plain HTTP
+    MixedContent,
+    /// A host name mixing scripts, which may imitate another site's
+    LookalikeDomain,
+    /// Plain HTTP, or HTTPS with a certificate that didn't verify
+    Insecure,
+}
+
+impl SecurityLevel {
+    /// Classify a page by its URI, the TLS errors of its certificate (None if the
+    /// connection had no TLS) and whether insecure content was detected on it
+    pub fn for_page(uri: &str, tls_errors: Option<gio::TlsCertificateFlags>, insecure_content: bool) -> Self {
+        let scheme = uri.split_once(':').map(|(scheme, _)| scheme).unwrap_or_default();
+        match scheme {
+            "https" => match tls_errors {
+                Some(errors) if errors.is_empty() && insecure_content => SecurityLevel::MixedContent,
+                Some(errors) if errors.is_empty() => SecurityLevel::Secure,
+                _ => SecurityLevel::Insecure,
+            },
+            "http" => SecurityLevel::Insecure,
+            _ => SecurityLevel::Local,
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            SecurityLevel::Local => "dialog-information-symbolic",
+            SecurityLevel::Secure | SecurityLevel::MixedContentBlocked => "channel-secure-symbolic",
+            SecurityLevel::MixedContent | SecurityLevel::LookalikeDomain => "dialog-warning-symbolic",
+            SecurityLevel::Insecure => "channel-insecure-symbolic",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SecurityLevel::Local => "Site Information",
+            SecurityLevel::Secure => "Connection is secure",
+            SecurityLevel::MixedContent => "Parts of this page are not secure",
+            SecurityLevel::MixedContentBlocked => "Insecure content on this page was blocked",
+            SecurityLevel::Insecure => "Connection is not secure",
+            SecurityLevel::LookalikeDomain => "This address may be imitating another site",
+        }
+    }
+}
+
+/// Summary of a server certificate for the site information popover
+pub fn certificate_details(certificate: &gio::TlsCertificate) -> String {
+    let mut lines = Vec::new();
+    if let Some(subject) = certificate.subject_name() {
+        lines.push(format!("Issued to: {}", describe_name(&subject)));
+    }
+    if let Some(issuer) = certificate.issuer_name() {
+        lines.push(format!("Issued by: {}", describe_name(&issuer)));
+    }
+    let format_date = |date: glib::DateTime| date.format("%x").ok().map(|d| d.to_string());
+    let valid_from = certificate.not_valid_before().and_then(format_date);
+    let valid_until = certificate.not_valid_after().and_then(format_date);
+    if let (Some(from), Some(until)) = (valid_from, valid_until) {
+        lines.push(format!("Valid from {} to {}", from, until));
+    }
+    lines.join("\n")
+}
+
+/// Readable form of a distinguished name such as "CN=R3,O=Let's Encrypt,C=US":
+/// the common name with the organization in parentheses
+fn describe_name(dn: &str) -> String {
+    let common_name = dn_attribute(dn, "CN");
+    let organization = dn_attribute(dn, "O");
+    match (common_name, organization) {
+        (Some(cn), Some(o)) if cn != o => format!("{} ({})", cn, o),
+        (Some(name), _) | (None, Some(name)) => name,
+        (None, None) => dn.to_string(),
+    }
+}
+
+/// Value of one attribute of an RFC 4514 distinguished name, unescaped
+fn dn_attribute(dn: &str, key: &str) -> Option<String> {
+    let mut attributes = Vec::new();
+    let mut current = String::new();
+    let mut chars = dn.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ',' | '+' => attributes.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    attributes.push(current);
+
+    attributes.into_iter().find_map(|attribute| {
+        let (k, v) = attribute.split_once('=')?;
+        k.trim().eq_ignore_ascii_case(key).then(|| v.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_level_for_page() {
+        let verified = Some(gio::TlsCertificateFlags::empty());
+
+        assert_eq!(SecurityLevel::for_page("https://example.com/", verified, false), SecurityLevel::Secure);
+        assert_eq!(SecurityLevel::for_page("https://example.com/", verified, true), SecurityLevel::MixedContent);
+        assert_eq!(
+            SecurityLevel::for_page("https://self-signed.example/", Some(gio::TlsCertificateFlags::UNKNOWN_CA), false),
+            SecurityLevel::Insecure
+        );
+        assert_eq!(SecurityLevel::for_page("https://example.com/", None, false), SecurityLevel::Insecure);
+        assert_eq!(SecurityLevel::for_page("http://example.com/", None, false), SecurityLevel::Insecure);
+        assert_eq!(SecurityLevel::for_page("qayeq://newtab", None, false), SecurityLevel::Local);
+        assert_eq!(SecurityLevel::for_page("about:blank", None, false), SecurityLevel::Local);
+    }
+
+    #[test]
+    fn test_most_severe_level() {
+        let levels = [
+            SecurityLevel::Secure,
+            SecurityLevel::LookalikeDomain,
+            SecurityLevel::Insecure,
+            SecurityLevel::MixedContent,
+        ];
+        assert_eq!(levels.iter().max(), Some(&SecurityLevel::Insecure));
+        assert!(SecurityLevel::LookalikeDomain > SecurityLevel::MixedContent);
+        assert!(SecurityLevel::MixedContent > SecurityLevel::MixedContentBlocked);
+    }
+
+    #[test]
+    fn test_distinguished_names() {
+        assert_eq!(describe_name("CN=R3,O=Let's Encrypt,C=US"), "R3 (Let's Encrypt)");
+        assert_eq!(describe_name("CN=example.com"), "example.com");
+        assert_eq!(describe_name("O=Example\\, Inc.,C=US"), "Example, Inc.");
+        assert_eq!(describe_name("CN=Same,O=Same"), "Same");
+        assert_eq!(dn_attribute("cn = spaced.example , c=US", "CN").as_deref(), Some("spaced.example"));
+        assert_eq!(dn_attribute("C=US", "CN"), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::ops::Range;
+use url::{Host, Url};
+
+/// A page URL shortened for the unfocused URL bar
+#[derive(Debug, PartialEq, Eq)]
+pub struct SimplifiedUrl {
+    pub text: String,
+    /// Byte range of the registrable domain in `text`, to highlight it
+    pub domain: Range<usize>,
+}
+
+/// Shorten a web page URL for display: no scheme, no "www.", no trailing slash
+///
+/// Only http and https URLs are simplified; anything else is shown as is.
+/// Hosts stay in their ASCII (punycode) form, so a lookalike internationalized
+/// domain can't pass for the one it imitates.
+pub fn simplify_url(uri: &str) -> Option<SimplifiedUrl> {
+    let url = Url::parse(uri).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host()?;
+    let host_str = url.host_str()?;
+    let host_clean = host_str.strip_prefix("www.").unwrap_or(host_str);
+
+    let mut text = host_clean.to_string();
+    if let Some(port) = url.port() {
+        text.push_str(&format!(":{}", port));
+    }
+    let path = url.path();
+    if path != "/" || url.query().is_some() {
+        text.push_str(path.strip_suffix('/').unwrap_or(path));
+    }
+    if let Some(query) = url.query() {
+        text.push('?');
+        text.push_str(query);
+    }
+    if let Some(fragment) = url.fragment() {
+        text.push('#');
+        text.push_str(fragment);
+    }
+
+    let domain = match host {
+        Host::Domain(_) => {
+            let start = host_clean.len() - registrable_domain(host_clean).len();
+            start..host_clean.len()
+        }
+        // Addresses have no domain to single out
+        Host::Ipv4(_) | Host::Ipv6(_) => 0..host_clean.len(),
+    };
+    Some(SimplifiedUrl { text, domain })
+}
+
+/// The registrable part of a host name, e.g. "example.co.uk" for "shop.example.co.uk"
+///
+/// Without the public suffix list this is an approximation: two labels, or
+/// three where the suffix looks like a country's second level ("co.uk").
+fn registrable_domain(host: &str) -> &str {
+    let labels: Vec<&str> = host.rsplit('.').collect();
+    let country_second_level = labels.len() >= 3 && labels[0].len() == 2 && labels[1].len() <= 3;
+    let count = if country_second_level { 3 } else { 2 };
+    if labels.len() <= count {
+        return host;
+    }
+    let suffix_len: usize = labels[..count].iter().map(|label| label.len()).sum::<usize>() + count - 1;
+    &host[host.len() - suffix_len..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify_url() {
+        let simplified = simplify_url("https://www.example.com/").unwrap();
+        assert_eq!(simplified.text, "example.com");
+        assert_eq!(&simplified.text[simplified.domain], "example.com");
+
+        let simplified = simplify_url("http://docs.example.co.uk:8080/guide/?page=2#intro").unwrap();
+        assert_eq!(simplified.text, "docs.example.co.uk:8080/guide?page=2#intro");
+        assert_eq!(&simplified.text[simplified.domain], "example.co.uk");
+
+        assert_eq!(simplify_url("https://xn--pple-43d.com/").unwrap().text, "xn--pple-43d.com");
+        assert!(simplify_url("file:///home/user/page.html").is_none());
+        assert!(simplify_url("qayeq://settings").is_none());
+    }
+}
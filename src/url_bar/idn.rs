@@ -0,0 +1,106 @@
+use url::{Host, Url};
+
+/// Writing systems told apart when checking host names for lookalike characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    Other,
+}
+
+/// Scripts that are written together in Japanese and Korean names
+const JAPANESE: &[Script] = &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana];
+const KOREAN: &[Script] = &[Script::Latin, Script::Han, Script::Hangul];
+
+/// Whether a URL's host looks like it imitates another: a label that mixes
+/// scripts, as in "аpple.com" with a Cyrillic "а", or punycode that doesn't decode
+///
+/// Labels written in a single script, such as an all-Cyrillic name, pass, as do
+/// the script combinations used in Japanese and Korean names.
+pub fn is_lookalike_host(url: &Url) -> bool {
+    let Some(Host::Domain(host)) = url.host() else {
+        return false;
+    };
+    host.split('.').any(|label| {
+        let Some(encoded) = label.strip_prefix("xn--") else {
+            // Plain ASCII, a single script
+            return false;
+        };
+        match idna::punycode::decode_to_string(encoded) {
+            Some(decoded) => mixes_scripts(&decoded),
+            None => true,
+        }
+    })
+}
+
+fn mixes_scripts(label: &str) -> bool {
+    let mut scripts: Vec<Script> = Vec::new();
+    for script in label.chars().filter_map(script_of) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    scripts.len() > 1
+        && !scripts.iter().all(|script| JAPANESE.contains(script))
+        && !scripts.iter().all(|script| KOREAN.contains(script))
+}
+
+/// The script of a character, or None for digits, hyphens and other characters shared by all
+fn script_of(c: char) -> Option<Script> {
+    let script = match c as u32 {
+        0x30..=0x39 | 0x2d | 0x5f => return None,
+        0x41..=0x5a | 0x61..=0x7a | 0xc0..=0x24f | 0x1e00..=0x1eff => Script::Latin,
+        0x370..=0x3ff | 0x1f00..=0x1fff => Script::Greek,
+        0x400..=0x52f => Script::Cyrillic,
+        0x530..=0x58f => Script::Armenian,
+        0x590..=0x5ff => Script::Hebrew,
+        0x600..=0x6ff => Script::Arabic,
+        // The prolonged sound mark is used with both kana
+        0x30fc => return None,
+        0x3040..=0x309f => Script::Hiragana,
+        0x30a0..=0x30ff => Script::Katakana,
+        0x3400..=0x4dbf | 0x4e00..=0x9fff => Script::Han,
+        0x1100..=0x11ff | 0xac00..=0xd7af => Script::Hangul,
+        _ => Script::Other,
+    };
+    Some(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookalike(url: &str) -> bool {
+        is_lookalike_host(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_mixed_script_hosts_are_lookalikes() {
+        // Cyrillic "а" in an otherwise Latin name
+        assert!(lookalike("https://xn--pple-43d.com/"));
+        assert!(lookalike("https://\u{430}pple.com/"));
+        // Greek omicron in "google"
+        assert!(lookalike("https://g\u{3bf}ogle.com/"));
+        // Cyrillic "е" in one label of a subdomain
+        assert!(lookalike("https://login.paypal.s\u{435}cure.com/"));
+    }
+
+    #[test]
+    fn test_single_script_hosts_pass() {
+        assert!(!lookalike("https://example.com/"));
+        assert!(!lookalike("https://caf\u{e9}.fr/"));
+        // All-Cyrillic name and TLD
+        assert!(!lookalike("https://\u{44f}\u{43d}\u{434}\u{435}\u{43a}\u{441}.\u{440}\u{444}/"));
+        // Han and Katakana together, as in Japanese
+        assert!(!lookalike("https://\u{65e5}\u{672c}\u{30c6}\u{30b9}\u{30c8}.jp/"));
+        assert!(!lookalike("http://192.168.1.1/"));
+    }
+}
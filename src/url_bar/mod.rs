@@ -1,3 +1,9 @@
+mod display;
+mod idn;
 mod parser;
+mod tracking;
 
-pub use parser::{parse_input, build_search_url, UrlBarInput};
+pub use display::simplify_url;
+pub use idn::is_lookalike_host;
+pub use parser::{parse_input, build_search_url, pasted_input, search_url, UrlBarInput};
+pub use tracking::{strip_tracking_params, tracking_parameters};
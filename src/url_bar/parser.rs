@@ -69,9 +69,18 @@ pub fn parse_input(input: &str) -> UrlBarInput {
 
 /// Build a search URL for the given query using the default search engine
 pub fn build_search_url(query: &str) -> Result<Url, url::ParseError> {
+    search_url(config::DEFAULT_SEARCH_URL, query)
+}
+
+/// Build a search URL for the given query from a template with a `{}` placeholder
+pub fn search_url(template: &str, query: &str) -> Result<Url, url::ParseError> {
     let encoded = urlencoding::encode(query);
-    let url_str = config::DEFAULT_SEARCH_URL.replace("{}", &encoded);
-    Url::parse(&url_str)
+    Url::parse(&template.replace("{}", &encoded))
+}
+
+/// The part of pasted text to go to: its first non-empty line, trimmed
+pub fn pasted_input(text: &str) -> Option<&str> {
+    text.lines().map(str::trim).find(|line| !line.is_empty())
 }
 
 #[cfg(test)]
@@ -126,10 +135,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pasted_input_takes_first_line() {
+        assert_eq!(pasted_input("  https://example.com  "), Some("https://example.com"));
+        assert_eq!(pasted_input("\n\n example.com/a \nsecond line\n"), Some("example.com/a"));
+        assert_eq!(pasted_input(" \n\t\n"), None);
+    }
+
     #[test]
     fn test_search_url_building() {
         let url = build_search_url("hello world").unwrap();
         assert!(url.as_str().contains("duckduckgo.com"));
         assert!(url.as_str().contains("hello%20world"));
     }
+
+    #[test]
+    fn test_custom_search_url() {
+        let url = search_url("https://search.example.internal/find?q={}", "a&b").unwrap();
+        assert_eq!(url.as_str(), "https://search.example.internal/find?q=a%26b");
+    }
 }
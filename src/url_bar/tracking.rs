@@ -0,0 +1,102 @@
+use url::{form_urlencoded, Url};
+
+/// Query parameters that only identify where a visitor came from.
+/// A trailing `*` matches any parameter starting with the rest.
+const TRACKING_PARAMETERS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+];
+
+/// The default tracking parameters plus user-added ones from a comma-separated list
+pub fn tracking_parameters(extra: &str) -> Vec<String> {
+    TRACKING_PARAMETERS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(
+            extra
+                .split(',')
+                .map(|name| name.trim().to_ascii_lowercase())
+                .filter(|name| !name.is_empty()),
+        )
+        .collect()
+}
+
+/// Remove query parameters matching `patterns` from a URL. Other parameters
+/// keep their order and encoding; a URL left with no parameters loses its `?`.
+pub fn strip_tracking_params(url: &Url, patterns: &[String]) -> Url {
+    let Some(query) = url.query() else {
+        return url.clone();
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let name = form_urlencoded::parse(pair.as_bytes())
+                .next()
+                .map(|(name, _)| name.to_ascii_lowercase())
+                .unwrap_or_default();
+            !patterns.iter().any(|pattern| matches_pattern(&name, pattern))
+        })
+        .collect();
+
+    let mut stripped = url.clone();
+    if kept.len() != query.split('&').count() {
+        stripped.set_query((!kept.is_empty()).then(|| kept.join("&")).as_deref());
+    }
+    stripped
+}
+
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(url: &str) -> String {
+        let patterns = tracking_parameters("");
+        strip_tracking_params(&Url::parse(url).unwrap(), &patterns).to_string()
+    }
+
+    #[test]
+    fn test_strips_tracking_and_keeps_the_rest() {
+        assert_eq!(
+            strip("https://example.com/item?id=42&utm_source=news&q=a%20b&fbclid=xyz#reviews"),
+            "https://example.com/item?id=42&q=a%20b#reviews"
+        );
+        assert_eq!(
+            strip("https://example.com/?utm_source=news&UTM_Medium=email"),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_untouched_without_tracking_params() {
+        assert_eq!(strip("https://example.com/path"), "https://example.com/path");
+        assert_eq!(strip("https://example.com/?q=rust&page=2"), "https://example.com/?q=rust&page=2");
+    }
+
+    #[test]
+    fn test_extra_parameters() {
+        let patterns = tracking_parameters(" ref, Campaign_* ,");
+        let url = Url::parse("https://example.com/?ref=home&campaign_id=7&id=1").unwrap();
+        assert_eq!(strip_tracking_params(&url, &patterns).as_str(), "https://example.com/?id=1");
+    }
+}
@@ -0,0 +1,52 @@
+//! Per-site user agent overrides ("request desktop/mobile site").
+
+use crate::site_settings::SiteStore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const OVERRIDES_FILE: &str = "user-agents.json";
+
+/// Desktop user agent for sites that misdetect the default one
+const DESKTOP_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15";
+
+/// Mobile user agent for sites with better small-screen layouts
+const MOBILE_USER_AGENT: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1";
+
+/// User agent requested for a site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserAgentMode {
+    Desktop,
+    Mobile,
+}
+
+impl UserAgentMode {
+    /// The user agent string sent for this mode
+    pub fn user_agent(&self) -> &'static str {
+        match self {
+            UserAgentMode::Desktop => DESKTOP_USER_AGENT,
+            UserAgentMode::Mobile => MOBILE_USER_AGENT,
+        }
+    }
+}
+
+/// User agents requested for hosts
+#[derive(Debug, Default)]
+pub struct UserAgentOverrides(SiteStore<UserAgentMode>);
+
+impl UserAgentOverrides {
+    /// Load the requests saved in the data directory (empty if none saved yet)
+    pub fn load(data_dir: &Path) -> Self {
+        Self(SiteStore::load(data_dir, OVERRIDES_FILE))
+    }
+
+    /// Get the user agent mode requested for a host
+    pub fn get(&self, host: &str) -> Option<UserAgentMode> {
+        self.0.get(host)
+    }
+
+    /// Set or clear the user agent mode for a host and persist the change
+    pub fn set(&mut self, host: &str, mode: Option<UserAgentMode>) {
+        self.0.set(host, mode);
+    }
+}
@@ -0,0 +1,233 @@
+//! Parser for the `// ==UserScript==` metadata block of Greasemonkey-style scripts
+//!
+//! Parses blocks like:
+//! ```text
+//! // ==UserScript==
+//! // @name     Example
+//! // @match    https://*.example.com/*
+//! // @run-at   document-start
+//! // ==/UserScript==
+//! ```
+
+/// When a user script runs, from its `@run-at` key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunAt {
+    /// Before the page's own scripts, as soon as the document exists
+    Start,
+    /// Once the document has been parsed (`document-end` and `document-idle`)
+    #[default]
+    End,
+}
+
+/// A user script's parsed metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// `@match` and `@include` patterns, in the order given
+    pub matches: Vec<String>,
+    /// `@exclude-match` and `@exclude` patterns
+    pub excludes: Vec<String>,
+    pub run_at: RunAt,
+    /// `@noframes`: run in the top frame only
+    pub no_frames: bool,
+}
+
+impl ScriptMetadata {
+    /// Parse the metadata block of a script's source.
+    ///
+    /// Returns None if the source has no complete metadata block.
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut lines = source.lines().map(str::trim);
+        lines.find(|line| is_marker(line, "==UserScript=="))?;
+
+        let mut metadata = ScriptMetadata::default();
+        for line in lines {
+            if is_marker(line, "==/UserScript==") {
+                return Some(metadata);
+            }
+            let Some(entry) = line.strip_prefix("//").map(str::trim_start) else {
+                continue;
+            };
+            let Some(entry) = entry.strip_prefix('@') else {
+                continue;
+            };
+            let (key, value) = match entry.split_once(char::is_whitespace) {
+                Some((key, value)) => (key, value.trim()),
+                None => (entry, ""),
+            };
+
+            match key {
+                "name" if metadata.name.is_none() => metadata.name = Some(value.to_string()),
+                "description" if metadata.description.is_none() => {
+                    metadata.description = Some(value.to_string())
+                }
+                "match" | "include" if !value.is_empty() => metadata.matches.push(value.to_string()),
+                "exclude-match" | "exclude" if !value.is_empty() => {
+                    metadata.excludes.push(value.to_string())
+                }
+                "run-at" => {
+                    metadata.run_at = match value {
+                        "document-start" => RunAt::Start,
+                        _ => RunAt::End,
+                    }
+                }
+                "noframes" => metadata.no_frames = true,
+                _ => {}
+            }
+        }
+
+        // The block was never closed
+        None
+    }
+
+    /// WebKit allow-list for the script's patterns; empty runs it everywhere.
+    ///
+    /// Returns None when the script names pages to run on but none of the
+    /// patterns can be expressed for WebKit, so it shouldn't run at all.
+    pub fn webkit_allow_list(&self) -> Option<Vec<String>> {
+        if self.matches.is_empty() {
+            return Some(Vec::new());
+        }
+        let mut allow_list = Vec::new();
+        for pattern in &self.matches {
+            match to_webkit_pattern(pattern) {
+                // Everything, which WebKit spells as an empty list
+                Some(WebKitPattern::All) => return Some(Vec::new()),
+                Some(WebKitPattern::Pattern(pattern)) => allow_list.push(pattern),
+                None => log::debug!("Skipping unsupported user script pattern: {}", pattern),
+            }
+        }
+        if allow_list.is_empty() {
+            None
+        } else {
+            Some(allow_list)
+        }
+    }
+
+    /// WebKit block-list for the script's exclusions.
+    ///
+    /// Returns None when an exclusion covers every page, or can't be
+    /// converted, so the script shouldn't run at all rather than run on
+    /// pages it excludes.
+    pub fn webkit_block_list(&self) -> Option<Vec<String>> {
+        let mut block_list = Vec::new();
+        for pattern in &self.excludes {
+            match to_webkit_pattern(pattern) {
+                Some(WebKitPattern::All) => return None,
+                Some(WebKitPattern::Pattern(pattern)) => block_list.push(pattern),
+                None => {
+                    log::warn!("Not running user script with unsupported exclusion: {}", pattern);
+                    return None;
+                }
+            }
+        }
+        Some(block_list)
+    }
+}
+
+/// A `// ==UserScript==` style marker line
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix("//").is_some_and(|rest| rest.trim() == marker)
+}
+
+/// A pattern converted for WebKit's allow and block lists
+#[derive(Debug, PartialEq)]
+enum WebKitPattern {
+    All,
+    Pattern(String),
+}
+
+/// Convert a `@match` pattern, or an `@include` written like one, to WebKit's
+/// `scheme://host/path` syntax. Wildcards are only allowed for the whole
+/// scheme, a leading host label and anywhere in the path.
+fn to_webkit_pattern(pattern: &str) -> Option<WebKitPattern> {
+    if pattern == "*" || pattern == "<all_urls>" {
+        return Some(WebKitPattern::All);
+    }
+    let (scheme, rest) = pattern.split_once("://")?;
+    let scheme = match scheme {
+        // Common in scripts written for Tampermonkey
+        "http*" => "*",
+        "*" | "http" | "https" | "file" | "ftp" => scheme,
+        _ => return None,
+    };
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        // @include often leaves the path out
+        None => (rest, "/*"),
+    };
+    let domain = host.strip_prefix("*.").unwrap_or(host);
+    if domain.contains('*') && host != "*" {
+        // Such as "*.example.*", which WebKit can't express
+        return None;
+    }
+    Some(WebKitPattern::Pattern(format!("{}://{}{}", scheme, host, path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"// ==UserScript==
+// @name        Wide Pages
+// @description Remove the width limit
+// @match       https://*.example.com/*
+// @include     http*://news.example.org/articles*
+// @exclude     https://example.com/admin/*
+// @run-at      document-start
+// @noframes
+// ==/UserScript==
+
+document.body.style.maxWidth = "none";
+"#;
+
+    #[test]
+    fn test_parse_metadata_block() {
+        let metadata = ScriptMetadata::parse(SCRIPT).unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Wide Pages"));
+        assert_eq!(metadata.description.as_deref(), Some("Remove the width limit"));
+        assert_eq!(metadata.matches.len(), 2);
+        assert_eq!(metadata.excludes, vec!["https://example.com/admin/*"]);
+        assert_eq!(metadata.run_at, RunAt::Start);
+        assert!(metadata.no_frames);
+
+        // Unclosed or missing blocks aren't user scripts
+        assert!(ScriptMetadata::parse("// ==UserScript==\n// @name Broken\n").is_none());
+        assert!(ScriptMetadata::parse("console.log('hi');").is_none());
+    }
+
+    #[test]
+    fn test_patterns_convert_to_webkit_lists() {
+        let metadata = ScriptMetadata::parse(SCRIPT).unwrap();
+        assert_eq!(
+            metadata.webkit_allow_list().unwrap(),
+            vec!["https://*.example.com/*", "*://news.example.org/articles*"]
+        );
+        assert_eq!(metadata.webkit_block_list().unwrap(), vec!["https://example.com/admin/*"]);
+
+        let with = |matches: &[&str]| ScriptMetadata {
+            matches: matches.iter().map(|m| m.to_string()).collect(),
+            ..Default::default()
+        };
+        // No patterns, or a catch-all, runs everywhere
+        assert_eq!(with(&[]).webkit_allow_list(), Some(vec![]));
+        assert_eq!(with(&["https://a.com/*", "<all_urls>"]).webkit_allow_list(), Some(vec![]));
+        assert_eq!(with(&["*://example.com"]).webkit_allow_list().unwrap(), vec!["*://example.com/*"]);
+        // Nothing usable means nowhere, not everywhere
+        assert_eq!(with(&["https://*.example.*/*", "/regex/"]).webkit_allow_list(), None);
+    }
+
+    #[test]
+    fn test_exclude_everything() {
+        let excluding = |excludes: &[&str]| ScriptMetadata {
+            excludes: excludes.iter().map(|e| e.to_string()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(excluding(&["https://a.com/*", "*"]).webkit_block_list(), None);
+        assert_eq!(excluding(&["<all_urls>"]).webkit_block_list(), None);
+        // An exclusion that can't be expressed keeps the script off every page
+        assert_eq!(excluding(&["/regex/"]).webkit_block_list(), None);
+        assert_eq!(excluding(&["https://a.com/*", "https://*.example.*/*"]).webkit_block_list(), None);
+    }
+}
@@ -0,0 +1,247 @@
+//! Greasemonkey-style user scripts
+//!
+//! Scripts are `*.user.js` files dropped into the `userscripts` directory of
+//! a container's profile directory. Each one is injected on the pages its
+//! metadata block matches in that container, and can be turned off on its
+//! own. The directory is watched, so added, edited and removed scripts apply
+//! to pages loaded afterwards.
+
+mod metadata;
+
+use crate::content_filter::ContentManagers;
+use gtk4::gio;
+use gtk4::prelude::*;
+use metadata::{RunAt, ScriptMetadata};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use webkit6::{UserContentInjectedFrames, UserScript, UserScriptInjectionTime};
+
+/// Directory in a profile directory holding user scripts
+const USERSCRIPTS_DIR: &str = "userscripts";
+
+/// File name suffix of user scripts
+const USERSCRIPT_SUFFIX: &str = ".user.js";
+
+/// A user script found in the scripts directory
+#[derive(Debug, Clone)]
+pub struct UserScriptInfo {
+    pub file_name: String,
+    /// The script's `@name`, or its file name without one
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+}
+
+/// Manages user script injection for one container
+pub struct UserScriptManager {
+    container: String,
+    dir: PathBuf,
+    content_managers: ContentManagers,
+    scripts: RefCell<Vec<UserScriptInfo>>,
+    injected: RefCell<Vec<UserScript>>,
+    monitor: RefCell<Option<gio::FileMonitor>>,
+    settings: gio::Settings,
+}
+
+impl UserScriptManager {
+    /// Create a manager injecting into a container's webviews, with its
+    /// scripts directory in the container's `profile_dir`
+    pub fn new(container: &str, profile_dir: &Path, managers: &ContentManagers) -> Rc<Self> {
+        let dir = profile_dir.join(USERSCRIPTS_DIR);
+        if let Err(e) = fs::create_dir_all(&dir) {
+            log::warn!("Failed to create user scripts directory: {}", e);
+        }
+
+        Rc::new(Self {
+            container: container.to_string(),
+            dir,
+            content_managers: managers.clone(),
+            scripts: RefCell::new(Vec::new()),
+            injected: RefCell::new(Vec::new()),
+            monitor: RefCell::new(None),
+            settings: gio::Settings::new(crate::config::APP_ID),
+        })
+    }
+
+    /// Inject the enabled scripts, then follow changes to the directory and to
+    /// which scripts are turned off
+    pub fn initialize(self: &Rc<Self>) {
+        self.reload();
+
+        let manager = Rc::downgrade(self);
+        self.settings
+            .connect_changed(Some("disabled-user-scripts"), move |_, _| {
+                if let Some(manager) = manager.upgrade() {
+                    manager.reload();
+                }
+            });
+
+        let monitor = gio::File::for_path(&self.dir)
+            .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE);
+        match monitor {
+            Ok(monitor) => {
+                let manager = Rc::downgrade(self);
+                monitor.connect_changed(move |_, file, other, event| {
+                    let is_script = |file: &gio::File| {
+                        file.basename()
+                            .is_some_and(|name| name.to_string_lossy().ends_with(USERSCRIPT_SUFFIX))
+                    };
+                    let relevant = !matches!(
+                        event,
+                        gio::FileMonitorEvent::Changed | gio::FileMonitorEvent::AttributeChanged
+                    );
+                    if relevant && (is_script(file) || other.is_some_and(is_script)) {
+                        if let Some(manager) = manager.upgrade() {
+                            manager.reload();
+                        }
+                    }
+                });
+                self.monitor.replace(Some(monitor));
+            }
+            Err(e) => log::warn!("Failed to watch user scripts directory: {}", e),
+        }
+    }
+
+    /// The directory users drop scripts into
+    pub fn directory(&self) -> &Path {
+        &self.dir
+    }
+
+    /// All scripts in the directory, enabled or not, by file name
+    pub fn scripts(&self) -> Vec<UserScriptInfo> {
+        self.scripts.borrow().clone()
+    }
+
+    /// Turn a script on or off. Open pages keep what they were loaded with.
+    pub fn set_enabled(&self, file_name: &str, enabled: bool) {
+        let key = disabled_key(&self.container, file_name);
+        let mut disabled: Vec<String> = self
+            .settings
+            .strv("disabled-user-scripts")
+            .iter()
+            .map(|name| name.to_string())
+            .filter(|name| *name != key)
+            .collect();
+        if !enabled {
+            disabled.push(key);
+        }
+        let disabled: Vec<&str> = disabled.iter().map(String::as_str).collect();
+        if let Err(e) = self.settings.set_strv("disabled-user-scripts", disabled.as_slice()) {
+            log::warn!("Failed to save disabled user scripts: {}", e);
+        }
+    }
+
+    /// Take the scripts out of the container's webviews and stop watching the
+    /// directory, for a container that's being deleted
+    pub fn unload(&self) {
+        self.monitor.take();
+        for script in self.injected.borrow_mut().drain(..) {
+            self.content_managers.remove_script(&script);
+        }
+    }
+
+    /// Read the scripts directory and inject the enabled scripts in place of
+    /// the ones injected before
+    fn reload(&self) {
+        for script in self.injected.borrow_mut().drain(..) {
+            self.content_managers.remove_script(&script);
+        }
+
+        let disabled = self.settings.strv("disabled-user-scripts");
+        let mut scripts = Vec::new();
+        let mut injected = Vec::new();
+        for (file_name, source) in read_scripts(&self.dir) {
+            let Some(metadata) = ScriptMetadata::parse(&source) else {
+                log::warn!("Skipping user script {} without a metadata block", file_name);
+                continue;
+            };
+            let key = disabled_key(&self.container, &file_name);
+            let enabled = !disabled.iter().any(|name| name.as_str() == key);
+            if enabled {
+                match compile(&source, &metadata) {
+                    Some(script) => {
+                        self.content_managers.add_container_script(&self.container, &script);
+                        injected.push(script);
+                    }
+                    None => log::warn!("User script {} matches no pages WebKit can target", file_name),
+                }
+            }
+            scripts.push(UserScriptInfo {
+                name: metadata
+                    .name
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| file_name.trim_end_matches(USERSCRIPT_SUFFIX).to_string()),
+                description: metadata.description.filter(|d| !d.is_empty()),
+                file_name,
+                enabled,
+            });
+        }
+
+        log::info!(
+            "Loaded {} user scripts for container '{}', {} injected",
+            scripts.len(),
+            self.container,
+            injected.len()
+        );
+        self.scripts.replace(scripts);
+        self.injected.replace(injected);
+    }
+}
+
+/// Entry of a container's script in the `disabled-user-scripts` setting
+fn disabled_key(container: &str, file_name: &str) -> String {
+    format!("{}/{}", container, file_name)
+}
+
+/// The `*.user.js` files in a directory as (file name, source), by file name
+fn read_scripts(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<(String, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.ends_with(USERSCRIPT_SUFFIX) {
+                return None;
+            }
+            match fs::read_to_string(entry.path()) {
+                Ok(source) => Some((file_name, source)),
+                Err(e) => {
+                    log::warn!("Failed to read user script {}: {}", file_name, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// Build the WebKit user script for a script, or None if it can't target its pages
+fn compile(source: &str, metadata: &ScriptMetadata) -> Option<UserScript> {
+    let allow_list = metadata.webkit_allow_list()?;
+    let allow_list_refs: Vec<&str> = allow_list.iter().map(|s| s.as_str()).collect();
+    let block_list = metadata.webkit_block_list()?;
+    let block_list_refs: Vec<&str> = block_list.iter().map(|s| s.as_str()).collect();
+
+    let frames = if metadata.no_frames {
+        UserContentInjectedFrames::TopFrame
+    } else {
+        UserContentInjectedFrames::AllFrames
+    };
+    let injection_time = match metadata.run_at {
+        RunAt::Start => UserScriptInjectionTime::Start,
+        RunAt::End => UserScriptInjectionTime::End,
+    };
+
+    Some(UserScript::new(
+        source,
+        frames,
+        injection_time,
+        &allow_list_refs,
+        &block_list_refs,
+    ))
+}
@@ -1,9 +1,19 @@
 //! WebView module using WebKit2GTK for web rendering.
 
+mod permissions;
+mod scheme;
+mod unload;
+mod view_source;
+
+pub use scheme::is_internal_uri;
+pub use unload::connect_beforeunload;
+pub use view_source::view_source_uri;
+
+use gtk4::{gio, glib};
 use gtk4::prelude::*;
 use url::Url;
 use webkit6::prelude::WebViewExt as WebKitWebViewExt;
-use webkit6::{NetworkSession, UserContentManager};
+use webkit6::{NetworkSession, UserContentManager, WebContext};
 
 /// Extension trait for WebKitWebView with convenience methods
 pub trait WebViewExt {
@@ -67,27 +77,35 @@ pub fn create_webview(
     settings.set_enable_javascript(true);
 
     // === Performance Settings ===
-    // Disable WebKit's smooth scrolling - uses its own kinetic behavior
-    // that doesn't match GNOME defaults. Disabling lets GTK handle scrolling more natively.
-    settings.set_enable_smooth_scrolling(false);
+    // WebKit's smooth scrolling uses its own kinetic behavior that doesn't match
+    // GNOME defaults, so it's off unless enabled in preferences
+    apply_scroll_settings(&settings);
+
+    // === Accessibility Settings ===
+    apply_font_settings(&settings);
+    apply_caret_browsing(&settings);
 
-    // Hardware acceleration - biggest impact on scrolling performance
-    settings.set_hardware_acceleration_policy(webkit6::HardwareAccelerationPolicy::Always);
+    // Hardware acceleration - biggest impact on scrolling performance.
+    // Read at creation only: changing it on a live webview doesn't fully take effect.
+    settings.set_hardware_acceleration_policy(hardware_acceleration_policy());
 
     // Page cache - improves navigation responsiveness
     settings.set_enable_page_cache(true);
 
     // Set a user agent
-    settings.set_user_agent_with_application_details(
-        Some(crate::config::APP_NAME),
-        Some(crate::config::APP_VERSION),
-    );
+    set_default_user_agent(&settings);
 
     // Build the WebView with network session (for cookie persistence) and
     // optionally user content manager (for content blocking)
+    // Website policies are construct-only, so autoplay changes apply to new tabs
+    let policies = webkit6::WebsitePolicies::builder()
+        .autoplay(autoplay_policy())
+        .build();
+
     let mut builder = webkit6::WebView::builder()
         .settings(&settings)
-        .network_session(network_session);
+        .network_session(network_session)
+        .website_policies(&policies);
 
     if let Some(ucm) = user_content_manager {
         builder = builder.user_content_manager(ucm);
@@ -95,6 +113,15 @@ pub fn create_webview(
 
     let webview = builder.build();
 
+    // Serve internal qayeq:// pages such as the new tab page
+    if let Some(context) = webview.web_context() {
+        scheme::register_internal_scheme(&context);
+        view_source::register_view_source_scheme(&context);
+    }
+
+    // Ask before sites use location, notifications, camera or microphone
+    permissions::setup_permission_requests(&webview);
+
     // Make it expand to fill space
     webview.set_hexpand(true);
     webview.set_vexpand(true);
@@ -103,3 +130,113 @@ pub fn create_webview(
 
     webview
 }
+
+/// Override a webview's user agent, or restore the default with None.
+/// Only touches this webview's settings; takes effect on the next load.
+pub fn apply_user_agent(webview: &webkit6::WebView, user_agent: Option<&str>) {
+    let Some(settings) = WebKitWebViewExt::settings(webview) else {
+        return;
+    };
+    match user_agent {
+        Some(ua) => {
+            if settings.user_agent().as_deref() != Some(ua) {
+                settings.set_user_agent(Some(ua));
+            }
+        }
+        None => set_default_user_agent(&settings),
+    }
+}
+
+/// Apply the smooth scrolling preference to a webview's settings.
+/// Settings are per webview, so existing tabs must be updated one by one.
+pub fn apply_scroll_settings(settings: &webkit6::Settings) {
+    let app_settings = gio::Settings::new(crate::config::APP_ID);
+    settings.set_enable_smooth_scrolling(app_settings.boolean("smooth-scrolling"));
+}
+
+/// Apply the spell checking preferences.
+/// Every tab shares the default WebContext, so this updates open tabs as well.
+pub fn apply_spell_checking(context: &WebContext, app_settings: &gio::Settings) {
+    let enabled = app_settings.boolean("spell-checking");
+    context.set_spell_checking_enabled(enabled);
+    if !enabled {
+        return;
+    }
+
+    let languages = spell_checking_languages(&app_settings.string("spell-checking-languages"));
+    let languages: Vec<&str> = languages.iter().map(|l| l.as_str()).collect();
+    context.set_spell_checking_languages(&languages);
+    log::debug!("Spell checking languages: {:?}", languages);
+}
+
+/// Languages from a comma-separated list, or the system language if the list is empty
+fn spell_checking_languages(list: &str) -> Vec<String> {
+    let languages: Vec<String> = list
+        .split(',')
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.replace('-', "_"))
+        .collect();
+    if !languages.is_empty() {
+        return languages;
+    }
+
+    // Locale names such as "en_US.UTF-8"; the dictionary wants "en_US"
+    glib::language_names()
+        .iter()
+        .map(|name| name.as_str())
+        .find(|name| !name.contains(['.', '@']) && *name != "C" && *name != "POSIX")
+        .map(|name| vec![name.to_string()])
+        .unwrap_or_default()
+}
+
+/// Apply the minimum font size preference to a webview's settings.
+/// WebKit reflows the page when it changes, so open tabs update in place.
+pub fn apply_font_settings(settings: &webkit6::Settings) {
+    let app_settings = gio::Settings::new(crate::config::APP_ID);
+    let minimum = app_settings.int("minimum-font-size").max(0) as u32;
+    if settings.minimum_font_size() != minimum {
+        settings.set_minimum_font_size(minimum);
+    }
+}
+
+/// Apply the caret browsing preference to a webview's settings
+pub fn apply_caret_browsing(settings: &webkit6::Settings) {
+    let app_settings = gio::Settings::new(crate::config::APP_ID);
+    settings.set_enable_caret_browsing(app_settings.boolean("caret-browsing"));
+}
+
+/// The autoplay policy chosen in preferences
+fn autoplay_policy() -> webkit6::AutoplayPolicy {
+    let app_settings = gio::Settings::new(crate::config::APP_ID);
+    match app_settings.string("autoplay").as_str() {
+        "allow" => webkit6::AutoplayPolicy::Allow,
+        "block" => webkit6::AutoplayPolicy::Deny,
+        _ => webkit6::AutoplayPolicy::AllowWithoutSound,
+    }
+}
+
+/// The hardware acceleration policy chosen in preferences
+fn hardware_acceleration_policy() -> webkit6::HardwareAccelerationPolicy {
+    let app_settings = gio::Settings::new(crate::config::APP_ID);
+    match app_settings.string("hardware-acceleration").as_str() {
+        "never" => webkit6::HardwareAccelerationPolicy::Never,
+        _ => webkit6::HardwareAccelerationPolicy::Always,
+    }
+}
+
+/// Enable or disable JavaScript in a webview; takes effect on the next load.
+pub fn apply_javascript(webview: &webkit6::WebView, enabled: bool) {
+    if let Some(settings) = WebKitWebViewExt::settings(webview) {
+        if settings.enables_javascript() != enabled {
+            settings.set_enable_javascript(enabled);
+        }
+    }
+}
+
+fn set_default_user_agent(settings: &webkit6::Settings) {
+    settings.set_user_agent_with_application_details(
+        Some(crate::config::APP_NAME),
+        Some(crate::config::APP_VERSION),
+    );
+}
@@ -0,0 +1,124 @@
+//! Site permission prompts (location, notifications, camera, microphone).
+
+use crate::profile::{with_session_profile, PermissionKind, Profile};
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use url::Url;
+use webkit6::prelude::*;
+use webkit6::PermissionRequest;
+
+/// Answer permission requests from remembered decisions, or prompt in the window.
+pub fn setup_permission_requests(webview: &webkit6::WebView) {
+    webview.connect_permission_request(|wv, request| {
+        let kinds = requested_permissions(request);
+        if kinds.is_empty() {
+            // Let WebKit apply its default (deny) for anything we don't manage
+            return false;
+        }
+
+        let Some(origin) = wv
+            .uri()
+            .and_then(|uri| Url::parse(&uri).ok())
+            .map(|url| url.origin())
+            .filter(|origin| origin.is_tuple())
+        else {
+            request.deny();
+            return true;
+        };
+        let origin = origin.ascii_serialization();
+
+        let remembered = with_profile(wv, |profile| profile.permission_decision(&origin, &kinds)).flatten();
+        match remembered {
+            Some(true) => {
+                log::info!("Allowing {:?} for {} (remembered)", kinds, origin);
+                request.allow();
+            }
+            Some(false) => {
+                log::info!("Blocking {:?} for {} (remembered)", kinds, origin);
+                request.deny();
+            }
+            None => prompt(wv, request, origin, kinds),
+        }
+        true
+    });
+}
+
+/// The permissions a request covers, empty if it isn't one we manage
+fn requested_permissions(request: &PermissionRequest) -> Vec<PermissionKind> {
+    if request.is::<webkit6::GeolocationPermissionRequest>() {
+        return vec![PermissionKind::Geolocation];
+    }
+    if request.is::<webkit6::NotificationPermissionRequest>() {
+        return vec![PermissionKind::Notifications];
+    }
+    if let Some(media) = request.downcast_ref::<webkit6::UserMediaPermissionRequest>() {
+        let mut kinds = Vec::new();
+        if media.is_for_video_device() {
+            kinds.push(PermissionKind::Camera);
+        }
+        if media.is_for_audio_device() {
+            kinds.push(PermissionKind::Microphone);
+        }
+        return kinds;
+    }
+    Vec::new()
+}
+
+/// Ask the user whether to allow the request, optionally remembering the answer
+fn prompt(webview: &webkit6::WebView, request: &PermissionRequest, origin: String, kinds: Vec<PermissionKind>) {
+    let host = Url::parse(&origin)
+        .ok()
+        .and_then(|url| url.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| origin.clone());
+    let what = kinds
+        .iter()
+        .map(|k| k.label().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" and ");
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some(&format!("Allow {} to use your {}?", host, what)),
+        None,
+    );
+    dialog.add_response("block", "Block");
+    dialog.add_response("once", "Allow Once");
+    dialog.add_response("always", "Always Allow");
+    dialog.set_response_appearance("always", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("once"));
+    // Dismissing denies this request without remembering anything
+    dialog.set_close_response("dismiss");
+
+    let webview_weak = webview.downgrade();
+    let request = request.clone();
+    dialog.connect_response(None, move |_, response| {
+        let remember = match response {
+            "once" => {
+                request.allow();
+                None
+            }
+            "always" => {
+                request.allow();
+                Some(true)
+            }
+            "block" => {
+                request.deny();
+                Some(false)
+            }
+            _ => {
+                request.deny();
+                None
+            }
+        };
+
+        if let (Some(allowed), Some(wv)) = (remember, webview_weak.upgrade()) {
+            with_profile(&wv, |profile| profile.remember_permission(&origin, &kinds, allowed));
+        }
+    });
+
+    dialog.present(webview.root().as_ref());
+}
+
+/// Run `f` with the profile (container) the webview belongs to
+fn with_profile<R>(webview: &webkit6::WebView, f: impl FnOnce(&Profile) -> R) -> Option<R> {
+    with_session_profile(webview.network_session().as_ref(), f)
+}
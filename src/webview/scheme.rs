@@ -0,0 +1,252 @@
+//! Internal `qayeq://` pages served through a custom URI scheme.
+
+use crate::application::QayeqApplication;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use url::Url;
+use webkit6::prelude::*;
+use webkit6::{URISchemeRequest, WebContext};
+
+/// Scheme used for browser-internal pages
+pub const SCHEME: &str = "qayeq";
+
+/// Number of most-visited sites shown on the new tab page
+const TOP_SITES_LIMIT: usize = 8;
+
+/// Placeholder in the bundled page replaced with the tile JSON
+const TOP_SITES_PLACEHOLDER: &str = "/*TOP_SITES*/";
+
+/// Placeholder in the statistics page replaced with the filter stats JSON
+const FILTER_STATS_PLACEHOLDER: &str = "/*FILTER_STATS*/";
+
+thread_local! {
+    static REGISTERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A tile on the new tab page
+#[derive(Debug, Serialize)]
+struct TopSite {
+    url: String,
+    title: Option<String>,
+    host: Option<String>,
+    /// Favicon as a data: URL, if one is cached
+    icon: Option<String>,
+}
+
+/// Filter list statistics shown on `qayeq://stats`
+#[derive(Debug, Default, Serialize)]
+struct FilterStatsPage {
+    /// Scriptlets are still being loaded or fetched
+    loading: bool,
+    rules: usize,
+    scripts: usize,
+    /// Seconds since the epoch of the last fetch of the remote lists
+    last_updated: Option<u64>,
+    network_filters: Vec<NetworkFilterEntry>,
+    lists: Vec<FilterListEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct NetworkFilterEntry {
+    name: &'static str,
+    active: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct FilterListEntry {
+    name: &'static str,
+    url: &'static str,
+    cached_bytes: Option<u64>,
+}
+
+/// Register the `qayeq://` scheme handler.
+/// Scheme handlers live on the shared WebContext rather than on each tab,
+/// so this only registers on the first call. Each request resolves its
+/// container from the requesting webview's network session.
+pub fn register_internal_scheme(context: &WebContext) {
+    if REGISTERED.get() {
+        return;
+    }
+    REGISTERED.set(true);
+
+    // Web content can't link to local schemes; only the browser can load them
+    if let Some(security_manager) = context.security_manager() {
+        security_manager.register_uri_scheme_as_local(SCHEME);
+        security_manager.register_uri_scheme_as_secure(SCHEME);
+    }
+
+    context.register_uri_scheme(SCHEME, handle_request);
+    log::info!("Registered internal {}:// scheme", SCHEME);
+}
+
+/// Whether a URI points at an internal page
+pub fn is_internal_uri(uri: &str) -> bool {
+    uri.starts_with(&format!("{}:", SCHEME))
+}
+
+fn handle_request(request: &URISchemeRequest) {
+    let page = request
+        .uri()
+        .and_then(|uri| Url::parse(&uri).ok())
+        .and_then(|url| url.host_str().map(|h| h.to_string()));
+
+    match page.as_deref() {
+        Some("newtab") => serve_new_tab_page(request),
+        Some("stats") => serve_filter_stats_page(request),
+        _ => {
+            let mut error = glib::Error::new(gio::IOErrorEnum::NotFound, "Unknown internal page");
+            request.finish_error(&mut error);
+        }
+    }
+}
+
+/// Serve the new tab page with the container's most visited sites
+fn serve_new_tab_page(request: &URISchemeRequest) {
+    let entries = most_visited_for_request(request);
+
+    let sites: Rc<RefCell<Vec<TopSite>>> = Rc::new(RefCell::new(
+        entries
+            .into_iter()
+            .map(|(url, title)| TopSite {
+                host: Url::parse(&url).ok().and_then(|u| u.host_str().map(|h| h.to_string())),
+                url,
+                title,
+                icon: None,
+            })
+            .collect(),
+    ));
+
+    let favicon_database = request
+        .web_view()
+        .and_then(|wv| wv.network_session())
+        .and_then(|session| session.website_data_manager())
+        .and_then(|manager| manager.favicon_database());
+
+    let count = sites.borrow().len();
+    let Some(database) = favicon_database.filter(|_| count > 0) else {
+        finish_new_tab_page(request, &sites.borrow());
+        return;
+    };
+
+    // Look up cached favicons, then finish once every lookup has returned
+    let remaining = Rc::new(Cell::new(count));
+    for index in 0..count {
+        let url = sites.borrow()[index].url.clone();
+        let sites = Rc::clone(&sites);
+        let remaining = Rc::clone(&remaining);
+        let request = request.clone();
+        database.favicon(&url, None::<&gio::Cancellable>, move |result| {
+            if let Ok(texture) = result {
+                let png = texture.save_to_png_bytes();
+                let encoded = glib::base64_encode(&png);
+                sites.borrow_mut()[index].icon = Some(format!("data:image/png;base64,{}", encoded));
+            }
+
+            remaining.set(remaining.get() - 1);
+            if remaining.get() == 0 {
+                finish_new_tab_page(&request, &sites.borrow());
+            }
+        });
+    }
+}
+
+fn finish_new_tab_page(request: &URISchemeRequest, sites: &[TopSite]) {
+    let json = serde_json::to_string(sites).unwrap_or_else(|_| "[]".to_string());
+    finish_page(request, "/dev/myyc/qayeq/newtab.html", TOP_SITES_PLACEHOLDER, &json);
+}
+
+/// Serve the filter statistics page. It reloads itself to stay current.
+fn serve_filter_stats_page(request: &URISchemeRequest) {
+    let stats = gio::Application::default()
+        .and_downcast::<QayeqApplication>()
+        .and_then(|app| app.content_filter_manager().map(|cfm| cfm.stats()));
+
+    let page = match stats {
+        Some(stats) => {
+            let (rules, scripts) = stats.scriptlets.unwrap_or_default();
+            FilterStatsPage {
+                loading: stats.scriptlets.is_none(),
+                rules,
+                scripts,
+                last_updated: stats
+                    .last_updated
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|age| age.as_secs()),
+                network_filters: stats
+                    .network_filters
+                    .into_iter()
+                    .map(|(name, active)| NetworkFilterEntry { name, active })
+                    .collect(),
+                lists: stats
+                    .lists
+                    .into_iter()
+                    .map(|list| FilterListEntry {
+                        name: list.name,
+                        url: list.url,
+                        cached_bytes: list.cached_bytes,
+                    })
+                    .collect(),
+            }
+        }
+        None => FilterStatsPage {
+            loading: true,
+            ..Default::default()
+        },
+    };
+
+    let json = serde_json::to_string(&page).unwrap_or_else(|_| "{}".to_string());
+    finish_page(request, "/dev/myyc/qayeq/stats.html", FILTER_STATS_PLACEHOLDER, &json);
+}
+
+/// Finish a request with a bundled page, its placeholder replaced with JSON
+fn finish_page(request: &URISchemeRequest, resource: &str, placeholder: &str, json: &str) {
+    let template = match gio::resources_lookup_data(resource, gio::ResourceLookupFlags::NONE) {
+        Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+        Err(e) => {
+            log::error!("Failed to load internal page {}: {}", resource, e);
+            let mut error = glib::Error::new(gio::IOErrorEnum::NotFound, "Internal page missing");
+            request.finish_error(&mut error);
+            return;
+        }
+    };
+
+    // Escape "</" so strings such as page titles can't close the script block
+    let html = template.replace(placeholder, &json.replace("</", "<\\/"));
+
+    let bytes = glib::Bytes::from_owned(html.into_bytes());
+    let length = bytes.len() as i64;
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+    request.finish(&stream, length, Some("text/html"));
+}
+
+/// Most visited (url, title) pairs from the history of the requesting tab's container
+fn most_visited_for_request(request: &URISchemeRequest) -> Vec<(String, Option<String>)> {
+    let Some(app) = gio::Application::default().and_downcast::<QayeqApplication>() else {
+        return Vec::new();
+    };
+    let Some(pm) = app.profile_manager() else {
+        return Vec::new();
+    };
+
+    let session = request.web_view().and_then(|wv| wv.network_session());
+    let profile = session
+        .and_then(|session| pm.profile_for_session(&session))
+        .or_else(|| pm.current_profile());
+    let Some(profile) = profile else {
+        return Vec::new();
+    };
+
+    match profile.history().get_most_visited(TOP_SITES_LIMIT) {
+        Ok(entries) => entries
+            .into_iter()
+            .map(|entry| (entry.url, entry.title))
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to load top sites: {}", e);
+            Vec::new()
+        }
+    }
+}
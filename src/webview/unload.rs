@@ -0,0 +1,64 @@
+//! Confirmation for pages that warn before being left (`beforeunload`).
+
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use std::rc::Rc;
+use webkit6::prelude::*;
+use webkit6::{ScriptDialog, ScriptDialogType};
+
+/// Ask before leaving a page whose `beforeunload` handler objects, whether the
+/// tab is closing or navigating away. `on_asked` is called as the dialog is
+/// shown, and `on_answered` gets whether the page is left.
+///
+/// WebKit waits for the answer without blocking: the dialog is kept and closed
+/// once the user responds, and a closing tab only emits `close` if they leave.
+pub fn connect_beforeunload<A, F>(webview: &webkit6::WebView, on_asked: A, on_answered: F)
+where
+    A: Fn(&webkit6::WebView) + 'static,
+    F: Fn(&webkit6::WebView, bool) + 'static,
+{
+    let on_answered = Rc::new(on_answered);
+    webview.connect_script_dialog(move |wv, dialog| {
+        if dialog.dialog_type() != ScriptDialogType::BeforeUnloadConfirm {
+            // Alerts and prompts keep WebKit's own dialogs
+            return false;
+        }
+
+        let settings = gio::Settings::new(crate::config::APP_ID);
+        if !settings.boolean("confirm-leaving-pages") {
+            dialog.confirm_set_confirmed(true);
+            on_answered(wv, true);
+            return true;
+        }
+
+        on_asked(wv);
+        prompt(wv, dialog, Rc::clone(&on_answered));
+        true
+    });
+}
+
+fn prompt<F: Fn(&webkit6::WebView, bool) + 'static>(webview: &webkit6::WebView, dialog: &ScriptDialog, on_answered: Rc<F>) {
+    let alert = libadwaita::AlertDialog::new(
+        Some("Leave Site?"),
+        Some("Changes you made may not be saved."),
+    );
+    alert.add_response("stay", "Stay");
+    alert.add_response("leave", "Leave");
+    alert.set_response_appearance("leave", libadwaita::ResponseAppearance::Destructive);
+    alert.set_default_response(Some("stay"));
+    alert.set_close_response("stay");
+
+    let dialog = dialog.clone();
+    let webview_weak = webview.downgrade();
+    alert.connect_response(None, move |_, response| {
+        let leave = response == "leave";
+        dialog.confirm_set_confirmed(leave);
+        dialog.close();
+        if let Some(wv) = webview_weak.upgrade() {
+            on_answered(&wv, leave);
+        }
+    });
+
+    alert.present(webview.root().as_ref());
+}
@@ -0,0 +1,153 @@
+//! `view-source:` pages showing a page's source.
+//!
+//! WebKitGTK doesn't render `view-source:` itself, so the resource is fetched
+//! again and shown as escaped, line-numbered text.
+
+use crate::html;
+use gtk4::{gio, glib};
+use soup::prelude::*;
+use std::cell::Cell;
+use url::Url;
+use webkit6::prelude::*;
+use webkit6::{URISchemeRequest, WebContext};
+
+/// Scheme for page source views
+pub const SCHEME: &str = "view-source";
+
+thread_local! {
+    static REGISTERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Register the `view-source:` scheme handler on the shared WebContext,
+/// only on the first call, like the internal `qayeq://` scheme.
+pub fn register_view_source_scheme(context: &WebContext) {
+    if REGISTERED.get() {
+        return;
+    }
+    REGISTERED.set(true);
+
+    // Only the browser opens source views, never links on web pages
+    if let Some(security_manager) = context.security_manager() {
+        security_manager.register_uri_scheme_as_local(SCHEME);
+        security_manager.register_uri_scheme_as_secure(SCHEME);
+    }
+
+    context.register_uri_scheme(SCHEME, handle_request);
+    log::info!("Registered {}: scheme", SCHEME);
+}
+
+/// The `view-source:` URI for a page
+pub fn view_source_uri(url: &str) -> String {
+    format!("{}:{}", SCHEME, url)
+}
+
+fn handle_request(request: &URISchemeRequest) {
+    let uri = request.uri().map(|uri| uri.to_string()).unwrap_or_default();
+    let target = uri.strip_prefix(&format!("{}:", SCHEME)).unwrap_or_default().to_string();
+
+    let url = match Url::parse(&target) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        Ok(_) => {
+            finish(
+                request,
+                &error_page(&target, "Source can only be shown for web pages (http and https)."),
+            );
+            return;
+        }
+        Err(_) => {
+            finish(request, &error_page(&target, "This isn't a valid address."));
+            return;
+        }
+    };
+
+    // Fetch with the tab's user agent so sites send the same page
+    let user_agent = request
+        .web_view()
+        .and_then(|wv| WebViewExt::settings(&wv))
+        .and_then(|settings| settings.user_agent());
+
+    let request = request.clone();
+    glib::spawn_future_local(async move {
+        let page = match fetch_source(url.as_str(), user_agent.as_deref()).await {
+            Ok(source) => source_page(url.as_str(), &source),
+            Err(message) => error_page(url.as_str(), &message),
+        };
+        finish(&request, &page);
+    });
+}
+
+/// Fetch a resource's body as text
+async fn fetch_source(url: &str, user_agent: Option<&str>) -> Result<String, String> {
+    let session = soup::Session::new();
+    if let Some(user_agent) = user_agent {
+        session.set_user_agent(user_agent);
+    }
+    let message = soup::Message::new("GET", url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    let bytes = session
+        .send_and_read_future(&message, soup::glib::Priority::DEFAULT)
+        .await
+        .map_err(|e| format!("The page couldn't be loaded: {}", e))?;
+
+    let status = message.status();
+    if status != soup::Status::Ok {
+        return Err(format!("The server answered with an error ({:?}).", status));
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn finish(request: &URISchemeRequest, html: &str) {
+    let bytes = glib::Bytes::from(html.as_bytes());
+    let length = bytes.len() as i64;
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+    request.finish(&stream, length, Some("text/html"));
+}
+
+const STYLE: &str = r#"<style>
+  body { margin: 0; font-family: "Adwaita Mono", "Source Code Pro", monospace; font-size: 13px; }
+  table { border-collapse: collapse; }
+  td { padding: 0 12px; vertical-align: top; }
+  td.line-number { text-align: right; color: GrayText; user-select: none; border-right: 1px solid GrayText; }
+  td.line { white-space: pre-wrap; word-break: break-all; }
+  .message { font-family: "Adwaita Sans", Cantarell, system-ui, sans-serif; max-width: 560px; margin: 15vh auto; padding: 0 24px; line-height: 1.5; }
+</style>"#;
+
+/// A page showing `source` with numbered lines
+fn source_page(url: &str, source: &str) -> String {
+    let mut rows = String::with_capacity(source.len() * 2);
+    for (index, line) in source.lines().enumerate() {
+        rows.push_str(&format!(
+            "<tr><td class=\"line-number\">{}</td><td class=\"line\">{}</td></tr>\n",
+            index + 1,
+            html::escape(line)
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"color-scheme\" content=\"light dark\">\n<title>Source of {url}</title>\n{STYLE}\n</head>\n<body>\n<table>\n{rows}</table>\n</body>\n</html>",
+        url = html::escape(url),
+    )
+}
+
+/// A page explaining why a source can't be shown
+fn error_page(url: &str, message: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"color-scheme\" content=\"light dark\">\n<title>Source of {url}</title>\n{STYLE}\n</head>\n<body>\n<div class=\"message\">\n<h1>Can't show the source</h1>\n<p>{message}</p>\n<p><code>{url}</code></p>\n</div>\n</body>\n</html>",
+        url = html::escape(url),
+        message = html::escape(message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_page_escapes_and_numbers_lines() {
+        let page = source_page("https://example.com/", "<p>\n  a & b\n</p>");
+        assert!(page.contains("<td class=\"line-number\">1</td><td class=\"line\">&lt;p&gt;</td>"));
+        assert!(page.contains("<td class=\"line-number\">2</td><td class=\"line\">  a &amp; b</td>"));
+        assert!(page.contains("<td class=\"line-number\">3</td>"));
+        assert!(!page.contains("<p>"));
+    }
+}
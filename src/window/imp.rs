@@ -3,22 +3,35 @@ use crate::completion::history_provider::get_history_completions;
 use crate::completion::provider::CompletionItem;
 use crate::completion::row::CompletionRow;
 use crate::completion::search_provider::fetch_search_suggestions;
+use crate::clear_data;
 use crate::config;
+use crate::cookies::CookiesDialog;
 use crate::download::{self, DownloadRow};
-use crate::recently_closed::{ClosedTab, ClosedWindow, RecentlyClosedTabs};
-use crate::session::{SavedWindow, Session};
-use crate::tab::{TabManager, TabRow};
+use crate::qr;
+use crate::recently_closed::{self, ClosedTab, ClosedWindow, RecentlyClosedTabs};
+use crate::session::{SavedWindow, Session, StartupMode};
+use crate::tab::{self, SecurityLevel, TabManager, TabRow, TabSearchDialog};
 use crate::url_bar;
+use crate::user_agent::UserAgentMode;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{gio, glib, CompositeTemplate, TemplateChild};
 use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
 use libadwaita::subclass::prelude::*;
-use std::cell::{Cell, RefCell};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::time::Duration;
 use url::Url;
 
+/// Window state from before a page went fullscreen, restored when it leaves
+#[derive(Debug, Clone, Copy)]
+pub struct FullscreenRestore {
+    toolbar_visible: bool,
+    sidebar_visible: bool,
+    window_fullscreen: bool,
+}
+
 #[derive(Default, CompositeTemplate)]
 #[template(file = "../../resources/ui/window.ui")]
 pub struct QayeqWindow {
@@ -44,7 +57,19 @@ pub struct QayeqWindow {
     pub tab_sidebar: TemplateChild<gtk4::Box>,
 
     #[template_child]
-    pub tabs_list: TemplateChild<gtk4::ListBox>,
+    pub offline_indicator: TemplateChild<gtk4::Image>,
+
+    #[template_child]
+    pub media_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub link_status: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub tabs_scroll: TemplateChild<gtk4::ScrolledWindow>,
+
+    #[template_child]
+    pub tabs_list: TemplateChild<gtk4::FlowBox>,
 
     #[template_child]
     pub new_tab_button: TemplateChild<gtk4::Button>,
@@ -58,6 +83,48 @@ pub struct QayeqWindow {
     #[template_child]
     pub menu_button: TemplateChild<gtk4::MenuButton>,
 
+    #[template_child]
+    pub site_info_button: TemplateChild<gtk4::MenuButton>,
+
+    #[template_child]
+    pub container_pill: TemplateChild<gtk4::MenuButton>,
+
+    #[template_child]
+    pub site_info_popover: TemplateChild<gtk4::Popover>,
+
+    #[template_child]
+    pub site_info_host: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub site_security_label: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub site_certificate_label: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub site_insecure_content_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub site_cookies_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub site_clear_data_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub site_forget_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub site_force_dark_check: TemplateChild<gtk4::CheckButton>,
+    #[template_child]
+    pub site_fingerprinting_check: TemplateChild<gtk4::CheckButton>,
+    #[template_child]
+    pub site_javascript_check: TemplateChild<gtk4::CheckButton>,
+
+    #[template_child]
+    pub recently_closed_menu: TemplateChild<gio::Menu>,
+    #[template_child]
+    pub move_to_window_menu: TemplateChild<gio::Menu>,
+
     #[template_child]
     pub window_controls: TemplateChild<gtk4::Box>,
 
@@ -73,9 +140,24 @@ pub struct QayeqWindow {
     #[template_child]
     pub download_empty_label: TemplateChild<gtk4::Label>,
 
+    #[template_child]
+    pub download_bulk_buttons: TemplateChild<gtk4::Box>,
+
+    #[template_child]
+    pub download_pause_all_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub download_resume_all_button: TemplateChild<gtk4::Button>,
+
+    #[template_child]
+    pub download_cancel_all_button: TemplateChild<gtk4::Button>,
+
     #[template_child]
     pub toast_overlay: TemplateChild<libadwaita::ToastOverlay>,
 
+    #[template_child]
+    pub restore_banner: TemplateChild<libadwaita::Banner>,
+
     #[template_child]
     pub main_overlay: TemplateChild<gtk4::Overlay>,
 
@@ -87,6 +169,9 @@ pub struct QayeqWindow {
 
     pub tab_manager: RefCell<Option<Rc<TabManager>>>,
 
+    // App settings, kept to follow the toolbar and sidebar visibility preferences
+    pub settings: OnceCell<gio::Settings>,
+
     // Hide timeout source IDs (Rc for sharing in closures)
     pub toolbar_hide_source: Rc<RefCell<Option<glib::SourceId>>>,
     pub sidebar_hide_source: Rc<RefCell<Option<glib::SourceId>>>,
@@ -107,11 +192,18 @@ pub struct QayeqWindow {
     // Flag to track if sidebar is pinned (via Ctrl+T, stays until click on webview)
     pub sidebar_pinned: Rc<Cell<bool>>,
 
+    // Focus mode: toolbar and sidebar stay hidden until it's turned off
+    pub focus_mode: Cell<bool>,
+
+    // While a page shows an element fullscreen, what to restore when it leaves
+    pub page_fullscreen: Cell<Option<FullscreenRestore>>,
+
     // Inline autocomplete state
     pub autocomplete_url: RefCell<Option<String>>,   // The full autocompleted URL
     pub user_input_len: Cell<usize>,                 // Length of user's actual input
     pub explicit_selection: Cell<bool>,              // True if user used arrow keys to select
     pub setting_autocomplete: Cell<bool>,            // True while programmatically setting text
+    pub full_url: RefCell<Option<String>>,           // The page URL while the bar shows it simplified
 
     // Double-tap Shift detection state
     pub last_shift_release: RefCell<Option<std::time::Instant>>,  // When Shift was last released
@@ -125,6 +217,9 @@ pub struct QayeqWindow {
 
     // Pending session data to restore (for new_with_session)
     pub pending_session: RefCell<Option<SavedWindow>>,
+
+    // Set once every tab's page has agreed to the window closing
+    pub close_confirmed: Cell<bool>,
 }
 
 #[glib::object_subclass]
@@ -162,6 +257,59 @@ impl ObjectImpl for QayeqWindow {
             .build();
         obj.add_action_entries([action_show_shortcuts]);
 
+        // "Always show" preferences take effect right away; turning one off hides
+        // the toolbar now and the sidebar after the usual delay
+        for key in ["always-show-toolbar", "always-show-sidebar"] {
+            let obj_weak = obj.downgrade();
+            self.settings().connect_changed(Some(key), move |settings, key| {
+                let Some(obj) = obj_weak.upgrade() else {
+                    return;
+                };
+                let imp = obj.imp();
+                match (key, settings.boolean(key)) {
+                    ("always-show-toolbar", true) => imp.show_toolbar(),
+                    ("always-show-toolbar", false) if !imp.toolbar_pinned.get() => imp.hide_toolbar(),
+                    ("always-show-sidebar", true) => imp.show_sidebar(),
+                    ("always-show-sidebar", false) if !imp.sidebar_pinned.get() => imp.flash_sidebar(),
+                    _ => {}
+                }
+            });
+        }
+        if self.settings().boolean("always-show-toolbar") {
+            self.show_toolbar();
+        }
+
+        let obj_weak = obj.downgrade();
+        self.settings().connect_changed(Some("tab-layout"), move |_, _| {
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().apply_tab_layout();
+            }
+        });
+
+        // A horizontal tab strip spans the window under the toolbar
+        let obj_weak = obj.downgrade();
+        self.main_overlay.connect_get_child_position(move |overlay, widget| {
+            let obj = obj_weak.upgrade()?;
+            let imp = obj.imp();
+            let sidebar = imp.tab_sidebar.upcast_ref::<gtk4::Widget>();
+            if widget != sidebar || imp.tab_sidebar.orientation() != gtk4::Orientation::Horizontal {
+                return None;
+            }
+            // Both measurements include the widgets' margins
+            let (_, toolbar_height, _, _) = imp.toolbar.measure(gtk4::Orientation::Vertical, -1);
+            let width = overlay.width();
+            let (_, height, _, _) = sidebar.measure(gtk4::Orientation::Vertical, width);
+            Some(gtk4::gdk::Rectangle::new(0, toolbar_height, width, height))
+        });
+        if self.settings().boolean("always-show-sidebar") {
+            self.show_sidebar();
+        }
+
+        // App actions that depend on the active tab follow the focused window
+        obj.connect_is_active_notify(|window| {
+            window.imp().update_save_page_action();
+        });
+
         // Defer initialization until application is available
         let obj_weak = obj.downgrade();
         glib::idle_add_local_once(move || {
@@ -170,7 +318,7 @@ impl ObjectImpl for QayeqWindow {
             }
         });
 
-        // Window-level key handler for Escape (toggle toolbar), F1 (toggle sidebar), and double-tap Shift
+        // Window-level key handler for Escape (stop loading or toggle toolbar), F1 (toggle sidebar), and double-tap Shift
         let window_key_controller = gtk4::EventControllerKey::new();
         window_key_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
         let obj_weak = obj.downgrade();
@@ -179,6 +327,14 @@ impl ObjectImpl for QayeqWindow {
                 let imp = obj.imp();
                 match key {
                     gtk4::gdk::Key::Escape => {
+                        // A page shown fullscreen gets Escape, and WebKit leaves fullscreen
+                        if imp.page_fullscreen.get().is_some() {
+                            return glib::Propagation::Proceed;
+                        }
+                        if imp.focus_mode.get() {
+                            imp.set_focus_mode(false);
+                            return glib::Propagation::Stop;
+                        }
                         // Cancel any pending completion requests
                         if let Some(source_id) = imp.completion_debounce.borrow_mut().take() {
                             source_id.remove();
@@ -195,14 +351,21 @@ impl ObjectImpl for QayeqWindow {
                             if let Some(url) = tm.current_url() {
                                 imp.url_entry.set_text(url.as_str());
                             }
+                            // A load in progress is stopped instead
+                            if tm.is_loading_current() {
+                                tm.stop_current();
+                                return glib::Propagation::Stop;
+                            }
                         }
                         // Toggle toolbar
                         imp.toggle_toolbar();
                         return glib::Propagation::Stop;
                     }
                     gtk4::gdk::Key::F1 => {
-                        // Toggle sidebar
-                        imp.toggle_sidebar();
+                        // Toggle sidebar, which stays hidden in focus mode
+                        if !imp.chrome_suppressed() {
+                            imp.toggle_sidebar();
+                        }
                         return glib::Propagation::Stop;
                     }
                     // Track Shift press - start clean if no other modifiers held
@@ -234,7 +397,7 @@ impl ObjectImpl for QayeqWindow {
                     // Copy out the last release time to avoid borrow conflict
                     let last_release = *imp.last_shift_release.borrow();
 
-                    if let Some(last) = last_release {
+                    if let Some(last) = last_release.filter(|_| !imp.chrome_suppressed()) {
                         if now.duration_since(last) < double_tap_threshold {
                             // Double-tap detected! Toggle sidebar
                             imp.toggle_sidebar();
@@ -252,14 +415,20 @@ impl ObjectImpl for QayeqWindow {
 
         obj.add_controller(window_key_controller);
 
-        // URL entry key handler for arrow navigation in completions
+        // URL entry key handler for arrow navigation in completions, and paste
+        // and go, which is only bound here so pages and the inspector keep Ctrl+Shift+V
         let entry_key_controller = gtk4::EventControllerKey::new();
         entry_key_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
         let obj_weak = obj.downgrade();
-        entry_key_controller.connect_key_pressed(move |_, key, _, _| {
+        entry_key_controller.connect_key_pressed(move |_, key, _, state| {
             if let Some(obj) = obj_weak.upgrade() {
                 let imp = obj.imp();
+                let control_shift = gtk4::gdk::ModifierType::CONTROL_MASK | gtk4::gdk::ModifierType::SHIFT_MASK;
                 match key {
+                    gtk4::gdk::Key::v | gtk4::gdk::Key::V if state.contains(control_shift) => {
+                        imp.paste_and_go(false);
+                        return glib::Propagation::Stop;
+                    }
                     gtk4::gdk::Key::Down => {
                         if imp.completion_scroll.is_visible() {
                             imp.explicit_selection.set(true);
@@ -283,6 +452,19 @@ impl ObjectImpl for QayeqWindow {
         });
         self.url_entry.add_controller(entry_key_controller);
 
+        // Middle-click in the URL bar goes to the selected text
+        let middle_click = gtk4::GestureClick::new();
+        middle_click.set_button(gtk4::gdk::BUTTON_MIDDLE);
+        middle_click.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let obj_weak = obj.downgrade();
+        middle_click.connect_pressed(move |gesture, _, _, _| {
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().paste_and_go(true);
+            }
+        });
+        self.url_entry.add_controller(middle_click);
+
         // Connect close button
         let obj_weak = obj.downgrade();
         self.close_button.connect_clicked(move |_| {
@@ -296,10 +478,15 @@ impl ObjectImpl for QayeqWindow {
         let toolbar = self.toolbar.clone();
         let window_controls = self.window_controls.clone();
         let toolbar_hide_source = Rc::clone(&self.toolbar_hide_source);
+        let obj_weak = obj.downgrade();
         self.menu_button.connect_active_notify(move |btn| {
             let is_active = btn.is_active();
             menu_open.set(is_active);
             if is_active {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().refresh_recently_closed_menu();
+                    obj.imp().refresh_move_to_window_menu();
+                }
                 // Cancel any pending hide and show both
                 if let Some(source_id) = toolbar_hide_source.borrow_mut().take() {
                     let _ = source_id.remove();
@@ -311,15 +498,152 @@ impl ObjectImpl for QayeqWindow {
             }
         });
 
+        // Site information popover: keep the toolbar up and show the current site
+        {
+            let menu_open = Rc::clone(&self.menu_open);
+            let obj_weak = obj.downgrade();
+            self.site_info_button.connect_active_notify(move |btn| {
+                menu_open.set(btn.is_active());
+                if btn.is_active() {
+                    if let Some(obj) = obj_weak.upgrade() {
+                        obj.imp().update_site_info();
+                    }
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_javascript_check.connect_toggled(move |check| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    if let Some(tm) = obj.imp().tab_manager.borrow().as_ref() {
+                        tm.set_site_javascript(!check.is_active());
+                    }
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_force_dark_check.connect_toggled(move |check| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().set_force_dark_for_current_site(check.is_active());
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_fingerprinting_check.connect_toggled(move |check| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().set_fingerprinting_protection_for_current_site(check.is_active());
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_cookies_button.connect_clicked(move |_| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().site_info_popover.popdown();
+                    obj.imp().show_cookies_dialog();
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_insecure_content_button.connect_clicked(move |_| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().site_info_popover.popdown();
+                    if let Some(tm) = obj.imp().tab_manager.borrow().as_ref() {
+                        tm.load_insecure_content();
+                    }
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_clear_data_button.connect_clicked(move |_| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().site_info_popover.popdown();
+                    obj.imp().confirm_clear_current_site_data();
+                }
+            });
+
+            let obj_weak = obj.downgrade();
+            self.site_forget_button.connect_clicked(move |_| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    obj.imp().site_info_popover.popdown();
+                    obj.imp().confirm_forget_current_site();
+                }
+            });
+        }
+
+        // Keyboard handling for the downloads list: Enter opens a finished download
+        // (Shift+Enter shows it in its folder), Delete dismisses it
+        self.download_list.connect_row_activated(|_, row| {
+            if let Some(item) = download_row_id(row).and_then(download::get_download) {
+                if item.status == download::DownloadStatus::Completed {
+                    download::open_file(&item.destination);
+                }
+            }
+        });
+
+        let download_key_controller = gtk4::EventControllerKey::new();
+        let download_list = self.download_list.clone();
+        download_key_controller.connect_key_pressed(move |_, key, _, modifier| {
+            let show_in_folder = matches!(key, gtk4::gdk::Key::Return | gtk4::gdk::Key::KP_Enter)
+                && modifier.contains(gtk4::gdk::ModifierType::SHIFT_MASK);
+            let dismiss = matches!(key, gtk4::gdk::Key::Delete | gtk4::gdk::Key::KP_Delete);
+            if !show_in_folder && !dismiss {
+                return glib::Propagation::Proceed;
+            }
+
+            let row = download_list
+                .focus_child()
+                .and_then(|w| w.downcast::<gtk4::ListBoxRow>().ok());
+            let Some(item) = row.as_ref().and_then(download_row_id).and_then(download::get_download) else {
+                return glib::Propagation::Proceed;
+            };
+            if show_in_folder {
+                if item.status == download::DownloadStatus::Completed {
+                    download::show_in_folder(&item.destination);
+                }
+            } else if !item.is_active() {
+                // Active downloads have to be cancelled first, like with the dismiss button
+                download::remove_download(item.id);
+            }
+            glib::Propagation::Stop
+        });
+        self.download_list.add_controller(download_key_controller);
+
+        self.download_pause_all_button.connect_clicked(|_| download::pause_all());
+        self.download_resume_all_button.connect_clicked(|_| download::resume_all());
+        let obj_weak = obj.downgrade();
+        self.download_cancel_all_button.connect_clicked(move |_| {
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().confirm_cancel_all_downloads();
+            }
+        });
+
         // Subscribe to download changes to update UI
         let download_button = self.download_button.clone();
+        let download_bulk_buttons = self.download_bulk_buttons.clone();
+        let download_pause_all_button = self.download_pause_all_button.clone();
+        let download_resume_all_button = self.download_resume_all_button.clone();
+        let download_cancel_all_button = self.download_cancel_all_button.clone();
         let download_progress = self.download_progress.clone();
         let download_list = self.download_list.clone();
         let download_empty_label = self.download_empty_label.clone();
         let toast_overlay = self.toast_overlay.clone();
         let last_download_count: Rc<Cell<usize>> = Rc::new(Cell::new(0));
 
+        // Between progress updates rows still refresh, to keep speed and time left honest
+        let refresh_timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
         download::subscribe_to_changes(move || {
+            if download::active_download_count() > 0 && refresh_timer.borrow().is_none() {
+                let timer = Rc::clone(&refresh_timer);
+                let source = glib::timeout_add_local(Duration::from_secs(1), move || {
+                    if download::refresh() {
+                        glib::ControlFlow::Continue
+                    } else {
+                        timer.borrow_mut().take();
+                        glib::ControlFlow::Break
+                    }
+                });
+                refresh_timer.replace(Some(source));
+            }
+
             let downloads = download::recent_downloads(10);
             let current_count = downloads.len();
             let prev_count = last_download_count.get();
@@ -342,25 +666,20 @@ impl ObjectImpl for QayeqWindow {
 
             download_button.set_visible(has_downloads);
 
-            // Update progress bar - show aggregate progress of active or paused downloads
-            let in_progress_downloads: Vec<_> = downloads.iter()
-                .filter(|d| d.is_active() || d.is_paused())
-                .collect();
-
-            if !in_progress_downloads.is_empty() {
-                let total_bytes: u64 = in_progress_downloads.iter().map(|d| d.total_bytes).sum();
-                let received_bytes: u64 = in_progress_downloads.iter().map(|d| d.received_bytes).sum();
+            // Bulk controls only while something is downloading or paused
+            let bulk = download::bulk_actions();
+            download_bulk_buttons.set_visible(bulk.can_cancel);
+            download_pause_all_button.set_sensitive(bulk.can_pause);
+            download_resume_all_button.set_sensitive(bulk.can_resume);
+            download_cancel_all_button.set_sensitive(bulk.can_cancel);
 
-                let fraction = if total_bytes > 0 {
-                    received_bytes as f64 / total_bytes as f64
-                } else {
-                    0.0
-                };
-
-                download_progress.set_visible(true);
-                download_progress.set_fraction(fraction);
-            } else {
-                download_progress.set_visible(false);
+            // Update progress bar - show aggregate progress of active or paused downloads
+            match download::aggregate_progress() {
+                Some(fraction) => {
+                    download_progress.set_visible(true);
+                    download_progress.set_fraction(fraction);
+                }
+                None => download_progress.set_visible(false),
             }
 
             // Update download list by reusing existing rows to prevent flickering
@@ -375,8 +694,8 @@ impl ObjectImpl for QayeqWindow {
             while let Some(widget) = child {
                 let next = widget.next_sibling();
                 if let Some(row) = widget.downcast_ref::<gtk4::ListBoxRow>() {
-                    if let Some(download_row) = row.child().and_then(|c| c.downcast::<DownloadRow>().ok()) {
-                        if !download_ids.contains(&download_row.download_id()) {
+                    if let Some(id) = download_row_id(row) {
+                        if !download_ids.contains(&id) {
                             rows_to_remove.push(row.clone());
                         }
                     }
@@ -384,6 +703,18 @@ impl ObjectImpl for QayeqWindow {
                 child = next;
             }
             for row in rows_to_remove {
+                // Keep keyboard focus in the list when the focused row goes away
+                if row.state_flags().contains(gtk4::StateFlags::FOCUS_WITHIN) {
+                    let neighbour = [row.next_sibling(), row.prev_sibling()]
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|w| w.downcast::<gtk4::ListBoxRow>().ok())
+                        .find(|r| download_row_id(r).is_some_and(|id| download_ids.contains(&id)));
+                    if let Some(neighbour) = neighbour {
+                        download_list.select_row(Some(&neighbour));
+                        neighbour.grab_focus();
+                    }
+                }
                 download_list.remove(&row);
             }
 
@@ -394,6 +725,7 @@ impl ObjectImpl for QayeqWindow {
                 let mut child = download_list.first_child();
                 while let Some(widget) = child {
                     if let Some(row) = widget.downcast_ref::<gtk4::ListBoxRow>() {
+                        // Rows are matched by ID and updated in place, so focus and selection stay put
                         if let Some(download_row) = row.child().and_then(|c| c.downcast::<DownloadRow>().ok()) {
                             if download_row.download_id() == item.id {
                                 download_row.update(item);
@@ -414,31 +746,8 @@ impl ObjectImpl for QayeqWindow {
                             download::cancel_download(id);
                         } else {
                             // Open folder containing the download
-                            if let Some(item) = download::recent_downloads(100)
-                                .iter()
-                                .find(|d| d.id == id)
-                            {
-                                let path = item.destination.clone();
-                                let file = gtk4::gio::File::for_path(&path);
-                                let launcher = gtk4::FileLauncher::new(Some(&file));
-                                launcher.open_containing_folder(
-                                    gtk4::Window::NONE,
-                                    gtk4::gio::Cancellable::NONE,
-                                    move |result| {
-                                        if let Err(e) = result {
-                                            log::warn!("Portal open_containing_folder failed: {}, trying xdg-open", e);
-                                            // Fallback to xdg-open
-                                            if let Some(parent) = path.parent() {
-                                                if let Err(e) = std::process::Command::new("xdg-open")
-                                                    .arg(parent)
-                                                    .spawn()
-                                                {
-                                                    log::error!("Failed to open folder with xdg-open: {}", e);
-                                                }
-                                            }
-                                        }
-                                    },
-                                );
+                            if let Some(item) = download::get_download(id) {
+                                download::show_in_folder(&item.destination);
                             }
                         }
                     });
@@ -460,12 +769,83 @@ impl ObjectImpl for QayeqWindow {
     }
 }
 
+/// Search URL template chosen in preferences, with the custom engine's own URL
+fn global_search_template(settings: &gio::Settings) -> String {
+    let search_engine = settings.string("search-engine");
+    let custom = settings.string("custom-search-url");
+    if search_engine == "custom" && custom.contains("{}") {
+        return custom.to_string();
+    }
+    config::SEARCH_ENGINES
+        .iter()
+        .find(|(id, _, url)| *id == search_engine.as_str() && !url.is_empty())
+        .map(|(_, _, url)| *url)
+        .unwrap_or(config::DEFAULT_SEARCH_URL)
+        .to_string()
+}
+
+/// Show a page's connection security on the site information button
+fn update_security_indicator(button: &gtk4::MenuButton, level: SecurityLevel) {
+    button.set_icon_name(level.icon_name());
+    button.set_tooltip_text(Some(level.description()));
+    if matches!(level, SecurityLevel::Insecure | SecurityLevel::LookalikeDomain) {
+        button.add_css_class("insecure");
+    } else {
+        button.remove_css_class("insecure");
+    }
+}
+
+/// Show a container's name and color on the header pill
+fn update_container_pill(pill: &gtk4::MenuButton, container_id: &str) {
+    let name = gio::Application::default()
+        .and_downcast::<QayeqApplication>()
+        .map(|app| app.container_name(container_id))
+        .unwrap_or_else(|| container_id.to_string());
+    pill.set_label(&name);
+    pill.set_tooltip_text(Some(&format!("Container: {}", name)));
+
+    for class in pill.css_classes() {
+        if class.starts_with("container-") && class != "container-pill" {
+            pill.remove_css_class(&class);
+        }
+    }
+    pill.add_css_class(&format!("container-{}", container_id));
+    pill.set_visible(true);
+}
+
+/// Menu of the container pill: open a new tab in any container
+fn container_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+    let app = gio::Application::default().and_downcast::<QayeqApplication>();
+    for container in app.map(|app| app.containers()).unwrap_or_default() {
+        menu.append(
+            Some(&format!("New {} Tab", container.name)),
+            Some(&format!("app.new-tab-{}", container.id)),
+        );
+    }
+    menu
+}
+
+/// The ID of the download shown in a row of the downloads list
+fn download_row_id(row: &gtk4::ListBoxRow) -> Option<u64> {
+    row.child()
+        .and_then(|c| c.downcast::<DownloadRow>().ok())
+        .map(|download_row| download_row.download_id())
+}
+
 impl QayeqWindow {
+    fn settings(&self) -> &gio::Settings {
+        self.settings.get_or_init(|| gio::Settings::new(config::APP_ID))
+    }
+
     fn show_toolbar(&self) {
         // Cancel any pending hide
         if let Some(source_id) = self.toolbar_hide_source.borrow_mut().take() {
             let _ = source_id.remove();
         }
+        if self.chrome_suppressed() {
+            return;
+        }
         // Show both toolbar and window controls together
         self.toolbar.remove_css_class("hidden");
         self.toolbar.set_can_target(true);
@@ -478,6 +858,9 @@ impl QayeqWindow {
         if let Some(source_id) = self.toolbar_hide_source.borrow_mut().take() {
             let _ = source_id.remove();
         }
+        if self.settings().boolean("always-show-toolbar") && !self.chrome_suppressed() {
+            return;
+        }
         self.toolbar.add_css_class("hidden");
         self.toolbar.set_can_target(false);
         self.window_controls.add_css_class("hidden");
@@ -494,22 +877,85 @@ impl QayeqWindow {
     }
 
     pub fn toggle_sidebar(&self) {
+        if self.tab_sidebar.has_css_class("hidden") {
+            self.show_sidebar();
+        } else {
+            self.hide_sidebar();
+        }
+    }
+
+    fn hide_sidebar(&self) {
         if let Some(source_id) = self.sidebar_hide_source.borrow_mut().take() {
             source_id.remove();
         }
-        if self.tab_sidebar.has_css_class("hidden") {
-            self.tab_sidebar.remove_css_class("hidden");
-            self.tab_sidebar.set_can_target(true);
+        if self.settings().boolean("always-show-sidebar") && !self.chrome_suppressed() {
+            return;
+        }
+        self.tab_sidebar.add_css_class("hidden");
+        self.tab_sidebar.set_can_target(false);
+    }
+
+    /// Show tabs in the vertical sidebar or in a horizontal strip, as set in preferences
+    fn apply_tab_layout(&self) {
+        let horizontal = self.settings().string("tab-layout") == "horizontal";
+        let sidebar = &self.tab_sidebar;
+        let scroll = &self.tabs_scroll;
+        if horizontal {
+            sidebar.set_orientation(gtk4::Orientation::Horizontal);
+            sidebar.set_halign(gtk4::Align::Fill);
+            sidebar.set_valign(gtk4::Align::Start);
+            sidebar.set_width_request(-1);
+            sidebar.set_margin_end(4);
+            scroll.set_policy(gtk4::PolicyType::External, gtk4::PolicyType::Never);
+            scroll.set_hexpand(true);
+            scroll.set_vexpand(false);
+            scroll.set_propagate_natural_height(true);
         } else {
-            self.tab_sidebar.add_css_class("hidden");
-            self.tab_sidebar.set_can_target(false);
+            sidebar.set_orientation(gtk4::Orientation::Vertical);
+            sidebar.set_halign(gtk4::Align::Start);
+            sidebar.set_valign(gtk4::Align::Fill);
+            sidebar.set_width_request(150);
+            sidebar.set_margin_end(0);
+            scroll.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::External);
+            scroll.set_hexpand(false);
+            scroll.set_vexpand(true);
+            scroll.set_propagate_natural_height(false);
+        }
+        if let Some(tm) = self.tab_manager.borrow().as_ref() {
+            tm.set_horizontal_tabs(horizontal);
         }
     }
 
+    /// Show the sidebar, then hide it again after the auto-hide delay
+    fn flash_sidebar(&self) {
+        if self.chrome_suppressed() {
+            return;
+        }
+        self.show_sidebar();
+        if self.settings().boolean("always-show-sidebar") {
+            return;
+        }
+
+        let delay = self.settings().int("auto-hide-delay").max(1) as u64;
+        let obj_weak = self.obj().downgrade();
+        let source_id = glib::timeout_add_local_once(Duration::from_secs(delay), move || {
+            if let Some(obj) = obj_weak.upgrade() {
+                let imp = obj.imp();
+                // The source is done; don't let hide_sidebar() remove it
+                imp.sidebar_hide_source.borrow_mut().take();
+                imp.hide_sidebar();
+            }
+        });
+        *self.sidebar_hide_source.borrow_mut() = Some(source_id);
+    }
+
     pub fn show_sidebar(&self) {
         if let Some(source_id) = self.sidebar_hide_source.borrow_mut().take() {
             source_id.remove();
         }
+        if self.chrome_suppressed() {
+            return;
+        }
         self.tab_sidebar.remove_css_class("hidden");
         self.tab_sidebar.set_can_target(true);
     }
@@ -519,6 +965,96 @@ impl QayeqWindow {
         self.show_sidebar();
     }
 
+    /// Turn focus mode on or off. While it's on, the toolbar and sidebar stay
+    /// hidden: their show paths return early and the "always show" preferences
+    /// are overridden. Turning it off brings back what those preferences keep up.
+    pub fn set_focus_mode(&self, enabled: bool) {
+        if self.focus_mode.get() == enabled {
+            return;
+        }
+        let obj = self.obj();
+        if enabled {
+            self.toolbar_pinned.set(false);
+            self.sidebar_pinned.set(false);
+            self.completion_scroll.set_visible(false);
+            self.focus_mode.set(true);
+            obj.add_css_class("focus-mode");
+            self.hide_toolbar();
+            self.hide_sidebar();
+            if let Some(tm) = self.tab_manager.borrow().as_ref() {
+                tm.focus_current_webview();
+            }
+        } else {
+            self.focus_mode.set(false);
+            obj.remove_css_class("focus-mode");
+            if self.settings().boolean("always-show-toolbar") {
+                self.show_toolbar();
+            }
+            if self.settings().boolean("always-show-sidebar") {
+                self.show_sidebar();
+            }
+        }
+    }
+
+    pub fn toggle_focus_mode(&self) {
+        self.set_focus_mode(!self.focus_mode.get());
+    }
+
+    /// Whether the toolbar and sidebar are kept hidden, by focus mode or by a
+    /// page shown fullscreen
+    fn chrome_suppressed(&self) -> bool {
+        self.focus_mode.get() || self.page_fullscreen.get().is_some()
+    }
+
+    /// Make the window fullscreen around a page that went fullscreen, hiding
+    /// the toolbar and sidebar
+    fn enter_page_fullscreen(&self) {
+        if self.page_fullscreen.get().is_some() {
+            return;
+        }
+        let obj = self.obj();
+        self.page_fullscreen.set(Some(FullscreenRestore {
+            toolbar_visible: !self.toolbar.has_css_class("hidden"),
+            sidebar_visible: !self.tab_sidebar.has_css_class("hidden"),
+            window_fullscreen: obj.is_fullscreen(),
+        }));
+        self.completion_scroll.set_visible(false);
+        self.hide_toolbar();
+        self.hide_sidebar();
+        obj.fullscreen();
+    }
+
+    /// Put the window back the way it was before the page went fullscreen,
+    /// whether the site left fullscreen or the user did
+    fn leave_page_fullscreen(&self) {
+        let Some(restore) = self.page_fullscreen.take() else {
+            return;
+        };
+        if !restore.window_fullscreen {
+            self.obj().unfullscreen();
+        }
+        if restore.toolbar_visible {
+            self.show_toolbar();
+        }
+        if restore.sidebar_visible {
+            self.show_sidebar();
+        }
+    }
+
+    /// F11: leave a page's fullscreen, or toggle the window's own
+    pub fn toggle_fullscreen(&self) {
+        let obj = self.obj();
+        if self.page_fullscreen.get().is_some() {
+            if let Some(tm) = self.tab_manager.borrow().as_ref() {
+                tm.exit_page_fullscreen();
+            }
+        } else if obj.is_fullscreen() {
+            obj.unfullscreen();
+        } else {
+            obj.fullscreen();
+        }
+    }
+
     /// Show the keyboard shortcuts window
     fn show_shortcuts_window(&self) {
         let builder = gtk4::Builder::from_resource("/dev/myyc/qayeq/ui/shortcuts.ui");
@@ -559,10 +1095,10 @@ impl QayeqWindow {
                 webkit6::NetworkSession::new_ephemeral()
             });
 
-        // Get UserContentManager from content filter manager (if available)
-        let user_content_manager = app
+        // Get the containers' content managers from the content filter manager (if available)
+        let content_managers = app
             .content_filter_manager()
-            .map(|cfm| cfm.user_content_manager().clone());
+            .map(|cfm| cfm.content_managers().clone());
 
         // Initialize tab manager with all needed widgets
         let tab_manager = TabManager::new(
@@ -573,17 +1109,101 @@ impl QayeqWindow {
             obj.upcast_ref::<libadwaita::ApplicationWindow>(),
             self.back_button.get(),
             self.forward_button.get(),
+            self.refresh_button.get(),
             &app,
             network_session,
-            user_content_manager,
+            content_managers,
         );
 
         // Set window reference
         tab_manager.set_window(obj.upcast_ref::<libadwaita::ApplicationWindow>());
 
+        // Offer to allow pop-ups once a tab starts being limited
+        let toast_overlay = self.toast_overlay.clone();
+        let tm_weak = Rc::downgrade(&tab_manager);
+        tab_manager.set_on_popups_blocked(move |tab_id| {
+            let toast = libadwaita::Toast::new("Pop-ups blocked");
+            toast.set_button_label(Some("Allow"));
+            toast.set_timeout(5);
+            let tm_weak = tm_weak.clone();
+            toast.connect_button_clicked(move |_| {
+                if let Some(tm) = tm_weak.upgrade() {
+                    tm.allow_popups(tab_id);
+                }
+            });
+            toast_overlay.add_toast(toast);
+        });
+
+        // Offer to stop a page that keeps loading; letting the toast go keeps waiting
+        let toast_overlay = self.toast_overlay.clone();
+        let tm_weak = Rc::downgrade(&tab_manager);
+        tab_manager.set_on_load_timed_out(move |tab_id| {
+            let Some(tm) = tm_weak.upgrade() else {
+                return;
+            };
+            let message = match tm.tab_title(tab_id).filter(|title| !title.is_empty()) {
+                // Toast titles are markup
+                Some(title) => format!("{} is taking a long time to load", glib::markup_escape_text(&title)),
+                None => "This page is taking a long time to load".to_string(),
+            };
+            let toast = libadwaita::Toast::new(&message);
+            toast.set_button_label(Some("Stop"));
+            toast.set_timeout(10);
+            let tm_weak = tm_weak.clone();
+            toast.connect_button_clicked(move |_| {
+                if let Some(tm) = tm_weak.upgrade() {
+                    tm.stop_loading(tab_id);
+                }
+            });
+            toast_overlay.add_toast(toast);
+        });
+
+        // Show which container the active tab is in
+        // Rebuilt on every open so user containers are listed
+        self.container_pill.set_create_popup_func(|pill| {
+            pill.set_menu_model(Some(&container_menu()));
+        });
+        let container_pill = self.container_pill.clone();
+        tab_manager.set_on_active_container_changed(move |container_id| {
+            update_container_pill(&container_pill, container_id);
+        });
+
+        // The site information button doubles as the connection security indicator
+        let site_info_button = self.site_info_button.clone();
+        tab_manager.set_on_security_changed(move |level| {
+            update_security_indicator(&site_info_button, level);
+        });
+
+        // "Save Page" follows the page the active tab shows
+        let window_weak = self.obj().downgrade();
+        tab_manager.set_on_page_changed(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().update_save_page_action();
+            }
+        });
+
+        // Show where a hovered link goes; the label keeps its text while fading out
+        let link_status = self.link_status.clone();
+        tab_manager.set_on_link_hovered(move |uri| match uri {
+            Some(uri) => {
+                link_status.set_label(uri);
+                link_status.remove_css_class("hidden");
+            }
+            None => link_status.add_css_class("hidden"),
+        });
+
+        // Tabs close once their page agrees to be left; record them for recently closed
+        let obj_weak = obj.downgrade();
+        tab_manager.set_on_tab_closed(move |closed_tab| {
+            if let Some(obj) = obj_weak.upgrade() {
+                let imp = obj.imp();
+                imp.recently_closed_tabs.borrow_mut().push(closed_tab);
+                imp.update_sidebar_visibility();
+            }
+        });
+
         // Set up tab count change callback for sidebar visibility
-        let tab_sidebar = self.tab_sidebar.clone();
-        let sidebar_hide_source = Rc::clone(&self.sidebar_hide_source);
+        let obj_weak = obj.downgrade();
         let initialized = Rc::clone(&self.initialized);
         tab_manager.set_on_tab_count_changed(move |_count| {
             // Skip during startup to avoid flash
@@ -591,21 +1211,9 @@ impl QayeqWindow {
                 return;
             }
             // Show sidebar briefly when tabs change
-            if let Some(source_id) = sidebar_hide_source.borrow_mut().take() {
-                source_id.remove();
-            }
-            tab_sidebar.remove_css_class("hidden");
-            tab_sidebar.set_can_target(true);
-
-            // Schedule auto-hide after 2 seconds
-            let sidebar_for_hide = tab_sidebar.clone();
-            let hide_source = sidebar_hide_source.clone();
-            let source_id = glib::timeout_add_local_once(Duration::from_secs(2), move || {
-                sidebar_for_hide.add_css_class("hidden");
-                sidebar_for_hide.set_can_target(false);
-                hide_source.borrow_mut().take();
-            });
-            *sidebar_hide_source.borrow_mut() = Some(source_id);
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().flash_sidebar();
+            }
         });
 
         // Set up webview focus callback - hide UI elements when user clicks on web content
@@ -617,8 +1225,7 @@ impl QayeqWindow {
                 // Hide sidebar if it was pinned
                 if imp.sidebar_pinned.get() {
                     imp.sidebar_pinned.set(false);
-                    imp.tab_sidebar.add_css_class("hidden");
-                    imp.tab_sidebar.set_can_target(false);
+                    imp.hide_sidebar();
                 }
                 // Hide toolbar if it was pinned
                 if imp.toolbar_pinned.get() {
@@ -628,19 +1235,48 @@ impl QayeqWindow {
             }
         });
 
-        // Set up session changed callback - save session immediately on changes
+        // Set up session changed callback - saves are coalesced by the application
         let app_weak = app.downgrade();
         tab_manager.set_on_session_changed(move || {
             if let Some(app) = app_weak.upgrade() {
-                app.save_all_windows();
+                app.request_session_save();
+            }
+        });
+
+        // Periodically capture scroll offsets so restored tabs return to the same spot
+        let tm_weak = Rc::downgrade(&tab_manager);
+        glib::timeout_add_seconds_local(config::SCROLL_CAPTURE_INTERVAL_SECS, move || {
+            match tm_weak.upgrade() {
+                Some(tm) => {
+                    tm.capture_scroll_positions();
+                    glib::ControlFlow::Continue
+                }
+                None => glib::ControlFlow::Break,
+            }
+        });
+
+        // Hibernate idle background tabs when enabled in preferences
+        let tm_weak = Rc::downgrade(&tab_manager);
+        let hibernation_settings = gio::Settings::new(config::APP_ID);
+        glib::timeout_add_seconds_local(config::HIBERNATION_CHECK_INTERVAL_SECS, move || {
+            let Some(tm) = tm_weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            let minutes = hibernation_settings.int("tab-hibernation-minutes");
+            if minutes > 0 {
+                tm.hibernate_idle_tabs(Duration::from_secs(minutes as u64 * 60));
             }
+            glib::ControlFlow::Continue
         });
 
         // Store tab manager before connecting signals
         *self.tab_manager.borrow_mut() = Some(Rc::clone(&tab_manager));
+        self.apply_tab_layout();
+        self.set_offline_indicator(app.profile_manager().is_some_and(|pm| pm.is_offline()));
 
         // Initialize completion list
         self.setup_completion_list(&app, &tab_manager);
+        self.setup_simplified_url();
 
         // Connect URL entry activate signal
         {
@@ -649,14 +1285,6 @@ impl QayeqWindow {
             let completion_list = self.completion_list.clone();
             let completion_debounce = Rc::clone(&self.completion_debounce);
             let obj_weak = obj.downgrade();
-            let settings = gio::Settings::new(config::APP_ID);
-            let search_engine = settings.string("search-engine");
-            let search_template: Rc<str> = config::SEARCH_ENGINES
-                .iter()
-                .find(|(id, _, _)| *id == search_engine.as_str())
-                .map(|(_, _, url)| *url)
-                .unwrap_or("https://duckduckgo.com/?q={}")
-                .into();
             let tm_for_reset = Rc::clone(&tm);
             self.url_entry.connect_activate(move |entry| {
                 // Cancel any pending completion requests
@@ -681,19 +1309,23 @@ impl QayeqWindow {
                     return;
                 }
 
+                // Searches go to the active tab's container's engine
+                let Some(obj) = obj_weak.upgrade() else {
+                    return;
+                };
+                let search_template = obj.imp().search_template();
+
                 // Check if there's an explicitly selected completion item (user navigated with arrows)
-                if let Some(obj) = obj_weak.upgrade() {
-                    if obj.imp().explicit_selection.get() {
-                        if let Some(selected_row) = completion_list.selected_row() {
-                            if let Some(child) = selected_row.child() {
-                                if let Ok(completion_row) = child.downcast::<CompletionRow>() {
-                                    if let Some(item) = completion_row.item() {
-                                        let url = item.action_url(&search_template);
-                                        if let Ok(parsed_url) = Url::parse(&url) {
-                                            tm.navigate_to(&parsed_url);
-                                            entry.set_text(&url);
-                                            return;
-                                        }
+                if obj.imp().explicit_selection.get() {
+                    if let Some(selected_row) = completion_list.selected_row() {
+                        if let Some(child) = selected_row.child() {
+                            if let Ok(completion_row) = child.downcast::<CompletionRow>() {
+                                if let Some(item) = completion_row.item() {
+                                    let url = item.action_url(&search_template);
+                                    if let Ok(parsed_url) = Url::parse(&url) {
+                                        tm.navigate_to(&parsed_url);
+                                        entry.set_text(&url);
+                                        return;
                                     }
                                 }
                             }
@@ -712,7 +1344,10 @@ impl QayeqWindow {
                         tm.navigate_to(&url);
                     }
                     url_bar::UrlBarInput::Search(query) => {
-                        if let Ok(url) = url_bar::build_search_url(&query) {
+                        // A broken container search URL falls back to the default engine
+                        let url = url_bar::search_url(&search_template, &query)
+                            .or_else(|_| url_bar::build_search_url(&query));
+                        if let Ok(url) = url {
                             tm.navigate_to(&url);
                         }
                     }
@@ -740,7 +1375,46 @@ impl QayeqWindow {
         {
             let tm = Rc::clone(&tab_manager);
             self.refresh_button.connect_clicked(move |_| {
-                tm.reload_current();
+                if tm.is_loading_current() {
+                    tm.stop_current();
+                } else {
+                    tm.reload_current();
+                }
+            });
+        }
+
+        // Connect media button, shown while any tab plays audio
+        {
+            let tm = Rc::clone(&tab_manager);
+            self.media_button.connect_clicked(move |_| {
+                tm.pause_all_media();
+            });
+            let media_button = self.media_button.clone();
+            tab_manager.set_on_media_playing_changed(move |playing| {
+                media_button.set_visible(playing);
+            });
+
+            let obj_weak = obj.downgrade();
+            tab_manager.set_on_fullscreen_changed(move |fullscreen| {
+                if let Some(obj) = obj_weak.upgrade() {
+                    if fullscreen {
+                        obj.imp().enter_page_fullscreen();
+                    } else {
+                        obj.imp().leave_page_fullscreen();
+                    }
+                }
+            });
+
+            // Leaving window fullscreen in other ways, such as from the window
+            // manager, takes the page out of fullscreen too
+            obj.connect_fullscreened_notify(|window| {
+                let imp = window.imp();
+                if !window.is_fullscreen() && imp.page_fullscreen.get().is_some() {
+                    if let Some(tm) = imp.tab_manager.borrow().as_ref() {
+                        tm.exit_page_fullscreen();
+                    }
+                    imp.leave_page_fullscreen();
+                }
             });
         }
 
@@ -754,124 +1428,858 @@ impl QayeqWindow {
             });
         }
 
-        // Connect row-selected signal for tab switching
-        {
-            let tm = Rc::clone(&tab_manager);
-            self.tabs_list.connect_row_selected(move |_list_box, row| {
-                if let Some(row) = row {
-                    // TabRow extends ListBoxRow directly, so downcast the row itself
-                    if let Ok(tab_row) = row.clone().downcast::<TabRow>() {
-                        tm.switch_to_tab(tab_row.tab_id());
-                    }
-                }
-            });
+        // Connect row-selected signal for tab switching
+        {
+            let tm = Rc::clone(&tab_manager);
+            self.tabs_list.connect_selected_children_changed(move |tabs_list| {
+                // TabRow extends FlowBoxChild directly, so downcast the child itself
+                let selected = tabs_list.selected_children().into_iter().next();
+                match selected.map(|child| child.downcast::<TabRow>()) {
+                    Some(Ok(tab_row)) => tm.switch_to_tab(tab_row.tab_id()),
+                    // A group header, which only collapses and expands its group
+                    Some(Err(_)) => tm.select_active_tab(),
+                    None => {}
+                }
+            });
+        }
+
+        // Check for pending session from new_with_session
+        let pending = self.pending_session.borrow_mut().take();
+        if let Some(saved_window) = pending {
+            self.restore_from_saved_window(&tab_manager, saved_window);
+        } else if self.skip_session_restore.get() || !self.restore_session() {
+            // Try to restore session (unless skip_session_restore is set), otherwise create initial tab
+            tab_manager.new_tab(None, None);
+        }
+        self.update_sidebar_visibility();
+        self.focus_url_bar();
+
+        // Mark initialization complete - sidebar will now show on tab changes
+        self.initialized.set(true);
+    }
+
+
+    /// Open a new tab and bring up the sidebar and URL bar for it. Returns the tab's ID.
+    pub fn new_tab(&self, url: Option<&Url>, container: Option<&str>) -> Option<u32> {
+        let tab_id = self.tab_manager.borrow().as_ref().map(|tab_manager| tab_manager.new_tab(url, container));
+        self.update_sidebar_visibility();
+        // Pin sidebar so it stays visible until user clicks on webview
+        self.sidebar_pinned.set(true);
+        self.show_sidebar();
+        // Show toolbar and focus URL bar for new tab
+        self.focus_url_bar();
+        tab_id
+    }
+
+    /// Close the current tab; `force` skips the protection of pinned tabs
+    pub fn close_current_tab(&self, force: bool) {
+        let tab_count = self.tab_manager.borrow().as_ref().map(|tm| tm.tab_count()).unwrap_or(0);
+
+        if tab_count <= 1 {
+            // Last tab - show confirmation to close window, which a pinned tab can forbid
+            let protection = self
+                .tab_manager
+                .borrow()
+                .as_ref()
+                .filter(|_| !force)
+                .and_then(|tm| tm.current_tab_close_protection());
+            match protection.as_deref() {
+                Some("shortcut") => self.obj().error_bell(),
+                Some("confirm") => self.show_close_window_dialog(true),
+                _ => self.show_close_window_dialog(false),
+            }
+        } else {
+            // Recorded for recently closed by the tab closed callback
+            if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
+                tab_manager.request_close_current_tab(force);
+            }
+        }
+    }
+
+    /// Reopen the most recently closed tab.
+    pub fn reopen_last_closed_tab(&self) {
+        self.reopen_closed_tab_at(0);
+    }
+
+    /// Reopen a specific recently closed tab (0 is the most recent).
+    pub fn reopen_closed_tab_at(&self, index: usize) {
+        let closed_tab = self.recently_closed_tabs.borrow_mut().reopen_at(index);
+        if let Some(closed_tab) = closed_tab {
+            if let Ok(url) = Url::parse(&closed_tab.url) {
+                self.new_tab(Some(&url), Some(&closed_tab.container));
+            } else {
+                // URL couldn't be parsed, just open a blank tab
+                self.new_tab(None, Some(&closed_tab.container));
+            }
+        }
+    }
+
+    /// List the other open windows to move the current tab to
+    fn refresh_move_to_window_menu(&self) {
+        let menu = &self.move_to_window_menu;
+        menu.remove_all();
+
+        let obj = self.obj();
+        let windows = obj.application().map(|app| app.windows()).unwrap_or_default();
+        for window in windows.iter().filter_map(|w| w.downcast_ref::<super::QayeqWindow>()) {
+            if window == &*obj {
+                continue;
+            }
+            let title = window.title().filter(|t| !t.is_empty()).unwrap_or_else(|| config::APP_NAME.into());
+            let item = gio::MenuItem::new(Some(&menu_label(&title)), None);
+            item.set_action_and_target_value(Some("app.move-tab-to-window"), Some(&window.id().to_variant()));
+            menu.append_item(&item);
+        }
+        if menu.n_items() == 0 {
+            // An item without an action shows as insensitive
+            menu.append(Some("No other windows"), None);
+        }
+    }
+
+    /// Close the current tab so it can be reopened in another window, without
+    /// recording it as recently closed. Returns its URL, container and whether
+    /// it was pinned; the window closes if that was its last tab.
+    pub fn take_current_tab(&self) -> Option<(Option<Url>, String, bool)> {
+        let tab_manager = self.tab_manager.borrow().clone()?;
+        let (url, container) = tab_manager.current_tab_location()?;
+        let pinned = tab_manager.is_current_tab_pinned();
+        let _ = tab_manager.close_current_tab();
+        if tab_manager.tab_count() == 0 {
+            // With no tabs left, closing records no closed window either
+            self.obj().close();
+        } else {
+            self.update_sidebar_visibility();
+        }
+        Some((url, container, pinned))
+    }
+
+    /// Rebuild the "Recently Closed" submenu from this window's closed tabs
+    /// and the application's closed windows
+    fn refresh_recently_closed_menu(&self) {
+        let menu = &self.recently_closed_menu;
+        menu.remove_all();
+
+        let tabs_section = gio::Menu::new();
+        for (index, tab) in self
+            .recently_closed_tabs
+            .borrow()
+            .list()
+            .take(recently_closed::MENU_ENTRY_LIMIT)
+            .enumerate()
+        {
+            let label = menu_label(tab.title.as_deref().filter(|t| !t.is_empty()).unwrap_or(&tab.url));
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(Some("app.reopen-tab-at"), Some(&(index as u32).to_variant()));
+            tabs_section.append_item(&item);
+        }
+
+        let windows_section = gio::Menu::new();
+        let app = self.obj().application().and_downcast::<QayeqApplication>();
+        if let Some(app) = app {
+            for (index, window) in app
+                .closed_windows()
+                .list()
+                .take(recently_closed::MENU_ENTRY_LIMIT)
+                .enumerate()
+            {
+                let first_title = window
+                    .tabs
+                    .first()
+                    .map(|t| t.title.as_deref().filter(|t| !t.is_empty()).unwrap_or(&t.url))
+                    .unwrap_or_default();
+                let label = match window.tabs.len() {
+                    1 => format!("{} (1 tab)", menu_label(first_title)),
+                    n => format!("{} ({} tabs)", menu_label(first_title), n),
+                };
+                let item = gio::MenuItem::new(Some(&label), None);
+                item.set_action_and_target_value(Some("app.reopen-window-at"), Some(&(index as u32).to_variant()));
+                windows_section.append_item(&item);
+            }
+        }
+
+        if tabs_section.n_items() == 0 && windows_section.n_items() == 0 {
+            // An item without an action shows as insensitive
+            menu.append(Some("Nothing recently closed"), None);
+            return;
+        }
+        if tabs_section.n_items() > 0 {
+            menu.append_section(Some("Tabs"), &tabs_section);
+        }
+        if windows_section.n_items() > 0 {
+            menu.append_section(Some("Windows"), &windows_section);
+        }
+    }
+
+    /// Ask before closing the window with its last tab, mentioning it if the tab is pinned
+    fn show_close_window_dialog(&self, pinned: bool) {
+        let body = if pinned {
+            "This will close the last tab, which is pinned, and the window."
+        } else {
+            "This will close the last tab and the window."
+        };
+        let dialog = libadwaita::AlertDialog::new(Some("Close Window?"), Some(body));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("close", "Close Window");
+        dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let window = self.obj().clone();
+        dialog.connect_response(None, move |_, response| {
+            if response == "close" {
+                window.close();
+            }
+        });
+
+        dialog.present(Some(&*self.obj()));
+    }
+
+    pub fn focus_url_bar(&self) {
+        // Typing an address needs the toolbar, so focus mode ends
+        self.set_focus_mode(false);
+        // Pin toolbar so it stays visible until user clicks on webview
+        self.toolbar_pinned.set(true);
+        self.show_toolbar();
+        self.url_entry.grab_focus();
+        self.url_entry.select_region(0, -1);
+    }
+
+    /// Put clipboard text in the URL bar and go to it, searching if it isn't
+    /// an address. `primary` reads the middle-click selection instead.
+    pub fn paste_and_go(&self, primary: bool) {
+        let obj = self.obj();
+        let clipboard = if primary {
+            obj.primary_clipboard()
+        } else {
+            obj.clipboard()
+        };
+        let obj_weak = obj.downgrade();
+        clipboard.read_text_async(None::<&gio::Cancellable>, move |result| {
+            let Some(obj) = obj_weak.upgrade() else {
+                return;
+            };
+            let text = match result {
+                Ok(Some(text)) => text,
+                Ok(None) => return,
+                Err(e) => {
+                    log::debug!("Nothing to paste: {}", e);
+                    return;
+                }
+            };
+            let Some(input) = url_bar::pasted_input(&text) else {
+                return;
+            };
+
+            // Same path as pressing Enter, without completions or simplifying getting in
+            let imp = obj.imp();
+            imp.explicit_selection.set(false);
+            imp.setting_autocomplete.set(true);
+            imp.url_entry.set_text(input);
+            imp.setting_autocomplete.set(false);
+            imp.url_entry.emit_activate();
+        });
+    }
+
+    /// Copy the active tab's address to the clipboard
+    pub fn copy_page_url(&self) {
+        let url = self.tab_manager.borrow().as_ref().and_then(|tm| tm.current_url_for_copy());
+        let Some(url) = url else {
+            return;
+        };
+        self.obj().clipboard().set_text(url.as_str());
+        self.show_toast("Copied page address");
+    }
+
+    /// Copy the active tab's title to the clipboard
+    pub fn copy_page_title(&self) {
+        let title = self.tab_manager.borrow().as_ref().and_then(|tm| tm.current_title());
+        let Some(title) = title.filter(|title| !title.is_empty()) else {
+            return;
+        };
+        self.obj().clipboard().set_text(&title);
+        self.show_toast("Copied page title");
+    }
+
+    pub fn reload(&self) {
+        if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
+            tab_manager.reload_current();
+            // Reset URL bar to current page URL
+            if let Some(url) = tab_manager.current_url() {
+                self.url_entry.set_text(url.as_str());
+            }
+        }
+    }
+
+    pub fn go_back(&self) {
+        if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
+            tab_manager.go_back();
+        }
+    }
+
+    pub fn go_forward(&self) {
+        if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
+            tab_manager.go_forward();
+        }
+    }
+
+    pub fn toggle_inspector(&self) {
+        if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
+            tab_manager.toggle_inspector();
+        }
+    }
+
+    /// Toggle the active site's requested user agent and tell the user what changed
+    pub fn toggle_site_user_agent(&self, mode: UserAgentMode) {
+        let result = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.toggle_site_user_agent(mode));
+
+        let message = match result {
+            Some(Some(UserAgentMode::Desktop)) => "Requesting desktop site",
+            Some(Some(UserAgentMode::Mobile)) => "Requesting mobile site",
+            Some(None) => "Using default site version",
+            None => return,
+        };
+        let toast = libadwaita::Toast::new(message);
+        toast.set_timeout(2);
+        self.toast_overlay.add_toast(toast);
+    }
+
+    /// Zoom the active tab in, out, or back to 100% (`step` 0)
+    pub fn zoom(&self, step: i32) {
+        let level = self.tab_manager.borrow().as_ref().and_then(|tm| tm.zoom_current(step));
+        if let Some(level) = level {
+            let toast = libadwaita::Toast::new(&format!("Zoom {}%", (level * 100.0).round()));
+            toast.set_timeout(1);
+            self.toast_overlay.add_toast(toast);
+        }
+    }
+
+    /// Enable "Save Page" only for a web page. The action is shared by all
+    /// windows, so only the focused one sets it.
+    fn update_save_page_action(&self) {
+        let obj = self.obj();
+        if !obj.is_active() {
+            return;
+        }
+        let Some(action) = obj
+            .application()
+            .and_then(|app| app.lookup_action("save-page"))
+            .and_downcast::<gio::SimpleAction>()
+        else {
+            return;
+        };
+        let can_save = self.tab_manager.borrow().as_ref().is_some_and(|tm| tm.can_save_page());
+        action.set_enabled(can_save);
+    }
+
+    /// Fill the site information popover for the active tab
+    fn update_site_info(&self) {
+        let host = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.current_url())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .and_then(|url| url.host_str().map(|h| h.to_string()));
+
+        self.site_info_host.set_label(host.as_deref().unwrap_or("No site loaded"));
+
+        let (level, certificate) = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .map(|tm| (tm.current_security_level(), tm.current_certificate()))
+            .unwrap_or((SecurityLevel::Local, None));
+        self.site_security_label.set_label(level.description());
+        self.site_security_label.set_visible(level != SecurityLevel::Local);
+        let details = certificate.map(|c| tab::certificate_details(&c)).unwrap_or_default();
+        self.site_certificate_label.set_label(&details);
+        self.site_certificate_label.set_visible(!details.is_empty());
+        self.site_insecure_content_button
+            .set_visible(level == SecurityLevel::MixedContentBlocked);
+        self.site_cookies_button.set_sensitive(host.is_some());
+        self.site_clear_data_button.set_sensitive(host.is_some());
+        self.site_forget_button.set_sensitive(host.is_some());
+
+        let javascript_enabled = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.site_javascript_enabled());
+        match javascript_enabled {
+            Some(enabled) => {
+                self.site_javascript_check.set_active(!enabled);
+                self.site_javascript_check.set_visible(true);
+            }
+            None => self.site_javascript_check.set_visible(false),
+        }
+
+        // Per-site opt-out, only relevant while force dark mode is on
+        let app = self.obj().application().and_downcast::<QayeqApplication>();
+        let cfm = app.as_ref().and_then(|app| app.content_filter_manager());
+        match (cfm, host.as_deref()) {
+            (Some(cfm), Some(host)) if cfm.force_dark_enabled() => {
+                self.site_force_dark_check.set_active(cfm.force_dark_applies_to(host));
+                self.site_force_dark_check.set_visible(true);
+            }
+            _ => self.site_force_dark_check.set_visible(false),
         }
 
-        // Check for pending session from new_with_session
-        let pending = self.pending_session.borrow_mut().take();
-        if let Some(saved_window) = pending {
-            self.restore_from_saved_window(&tab_manager, saved_window);
-        } else if self.skip_session_restore.get() || !self.restore_session() {
-            // Try to restore session (unless skip_session_restore is set), otherwise create initial tab
-            tab_manager.new_tab(None, None);
+        // Per-site opt-out, only relevant while fingerprinting protection is on
+        match (cfm, host.as_deref()) {
+            (Some(cfm), Some(host)) if cfm.fingerprinting_protection_enabled() => {
+                self.site_fingerprinting_check.set_active(cfm.fingerprinting_protection_applies_to(host));
+                self.site_fingerprinting_check.set_visible(true);
+            }
+            _ => self.site_fingerprinting_check.set_visible(false),
         }
-        self.update_sidebar_visibility();
-        self.focus_url_bar();
-
-        // Mark initialization complete - sidebar will now show on tab changes
-        self.initialized.set(true);
     }
 
+    /// Opt the active tab's site in or out of force dark mode
+    fn set_force_dark_for_current_site(&self, enabled: bool) {
+        let host = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.current_url())
+            .and_then(|url| url.host_str().map(|h| h.to_string()));
+        let app = self.obj().application().and_downcast::<QayeqApplication>();
+        if let (Some(host), Some(cfm)) = (host, app.as_ref().and_then(|app| app.content_filter_manager())) {
+            cfm.set_force_dark_for_site(&host, enabled);
+        }
+    }
 
-    pub fn new_tab(&self, url: Option<&Url>, container: Option<&str>) {
-        if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-            tab_manager.new_tab(url, container);
+    /// Opt the active tab's site in or out of fingerprinting protection
+    fn set_fingerprinting_protection_for_current_site(&self, enabled: bool) {
+        let host = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.current_url())
+            .and_then(|url| url.host_str().map(|h| h.to_string()));
+        let app = self.obj().application().and_downcast::<QayeqApplication>();
+        if let (Some(host), Some(cfm)) = (host, app.as_ref().and_then(|app| app.content_filter_manager())) {
+            cfm.set_fingerprinting_protection_for_site(&host, enabled);
         }
-        self.update_sidebar_visibility();
-        // Pin sidebar so it stays visible until user clicks on webview
-        self.sidebar_pinned.set(true);
-        self.show_sidebar();
-        // Show toolbar and focus URL bar for new tab
-        self.focus_url_bar();
     }
 
-    pub fn close_current_tab(&self) {
-        let tab_count = self.tab_manager.borrow().as_ref().map(|tm| tm.tab_count()).unwrap_or(0);
+    /// Show the cookies the active tab's site has set in its container
+    fn show_cookies_dialog(&self) {
+        let (url, session) = {
+            let tab_manager = self.tab_manager.borrow();
+            let Some(tab_manager) = tab_manager.as_ref() else {
+                return;
+            };
+            (tab_manager.current_url(), tab_manager.current_network_session())
+        };
+        let (Some(url), Some(session)) = (url, session) else {
+            return;
+        };
 
-        if tab_count <= 1 {
-            // Last tab - show confirmation to close window
-            self.show_close_window_dialog();
-        } else {
-            if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-                if let Some(closed_tab) = tab_manager.close_current_tab() {
-                    // Record for recently closed
-                    self.recently_closed_tabs.borrow_mut().push(closed_tab);
+        let dialog = CookiesDialog::new(&url, &session);
+        dialog.present(&*self.obj());
+    }
+
+    /// Ask before removing the current site from the active container's history
+    fn confirm_forget_current_site(&self) {
+        let (url, session) = {
+            let tab_manager = self.tab_manager.borrow();
+            let Some(tab_manager) = tab_manager.as_ref() else {
+                return;
+            };
+            (tab_manager.current_url(), tab_manager.current_network_session())
+        };
+        let (Some(host), Some(session)) = (url.and_then(|u| u.host_str().map(|h| h.to_string())), session) else {
+            return;
+        };
+
+        let dialog = libadwaita::AlertDialog::new(
+            Some(&format!("Forget {}?", host)),
+            Some("Every page from this site and its subdomains will be removed from history."),
+        );
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("forget", "Forget");
+        dialog.set_response_appearance("forget", libadwaita::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let window = self.obj().clone();
+        dialog.connect_response(None, move |_, response| {
+            if response != "forget" {
+                return;
+            }
+            let app = window.application().and_downcast::<QayeqApplication>();
+            let profile = app
+                .as_ref()
+                .and_then(|app| app.profile_manager())
+                .and_then(|pm| pm.profile_for_session(&session));
+            let Some(profile) = profile else {
+                return;
+            };
+            match profile.history().delete_by_domain(&host) {
+                Ok(count) => window.imp().show_toast(&format!("Removed {} pages from history", count)),
+                Err(e) => {
+                    log::error!("Failed to forget {}: {}", host, e);
+                    window.imp().show_toast("Could not clear history for this site");
                 }
             }
-            self.update_sidebar_visibility();
-        }
+        });
+
+        dialog.present(Some(&*self.obj()));
     }
 
-    /// Reopen the most recently closed tab.
-    pub fn reopen_last_closed_tab(&self) {
-        if let Some(closed_tab) = self.recently_closed_tabs.borrow_mut().pop() {
-            if let Ok(url) = Url::parse(&closed_tab.url) {
-                self.new_tab(Some(&url), Some(&closed_tab.container));
-            } else {
-                // URL couldn't be parsed, just open a blank tab
-                self.new_tab(None, Some(&closed_tab.container));
-            }
-        }
+    /// Ask before cancelling every unfinished download, as they can't be picked up again
+    fn confirm_cancel_all_downloads(&self) {
+        let dialog = libadwaita::AlertDialog::new(
+            Some("Cancel All Downloads?"),
+            Some("Downloads in progress or paused will stop and can't be resumed."),
+        );
+        dialog.add_response("keep", "Keep Downloading");
+        dialog.add_response("cancel-all", "Cancel All");
+        dialog.set_response_appearance("cancel-all", libadwaita::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("keep"));
+        dialog.set_close_response("keep");
+        dialog.connect_response(Some("cancel-all"), |_, _| download::cancel_all());
+        dialog.present(Some(&*self.obj()));
     }
 
-    fn show_close_window_dialog(&self) {
+    fn confirm_clear_current_site_data(&self) {
+        let (url, session) = {
+            let tab_manager = self.tab_manager.borrow();
+            let Some(tab_manager) = tab_manager.as_ref() else {
+                return;
+            };
+            (tab_manager.current_url(), tab_manager.current_network_session())
+        };
+        let (Some(host), Some(session)) = (url.and_then(|u| u.host_str().map(|h| h.to_string())), session) else {
+            return;
+        };
+
         let dialog = libadwaita::AlertDialog::new(
-            Some("Close Window?"),
-            Some("This will close the last tab and the window."),
+            Some(&format!("Clear data for {}?", host)),
+            Some("Cookies, local storage, databases and service workers of this site will be removed. You may be signed out."),
         );
         dialog.add_response("cancel", "Cancel");
-        dialog.add_response("close", "Close Window");
-        dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Destructive);
+        dialog.add_response("clear", "Clear");
+        dialog.set_response_appearance("clear", libadwaita::ResponseAppearance::Destructive);
         dialog.set_default_response(Some("cancel"));
         dialog.set_close_response("cancel");
 
         let window = self.obj().clone();
         dialog.connect_response(None, move |_, response| {
-            if response == "close" {
-                window.close();
+            if response != "clear" {
+                return;
             }
+            // WebKit wants a Send callback; it still runs on the main thread
+            let window: glib::SendWeakRef<super::QayeqWindow> = window.downgrade().into();
+            let host_for_toast = host.clone();
+            clear_data::clear_site_data(&session, &host, move |result| {
+                let Some(window) = window.upgrade() else {
+                    return;
+                };
+                match result {
+                    Ok(true) => window.imp().show_toast(&format!("Cleared data for {}", host_for_toast)),
+                    Ok(false) => window.imp().show_toast(&format!("No data stored for {}", host_for_toast)),
+                    Err(e) => {
+                        log::error!("Failed to clear data for {}: {}", host_for_toast, e);
+                        window.imp().show_toast("Could not clear data for this site");
+                    }
+                }
+            });
         });
 
         dialog.present(Some(&*self.obj()));
     }
 
-    pub fn focus_url_bar(&self) {
-        // Pin toolbar so it stays visible until user clicks on webview
-        self.toolbar_pinned.set(true);
-        self.show_toolbar();
-        self.url_entry.grab_focus();
-        self.url_entry.select_region(0, -1);
+    /// Open the tab search dialog and switch to the picked tab
+    pub fn show_tab_search(&self) {
+        let tabs = match self.tab_manager.borrow().as_ref() {
+            Some(tab_manager) => tab_manager.tab_summaries(),
+            None => return,
+        };
+
+        let dialog = TabSearchDialog::new(tabs);
+        let window_weak = self.obj().downgrade();
+        dialog.present(&*self.obj(), move |tab_id| {
+            if let Some(window) = window_weak.upgrade() {
+                if let Some(tab_manager) = window.imp().tab_manager.borrow().as_ref() {
+                    tab_manager.switch_to_tab(tab_id);
+                }
+            }
+        });
     }
 
-    pub fn reload(&self) {
+    pub fn switch_to_tab_at(&self, index: usize) {
         if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-            tab_manager.reload_current();
-            // Reset URL bar to current page URL
-            if let Some(url) = tab_manager.current_url() {
-                self.url_entry.set_text(url.as_str());
-            }
+            tab_manager.switch_to_tab_at(index);
         }
     }
 
-    pub fn go_back(&self) {
+    /// Whether any tab of this window is in the container
+    pub fn uses_container(&self, container_id: &str) -> bool {
+        self.tab_manager
+            .borrow()
+            .as_ref()
+            .is_some_and(|tm| tm.has_tabs_in_container(container_id))
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        let toast = libadwaita::Toast::new(message);
+        toast.set_timeout(3);
+        self.toast_overlay.add_toast(toast);
+    }
+
+    pub fn print_page(&self) {
         if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-            tab_manager.go_back();
+            tab_manager.print();
         }
     }
 
-    pub fn go_forward(&self) {
+    /// Show a QR code of the active tab's URL below the URL entry
+    pub fn show_qr_code(&self) {
+        let url = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.current_url())
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
+        let Some(url) = url else {
+            self.show_toast("This page can't be shared");
+            return;
+        };
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(6);
+        content.set_margin_end(6);
+        match qr::encode(url.as_str()) {
+            Some(code) => {
+                let picture = gtk4::Picture::for_paintable(&qr::to_texture(&code));
+                picture.set_can_shrink(false);
+                content.append(&picture);
+            }
+            None => {
+                log::warn!("URL too long for a QR code ({} bytes)", url.as_str().len());
+                content.append(&gtk4::Label::new(Some("This address is too long for a QR code")));
+            }
+        }
+        let label = gtk4::Label::new(Some(url.as_str()));
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+        label.set_max_width_chars(40);
+        label.set_selectable(true);
+        label.add_css_class("caption");
+        content.append(&label);
+
+        // Click-away closes it; keep the toolbar up meanwhile
+        let popover = gtk4::Popover::new();
+        popover.set_child(Some(&content));
+        popover.set_parent(&*self.url_entry);
+        let menu_open = Rc::clone(&self.menu_open);
+        menu_open.set(true);
+        popover.connect_closed(move |popover| {
+            menu_open.set(false);
+            popover.unparent();
+        });
+        self.show_toolbar();
+        popover.popup();
+    }
+
+    /// Open the active page's source in a new tab of the same container
+    pub fn view_source(&self) {
+        let Some(tab_manager) = self.tab_manager.borrow().clone() else {
+            return;
+        };
+        let Some((Some(url), container)) = tab_manager.current_tab_location() else {
+            return;
+        };
+        if url.scheme() == "view-source" {
+            return;
+        }
+        let Ok(source_url) = Url::parse(&crate::webview::view_source_uri(url.as_str())) else {
+            return;
+        };
+        tab_manager.new_tab(Some(&source_url), Some(&container));
+        self.update_sidebar_visibility();
+    }
+
+    /// Ask where to save the active page as a PDF, then export it
+    pub fn save_as_pdf(&self) {
+        let Some(tab_manager) = self.tab_manager.borrow().clone() else {
+            return;
+        };
+
+        let title = tab_manager
+            .current_title()
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "page".to_string());
+        let initial_name = format!("{}.pdf", title.replace(['/', '\\'], "_"));
+        let url = tab_manager.current_url().map(|u| u.to_string()).unwrap_or_default();
+
+        let dialog = gtk4::FileDialog::builder()
+            .title("Save as PDF")
+            .initial_name(initial_name.as_str())
+            .initial_folder(&gio::File::for_path(download::last_save_directory()))
+            .build();
+
+        let window = self.obj();
+        let toast_overlay = self.toast_overlay.clone();
+        dialog.save(Some(&*window), gio::Cancellable::NONE, move |result| {
+            let Some(path) = result.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            if let Some(parent) = path.parent() {
+                download::set_last_save_directory(parent.to_path_buf());
+            }
+
+            let filename = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let destination = path.clone();
+            let url = url.clone();
+            let toast_overlay = toast_overlay.clone();
+            tab_manager.export_pdf(&path, move |result| {
+                let message = match result {
+                    Ok(()) => {
+                        // List the export alongside regular downloads
+                        let id = download::add_download(&url, &filename, destination.clone());
+                        download::set_download_status(id, download::DownloadStatus::Completed);
+                        format!("Saved {}", glib::markup_escape_text(&filename))
+                    }
+                    Err(_) => format!("Couldn't save {}", glib::markup_escape_text(&filename)),
+                };
+                let toast = libadwaita::Toast::new(&message);
+                toast.set_timeout(3);
+                toast_overlay.add_toast(toast);
+            });
+        });
+    }
+
+    pub fn set_offline_indicator(&self, offline: bool) {
+        self.offline_indicator.set_visible(offline);
+    }
+
+    /// Ask where to save the active page, then write it with its resources as MHTML
+    pub fn save_page(&self) {
+        let Some(tab_manager) = self.tab_manager.borrow().clone() else {
+            return;
+        };
+        if !tab_manager.can_save_page() {
+            self.show_toast("This page can't be saved");
+            return;
+        }
+
+        let title = tab_manager
+            .current_title()
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "page".to_string());
+        let initial_name = format!("{}.mhtml", title.replace(['/', '\\'], "_"));
+        let url = tab_manager.current_url().map(|u| u.to_string()).unwrap_or_default();
+
+        let dialog = gtk4::FileDialog::builder()
+            .title("Save Page")
+            .initial_name(initial_name.as_str())
+            .initial_folder(&gio::File::for_path(download::last_save_directory()))
+            .build();
+
+        let window = self.obj();
+        let toast_overlay = self.toast_overlay.clone();
+        dialog.save(Some(&*window), gio::Cancellable::NONE, move |result| {
+            let Some(path) = result.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            if let Some(parent) = path.parent() {
+                download::set_last_save_directory(parent.to_path_buf());
+            }
+
+            let filename = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let destination = path.clone();
+            let toast_overlay = toast_overlay.clone();
+            tab_manager.save_page(&path, move |result| {
+                let message = match result {
+                    Ok(()) => {
+                        // List the saved page alongside regular downloads
+                        let id = download::add_download(&url, &filename, destination);
+                        download::set_download_status(id, download::DownloadStatus::Completed);
+                        format!("Saved {}", glib::markup_escape_text(&filename))
+                    }
+                    Err(_) => format!("Couldn't save {}", glib::markup_escape_text(&filename)),
+                };
+                let toast = libadwaita::Toast::new(&message);
+                toast.set_timeout(3);
+                toast_overlay.add_toast(toast);
+            });
+        });
+    }
+
+    /// Capture the active page and ask where to save it as a PNG
+    pub fn save_screenshot(&self, region: webkit6::SnapshotRegion) {
+        let Some(tab_manager) = self.tab_manager.borrow().clone() else {
+            return;
+        };
+
+        let title = tab_manager
+            .current_title()
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "page".to_string());
+        let initial_name = format!("Screenshot of {}.png", title.replace(['/', '\\'], "_"));
+
+        let window = self.obj().clone();
+        let toast_overlay = self.toast_overlay.clone();
+        // Snapshot first so the save dialog never ends up in the picture
+        tab_manager.capture_snapshot(region, move |result| {
+            let texture = match result {
+                Ok(texture) => texture,
+                Err(e) => {
+                    log::warn!("Failed to capture screenshot: {}", e);
+                    let toast = libadwaita::Toast::new("Can't take a screenshot of this page");
+                    toast.set_timeout(3);
+                    toast_overlay.add_toast(toast);
+                    return;
+                }
+            };
+
+            let downloads_dir = glib::user_special_dir(glib::UserDirectory::Downloads)
+                .unwrap_or_else(download::last_save_directory);
+            let dialog = gtk4::FileDialog::builder()
+                .title("Save Screenshot")
+                .initial_name(initial_name.as_str())
+                .initial_folder(&gio::File::for_path(downloads_dir))
+                .build();
+
+            dialog.save(Some(&window), gio::Cancellable::NONE, move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+
+                let filename = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let message = match texture.save_to_png(&path) {
+                    Ok(()) => format!("Saved {}", glib::markup_escape_text(&filename)),
+                    Err(e) => {
+                        log::warn!("Failed to save screenshot to {:?}: {}", path, e);
+                        format!("Couldn't save {}", glib::markup_escape_text(&filename))
+                    }
+                };
+                let toast = libadwaita::Toast::new(&message);
+                toast.set_timeout(3);
+                toast_overlay.add_toast(toast);
+            });
+        });
+    }
+
+    pub fn go_home(&self) {
         if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-            tab_manager.go_forward();
+            tab_manager.go_home();
         }
     }
 
@@ -904,20 +2312,47 @@ impl QayeqWindow {
         log::info!("Restoring window with {} tabs", saved.tabs.len());
 
         let mut tab_ids = Vec::new();
-        for saved_tab in saved.tabs.iter() {
+        // Saved group IDs to those of the recreated groups
+        let mut group_ids: HashMap<&str, String> = HashMap::new();
+        for (index, saved_tab) in saved.tabs.iter().enumerate() {
             let container = saved_tab.container.as_deref();
-            let tab_id = if let Ok(url) = Url::parse(&saved_tab.url) {
-                tab_manager.new_tab(Some(&url), container)
-            } else {
-                tab_manager.new_tab(None, container)
-            };
+            let active = index == saved.active_tab_index;
+            let tab_id = tab_manager.restore_tab(&saved_tab.url, saved_tab.title.as_deref(), container, active);
+            if let Some(scroll_y) = saved_tab.scroll_y {
+                tab_manager.restore_scroll_position(tab_id, scroll_y);
+            }
+            if saved_tab.pinned {
+                tab_manager.set_tab_pinned(tab_id, true);
+            }
+            let saved_group = saved_tab
+                .group
+                .as_deref()
+                .and_then(|id| saved.groups.iter().find(|group| group.id == id));
+            if let Some(saved_group) = saved_group {
+                let group_id = group_ids
+                    .entry(&saved_group.id)
+                    .or_insert_with(|| tab_manager.restore_group(saved_group));
+                tab_manager.add_tab_to_group(tab_id, group_id);
+            }
             tab_ids.push(tab_id);
         }
 
+        // Collapse groups before switching, which expands the active tab's group
+        for saved_group in saved.groups.iter().filter(|group| group.collapsed) {
+            if let Some(group_id) = group_ids.get(saved_group.id.as_str()) {
+                tab_manager.set_group_collapsed(group_id, true);
+            }
+        }
+
         // Switch to the previously active tab
         if let Some(&active_tab_id) = tab_ids.get(saved.active_tab_index) {
             tab_manager.switch_to_tab(active_tab_id);
         }
+        log::info!(
+            "Restored {} tabs, {} deferred until first shown",
+            tab_ids.len(),
+            tab_manager.suspended_tab_count()
+        );
     }
 
     /// Get the current window's session data for multi-window save
@@ -925,16 +2360,7 @@ impl QayeqWindow {
         let tab_manager = self.tab_manager.borrow();
         let tab_manager = tab_manager.as_ref()?;
 
-        let (tabs_data, active_index) = tab_manager.get_all_tabs_with_containers();
-
-        let tabs = tabs_data
-            .into_iter()
-            .map(|(url, title, container)| crate::session::SavedTab {
-                url,
-                title,
-                container: Some(container),
-            })
-            .collect();
+        let (tabs, active_index) = tab_manager.get_saved_tabs();
 
         let obj = self.obj();
         Some(SavedWindow {
@@ -942,6 +2368,7 @@ impl QayeqWindow {
             active_tab_index: active_index,
             window_width: Some(obj.width()),
             window_height: Some(obj.height()),
+            groups: tab_manager.saved_groups(),
         })
     }
 
@@ -955,11 +2382,11 @@ impl QayeqWindow {
         let profile_path = profile_path.unwrap();
 
         if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
-            let (tabs_data, active_index) = tab_manager.get_all_tabs();
+            let (tabs_data, active_index) = tab_manager.get_all_tabs_with_containers();
 
             let mut session = Session::new();
-            for (url, title) in tabs_data {
-                session.add_tab(url, title);
+            for (url, title, container) in tabs_data {
+                session.add_tab(url, title, Some(container));
             }
             session.active_tab_index = active_index;
 
@@ -978,6 +2405,9 @@ impl QayeqWindow {
 
     /// Restore session from the profile directory
     pub fn restore_session(&self) -> bool {
+        if StartupMode::current(self.settings()) != StartupMode::RestoreSession {
+            return false;
+        }
         let profile_path = self.get_profile_path();
         if profile_path.is_none() {
             log::warn!("Cannot restore session: no profile path available");
@@ -999,13 +2429,15 @@ impl QayeqWindow {
 
                     if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
                         let mut tab_ids = Vec::new();
-                        for saved_tab in session.tabs.iter() {
-                            // TODO: Restore tab's container from saved session
-                            let tab_id = if let Ok(url) = Url::parse(&saved_tab.url) {
-                                tab_manager.new_tab(Some(&url), None)
-                            } else {
-                                tab_manager.new_tab(None, None)
-                            };
+                        for (index, saved_tab) in session.tabs.iter().enumerate() {
+                            // Tabs saved without a container land in the default one
+                            let container = saved_tab.container.as_deref();
+                            let active = index == session.active_tab_index;
+                            let tab_id =
+                                tab_manager.restore_tab(&saved_tab.url, saved_tab.title.as_deref(), container, active);
+                            if let Some(scroll_y) = saved_tab.scroll_y {
+                                tab_manager.restore_scroll_position(tab_id, scroll_y);
+                            }
                             tab_ids.push(tab_id);
                         }
 
@@ -1037,25 +2469,31 @@ impl QayeqWindow {
         Some(profile.path().clone())
     }
 
+    /// Search URL template for the active tab: its container's own search
+    /// engine if it has one, otherwise the one chosen in preferences
+    fn search_template(&self) -> String {
+        let container_id = self
+            .tab_manager
+            .borrow()
+            .as_ref()
+            .and_then(|tm| tm.current_tab_location())
+            .map(|(_, container_id)| container_id);
+        let app = self.obj().application().and_downcast::<QayeqApplication>();
+        let container_search_url = container_id.zip(app).and_then(|(container_id, app)| {
+            app.profile_manager()?.get_profile(&container_id)?.search_url()
+        });
+        container_search_url.unwrap_or_else(|| global_search_template(self.settings()))
+    }
+
     /// Set up the integrated completion list
     fn setup_completion_list(&self, app: &QayeqApplication, tab_manager: &Rc<TabManager>) {
-        // Get search URL template from settings
-        let settings = gio::Settings::new(config::APP_ID);
-        let search_engine = settings.string("search-engine");
-        let search_url_template: Rc<str> = config::SEARCH_ENGINES
-            .iter()
-            .find(|(id, _, _)| *id == search_engine.as_str())
-            .map(|(_, _, url)| *url)
-            .unwrap_or("https://duckduckgo.com/?q={}")
-            .into();
-
         // Check if suggestions are enabled
+        let settings = gio::Settings::new(config::APP_ID);
         let suggestions_enabled = settings.boolean("search-suggestions-enabled");
 
         // Connect URL entry changed signal
         let obj = self.obj().clone();
         let app_weak = app.downgrade();
-        let search_template = Rc::clone(&search_url_template);
         self.url_entry.connect_changed(move |entry| {
             let imp = obj.imp();
 
@@ -1107,24 +2545,28 @@ impl QayeqWindow {
             *imp.autocomplete_url.borrow_mut() = None;
             imp.user_input_len.set(0);
 
-            imp.update_completions(&query, &app_weak, suggestions_enabled, &search_template);
+            imp.update_completions(&query, &app_weak, suggestions_enabled);
         });
 
         // Connect list row activation to navigate
         let tm = Rc::clone(tab_manager);
         let url_entry = self.url_entry.clone();
         let completion_scroll = self.completion_scroll.clone();
-        let search_template = search_url_template;
+        let obj_weak = self.obj().downgrade();
         self.completion_list.connect_row_activated(move |_, list_row| {
+            let Some(obj) = obj_weak.upgrade() else {
+                return;
+            };
             if let Some(row) = list_row.child() {
                 if let Ok(completion_row) = row.downcast::<CompletionRow>() {
                     if let Some(item) = completion_row.item() {
+                        let search_template = obj.imp().search_template();
                         let url = item.action_url(&search_template);
                         completion_scroll.set_visible(false);
                         if let Ok(parsed_url) = Url::parse(&url) {
                             tm.navigate_to(&parsed_url);
                             url_entry.set_text(&url);
-                        } else if let Ok(search_url) = url_bar::build_search_url(&url) {
+                        } else if let Ok(search_url) = url_bar::search_url(&search_template, &url) {
                             tm.navigate_to(&search_url);
                         }
                     }
@@ -1133,6 +2575,95 @@ impl QayeqWindow {
         });
     }
 
+    /// Show the page URL simplified while the URL bar isn't being edited, when enabled
+    fn setup_simplified_url(&self) {
+        // Page URLs are set while the bar is unfocused; simplify them as they arrive
+        let obj_weak = self.obj().downgrade();
+        self.url_entry.connect_changed(move |entry| {
+            if let Some(obj) = obj_weak.upgrade() {
+                let imp = obj.imp();
+                if !imp.setting_autocomplete.get() && !entry.state_flags().contains(gtk4::StateFlags::FOCUS_WITHIN) {
+                    imp.simplify_url_entry();
+                }
+            }
+        });
+
+        let focus = gtk4::EventControllerFocus::new();
+        let obj_weak = self.obj().downgrade();
+        focus.connect_enter(move |_| {
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().expand_url_entry();
+            }
+        });
+        let obj_weak = self.obj().downgrade();
+        focus.connect_leave(move |_| {
+            let Some(obj) = obj_weak.upgrade() else {
+                return;
+            };
+            let imp = obj.imp();
+            // Leave anything the user typed alone
+            let text = imp.url_entry.text();
+            let unedited = imp.full_url.borrow().as_deref() == Some(text.as_str())
+                || imp
+                    .tab_manager
+                    .borrow()
+                    .as_ref()
+                    .and_then(|tm| tm.current_url())
+                    .is_some_and(|url| url.as_str() == text.as_str());
+            if unedited {
+                imp.simplify_url_entry();
+            }
+        });
+        self.url_entry.add_controller(focus);
+
+        let obj_weak = self.obj().downgrade();
+        self.settings().connect_changed(Some("simplify-url-bar"), move |_, _| {
+            if let Some(obj) = obj_weak.upgrade() {
+                let imp = obj.imp();
+                imp.expand_url_entry();
+                if !imp.url_entry.state_flags().contains(gtk4::StateFlags::FOCUS_WITHIN) {
+                    imp.simplify_url_entry();
+                }
+            }
+        });
+    }
+
+    /// Replace a page URL in the bar with its simplified form, keeping the real one
+    fn simplify_url_entry(&self) {
+        let text = self.url_entry.text();
+        let simplified = url_bar::simplify_url(&text).filter(|_| self.settings().boolean("simplify-url-bar"));
+        let Some(simplified) = simplified else {
+            self.full_url.replace(None);
+            self.url_entry.set_attributes(&gtk4::pango::AttrList::new());
+            return;
+        };
+        self.full_url.replace(Some(text.to_string()));
+
+        // Dim everything but the registrable domain
+        let attributes = gtk4::pango::AttrList::new();
+        let mut dim = gtk4::pango::AttrInt::new_foreground_alpha(u16::MAX / 2);
+        dim.set_end_index(simplified.domain.start as u32);
+        attributes.insert(dim);
+        let mut dim = gtk4::pango::AttrInt::new_foreground_alpha(u16::MAX / 2);
+        dim.set_start_index(simplified.domain.end as u32);
+        attributes.insert(dim);
+
+        self.setting_autocomplete.set(true);
+        self.url_entry.set_text(&simplified.text);
+        self.url_entry.set_attributes(&attributes);
+        self.setting_autocomplete.set(false);
+    }
+
+    /// Put the real page URL back in the bar for editing
+    fn expand_url_entry(&self) {
+        self.url_entry.set_attributes(&gtk4::pango::AttrList::new());
+        if let Some(url) = self.full_url.take() {
+            self.setting_autocomplete.set(true);
+            self.url_entry.set_text(&url);
+            self.setting_autocomplete.set(false);
+        }
+    }
+
     /// Trigger completion fetch for current URL entry text (used by Down arrow)
     fn trigger_completions(&self) {
         let query = self.url_entry.text().to_string();
@@ -1149,16 +2680,7 @@ impl QayeqWindow {
         let settings = gio::Settings::new(config::APP_ID);
         let suggestions_enabled = settings.boolean("search-suggestions-enabled");
 
-        // Get search URL template from settings
-        let search_engine = settings.string("search-engine");
-        let search_template: Rc<str> = config::SEARCH_ENGINES
-            .iter()
-            .find(|(id, _, _)| *id == search_engine.as_str())
-            .map(|(_, _, url)| *url)
-            .unwrap_or("https://duckduckgo.com/?q={}")
-            .into();
-
-        self.update_completions(&query, &app_weak, suggestions_enabled, &search_template);
+        self.update_completions(&query, &app_weak, suggestions_enabled);
     }
 
     /// Update completions for the given query
@@ -1167,7 +2689,6 @@ impl QayeqWindow {
         query: &str,
         app_weak: &glib::WeakRef<QayeqApplication>,
         suggestions_enabled: bool,
-        search_template: &Rc<str>,
     ) {
         // Cancel any pending debounce
         if let Some(source_id) = self.completion_debounce.borrow_mut().take() {
@@ -1188,14 +2709,13 @@ impl QayeqWindow {
         let query = query.to_string();
         let obj = self.obj().clone();
         let app_weak = app_weak.clone();
-        let search_template = Rc::clone(search_template);
         let source_ref = Rc::clone(&self.completion_debounce);
 
         let source_id = glib::timeout_add_local_once(
             Duration::from_millis(config::COMPLETION_DEBOUNCE_MS),
             move || {
                 *source_ref.borrow_mut() = None;
-                obj.imp().fetch_completions(&query, &app_weak, suggestions_enabled, &search_template);
+                obj.imp().fetch_completions(&query, &app_weak, suggestions_enabled);
             },
         );
         *self.completion_debounce.borrow_mut() = Some(source_id);
@@ -1207,7 +2727,6 @@ impl QayeqWindow {
         query: &str,
         app_weak: &glib::WeakRef<QayeqApplication>,
         suggestions_enabled: bool,
-        _search_template: &Rc<str>,
     ) {
         // Get history completions
         let history_items = if let Some(app) = app_weak.upgrade() {
@@ -1392,6 +2911,19 @@ fn simplify_url_for_autocomplete(url_str: &str) -> String {
     }
 }
 
+/// Shorten a title or URL for display in a menu. Underscores are doubled so
+/// they show as typed rather than marking a mnemonic.
+fn menu_label(text: &str) -> String {
+    const MAX_CHARS: usize = 50;
+    let label = if text.chars().count() > MAX_CHARS {
+        let truncated: String = text.chars().take(MAX_CHARS - 1).collect();
+        format!("{}…", truncated)
+    } else {
+        text.to_string()
+    };
+    label.replace('_', "__")
+}
+
 impl WidgetImpl for QayeqWindow {}
 
 impl WindowImpl for QayeqWindow {
@@ -1399,6 +2931,24 @@ impl WindowImpl for QayeqWindow {
         // Note: Session saving is handled by the application on shutdown,
         // which aggregates all open windows into a multi-window session.
 
+        // Pages may warn before being left; close once every tab has agreed
+        if !self.close_confirmed.get() {
+            let tab_manager = self.tab_manager.borrow().clone();
+            if let Some(tab_manager) = tab_manager {
+                let obj_weak = self.obj().downgrade();
+                tab_manager.confirm_close_all(move || {
+                    // Out of the webview's signal handler before the window goes away
+                    glib::idle_add_local_once(move || {
+                        if let Some(obj) = obj_weak.upgrade() {
+                            obj.imp().close_confirmed.set(true);
+                            obj.close();
+                        }
+                    });
+                });
+                return glib::Propagation::Stop;
+            }
+        }
+
         // Record closed window for "Reopen Closed Window" functionality
         if let Some(tab_manager) = self.tab_manager.borrow().as_ref() {
             let (tabs_data, active_index) = tab_manager.get_all_tabs_with_containers();
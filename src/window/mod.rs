@@ -50,8 +50,40 @@ impl QayeqWindow {
         self.imp().focus_url_bar();
     }
 
+    /// Close the current tab to move it to another window; see `imp::take_current_tab`
+    pub fn take_current_tab(&self) -> Option<(Option<Url>, String, bool)> {
+        self.imp().take_current_tab()
+    }
+
+    /// Open a tab moved from another window, pinned again if it was
+    pub fn add_moved_tab(&self, url: Option<&Url>, container: &str, pinned: bool) {
+        let tab_id = self.imp().new_tab(url, Some(container));
+        if pinned {
+            if let (Some(tab_id), Some(tab_manager)) = (tab_id, self.imp().tab_manager.borrow().as_ref()) {
+                tab_manager.set_tab_pinned(tab_id, true);
+            }
+        }
+        self.imp().focus_url_bar();
+    }
+
     pub fn close_current_tab(&self) {
-        self.imp().close_current_tab();
+        self.imp().close_current_tab(false);
+        self.imp().focus_url_bar();
+    }
+
+    /// Toggle window fullscreen, or take a page out of its own fullscreen
+    pub fn toggle_fullscreen(&self) {
+        self.imp().toggle_fullscreen();
+    }
+
+    /// Hide or bring back the toolbar and sidebar for distraction-free reading
+    pub fn toggle_focus_mode(&self) {
+        self.imp().toggle_focus_mode();
+    }
+
+    /// Close the current tab even if it's pinned
+    pub fn force_close_current_tab(&self) {
+        self.imp().close_current_tab(true);
         self.imp().focus_url_bar();
     }
 
@@ -71,6 +103,81 @@ impl QayeqWindow {
         self.imp().go_forward();
     }
 
+    pub fn toggle_inspector(&self) {
+        self.imp().toggle_inspector();
+    }
+
+    pub fn toggle_site_user_agent(&self, mode: crate::user_agent::UserAgentMode) {
+        self.imp().toggle_site_user_agent(mode);
+    }
+
+    /// Copy the active tab's address to the clipboard
+    pub fn copy_page_url(&self) {
+        self.imp().copy_page_url();
+    }
+
+    /// Copy the active tab's title to the clipboard
+    pub fn copy_page_title(&self) {
+        self.imp().copy_page_title();
+    }
+
+    /// Zoom the active tab in (`step` > 0), out (`step` < 0), or reset it (0)
+    pub fn zoom(&self, step: i32) {
+        self.imp().zoom(step);
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        self.imp().show_toast(message);
+    }
+
+    pub fn switch_to_tab_at(&self, index: usize) {
+        self.imp().switch_to_tab_at(index);
+    }
+
+    pub fn show_tab_search(&self) {
+        self.imp().show_tab_search();
+    }
+
+    pub fn show_qr_code(&self) {
+        self.imp().show_qr_code();
+    }
+
+    pub fn uses_container(&self, container_id: &str) -> bool {
+        self.imp().uses_container(container_id)
+    }
+
+    pub fn print_page(&self) {
+        self.imp().print_page();
+    }
+
+    pub fn save_as_pdf(&self) {
+        self.imp().save_as_pdf();
+    }
+
+    pub fn view_source(&self) {
+        self.imp().view_source();
+    }
+
+    pub fn save_page(&self) {
+        self.imp().save_page();
+    }
+
+    pub fn set_restore_banner_revealed(&self, revealed: bool) {
+        self.imp().restore_banner.set_revealed(revealed);
+    }
+
+    pub fn set_offline_indicator(&self, offline: bool) {
+        self.imp().set_offline_indicator(offline);
+    }
+
+    pub fn save_screenshot(&self, region: webkit6::SnapshotRegion) {
+        self.imp().save_screenshot(region);
+    }
+
+    pub fn go_home(&self) {
+        self.imp().go_home();
+    }
+
     pub fn navigate_to(&self, url: &Url) {
         self.imp().navigate_to(url);
     }